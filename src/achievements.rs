@@ -0,0 +1,188 @@
+//! goals players unlock once by how they finish levels, kept in a file between runs
+
+use crate::stats::LevelStats;
+use crate::TARGET_FPS;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// levels finished faster than this many seconds unlock [`Achievement::Quick`]
+const QUICK_SECONDS: u64 = 60;
+/// and faster than this [`Achievement::Speedrun`]
+const SPEEDRUN_SECONDS: u64 = 30;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub(crate) enum Achievement {
+    /// finish a level without losing any health
+    Untouched,
+    /// find every secret of a level that has some
+    Explorer,
+    Quick,
+    Speedrun,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 4] = [
+        Achievement::Untouched,
+        Achievement::Explorer,
+        Achievement::Quick,
+        Achievement::Speedrun,
+    ];
+
+    /// how it is saved
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::Untouched => "Untouched",
+            Achievement::Explorer => "Explorer",
+            Achievement::Quick => "Quick",
+            Achievement::Speedrun => "Speedrun",
+        }
+    }
+
+    /// language keys of its name and of what unlocks it
+    pub fn keys(self) -> (&'static str, &'static str) {
+        match self {
+            Achievement::Untouched => (
+                "achievement.untouched.name",
+                "achievement.untouched.description",
+            ),
+            Achievement::Explorer => (
+                "achievement.explorer.name",
+                "achievement.explorer.description",
+            ),
+            Achievement::Quick => ("achievement.quick.name", "achievement.quick.description"),
+            Achievement::Speedrun => (
+                "achievement.speedrun.name",
+                "achievement.speedrun.description",
+            ),
+        }
+    }
+
+    /// how fast a level needs finishing to unlock it, for the ones about time
+    pub fn seconds(self) -> Option<u64> {
+        match self {
+            Achievement::Quick => Some(QUICK_SECONDS),
+            Achievement::Speedrun => Some(SPEEDRUN_SECONDS),
+            Achievement::Untouched | Achievement::Explorer => None,
+        }
+    }
+
+    /// whether finishing a level like this unlocks it
+    fn earned_by(self, stats: &LevelStats) -> bool {
+        match self {
+            Achievement::Untouched => !stats.hurt,
+            Achievement::Explorer => stats.secret_total > 0 && stats.secrets >= stats.secret_total,
+            Achievement::Quick | Achievement::Speedrun => {
+                stats.frames < self.seconds().unwrap_or_default() * TARGET_FPS
+            }
+        }
+    }
+}
+
+/// achievements unlocked so far, one name per line in the file
+pub(crate) struct Achievements {
+    path: PathBuf,
+    unlocked: BTreeSet<Achievement>,
+}
+
+impl Achievements {
+    /// nothing unlocked yet, saved to `path` once something is
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            unlocked: BTreeSet::new(),
+        }
+    }
+
+    /// read what was unlocked from `path`, nothing when there is no file yet
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let mut achievements = Self::new(path);
+        match std::fs::read_to_string(&achievements.path) {
+            Ok(text) => {
+                for name in text.lines().filter(|line| !line.is_empty()) {
+                    let achievement = Achievement::ALL
+                        .into_iter()
+                        .find(|achievement| achievement.name() == name);
+                    match achievement {
+                        Some(achievement) => {
+                            achievements.unlocked.insert(achievement);
+                        }
+                        // might be from a newer version, keep going without it
+                        None => log::warn!("unknown achievement: {name}"),
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(achievements)
+    }
+
+    pub fn unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// unlock whatever finishing a level like this earns and save, returns what is new
+    pub fn finish_level(&mut self, stats: &LevelStats) -> anyhow::Result<Vec<Achievement>> {
+        let new = Achievement::ALL
+            .into_iter()
+            .filter(|achievement| achievement.earned_by(stats) && !self.unlocked(*achievement))
+            .collect::<Vec<_>>();
+        if new.is_empty() {
+            return Ok(new);
+        }
+
+        self.unlocked.extend(new.iter().copied());
+        let text = self
+            .unlocked
+            .iter()
+            .map(|achievement| format!("{}\n", achievement.name()))
+            .collect::<String>();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, text)?;
+
+        Ok(new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_unlock_achievements_once() {
+        let path =
+            std::env::temp_dir().join(format!("yaw-achievements-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut achievements = Achievements::load(path.clone()).unwrap();
+
+        let slow = LevelStats {
+            frames: QUICK_SECONDS * TARGET_FPS,
+            hurt: true,
+            secrets: 2,
+            secret_total: 2,
+            ..LevelStats::default()
+        };
+        assert_eq!(
+            achievements.finish_level(&slow).unwrap(),
+            [Achievement::Explorer]
+        );
+        assert!(achievements.finish_level(&slow).unwrap().is_empty());
+
+        let fast = LevelStats {
+            frames: TARGET_FPS * 40,
+            ..LevelStats::default()
+        };
+        assert_eq!(
+            achievements.finish_level(&fast).unwrap(),
+            [Achievement::Untouched, Achievement::Quick]
+        );
+
+        let achievements = Achievements::load(path.clone()).unwrap();
+        assert!(achievements.unlocked(Achievement::Quick));
+        assert!(!achievements.unlocked(Achievement::Speedrun));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
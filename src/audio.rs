@@ -0,0 +1,71 @@
+use crate::StringToAnyhow;
+use sdl2::audio::{AudioCVT, AudioFormat, AudioQueue, AudioSpecDesired, AudioSpecWAV};
+use sdl2::AudioSubsystem;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use yaw_core::assets;
+
+/// simple sound player, samples are converted to the device format once and then queued
+pub(crate) struct Audio {
+    queue: AudioQueue<i16>,
+    samples: HashMap<PathBuf, Vec<i16>>,
+    /// skip playing anything
+    pub muted: bool,
+}
+
+impl Audio {
+    pub fn new(subsystem: &AudioSubsystem) -> anyhow::Result<Self> {
+        let queue = subsystem
+            .open_queue::<i16, _>(
+                None,
+                &AudioSpecDesired {
+                    freq: Some(44_100),
+                    channels: Some(2),
+                    samples: None,
+                },
+            )
+            .ah()?;
+        queue.resume();
+
+        Ok(Self {
+            queue,
+            samples: HashMap::new(),
+            muted: false,
+        })
+    }
+
+    /// load a wav file (cached) and queue it for playback
+    pub fn play(&mut self, path: &Path) -> anyhow::Result<()> {
+        if self.muted {
+            return Ok(());
+        }
+
+        if !self.samples.contains_key(path) {
+            log::info!("loading sound at {}", path.display());
+            let wav = AudioSpecWAV::load_wav(assets::resolve(path)).ah()?;
+            let spec = self.queue.spec();
+            let cvt = AudioCVT::new(
+                wav.format,
+                wav.channels,
+                wav.freq,
+                AudioFormat::s16_sys(),
+                spec.channels,
+                spec.freq,
+            )
+            .ah()?;
+            let data = cvt
+                .convert(wav.buffer().to_vec())
+                .chunks_exact(2)
+                .map(|bytes| i16::from_ne_bytes([bytes[0], bytes[1]]))
+                .collect();
+            self.samples.insert(path.into(), data);
+        }
+
+        self.queue.queue_audio(&self.samples[path]).ah()
+    }
+
+    /// drop everything that is still queued
+    pub fn stop(&mut self) {
+        self.queue.clear();
+    }
+}
@@ -0,0 +1,219 @@
+//! full screen map of the parts of a level that were seen, opened with tab
+//!
+//! unlike the minimap overlay it is drawn instead of the 3d view, so nothing is cast while
+//! it is open
+
+use crate::{HEIGHT, WIDTH};
+use glam::Vec2;
+use sdl2::keyboard::Keycode;
+use yaw_core::color::Color;
+use yaw_core::map::{Map, Tile, TILE_SIZE};
+use yaw_core::render::{Rect, Renderer};
+use yaw_core::visibility::Visibility;
+
+/// pixels per tile the map can be zoomed between, and by how much each key press zooms
+const MIN_SCALE: f32 = 2.;
+const MAX_SCALE: f32 = 64.;
+const ZOOM_STEP: f32 = 1.25;
+/// tiles the view moves per key press
+const PAN_STEP: f32 = 2.;
+
+const WALL_COLOR: Color = Color::RGB(0, 0xdd, 0);
+const DOOR_COLOR: Color = Color::RGB(0xdd, 0xaa, 0);
+const EXIT_COLOR: Color = Color::RGB(0xdd, 0, 0xdd);
+const WATER_COLOR: Color = Color::RGB(0x20, 0x50, 0xa0);
+const ITEM_COLOR: Color = Color::RGB(0xff, 0xdd, 0);
+const PLAYER_COLOR: Color = Color::WHITE;
+
+/// which tiles of a level were seen and how the automap looks at them
+#[derive(Default)]
+pub(crate) struct Automap {
+    explored: Vec<bool>,
+    width: usize,
+    /// tile in the middle of the screen
+    center: Vec2,
+    /// pixels per tile, none to fit the whole map on screen
+    scale: Option<f32>,
+}
+
+impl Automap {
+    /// nothing of `map` seen yet
+    pub fn new(map: &Map) -> Self {
+        Self {
+            explored: vec![false; map.main_tiles.len()],
+            width: map.width,
+            ..Self::default()
+        }
+    }
+
+    /// remember the tiles seen this frame
+    pub fn explore(&mut self, visibility: &Visibility) {
+        for (idx, explored) in self.explored.iter_mut().enumerate() {
+            *explored |= visibility.tile(idx % self.width, idx / self.width);
+        }
+    }
+
+    /// also remember the tile a position is on, for floors rays never hit
+    pub fn visit(&mut self, pos: Vec2) {
+        let (x, y) = ((pos.x / TILE_SIZE) as usize, (pos.y / TILE_SIZE) as usize);
+        if x < self.width {
+            if let Some(explored) = self.explored.get_mut((y * self.width) + x) {
+                *explored = true;
+            }
+        }
+    }
+
+    pub fn explored(&self, x: usize, y: usize) -> bool {
+        x < self.width && self.explored.get((y * self.width) + x) == Some(&true)
+    }
+
+    /// show the map around a position in pixels
+    pub fn center_on(&mut self, pos: Vec2) {
+        self.center = pos / TILE_SIZE;
+    }
+
+    /// pixels per tile, zoomed out far enough for all of the map by default
+    fn scale(&self, map: &Map) -> f32 {
+        self.scale.unwrap_or_else(|| {
+            (WIDTH as f32 / map.width as f32)
+                .min(HEIGHT as f32 / map.height as f32)
+                .clamp(MIN_SCALE, MAX_SCALE)
+        })
+    }
+
+    /// pan and zoom, returns whether the key did either
+    pub fn key(&mut self, key: Keycode, map: &Map) -> bool {
+        let pan = match key {
+            Keycode::Left | Keycode::A => Vec2::new(-PAN_STEP, 0.),
+            Keycode::Right | Keycode::D => Vec2::new(PAN_STEP, 0.),
+            Keycode::Up | Keycode::W => Vec2::new(0., -PAN_STEP),
+            Keycode::Down | Keycode::S => Vec2::new(0., PAN_STEP),
+            Keycode::Equals | Keycode::KpPlus => {
+                self.scale = Some((self.scale(map) * ZOOM_STEP).min(MAX_SCALE));
+                return true;
+            }
+            Keycode::Minus | Keycode::KpMinus => {
+                self.scale = Some((self.scale(map) / ZOOM_STEP).max(MIN_SCALE));
+                return true;
+            }
+            _ => return false,
+        };
+        self.center =
+            (self.center + pan).clamp(Vec2::ZERO, Vec2::new(map.width as f32, map.height as f32));
+        true
+    }
+
+    /// where a position in pixels of the map is on screen
+    fn to_screen(&self, map: &Map, pos: Vec2) -> Vec2 {
+        ((pos / TILE_SIZE) - self.center) * self.scale(map)
+            + Vec2::new(WIDTH as f32 / 2., HEIGHT as f32 / 2.)
+    }
+
+    /// draw the seen tiles, `items` as dots and `players` as arrows of their position and
+    /// direction
+    pub fn draw(
+        &self,
+        renderer: &mut dyn Renderer,
+        map: &Map,
+        items: &[Vec2],
+        players: &[(Vec2, f32)],
+    ) -> anyhow::Result<()> {
+        renderer.draw_rect(None, Color::BLACK)?;
+
+        let scale = self.scale(map);
+        let size = scale.ceil() as u32;
+        for (idx, tile) in map.main_tiles.iter().enumerate() {
+            let (x, y) = (idx % map.width, idx / map.width);
+            let Tile::Custom(id) = tile else {
+                continue;
+            };
+            if !self.explored(x, y) {
+                continue;
+            }
+
+            let custom = &map.custom_tiles[id];
+            let color = if custom.exit {
+                EXIT_COLOR
+            } else if custom.water {
+                WATER_COLOR
+            } else if custom.floor {
+                continue;
+            } else if custom.collidable {
+                WALL_COLOR
+            } else {
+                // walls players walk through are doors
+                DOOR_COLOR
+            };
+            let corner = self.to_screen(map, map.idx_to_vec(idx));
+            renderer.draw_rect(
+                Some(Rect::new(corner.x as i32, corner.y as i32, size, size)),
+                color,
+            )?;
+        }
+
+        let dot = (scale / 4.).max(2.) as u32;
+        for item in items {
+            let (x, y) = ((item.x / TILE_SIZE) as usize, (item.y / TILE_SIZE) as usize);
+            if !self.explored(x, y) {
+                continue;
+            }
+            let pos = self.to_screen(map, *item);
+            renderer.draw_rect(
+                Some(Rect::new(
+                    pos.x as i32 - (dot as i32 / 2),
+                    pos.y as i32 - (dot as i32 / 2),
+                    dot,
+                    dot,
+                )),
+                ITEM_COLOR,
+            )?;
+        }
+
+        // arrows a little bigger than a tile, so they stay visible zoomed out
+        let length = scale.max(8.);
+        for (pos, direction) in players {
+            let pos = self.to_screen(map, *pos);
+            let forward = Vec2::from_angle(*direction);
+            let tip = pos + (forward * length * 0.6);
+            let tail = pos - (forward * length * 0.4);
+            let point = |at: Vec2| (at.x as i32, at.y as i32);
+            renderer.draw_line(point(tail), point(tip), PLAYER_COLOR)?;
+            for side in [-1., 1.] {
+                let wing = tip - (forward * length * 0.4) + (forward.perp() * side * length * 0.3);
+                renderer.draw_line(point(wing), point(tip), PLAYER_COLOR)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn keys_pan_and_zoom_within_bounds() {
+        let map = Map::parse("!!!!MAIN\n#wall.png\n\n#*  \n#   ", Path::new("")).unwrap();
+        let mut automap = Automap::new(&map);
+        assert!(!automap.explored(1, 0));
+        automap.visit(Vec2::new(1.5, 0.5) * TILE_SIZE);
+        assert!(automap.explored(1, 0));
+        assert!(!automap.explored(9, 0));
+
+        // a tiny map is shown as big as it can be
+        assert_eq!(automap.scale(&map), MAX_SCALE);
+        assert!(automap.key(Keycode::Equals, &map));
+        assert_eq!(automap.scale(&map), MAX_SCALE);
+        assert!(automap.key(Keycode::Minus, &map));
+        assert!(automap.scale(&map) < MAX_SCALE);
+
+        automap.center_on(Vec2::ZERO);
+        assert!(automap.key(Keycode::Left, &map));
+        assert_eq!(automap.center, Vec2::ZERO);
+        assert!(automap.key(Keycode::Down, &map));
+        assert_eq!(automap.center, Vec2::new(0., 2.));
+        assert!(!automap.key(Keycode::Return, &map));
+    }
+}
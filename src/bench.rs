@@ -0,0 +1,50 @@
+use crate::game::{Game, GameState};
+use std::time::{Duration, Instant};
+
+/// render frames along a fixed camera path without any frame pacing and print timings
+pub(crate) fn run(game: &mut Game, frames: usize) -> anyhow::Result<()> {
+    log::info!("benchmarking {frames} frames");
+    game.game_state = GameState::Playing;
+
+    let mut cast_times = Vec::with_capacity(frames);
+    let mut draw_times = Vec::with_capacity(frames);
+    for frame in 0..frames {
+        game.bench_camera(frame as f32 / frames as f32)?;
+
+        let start = Instant::now();
+        game.cast_rays();
+        cast_times.push(start.elapsed());
+
+        let start = Instant::now();
+        game.playing_draw()?;
+        draw_times.push(start.elapsed());
+
+        game.renderer.present()?;
+    }
+
+    println!("{frames} frames");
+    report("cast_rays", &mut cast_times);
+    report("playing_draw", &mut draw_times);
+
+    Ok(())
+}
+
+/// print average and percentile times
+fn report(name: &str, times: &mut [Duration]) {
+    if times.is_empty() {
+        return;
+    }
+
+    times.sort_unstable();
+    let ms = |duration: Duration| duration.as_secs_f64() * 1_000.;
+    let percentile = |p: usize| ms(times[((times.len() - 1) * p) / 100]);
+    let avg = ms(times.iter().sum::<Duration>()) / times.len() as f64;
+
+    println!(
+        "{name:>12}: avg {avg:.3}ms, p50 {:.3}ms, p95 {:.3}ms, p99 {:.3}ms, max {:.3}ms",
+        percentile(50),
+        percentile(95),
+        percentile(99),
+        ms(times[times.len() - 1]),
+    );
+}
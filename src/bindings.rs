@@ -0,0 +1,90 @@
+use anyhow::Context;
+use sdl2::keyboard::Keycode;
+
+/// keys a player walks with
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Bindings {
+    pub forward: Keycode,
+    pub back: Keycode,
+    pub strafe_left: Keycode,
+    pub strafe_right: Keycode,
+    pub turn_left: Keycode,
+    pub turn_right: Keycode,
+    pub sprint: Keycode,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            forward: Keycode::W,
+            back: Keycode::S,
+            strafe_left: Keycode::A,
+            strafe_right: Keycode::D,
+            turn_left: Keycode::Left,
+            turn_right: Keycode::Right,
+            sprint: Keycode::LShift,
+        }
+    }
+}
+
+impl Bindings {
+    /// default keys of the second player in split-screen
+    pub fn second() -> Self {
+        Self {
+            forward: Keycode::I,
+            back: Keycode::K,
+            strafe_left: Keycode::J,
+            strafe_right: Keycode::L,
+            turn_left: Keycode::U,
+            turn_right: Keycode::O,
+            sprint: Keycode::RShift,
+        }
+    }
+
+    /// parse `forward,back,strafe_left,strafe_right,turn_left,turn_right,sprint`
+    pub fn parse(keys: &str) -> anyhow::Result<Self> {
+        let keys = keys
+            .split(',')
+            .map(parse_key)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let [forward, back, strafe_left, strafe_right, turn_left, turn_right, sprint] = keys[..]
+        else {
+            anyhow::bail!("expected 7 keys, got {}", keys.len());
+        };
+
+        Ok(Self {
+            forward,
+            back,
+            strafe_left,
+            strafe_right,
+            turn_left,
+            turn_right,
+            sprint,
+        })
+    }
+}
+
+/// key names as written on the command line and in scripts
+pub(crate) fn parse_key(name: &str) -> anyhow::Result<Keycode> {
+    let key = match name {
+        "return" | "enter" => Keycode::Return,
+        "escape" | "esc" => Keycode::Escape,
+        "backspace" => Keycode::Backspace,
+        "space" => Keycode::Space,
+        "tab" => Keycode::Tab,
+        "shift" | "lshift" => Keycode::LShift,
+        "rshift" => Keycode::RShift,
+        "up" => Keycode::Up,
+        "down" => Keycode::Down,
+        "left" => Keycode::Left,
+        "right" => Keycode::Right,
+        "f12" => Keycode::F12,
+        // single printable keys share their codes with ascii
+        _ if name.len() == 1 && name.is_ascii() => {
+            Keycode::from_i32(name.to_ascii_lowercase().as_bytes()[0] as i32).context("no key")?
+        }
+        _ => anyhow::bail!("unknown key: {name}"),
+    };
+
+    Ok(key)
+}
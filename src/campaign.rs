@@ -0,0 +1,281 @@
+use anyhow::Context;
+use std::collections::HashMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use yaw_core::assets;
+
+/// map played as part of a campaign
+#[derive(Clone, PartialEq)]
+pub(crate) struct Level {
+    pub map: PathBuf,
+    /// shown before the level in place of the map's own briefing
+    pub briefing: Option<PathBuf>,
+    pub music: Option<PathBuf>,
+}
+
+/// text screen shown between levels
+#[derive(Clone, PartialEq)]
+pub(crate) struct Intermission {
+    pub text: String,
+    pub image: Option<PathBuf>,
+    pub music: Option<PathBuf>,
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) enum Step {
+    Level(Level),
+    Intermission(Intermission),
+}
+
+/// an ordered set of maps and text screens played one after another
+#[derive(Clone, PartialEq)]
+pub(crate) struct Campaign {
+    /// file it was loaded from, which progress is saved by
+    pub path: PathBuf,
+    pub name: String,
+    pub steps: Vec<Step>,
+    /// health the player starts the campaign with
+    pub health: u8,
+    /// keep health between levels instead of resetting it
+    pub carry_health: bool,
+    /// keep ammo between levels of the hub, campaigns without one always do
+    pub carry_ammo: bool,
+    /// map levels are entered from and come back to in any order, instead of playing them
+    /// one after another
+    pub hub: Option<PathBuf>,
+    /// campaign that has to be finished before this one can be played
+    pub unlock: Option<PathBuf>,
+}
+
+impl Campaign {
+    pub fn load(name: PathBuf) -> anyhow::Result<Self> {
+        log::info!("loading campaign at {}", name.display());
+        let file = read_to_string(assets::resolve(&name))?;
+        let prefix: PathBuf = name.parent().map(Into::into).unwrap_or_default();
+        let mut lines = file.lines();
+        let mut this = Self {
+            name: name
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: name.clone(),
+            steps: vec![],
+            health: 255,
+            carry_health: false,
+            carry_ammo: false,
+            hub: None,
+            unlock: None,
+        };
+
+        while let Some(line) = lines.by_ref().next() {
+            match line {
+                "!!!!META" => this.parse_meta(&mut lines, &prefix)?,
+                "!!!!LEVELS" => this.parse_levels(&mut lines, &prefix)?,
+                "" => {}
+                other => anyhow::bail!("unrecognized directive: {other}"),
+            }
+        }
+
+        if this.levels() == 0 {
+            anyhow::bail!("campaign {} has no levels", this.name);
+        }
+        // nothing comes between levels played in any order
+        if this.hub.is_some() && this.levels() != this.steps.len() {
+            anyhow::bail!("campaign {} has a hub and text screens", this.name);
+        }
+
+        Ok(this)
+    }
+
+    /// number of playable levels
+    pub fn levels(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| matches!(step, Step::Level(_)))
+            .count()
+    }
+
+    /// step of the level playing a map
+    pub fn step_of(&self, map: &Path) -> Option<usize> {
+        self.steps
+            .iter()
+            .position(|step| matches!(step, Step::Level(level) if level.map == map))
+    }
+
+    /// load every campaign in a data directory, sorted by file name, campaigns that cannot be
+    /// read are left out
+    pub fn load_all(dir: &Path) -> anyhow::Result<Vec<Self>> {
+        let mut paths = read_dir(assets::resolve(dir))?
+            .map(|entry| entry.map(|entry| dir.join(entry.file_name())))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "yawc"));
+        paths.sort();
+
+        Ok(paths
+            .into_iter()
+            .filter_map(|path| {
+                Self::load(path.clone())
+                    .inspect_err(|err| log::warn!("could not read {}: {err}", path.display()))
+                    .ok()
+            })
+            .collect())
+    }
+
+    fn parse_meta<'lines>(
+        &mut self,
+        lines: impl Iterator<Item = &'lines str>,
+        prefix: &Path,
+    ) -> anyhow::Result<()> {
+        for line in lines {
+            if line.is_empty() {
+                break;
+            }
+
+            match line.split_once('=') {
+                Some(("name", name)) => self.name = name.into(),
+                Some(("health", health)) => self.health = health.parse()?,
+                Some(("unlock", campaign)) => self.unlock = Some(prefix.join(campaign)),
+                Some(("hub", map)) => self.hub = Some(prefix.join(map)),
+                Some(("carry", carry)) => {
+                    for stat in carry.split(',') {
+                        match stat {
+                            "health" => self.carry_health = true,
+                            "ammo" => self.carry_ammo = true,
+                            other => anyhow::bail!("cannot carry over {other}"),
+                        }
+                    }
+                }
+                _ => anyhow::bail!("unrecognized campaign meta: {line}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_levels<'lines>(
+        &mut self,
+        lines: impl Iterator<Item = &'lines str>,
+        prefix: &Path,
+    ) -> anyhow::Result<()> {
+        for line in lines {
+            if line.is_empty() {
+                break;
+            }
+
+            // text screens look like `text=story.txt,image=bg.png,music=theme.wav`
+            if line.starts_with("text=") {
+                let params = parse_params(line).context("incorrectly formatted text screen")?;
+                let text = prefix.join(params["text"]);
+
+                self.steps.push(Step::Intermission(Intermission {
+                    text: read_to_string(assets::resolve(&text))
+                        .with_context(|| format!("could not read {}", text.display()))?,
+                    image: params.get("image").map(|image| prefix.join(image)),
+                    music: params.get("music").map(|music| prefix.join(music)),
+                }));
+            } else {
+                // levels look like `cellar.yaw,briefing=cellar.txt,music=theme.wav`
+                let (map, params) = line.split_once(',').unwrap_or((line, ""));
+                let params = parse_params(params)
+                    .with_context(|| format!("incorrectly formatted level: {line}"))?;
+                if let Some(param) = params
+                    .keys()
+                    .find(|param| !["briefing", "music"].contains(param))
+                {
+                    anyhow::bail!("unrecognized level param: {param}");
+                }
+
+                self.steps.push(Step::Level(Level {
+                    map: prefix.join(map),
+                    briefing: params.get("briefing").map(|briefing| prefix.join(briefing)),
+                    music: params.get("music").map(|music| prefix.join(music)),
+                }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `key=value` params split by commas
+fn parse_params(text: &str) -> Option<HashMap<&str, &str>> {
+    text.split(',')
+        .filter(|param| !param.is_empty())
+        .map(|param| param.split_once('='))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn levels_have_briefings_and_music() {
+        let temp = TempDir::new("campaign").unwrap();
+        let dir = temp.path();
+        let path = dir.join("campaign.yawc");
+        std::fs::write(
+            &path,
+            "!!!!META\nname=Test\nunlock=campaign.yawc\n\n\
+             !!!!LEVELS\nmap.yaw\ncellar.yaw,briefing=cellar.txt,music=theme.wav\n",
+        )
+        .unwrap();
+        let campaign = Campaign::load(path.clone()).unwrap();
+        assert_eq!(campaign.path, path);
+        assert_eq!(campaign.name, "Test");
+        assert_eq!(campaign.unlock, Some(dir.join("campaign.yawc")));
+        assert!(matches!(
+            &campaign.steps[..],
+            [Step::Level(first), Step::Level(second)]
+                if first.map == dir.join("map.yaw")
+                    && first.briefing.is_none()
+                    && second.briefing == Some(dir.join("cellar.txt"))
+                    && second.music == Some(dir.join("theme.wav"))
+        ));
+
+        std::fs::write(&path, "!!!!LEVELS\nmap.yaw,briefing\n").unwrap();
+        assert!(Campaign::load(path.clone()).is_err());
+        std::fs::write(&path, "!!!!LEVELS\nmap.yaw,speed=2\n").unwrap();
+        assert!(Campaign::load(path.clone()).is_err());
+    }
+
+    #[test]
+    fn hubs_lead_to_levels_and_keep_what_players_carry() {
+        let temp = TempDir::new("hub").unwrap();
+        let dir = temp.path();
+        let path = dir.join("campaign.yawc");
+        std::fs::write(
+            &path,
+            "!!!!META\nhub=hub.yaw\ncarry=health,ammo\n\n!!!!LEVELS\nmap.yaw\ncellar.yaw\n",
+        )
+        .unwrap();
+        let campaign = Campaign::load(path.clone()).unwrap();
+        assert_eq!(campaign.hub, Some(dir.join("hub.yaw")));
+        assert!(campaign.carry_health && campaign.carry_ammo);
+        assert_eq!(campaign.step_of(&dir.join("cellar.yaw")), Some(1));
+        assert_eq!(campaign.step_of(&dir.join("hub.yaw")), None);
+
+        std::fs::write(dir.join("story.txt"), "Meanwhile").unwrap();
+        std::fs::write(
+            &path,
+            "!!!!META\nhub=hub.yaw\n\n!!!!LEVELS\nmap.yaw\ntext=story.txt\n",
+        )
+        .unwrap();
+        assert!(Campaign::load(path.clone()).is_err());
+        std::fs::write(&path, "!!!!META\ncarry=keys\n\n!!!!LEVELS\nmap.yaw\n").unwrap();
+        assert!(Campaign::load(path).is_err());
+    }
+
+    #[test]
+    fn broken_campaigns_are_left_out() {
+        let temp = TempDir::new("campaigns").unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("broken.yawc"), "!!!!LEVELS\nmap.yaw,speed=2\n").unwrap();
+        std::fs::write(dir.join("good.yawc"), "!!!!LEVELS\nmap.yaw\n").unwrap();
+
+        let campaigns = Campaign::load_all(dir).unwrap();
+        assert_eq!(campaigns.len(), 1);
+        assert_eq!(campaigns[0].path, dir.join("good.yawc"));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::bindings::Bindings;
+#[cfg(not(target_os = "emscripten"))]
+use crate::net::{Role, DEFAULT_PORT};
+use crate::pacing::FrameRate;
+use std::path::PathBuf;
+use std::str::FromStr;
+use yaw_core::map::{PlayerMeta, Transform};
+
+/// frames rendered by `--bench` when no count is given
+const BENCH_FRAMES: usize = 1_000;
+
+/// how frames are drawn
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub(crate) enum Backend {
+    /// copy texture strips with the sdl renderer
+    #[default]
+    Sdl,
+    /// draw pixels into a framebuffer on the cpu and upload it once per frame
+    Software,
+    /// print characters to the terminal instead of opening a window
+    Tty,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sdl" => Ok(Self::Sdl),
+            "software" => Ok(Self::Software),
+            "tty" => Ok(Self::Tty),
+            _ => anyhow::bail!("unknown backend: {s}"),
+        }
+    }
+}
+
+/// command line options
+#[derive(Default)]
+pub(crate) struct Args {
+    /// transformations applied to the map after loading, in order
+    pub transforms: Vec<Transform>,
+    /// seed for shuffling textures and tinting tiles
+    pub randomize: Option<u64>,
+    /// render this many frames as fast as possible and print timings
+    pub bench: Option<usize>,
+    /// renderer used for every frame
+    pub backend: Backend,
+    /// how often frames are shown
+    pub frame_rate: FrameRate,
+    /// play back this script without a window instead of playing
+    pub headless: Option<PathBuf>,
+    /// keys of the first player
+    pub keys: Bindings,
+    /// how players turn and step sideways, on maps that do not say
+    pub movement: PlayerMeta,
+    /// play split-screen with a second player using these keys
+    pub split: Option<Bindings>,
+    /// play co-op with another instance over the network
+    #[cfg(not(target_os = "emscripten"))]
+    pub net: Option<Role>,
+    /// write a `.yaw` map to a binary map or archive and exit
+    pub compile: Option<Compile>,
+    /// check a map for mistakes, print what was found and exit
+    pub lint: Option<PathBuf>,
+    /// write a top-down picture of a map to a png and exit
+    pub thumbnail: Option<Thumbnail>,
+    /// code of the language to start in, like `de`
+    pub lang: Option<String>,
+    /// draw text in this ttf font instead of the one built in
+    pub font: Option<PathBuf>,
+    /// how text, menus and the hud look, instead of `theme.toml` in the data directories
+    pub theme: Option<PathBuf>,
+    /// also write logs to this file, as one json object per line
+    pub log_file: Option<PathBuf>,
+}
+
+/// arguments of `yaw compile <map.yaw> <map.yawb> [--embed]`
+/// and `yaw pack <map.yaw> <map.yawpak>`
+pub(crate) struct Compile {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// store the textures of the map in the output too
+    pub embed: bool,
+    /// write an archive of the map file and its textures instead
+    pub pack: bool,
+}
+
+impl Compile {
+    fn parse(args: impl Iterator<Item = String>, pack: bool) -> anyhow::Result<Self> {
+        let mut paths = Vec::new();
+        let mut embed = false;
+        for arg in args {
+            match arg.as_str() {
+                "--embed" if !pack => embed = true,
+                _ if arg.starts_with("--") => anyhow::bail!("unrecognized argument: {arg}"),
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+
+        match <[PathBuf; 2]>::try_from(paths) {
+            Ok([from, to]) => Ok(Self {
+                from,
+                to,
+                embed,
+                pack,
+            }),
+            Err(_) if pack => anyhow::bail!("usage: yaw pack <map.yaw> <map.yawpak>"),
+            Err(_) => anyhow::bail!("usage: yaw compile <map.yaw> <map.yawb> [--embed]"),
+        }
+    }
+}
+
+/// arguments of `yaw --thumbnail <map> <out.png>`
+pub(crate) struct Thumbnail {
+    pub map: PathBuf,
+    pub to: PathBuf,
+}
+
+impl Thumbnail {
+    /// the two paths after `--thumbnail`, wherever it is among the other options
+    fn parse(args: &mut impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        match (args.next(), args.next()) {
+            (Some(map), Some(to)) if !map.starts_with("--") && !to.starts_with("--") => Ok(Self {
+                map: map.into(),
+                to: to.into(),
+            }),
+            _ => anyhow::bail!("usage: yaw --thumbnail <map> <out.png>"),
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> anyhow::Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    /// [`Args::parse`] from arguments without the program name
+    fn parse_from(args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut this = Self::default();
+
+        let mut args = args.peekable();
+        match args.peek().map(String::as_str) {
+            Some("compile") => {
+                this.compile = Some(Compile::parse(args.skip(1), false)?);
+                return Ok(this);
+            }
+            Some("pack") => {
+                this.compile = Some(Compile::parse(args.skip(1), true)?);
+                return Ok(this);
+            }
+            Some("lint") => {
+                let mut paths = args.skip(1);
+                match (paths.next(), paths.next()) {
+                    (Some(path), None) if !path.starts_with("--") => this.lint = Some(path.into()),
+                    _ => anyhow::bail!("usage: yaw lint <map.yaw>"),
+                }
+                return Ok(this);
+            }
+            _ => {}
+        }
+
+        while let Some(arg) = args.next() {
+            let (name, value) = arg
+                .split_once('=')
+                .map_or((arg.as_str(), None), |(name, value)| (name, Some(value)));
+
+            match (name, value) {
+                ("--transform", Some(transforms)) => {
+                    for transform in transforms.split(',') {
+                        this.transforms.push(transform.parse()?);
+                    }
+                }
+                ("--randomize", Some(seed)) => this.randomize = Some(seed.parse()?),
+                ("--bench", frames) => {
+                    this.bench = Some(frames.map(str::parse).transpose()?.unwrap_or(BENCH_FRAMES))
+                }
+                ("--backend", Some(backend)) => this.backend = backend.parse()?,
+                ("--fps", Some(rate)) => this.frame_rate = rate.parse()?,
+                ("--headless", Some(script)) => this.headless = Some(script.into()),
+                ("--keys", Some(keys)) => this.keys = Bindings::parse(keys)?,
+                ("--turn-speed", Some(speed)) => {
+                    this.movement.turn_speed = Some(parse_speed(speed)?)
+                }
+                ("--strafe-speed", Some(speed)) => {
+                    this.movement.strafe_speed = Some(parse_speed(speed)?)
+                }
+                ("--lang", Some(code)) => this.lang = Some(code.into()),
+                ("--font", Some(path)) => this.font = Some(path.into()),
+                ("--theme", Some(path)) => this.theme = Some(path.into()),
+                ("--log-file", Some(path)) => this.log_file = Some(path.into()),
+                ("--thumbnail", None) => this.thumbnail = Some(Thumbnail::parse(&mut args)?),
+                ("--split", keys) => {
+                    this.split = Some(
+                        keys.map(Bindings::parse)
+                            .transpose()?
+                            .unwrap_or(Bindings::second()),
+                    )
+                }
+                #[cfg(not(target_os = "emscripten"))]
+                ("--host", port) => {
+                    this.net = Some(Role::Host(
+                        port.map(str::parse).transpose()?.unwrap_or(DEFAULT_PORT),
+                    ))
+                }
+                #[cfg(not(target_os = "emscripten"))]
+                ("--connect", Some(addr)) => this.net = Some(Role::Connect(addr.into())),
+                _ => anyhow::bail!("unrecognized argument: {arg}"),
+            }
+        }
+
+        Ok(this)
+    }
+}
+
+/// a speed per second, which has to move players at all
+pub(crate) fn parse_speed(s: &str) -> anyhow::Result<f32> {
+    match s.parse() {
+        Ok(speed) if f32::is_finite(speed) && speed > 0. => Ok(speed),
+        _ => anyhow::bail!("speed must be a positive number: {s}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> anyhow::Result<Args> {
+        Args::parse_from(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn thumbnails_are_asked_for_anywhere() {
+        for args in [
+            "--thumbnail map/map.yaw map.png",
+            "--fps=60 --thumbnail map/map.yaw map.png --transform=flip_h",
+        ] {
+            let thumbnail = parse(args).unwrap().thumbnail.unwrap();
+            assert_eq!(thumbnail.map, PathBuf::from("map/map.yaw"));
+            assert_eq!(thumbnail.to, PathBuf::from("map.png"));
+        }
+
+        assert!(parse("--fps=60 --thumbnail map/map.yaw").is_err());
+        assert!(parse("--thumbnail map/map.yaw --fps=60").is_err());
+        assert!(parse("--fps=60").unwrap().thumbnail.is_none());
+    }
+}
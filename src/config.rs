@@ -0,0 +1,138 @@
+//! settings kept in a file between runs, one `key = value` line each
+
+use crate::cli::parse_speed;
+use crate::theme::Palette;
+use anyhow::Context;
+use std::path::PathBuf;
+
+pub(crate) struct Config {
+    path: PathBuf,
+    /// bigger text and hud colors that stand out more
+    pub high_contrast: bool,
+    /// colors of the hud and minimap
+    pub palette: Palette,
+    /// fog and flickering lights, off for players bothered by them
+    pub effects: bool,
+    /// keep the view from wobbling, like in water
+    pub reduce_motion: bool,
+    /// ttf font all text is drawn in instead of the one built in, read at startup
+    pub font: Option<PathBuf>,
+    /// radians turned per second, for maps and command lines that leave it be
+    pub turn_speed: Option<f32>,
+    /// pixels walked sideways per second, for maps and command lines that leave it be
+    pub strafe_speed: Option<f32>,
+}
+
+impl Config {
+    /// the default settings, saved to `path` once changed
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            high_contrast: false,
+            palette: Palette::default(),
+            effects: true,
+            reduce_motion: false,
+            font: None,
+            turn_speed: None,
+            strafe_speed: None,
+        }
+    }
+
+    /// read the settings saved at `path`, the default ones when there is no file yet
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let mut config = Self::new(path);
+        match std::fs::read_to_string(&config.path) {
+            Ok(text) => {
+                for line in text.lines().filter(|line| !line.is_empty()) {
+                    let (key, value) = line
+                        .split_once('=')
+                        .with_context(|| format!("invalid setting: {line}"))?;
+                    let value = value.trim();
+                    match key.trim() {
+                        "high_contrast" => config.high_contrast = value.parse()?,
+                        "palette" => {
+                            config.palette = Palette::from_name(value)
+                                .with_context(|| format!("unknown palette: {value}"))?;
+                        }
+                        "effects" => config.effects = value.parse()?,
+                        "reduce_motion" => config.reduce_motion = value.parse()?,
+                        "font" => config.font = (!value.is_empty()).then(|| value.into()),
+                        "turn_speed" => config.turn_speed = Some(parse_speed(value)?),
+                        "strafe_speed" => config.strafe_speed = Some(parse_speed(value)?),
+                        // might be from a newer version, keep going without it
+                        key => log::warn!("unknown setting: {key}"),
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(config)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let mut text = format!(
+            "high_contrast = {}\npalette = {}\neffects = {}\nreduce_motion = {}\n",
+            self.high_contrast,
+            self.palette.name(),
+            self.effects,
+            self.reduce_motion
+        );
+        if let Some(font) = &self.font {
+            text += &format!("font = {}\n", font.display());
+        }
+        if let Some(speed) = self.turn_speed {
+            text += &format!("turn_speed = {speed}\n");
+        }
+        if let Some(speed) = self.strafe_speed {
+            text += &format!("strafe_speed = {speed}\n");
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, text)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_kept_between_runs() {
+        let path = std::env::temp_dir().join(format!("yaw-config-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut config = Config::load(path.clone()).unwrap();
+        assert!(config.effects && !config.high_contrast && !config.reduce_motion);
+        config.high_contrast = true;
+        config.effects = false;
+        config.palette = Palette::Tritanopia;
+        config.save().unwrap();
+
+        let mut config = Config::load(path.clone()).unwrap();
+        assert!(config.high_contrast && !config.effects && !config.reduce_motion);
+        assert_eq!(config.palette, Palette::Tritanopia);
+        assert_eq!(config.font, None);
+        assert_eq!((config.turn_speed, config.strafe_speed), (None, None));
+        config.font = Some("fonts/my font.ttf".into());
+        config.turn_speed = Some(2.5);
+        config.strafe_speed = Some(90.);
+        config.save().unwrap();
+        let config = Config::load(path.clone()).unwrap();
+        assert_eq!(config.font, Some("fonts/my font.ttf".into()));
+        assert_eq!(
+            (config.turn_speed, config.strafe_speed),
+            (Some(2.5), Some(90.))
+        );
+
+        std::fs::write(&path, "effects = maybe\n").unwrap();
+        assert!(Config::load(path.clone()).is_err());
+        std::fs::write(&path, "turn_speed = -1\n").unwrap();
+        assert!(Config::load(path.clone()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
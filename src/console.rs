@@ -0,0 +1,211 @@
+//! developer console toggled with backquote, for moving around and loading maps while debugging
+
+use crate::textfield::TextField;
+use crate::theme::HudTheme;
+use sdl2::keyboard::Keycode;
+use std::str::FromStr;
+use yaw_core::color::Color;
+use yaw_core::render::{Rect, Renderer};
+
+/// lines of output kept around
+const HISTORY: usize = 12;
+/// longest line that can be typed
+const MAX_INPUT: usize = 100;
+
+pub(crate) const HELP: &str =
+    "commands: tp <x> <y>, noclip, freecam, give health <n>, map <name> [spawn|random], fov <degrees>, clear, help";
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Command {
+    /// move the player to the center of a tile
+    Teleport(usize, usize),
+    Noclip,
+    /// detach the view from the player
+    FreeCamera,
+    GiveHealth(u8),
+    /// load a map from the map directory by name, entering it at a spawn if one is given
+    Map(String, Option<Entrance>),
+    Fov(f32),
+    Clear,
+    Help,
+}
+
+/// which spawn of a map to enter it at
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Entrance {
+    Spawn(char),
+    Random,
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        Ok(match words[..] {
+            ["tp", x, y] => Command::Teleport(x.parse()?, y.parse()?),
+            ["noclip"] => Command::Noclip,
+            ["freecam"] => Command::FreeCamera,
+            ["give", "health", amount] => Command::GiveHealth(amount.parse()?),
+            ["map", name] => Command::Map(name.into(), None),
+            ["map", name, "random"] => Command::Map(name.into(), Some(Entrance::Random)),
+            ["map", name, id] => {
+                let mut chars = id.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(id), None) => Command::Map(name.into(), Some(Entrance::Spawn(id))),
+                    _ => anyhow::bail!("spawn ids are one character: {id}"),
+                }
+            }
+            ["fov", degrees] => Command::Fov(degrees.parse()?),
+            ["clear"] => Command::Clear,
+            ["help"] => Command::Help,
+            _ => anyhow::bail!("unknown command: {s}"),
+        })
+    }
+}
+
+pub(crate) struct Console {
+    pub open: bool,
+    input: TextField,
+    history: Vec<String>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self {
+            open: false,
+            // the key that opened the console also arrives as text
+            input: TextField::new(MAX_INPUT, |ch| ch != '`' && !ch.is_control()),
+            history: Vec::new(),
+        }
+    }
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.input.clear();
+    }
+
+    /// add typed text to the input line
+    pub fn type_text(&mut self, text: &str) {
+        self.input.type_text(text);
+    }
+
+    /// edit the input line, returns it once submitted
+    pub fn key(&mut self, key: Keycode) -> Option<String> {
+        if self.input.key(key) {
+            return None;
+        }
+
+        match key {
+            Keycode::Escape => self.toggle(),
+            Keycode::Return if !self.input.text().trim().is_empty() => {
+                let line = self.input.take();
+                self.print(format!("> {line}"));
+                return Some(line);
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.history.push(line.into());
+        if self.history.len() > HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// draw history and input over the top of the screen, long lines wrap and take more room
+    pub fn draw(
+        &self,
+        renderer: &mut dyn Renderer,
+        width: u32,
+        theme: &HudTheme,
+    ) -> anyhow::Result<()> {
+        let style = theme.body().wrapped(width.saturating_sub(16));
+        let line_height = 20;
+        let heights = self
+            .history
+            .iter()
+            .map(|line| {
+                let (_, height) = renderer.measure_text(line, &style)?;
+                Ok(line_height.max(height as i32))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let input_y = 4 + (HISTORY as i32 * line_height).max(heights.iter().sum());
+        renderer.draw_rect(
+            Some(Rect::new(0, 0, width, (input_y + line_height + 4) as u32)),
+            Color::RGBA(0, 0, 0, 0xCC),
+        )?;
+
+        let mut y = 4;
+        for (line, height) in self.history.iter().zip(heights) {
+            renderer.draw_text(line, &style, 8, y)?;
+            y += height;
+        }
+        renderer.draw_text(
+            &format!("] {}", self.input.with_cursor('_')),
+            &style,
+            8,
+            input_y,
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            "tp 3 4".parse::<Command>().unwrap(),
+            Command::Teleport(3, 4)
+        );
+        assert_eq!(
+            " give  health 50 ".parse::<Command>().unwrap(),
+            Command::GiveHealth(50)
+        );
+        assert_eq!(
+            "map cellar".parse::<Command>().unwrap(),
+            Command::Map("cellar".into(), None)
+        );
+        assert_eq!(
+            "map hub 2".parse::<Command>().unwrap(),
+            Command::Map("hub".into(), Some(Entrance::Spawn('2')))
+        );
+        assert_eq!(
+            "map hub random".parse::<Command>().unwrap(),
+            Command::Map("hub".into(), Some(Entrance::Random))
+        );
+        assert!("map hub 12".parse::<Command>().is_err());
+        assert!("tp 3".parse::<Command>().is_err());
+        assert!("fov wide".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn submits_typed_lines() {
+        let mut console = Console::default();
+        console.toggle();
+        console.type_text("`noclipp");
+        console.key(Keycode::Backspace);
+        assert_eq!(console.key(Keycode::Return), Some("noclip".into()));
+        assert_eq!(console.history, ["> noclip"]);
+        // nothing to submit
+        assert_eq!(console.key(Keycode::Return), None);
+
+        for idx in 0..HISTORY * 2 {
+            console.print(idx.to_string());
+        }
+        assert_eq!(console.history.len(), HISTORY);
+    }
+}
@@ -0,0 +1,139 @@
+//! demos played after a while without input on the menu
+//!
+//! a demo is a script like the ones played back by `--headless`, starting on the menu with the
+//! default map loaded, `type` and `expect` lines are skipped, `modifiers` lines pick the ones
+//! the demo plays with and `state` lines give entities state in place of the saved one
+
+use crate::entity_state::EntityStates;
+use crate::harness::{self, Action};
+use crate::modifiers::Modifiers;
+use anyhow::Context;
+use sdl2::keyboard::Keycode;
+use std::collections::HashSet;
+use std::path::Path;
+use yaw_core::assets;
+
+/// where the demo is looked up in the data directories
+const PATH: &str = "map/demo.txt";
+
+/// input of one tick
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct Tick {
+    /// pressed at the start of the tick
+    pub pressed: Vec<Keycode>,
+    pub held: HashSet<Keycode>,
+    /// released at the end of the tick
+    pub released: Vec<Keycode>,
+}
+
+pub(crate) struct Demo {
+    ticks: Vec<Tick>,
+    /// ticks played so far
+    played: usize,
+    /// entity state the levels of the demo start with, never saved
+    pub states: EntityStates,
+    /// played with in place of the ones picked on the modifiers screen
+    pub modifiers: Modifiers,
+}
+
+impl Demo {
+    /// the demo in the data directories, none if there is none
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        let path = assets::resolve(Path::new(PATH));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let script = std::fs::read_to_string(&path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let actions = harness::parse_script(&script)
+            .with_context(|| format!("could not parse {}", path.display()))?;
+        Ok(Some(Self::new(&actions)))
+    }
+
+    pub fn new(actions: &[Action]) -> Self {
+        let mut ticks = Vec::new();
+        let mut states = EntityStates::default();
+        let mut modifiers = Modifiers::default();
+        for action in actions {
+            match action {
+                Action::Tap(key) => ticks.push(Tick {
+                    pressed: vec![*key],
+                    held: HashSet::from([*key]),
+                    released: vec![*key],
+                }),
+                Action::Hold(keys, count) => {
+                    let start = ticks.len();
+                    ticks.extend((0..*count).map(|_| Tick {
+                        held: keys.iter().copied().collect(),
+                        ..Tick::default()
+                    }));
+                    // keys held for no ticks at all are still pressed and released
+                    if ticks.len() == start {
+                        ticks.push(Tick::default());
+                    }
+                    ticks[start].pressed = keys.clone();
+                    ticks.last_mut().unwrap().released = keys.clone();
+                }
+                Action::Wait(count) => ticks.extend((0..*count).map(|_| Tick::default())),
+                Action::Type(_) | Action::Expect(_) => {}
+                Action::Modifiers(picked) => modifiers = *picked,
+                Action::State {
+                    level,
+                    at,
+                    key,
+                    value,
+                } => states.set(level, *at, key, value),
+            }
+        }
+
+        Self {
+            ticks,
+            played: 0,
+            states,
+            modifiers,
+        }
+    }
+
+    /// input of the next tick, none once the demo is over
+    pub fn next(&mut self) -> Option<&Tick> {
+        let tick = self.ticks.get(self.played)?;
+        self.played += 1;
+        Some(tick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_become_input_per_tick() {
+        let actions = harness::parse_script(
+            "modifiers mirrored\ntap return\nwait 2\nhold w+d 2\nexpect state playing\n\
+             state map/map.yaw 3 4 said hi there",
+        )
+        .unwrap();
+        let mut demo = Demo::new(&actions);
+        assert_eq!(demo.states.get("map/map.yaw")[&(3, 4)]["said"], "hi there");
+        assert!(demo.modifiers.mirrored && !demo.modifiers.fast_enemies);
+
+        let tap = demo.next().unwrap();
+        assert_eq!(tap.pressed, [Keycode::Return]);
+        assert_eq!(tap.released, [Keycode::Return]);
+        assert_eq!(demo.next(), Some(&Tick::default()));
+        assert_eq!(demo.next(), Some(&Tick::default()));
+
+        let held = HashSet::from([Keycode::W, Keycode::D]);
+        let first = demo.next().unwrap();
+        assert_eq!(
+            (&first.pressed[..], &first.held),
+            (&[Keycode::W, Keycode::D][..], &held)
+        );
+        assert!(first.released.is_empty());
+        let last = demo.next().unwrap();
+        assert!(last.pressed.is_empty());
+        assert_eq!(last.released, [Keycode::W, Keycode::D]);
+
+        assert_eq!(demo.next(), None);
+    }
+}
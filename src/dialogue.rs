@@ -0,0 +1,370 @@
+//! what friendly characters say, read from text files next to maps and shown a page at a time
+//!
+//! pages are separated by empty lines, `[name]` lines start another part and the talk starts
+//! at the first one. `> answer -> name` lines after the last page of a part are answers to
+//! pick from, going on to that part, or ending the talk without `-> name`:
+//!
+//! ```text
+//! Evening. Lost?
+//!
+//! The cellar door is stuck again.
+//! > Can I help? -> help
+//! > Bye
+//!
+//! [help]
+//! There is a lever behind the barrels.
+//! ```
+
+use crate::theme::HudTheme;
+use crate::{HEIGHT, WIDTH};
+use anyhow::Context;
+use sdl2::keyboard::Keycode;
+use std::path::Path;
+use yaw_core::assets;
+use yaw_core::render::{renderer_lines, Rect, Renderer, TextStyle};
+
+/// letters that appear each tick as a page is typed out
+const TYPE_SPEED: usize = 2;
+/// height of the box text is shown in, at the bottom of the screen
+const BOX_HEIGHT: i32 = 176;
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Dialogue {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+struct Part {
+    name: String,
+    pages: Vec<String>,
+    choices: Vec<Choice>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Choice {
+    text: String,
+    /// part it goes on to, the talk ends without one
+    next: Option<usize>,
+}
+
+impl Dialogue {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes =
+            assets::read(path).with_context(|| format!("could not read {}", path.display()))?;
+        Self::parse(&String::from_utf8_lossy(&bytes))
+            .with_context(|| format!("could not parse {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut parts = vec![Part::default()];
+        // answers by the name of the part they go on to, until every part is known
+        let mut targets = Vec::new();
+        let mut page = String::new();
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            let current = parts.len() - 1;
+            let part = &mut parts[current];
+            let ends_page = line.is_empty() || line.starts_with('[') || line.starts_with('>');
+            if ends_page && !page.is_empty() {
+                part.pages.push(std::mem::take(&mut page));
+            }
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                parts.push(Part {
+                    name: name.trim().into(),
+                    ..Part::default()
+                });
+            } else if let Some(choice) = line.strip_prefix('>') {
+                let (text, next) = match choice.rsplit_once("->") {
+                    Some((text, next)) => (text, Some(next.trim())),
+                    None => (choice, None),
+                };
+                targets.push((current, next.map(String::from), idx + 1));
+                part.choices.push(Choice {
+                    text: text.trim().into(),
+                    next: None,
+                });
+            } else if !line.is_empty() {
+                anyhow::ensure!(
+                    part.choices.is_empty(),
+                    "line {}: text after the answers of a part",
+                    idx + 1
+                );
+                if !page.is_empty() {
+                    page.push('\n');
+                }
+                page += line.trim_start();
+            }
+        }
+        if !page.is_empty() {
+            parts.last_mut().unwrap().pages.push(page);
+        }
+
+        // files starting with a part name have nothing before it
+        if parts.len() > 1 && parts[0] == Part::default() {
+            targets.iter_mut().for_each(|(part, ..)| *part -= 1);
+            parts.remove(0);
+        }
+        for part in &parts {
+            anyhow::ensure!(!part.pages.is_empty(), "part {:?} has no text", part.name);
+            anyhow::ensure!(
+                parts.iter().filter(|other| other.name == part.name).count() == 1,
+                "more than one part is called {:?}",
+                part.name
+            );
+        }
+
+        // answers of each part are in order, so they are filled in the same order
+        let mut answered = vec![0; parts.len()];
+        for (part, target, line) in targets {
+            let next = match target {
+                Some(target) => Some(
+                    parts
+                        .iter()
+                        .position(|part| part.name == target)
+                        .with_context(|| format!("line {line}: no part called {target:?}"))?,
+                ),
+                None => None,
+            };
+            parts[part].choices[answered[part]].next = next;
+            answered[part] += 1;
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+/// a dialogue being shown
+pub(crate) struct Conversation {
+    dialogue: Dialogue,
+    part: usize,
+    page: usize,
+    /// letters of the page typed out so far
+    typed: usize,
+    /// answer picked, once they are shown
+    selected: usize,
+}
+
+impl Conversation {
+    pub fn new(dialogue: Dialogue) -> Self {
+        Self {
+            dialogue,
+            part: 0,
+            page: 0,
+            typed: 0,
+            selected: 0,
+        }
+    }
+
+    fn part(&self) -> &Part {
+        &self.dialogue.parts[self.part]
+    }
+
+    fn text(&self) -> &str {
+        &self.part().pages[self.page]
+    }
+
+    fn typing(&self) -> bool {
+        self.typed < self.text().chars().count()
+    }
+
+    fn last_page(&self) -> bool {
+        self.page + 1 == self.part().pages.len()
+    }
+
+    /// answers to pick from, once the last page of a part is typed out
+    fn choices(&self) -> &[Choice] {
+        if self.typing() || !self.last_page() {
+            return &[];
+        }
+        &self.part().choices
+    }
+
+    /// type out more of the page, returns whether anything more is shown
+    pub fn tick(&mut self) -> bool {
+        if !self.typing() {
+            return false;
+        }
+        self.typed += TYPE_SPEED;
+        true
+    }
+
+    /// show the rest of the page, the next one or the part an answer leads to, returns
+    /// whether the talk goes on
+    pub fn key(&mut self, key: Keycode) -> bool {
+        match key {
+            Keycode::Escape => return false,
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => {
+                self.selected = (self.selected + 1).min(self.choices().len().saturating_sub(1))
+            }
+            Keycode::Return | Keycode::Space | Keycode::E => {
+                if self.typing() {
+                    self.typed = self.text().chars().count();
+                } else if !self.last_page() {
+                    self.page += 1;
+                    self.typed = 0;
+                } else {
+                    let Some(Choice {
+                        next: Some(next), ..
+                    }) = self.choices().get(self.selected)
+                    else {
+                        return false;
+                    };
+                    (self.part, self.page, self.typed, self.selected) = (*next, 0, 0, 0);
+                }
+            }
+            _ => {}
+        }
+
+        true
+    }
+
+    /// draw the box over the bottom of the screen with as much of the page as is typed out,
+    /// and the answers below it
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &HudTheme) -> anyhow::Result<()> {
+        let top = HEIGHT as i32 - BOX_HEIGHT - theme.margin;
+        renderer.draw_rect(
+            Some(Rect::new(
+                theme.margin,
+                top,
+                WIDTH as u32 - (theme.margin as u32 * 2),
+                BOX_HEIGHT as u32,
+            )),
+            theme.background,
+        )?;
+
+        // wrapped as a whole, so words do not jump to the next line as they are typed
+        let style = theme.body();
+        let wrapped = style.wrapped(theme.wrap_width(WIDTH as u32));
+        let lines = renderer_lines(renderer, self.text(), &wrapped);
+        let mut left = self.typed;
+        let mut shown = Vec::new();
+        for line in &lines {
+            let typed = line.chars().take(left).collect::<String>();
+            left = left.saturating_sub(line.chars().count());
+            shown.push(typed);
+        }
+        let shown = shown.join("\n");
+        let (x, y) = (
+            theme.margin + theme.padding.0 as i32,
+            top + theme.padding.1 as i32,
+        );
+        // nothing typed yet cannot be rendered
+        if !shown.trim().is_empty() {
+            renderer.draw_text(shown.trim_end(), &style, x, y)?;
+        }
+
+        let (_, height) = renderer.measure_text(self.text(), &wrapped)?;
+        let mut y = y + height as i32 + (theme.line_height / 2);
+        for (idx, choice) in self.choices().iter().enumerate() {
+            let style = if idx == self.selected {
+                theme
+                    .style(theme.text_size, theme.selected)
+                    .boxed(theme.selected_background, theme.padding)
+            } else {
+                TextStyle {
+                    padding: theme.padding,
+                    ..style
+                }
+            };
+            renderer.draw_text(&format!("> {}", choice.text), &style, x, y)?;
+            y += theme.line_height;
+        }
+        if !self.typing() && !self.last_page() {
+            renderer.draw_text(
+                "v",
+                &style.italic(),
+                WIDTH as i32 - (theme.margin * 2) - theme.padding.0 as i32,
+                top + BOX_HEIGHT - theme.line_height,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUARD: &str = "Evening. Lost?
+
+The cellar door
+is stuck again.
+> Can I help? -> help
+> Bye
+
+[help]
+There is a lever behind the barrels.
+";
+
+    #[test]
+    fn parses_pages_and_answers() {
+        let dialogue = Dialogue::parse(GUARD).unwrap();
+        assert_eq!(dialogue.parts.len(), 2);
+        assert_eq!(
+            dialogue.parts[0].pages,
+            ["Evening. Lost?", "The cellar door\nis stuck again."]
+        );
+        assert_eq!(
+            dialogue.parts[0].choices,
+            [
+                Choice {
+                    text: "Can I help?".into(),
+                    next: Some(1)
+                },
+                Choice {
+                    text: "Bye".into(),
+                    next: None
+                }
+            ]
+        );
+        assert_eq!(dialogue.parts[1].name, "help");
+
+        // starting with a part is the same as without its name
+        let named = Dialogue::parse(&format!("[start]\n{GUARD}")).unwrap();
+        assert_eq!(named.parts.len(), 2);
+        assert_eq!(named.parts[0].choices[0].next, Some(1));
+
+        for text in [
+            "",
+            "Hello\n> Hi -> nowhere",
+            "Hello\n> Hi\nmore",
+            "Hello\n[a]\nA\n[a]\nB",
+            "Hello\n[empty]\n",
+        ] {
+            assert!(Dialogue::parse(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn talks_page_by_page() {
+        let mut talk = Conversation::new(Dialogue::parse(GUARD).unwrap());
+        assert!(talk.tick());
+        assert_eq!(talk.typed, TYPE_SPEED);
+        // the first press shows the whole page, the next one goes on
+        assert!(talk.key(Keycode::Return));
+        assert!(!talk.typing() && !talk.tick());
+        assert!(talk.key(Keycode::Return));
+        assert_eq!((talk.page, talk.typed), (1, 0));
+        assert!(talk.choices().is_empty());
+
+        talk.key(Keycode::Return);
+        assert_eq!(talk.choices().len(), 2);
+        talk.key(Keycode::Down);
+        talk.key(Keycode::Down);
+        assert_eq!(talk.selected, 1);
+        talk.key(Keycode::Up);
+        assert!(talk.key(Keycode::Return));
+        assert_eq!((talk.part, talk.page), (1, 0));
+
+        talk.key(Keycode::Return);
+        assert!(!talk.key(Keycode::Return));
+        assert!(!Conversation::new(Dialogue::parse(GUARD).unwrap()).key(Keycode::Escape));
+    }
+}
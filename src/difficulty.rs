@@ -0,0 +1,87 @@
+//! how hard the game is, scaling damage, enemies, pickups and starting health
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// language key of its name
+    pub fn key(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "difficulty.easy",
+            Difficulty::Normal => "difficulty.normal",
+            Difficulty::Hard => "difficulty.hard",
+        }
+    }
+
+    /// the next one, for menus that cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// how much health a hit that takes `amount` on normal takes
+    pub fn damage(self, amount: u8) -> u8 {
+        match self {
+            Difficulty::Easy => amount / 2,
+            Difficulty::Normal => amount,
+            Difficulty::Hard => amount.saturating_add(amount / 2),
+        }
+    }
+
+    /// how much a pickup worth `amount` on normal gives
+    pub fn pickup(self, amount: u16) -> u16 {
+        match self {
+            Difficulty::Easy => amount.saturating_add(amount / 2),
+            Difficulty::Normal => amount,
+            Difficulty::Hard => amount / 2,
+        }
+    }
+
+    /// how fast enemies are compared to normal
+    pub fn enemy_speed(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.25,
+        }
+    }
+
+    /// what players start with when they would start with `health` on normal
+    pub fn health(self, health: u8) -> u8 {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => health,
+            Difficulty::Hard => health / 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_is_harder() {
+        let all = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+        for pair in all.windows(2) {
+            let (easier, harder) = (pair[0], pair[1]);
+            assert_eq!(easier.next(), harder);
+            assert!(easier.damage(20) < harder.damage(20));
+            assert!(easier.pickup(10) > harder.pickup(10));
+            assert!(easier.enemy_speed() < harder.enemy_speed());
+            assert!(easier.health(255) >= harder.health(255));
+        }
+
+        // normal changes nothing
+        assert_eq!(Difficulty::default().damage(7), 7);
+        assert_eq!(Difficulty::default().health(255), 255);
+        assert_eq!(Difficulty::Hard.damage(200), 255);
+    }
+}
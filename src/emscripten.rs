@@ -1,6 +1,7 @@
-use std::os::raw::{c_double, c_uint};
+use std::os::raw::{c_char, c_double, c_uint};
 
 extern "C" {
     pub fn emscripten_get_now() -> c_double;
     pub fn emscripten_sleep(millis: c_uint);
+    pub fn emscripten_run_script(script: *const c_char);
 }
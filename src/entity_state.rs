@@ -0,0 +1,146 @@
+//! state scripts kept on the entities of each level, kept in a file between runs
+//!
+//! a `[<level>]` line starts the entities of a level, followed by one `<x> <y> <key> <value>`
+//! line per value, by the tile the map placed the entity on
+
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use yaw_core::entity::StateBag;
+
+/// state of the entities of one level, by the tile they came from
+pub(crate) type LevelStates = BTreeMap<(usize, usize), StateBag>;
+
+#[derive(Default)]
+pub(crate) struct EntityStates {
+    /// where they are saved, never for the ones demos bring along
+    path: Option<PathBuf>,
+    levels: BTreeMap<String, LevelStates>,
+}
+
+impl EntityStates {
+    /// nothing kept yet, saved to `path` once something is
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            levels: BTreeMap::new(),
+        }
+    }
+
+    /// read the states saved at `path`, none when there is no file yet
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new(path)),
+            Err(err) => return Err(err.into()),
+        };
+        let mut states = Self::new(path);
+
+        let mut level = None;
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                level = Some(name.to_string());
+                continue;
+            }
+            let level = level
+                .clone()
+                .with_context(|| format!("entity state outside of a level: {line}"))?;
+            let mut words = line.splitn(4, ' ');
+            let (Some(x), Some(y), Some(key), value) =
+                (words.next(), words.next(), words.next(), words.next())
+            else {
+                anyhow::bail!("invalid entity state: {line}");
+            };
+            states.set(
+                &level,
+                (x.parse()?, y.parse()?),
+                key,
+                value.unwrap_or_default(),
+            );
+        }
+
+        Ok(states)
+    }
+
+    /// state of the entities of a level, empty when nothing was kept
+    pub fn get(&self, level: &str) -> LevelStates {
+        self.levels.get(level).cloned().unwrap_or_default()
+    }
+
+    /// set one value of the entity a level placed on a tile, without saving
+    pub fn set(&mut self, level: &str, origin: (usize, usize), key: &str, value: &str) {
+        self.levels
+            .entry(level.to_string())
+            .or_default()
+            .entry(origin)
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// replace what is kept for a level and save
+    pub fn keep(&mut self, level: &str, states: LevelStates) -> anyhow::Result<()> {
+        if states.is_empty() {
+            self.levels.remove(level);
+        } else {
+            self.levels.insert(level.to_string(), states);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for (level, states) in &self.levels {
+            text += &format!("[{level}]\n");
+            for ((x, y), state) in states {
+                for (key, value) in state {
+                    text += &format!("{x} {y} {key} {value}\n");
+                }
+            }
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn states_are_kept_between_runs() {
+        let dir = TempDir::new("entity_state").unwrap();
+        let path = dir.path().join("entity_state.txt");
+
+        let mut states = EntityStates::load(path.clone()).unwrap();
+        assert!(states.get("map/map.yaw").is_empty());
+        let mut level = LevelStates::new();
+        level.insert(
+            (3, 4),
+            StateBag::from([
+                ("opened".into(), "yes".into()),
+                ("said".into(), "hello there".into()),
+            ]),
+        );
+        states.keep("map/map.yaw", level.clone()).unwrap();
+        states.set("map/other.yaw", (1, 1), "empty", "");
+        states.keep("map/gone.yaw", LevelStates::new()).unwrap();
+
+        let states = EntityStates::load(path.clone()).unwrap();
+        assert_eq!(states.get("map/map.yaw"), level);
+        assert_eq!(states.get("map/other.yaw")[&(1, 1)]["empty"], "");
+        assert!(states.get("map/gone.yaw").is_empty());
+
+        std::fs::write(&path, "1 1 opened yes\n").unwrap();
+        assert!(EntityStates::load(path.clone()).is_err());
+        std::fs::write(&path, "[map/map.yaw]\n1 opened yes\n").unwrap();
+        assert!(EntityStates::load(path).is_err());
+    }
+}
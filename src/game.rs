@@ -1,159 +1,3202 @@
-use crate::map::{Map, Meta, Tile, TILE_SIZE};
-use crate::ray::{Cardinal, RayCast};
-use crate::{StringToAnyhow, HEIGHT, WIDTH};
+use crate::achievements::{Achievement, Achievements};
+use crate::audio::Audio;
+use crate::automap::Automap;
+use crate::bindings::Bindings;
+use crate::campaign::{Campaign, Intermission, Level, Step};
+use crate::cli::Args;
+use crate::config::Config;
+use crate::console::{self, Console, Entrance};
+use crate::demo::Demo;
+use crate::dialogue::{Conversation, Dialogue};
+use crate::difficulty::Difficulty;
+use crate::entity_state::EntityStates;
+use crate::hud::{self, Crosshair, MinimapView, Status};
+use crate::lang::Lang;
+use crate::loading::{self, Loader};
+use crate::menu::Menu;
+use crate::modifiers::Modifiers;
+#[cfg(not(target_os = "emscripten"))]
+use crate::net::{Event, Net, Packet};
+use crate::postfx::{PostEffect, PostEffects};
+use crate::profile::FrameStats;
+use crate::progress::Progress;
+use crate::recording::Recording;
+use crate::scale::{RenderScale, Scaler};
+use crate::screenshot;
+use crate::script::{Command, Script, State};
+use crate::scroll::ScrollText;
+use crate::stats::{self, BestTimes, LevelStats};
+use crate::textfield::TextField;
+use crate::theme::HudTheme;
+use crate::{HEIGHT, TARGET_FPS, TITLE, WIDTH};
 use anyhow::Context;
 use glam::Vec2;
-#[cfg(not(target_os = "emscripten"))]
-use sdl2::image::ImageRWops;
-#[cfg(target_os = "emscripten")]
-use sdl2::image::LoadTexture;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::{Point, Rect};
-use sdl2::render::{BlendMode, Canvas, TextureCreator, TextureQuery};
-use sdl2::rwops::RWops;
-use sdl2::ttf::{FontStyle, Sdl2TtfContext};
-use sdl2::video::{Window, WindowContext};
-use std::f32::consts::{FRAC_PI_2, PI};
+use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::f32::consts::PI;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+use yaw_core::assets;
+use yaw_core::camera::{Camera, ColumnTable, Sprite, ZBuffer};
+use yaw_core::color::Color;
+use yaw_core::entity::{Ai, Collider, Entity, World};
+use yaw_core::framebuffer::Image;
+use yaw_core::map::{Map, Objective, PlayerMeta, Tile, Transform, TILE_SIZE};
+use yaw_core::mapgen::{self, Layout};
+use yaw_core::particle::{Effect, Particles};
+use yaw_core::ray::RayCast;
+use yaw_core::render::{Rect, Renderer};
+use yaw_core::thumbnail;
+use yaw_core::trigger::{Action, Triggers};
+use yaw_core::visibility::Visibility;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub(crate) enum GameState {
     Menu,
     Playing,
     Minimap,
+    /// full screen map of what was seen, the level waits while it is open
+    Automap,
+    /// talking to someone, see [`Game::conversation`]
+    Dialogue,
+    /// text of the map before or after it is played, see [`Game::briefing`]
+    Briefing,
     Paused,
+    Campaigns,
+    /// list of achievements and which are unlocked
+    Achievements,
+    MapSelect,
+    /// picking the seed and layout of a random level
+    RandomLevel,
+    Settings,
+    /// gameplay changes unlocked by finishing a campaign
+    Modifiers,
+    Intermission,
+    /// how the level that was just finished went
+    Stats,
+    /// flying around the finished level, see [`Game::spectator`]
+    Spectating,
+    /// keys and what they do
+    Help,
+    Credits,
+    /// a level is being read, see [`Loader`]
+    Loading,
+    /// something went wrong, see [`Failure`]
+    Error,
     Exit,
 }
 
-#[derive(Clone, Copy, PartialEq)]
-struct Player {
-    pos: Vec2,
-    direction: f32,
-    speed: f32,
-    health: u8,
-}
+/// options of the main menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MenuItem {
+    Continue,
+    Play,
+    MapSelect,
+    RandomLevel,
+    Campaigns,
+    Modifiers,
+    Achievements,
+    Difficulty,
+    Settings,
+    Help,
+    Credits,
+    Quit,
+}
+
+/// options of the random level menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum RandomItem {
+    Seed,
+    Layout,
+    Play,
+    Back,
+}
+
+/// options of the modifiers menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ModifierItem {
+    FastEnemies,
+    Mirrored,
+    Back,
+}
+
+/// options of the pause menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PauseItem {
+    Resume,
+    Restart,
+    Settings,
+    QuitToMenu,
+}
+
+/// options of the error screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ErrorItem {
+    Retry,
+    QuitToMenu,
+}
+
+/// an error shown on screen instead of quitting
+struct Failure {
+    /// the error and what caused it, one per line
+    lines: Vec<String>,
+    retry: Retry,
+}
+
+/// what the error screen tries again
+enum Retry {
+    /// load a map, going to a state once it is loaded
+    Load(PathBuf, GameState),
+    /// go back to a state that could not be drawn
+    Draw(GameState),
+}
+
+/// options of the settings menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Setting {
+    Sound,
+    Fov,
+    Minimap,
+    MinimapView,
+    Crosshair,
+    RenderScale,
+    Timer,
+    Language,
+    HighContrast,
+    Palette,
+    Effects,
+    ReduceMotion,
+    Back,
+}
+
+/// range the field of view can be set to, in degrees
+const MIN_FOV: f32 = 50.;
+const MAX_FOV: f32 = 110.;
+/// how much the settings menu changes the field of view at once
+const FOV_STEP: f32 = 10.;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Player {
+    pub pos: Vec2,
+    pub direction: f32,
+    /// pixels walked forward or back per second
+    pub speed: f32,
+    /// pixels walked sideways per second
+    pub strafe_speed: f32,
+    /// radians turned per second
+    pub turn_speed: f32,
+    /// radians being turned per second right now, to the right when positive
+    pub turning: f32,
+    /// which way turn keys are held this tick, -1 left and 1 right
+    steer: f32,
+    /// whether walking keys moved them this tick, which sprinting takes stamina for
+    walked: bool,
+    /// pixels moved per tick while on slippery tiles
+    pub sliding: Vec2,
+    pub health: u8,
+    pub stamina: f32,
+    pub ammo: u16,
+    /// how many keys were picked up
+    pub keys: u8,
+    pub sprinting: bool,
+    pub flashlight: bool,
+    /// walk through walls
+    pub noclip: bool,
+}
+
+impl Player {
+    fn new(health: u8) -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            direction: 0.,
+            speed: PLAYER_SPEED,
+            strafe_speed: STRAFE_SPEED,
+            turn_speed: TURN_SPEED,
+            turning: 0.,
+            steer: 0.,
+            walked: false,
+            sliding: Vec2::ZERO,
+            health,
+            stamina: MAX_STAMINA,
+            ammo: 0,
+            keys: 0,
+            sprinting: false,
+            flashlight: false,
+            noclip: false,
+        }
+    }
+
+    /// pixels walked in a tick at `speed`, faster while sprinting
+    fn stride(&self, speed: f32) -> f32 {
+        let speed = if self.sprinting && self.stamina > 0. {
+            speed * SPRINT_MULTIPLIER
+        } else {
+            speed
+        };
+
+        speed * TICK
+    }
+
+    /// movement of a tick walking forward
+    fn step(&self) -> Vec2 {
+        Vec2::from_angle(self.direction) * self.stride(self.speed)
+    }
+
+    /// movement of a tick stepping right
+    fn strafe(&self) -> Vec2 {
+        Vec2::from_angle(self.direction).perp() * self.stride(self.strafe_speed)
+    }
+
+    /// turn for a tick, speeding up while a turn key is held and slowing down once none is,
+    /// returns whether the player turned
+    fn turn(&mut self) -> bool {
+        let target = self.steer.clamp(-1., 1.) * self.turn_speed;
+        let change = TURN_ACCELERATION * self.turn_speed * TICK;
+        self.turning += (target - self.turning).clamp(-change, change);
+        self.steer = 0.;
+
+        self.direction = (self.direction + (self.turning * TICK)).rem_euclid(2. * PI);
+        self.turning != 0.
+    }
+
+    /// drain stamina for a tick of sprinting, or regenerate some otherwise
+    fn tire(&mut self) {
+        if self.sprinting && self.walked && self.stamina > 0. {
+            self.stamina = (self.stamina - STAMINA_DRAIN).max(0.);
+        } else {
+            self.stamina = (self.stamina + STAMINA_REGEN).min(MAX_STAMINA);
+        }
+        self.walked = false;
+    }
+}
+
+/// where players come back after dying
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Checkpoint {
+    tile: (usize, usize),
+    direction: f32,
+    health: u8,
+}
+
+/// seconds a tick takes, speeds are per second so they are scaled by this
+const TICK: f32 = 1. / TARGET_FPS as f32;
+/// pixels players walk and step sideways and radians they turn per second, unless the command
+/// line or the map says otherwise
+const PLAYER_SPEED: f32 = 60.;
+const STRAFE_SPEED: f32 = 60.;
+const TURN_SPEED: f32 = 3.;
+/// how much of the turn speed is gained or lost per second, speeding up and slowing down take
+/// the same time so holding a turn key for a while turns as far as without either
+const TURN_ACCELERATION: f32 = 10.;
+/// health players start with before difficulty, unless the map says otherwise
+const START_HEALTH: u8 = 255;
+
+const MAX_STAMINA: f32 = 100.;
+const STAMINA_DRAIN: f32 = 1.5;
+const STAMINA_REGEN: f32 = 0.5;
+const SPRINT_MULTIPLIER: f32 = 1.75;
+
+/// how far away tiles can be used from
+const USE_RANGE: f32 = TILE_SIZE;
+/// how straight at someone players look to talk to them, the cosine of the angle
+const NPC_FACING: f32 = 0.8;
+
+/// how fast entities moved by triggers and scripts walk
+const WALK_SPEED: f32 = 1.5;
+/// how fast crates slide to the next tile when pushed
+const PUSH_SPEED: f32 = 2.;
+
+/// how fast enemies chase players, from how far they see them and how close they get to hurt
+const ENEMY_SPEED: f32 = 1.2;
+const ENEMY_RANGE: f32 = TILE_SIZE * 8.;
+const ENEMY_REACH: f32 = TILE_SIZE * 0.75;
+/// health an enemy takes on normal difficulty, once every this many frames
+const ENEMY_DAMAGE: u8 = 20;
+const ENEMY_COOLDOWN: u64 = TARGET_FPS;
+
+/// how much slower players are in water, and how quickly the view wobbles there
+const WATER_SPEED: f32 = 0.5;
+const WOBBLE_SPEED: f32 = 0.15;
+
+/// how much of the speed players sliding on slippery tiles keep each tick, walking makes up
+/// the rest so they slide as fast as they would walk
+const SLIDE_FRICTION: f32 = 0.92;
+/// slower than this in pixels per tick, sliding players come to a stop
+const SLIDE_STOP: f32 = 0.05;
+
+/// health taken on normal difficulty from players inside a tile when it closes
+const CRUSH_DAMAGE: u8 = 50;
+
+/// distance walked between footstep sounds
+const FOOTSTEP_DISTANCE: f32 = TILE_SIZE * 0.75;
+
+/// how many frames a hud message stays on screen
+const MESSAGE_FRAMES: u64 = TARGET_FPS * 3;
+
+/// map played outside of campaigns
+const DEFAULT_MAP: &str = "map/map.yaw";
+/// where campaigns are looked for
+const CAMPAIGN_DIR: &str = "map";
+/// campaign play on the main menu starts, when there is one
+pub(crate) const MAIN_CAMPAIGN: &str = "map/episode1.yawc";
+/// where maps for map select are looked for
+const MAP_DIR: &str = "map";
+/// map whose tiles and fog random levels use
+const MAPGEN_TEMPLATE: &str = DEFAULT_MAP;
+/// size of random levels in tiles
+const RANDOM_WIDTH: usize = 31;
+const RANDOM_HEIGHT: usize = 21;
+/// digits a typed seed can have before it would not fit
+const MAX_SEED_DIGITS: usize = 19;
+/// where the best time of each map is kept
+const BEST_TIMES: &str = "best_times.txt";
+/// where the level each campaign is at is kept
+const PROGRESS: &str = "progress.txt";
+/// where scripts keep the state of entities in finished levels
+const ENTITY_STATES: &str = "entity_state.txt";
+/// where unlocked achievements are kept
+const ACHIEVEMENTS: &str = "achievements.txt";
+/// where settings are kept
+pub(crate) const CONFIG: &str = "config.txt";
+/// billboard of the other player in co-op
+const PLAYER_SPRITE: &str = "images/player.png";
+/// radians the camera behind the menu turns per tick
+const ATTRACT_TURN: f32 = 0.01;
+/// darkens the view behind the menu so its text stays readable
+const MENU_DIM: Color = Color::RGBA(0, 0, 0, 0xb0);
+/// ticks without input on the menu before the demo plays
+const DEMO_IDLE: u64 = TARGET_FPS * 30;
+/// looks around finished levels and demos with the spectator camera
+const SPECTATE_KEY: Keycode = Keycode::Tab;
+/// radians the spectator camera turns per pixel the mouse moves
+const MOUSE_TURN: f32 = 0.004;
+/// shown on the credits screen
+const CREDITS: &str = include_str!("credits.txt");
+
+pub(crate) struct Game {
+    map: Map,
+    /// file the current map was loaded from
+    pub level: PathBuf,
+    pub player: Player,
+    /// second player in split-screen
+    pub second: Option<Player>,
+    /// keys of the first and second player
+    bindings: [Bindings; 2],
+    /// how players turn and step sideways unless the map says otherwise
+    movement: PlayerMeta,
+    pub game_state: GameState,
+    pub slices: Vec<RayCast>,
+    /// rays of the second player
+    second_slices: Vec<RayCast>,
+    /// where the columns of both views look, see [`ColumnTable`]
+    columns: ColumnTable,
+    second_columns: ColumnTable,
+    /// depth of each column of both views, for drawing things over walls
+    depth: ZBuffer,
+    second_depth: ZBuffer,
+    /// cameras the current rays were cast from, rays are only cast again once one moves
+    cast_from: Vec<Camera>,
+    /// the map changed, so rays need casting again even if no camera moved
+    pub rays_dirty: bool,
+    pub renderer: Box<dyn Renderer>,
+    audio: Option<Audio>,
+    /// dynamic objects of the current level
+    pub entities: World,
+    particles: Particles,
+    /// which triggers of the current level fired
+    triggers: Triggers,
+    /// script shipped with the current level
+    script: Option<Script>,
+    /// tile the first player was on last frame, for `on_enter`
+    last_tile: (usize, usize),
+    /// entity standing in for the other player in co-op
+    #[cfg(not(target_os = "emscripten"))]
+    peer: Option<Entity>,
+    /// connection to another instance in co-op
+    #[cfg(not(target_os = "emscripten"))]
+    net: Option<Net>,
+    step_distance: f32,
+    /// health of both players last frame, to flash the screen when it changes
+    last_health: [u8; 2],
+    /// state drawn last frame, to fade in when switching screens
+    last_state: GameState,
+    post: PostEffects,
+    /// text on the hud and for how many more frames
+    pub messages: Vec<(String, u64)>,
+    /// tile the first player was last told how to use
+    prompted: Option<(usize, usize)>,
+    /// last checkpoint touched in the current level
+    checkpoint: Option<Checkpoint>,
+    /// ids of the objectives of the current level done so far
+    completed: HashSet<String>,
+    /// times players can still come back from a checkpoint, none for as often as they like
+    lives: Option<u8>,
+    pub stats: LevelStats,
+    /// scales damage, enemies, pickups and starting health
+    difficulty: Difficulty,
+    pub best_times: BestTimes,
+    pub achievements: Achievements,
+    pub progress: Progress,
+    pub entity_states: EntityStates,
+    /// text of the language being shown
+    pub lang: Lang,
+    achievement_menu: Menu<Achievement>,
+    /// the level was just finished faster than ever before
+    new_best: bool,
+    /// show the time spent in the level on the hud
+    show_timer: bool,
+    screenshot: bool,
+    /// clip being recorded, toggled with f10
+    recording: Option<Recording>,
+    seed: Option<u64>,
+    transforms: Vec<Transform>,
+    pub campaigns: Vec<Campaign>,
+    /// index of the campaign being played and its current step
+    campaign: Option<(usize, usize)>,
+    /// playing the hub of the campaign, which is at the step of the level last left
+    hub: bool,
+    /// text screen being shown
+    intermission: Option<Intermission>,
+    /// what someone the first player talks to is saying, the level waits meanwhile
+    conversation: Option<Conversation>,
+    /// text of the map being read and the state after it
+    pub briefing: Option<(ScrollText, GameState)>,
+    /// error being shown
+    failure: Option<Failure>,
+    menu: Menu<MenuItem>,
+    pause_menu: Menu<PauseItem>,
+    error_menu: Menu<ErrorItem>,
+    /// campaigns by index
+    campaign_menu: Menu<usize>,
+    /// maps of map select
+    maps: Vec<MapInfo>,
+    map_menu: Menu<PathBuf>,
+    settings: Menu<Setting>,
+    random_menu: Menu<RandomItem>,
+    /// picked on the modifiers screen, played with from the next level on
+    pub modifiers: Modifiers,
+    modifier_menu: Menu<ModifierItem>,
+    /// seed and layout of the next random level
+    random_seed: TextField,
+    random_layout: Layout,
+    /// state the settings menu goes back to
+    settings_return: GameState,
+    console: Console,
+    /// view detached from the player, flying through walls
+    free_camera: Option<Player>,
+    /// view flown around a finished level or a demo with real input, apart from the player
+    pub spectator: Option<Player>,
+    /// turning view of the level shown behind the menu
+    attract: Player,
+    /// ticks spent on the menu without input
+    menu_idle: u64,
+    /// text of the help or credits screen
+    scroll: ScrollText,
+    /// playing while nobody is, until a key is pressed
+    demo: Option<Demo>,
+    /// horizontal field of view of a full screen view, in degrees
+    fov: f32,
+    /// what was in sight last frame
+    visibility: Visibility,
+    /// what was seen so far in the current level
+    automap: Automap,
+    /// only show walls in sight on the minimap
+    minimap_seen: bool,
+    minimap_view: MinimapView,
+    crosshair: Crosshair,
+    /// how many pixels across rays are drawn
+    pub scaler: Scaler,
+    /// whether the face images of the status bar are there
+    faces: bool,
+    /// the theme as loaded, and as drawn with the settings of `config` applied
+    base_theme: HudTheme,
+    theme: HudTheme,
+    config: Config,
+    /// keys that closed a menu, ignored until released so they do not also move the player
+    ignored: HashSet<Keycode>,
+    /// level being loaded and the state to go to once it is
+    loading: Option<(Loader, GameState)>,
+    /// spawn to enter the next level at instead of its main one
+    entrance: Option<Entrance>,
+    /// frames shown since `fps_since`, for the frame rate in the window title
+    fps_frames: u32,
+    fps_since: Instant,
+    /// logged once a second at trace level
+    frame_stats: FrameStats,
+    /// frame rate over the last second, none until a second has passed
+    fps: Option<u32>,
+    /// what the window title says
+    title: String,
+    pub update: bool,
+}
+
+impl Game {
+    /// initialize game, with settings, progress and best times kept in `saves` if given and
+    /// else wherever they are found or the data directory
+    pub fn new(
+        renderer: Box<dyn Renderer>,
+        audio: Option<Audio>,
+        args: &Args,
+        saves: Option<&Path>,
+    ) -> anyhow::Result<Self> {
+        let save_path = |name: &str| {
+            saves.map_or_else(|| assets::save_path(name.as_ref()), |dir| dir.join(name))
+        };
+        let game_state = GameState::Menu;
+        let slices = Vec::<RayCast>::with_capacity(WIDTH);
+        let campaigns = Campaign::load_all(CAMPAIGN_DIR.as_ref())?;
+        let maps = list_maps(MAP_DIR.as_ref())?
+            .into_iter()
+            .map(MapInfo::load)
+            .collect();
+        let theme = HudTheme::load(args.theme.as_deref())?;
+
+        let mut this = Self {
+            map: Map::default(),
+            level: PathBuf::new(),
+            player: Player::new(START_HEALTH),
+            second: args.split.map(|_| Player::new(START_HEALTH)),
+            bindings: [args.keys, args.split.unwrap_or(Bindings::second())],
+            movement: args.movement,
+            game_state,
+            slices,
+            second_slices: Vec::new(),
+            columns: ColumnTable::default(),
+            second_columns: ColumnTable::default(),
+            depth: ZBuffer::default(),
+            second_depth: ZBuffer::default(),
+            cast_from: Vec::new(),
+            rays_dirty: true,
+            renderer,
+            audio,
+            entities: World::default(),
+            particles: Particles::default(),
+            triggers: Triggers::default(),
+            script: None,
+            last_tile: (0, 0),
+            #[cfg(not(target_os = "emscripten"))]
+            peer: None,
+            #[cfg(not(target_os = "emscripten"))]
+            net: args.net.as_ref().map(Net::new).transpose()?,
+            step_distance: 0.,
+            last_health: [255; 2],
+            last_state: game_state,
+            post: PostEffects::default(),
+            messages: Vec::new(),
+            prompted: None,
+            checkpoint: None,
+            completed: HashSet::new(),
+            lives: None,
+            stats: LevelStats::default(),
+            difficulty: Difficulty::default(),
+            best_times: BestTimes::load(save_path(BEST_TIMES)).unwrap_or_else(|err| {
+                log::warn!("could not read best times: {err}");
+                BestTimes::new(save_path(BEST_TIMES))
+            }),
+            achievements: Achievements::load(save_path(ACHIEVEMENTS)).unwrap_or_else(|err| {
+                log::warn!("could not read achievements: {err}");
+                Achievements::new(save_path(ACHIEVEMENTS))
+            }),
+            progress: Progress::load(save_path(PROGRESS)).unwrap_or_else(|err| {
+                log::warn!("could not read campaign progress: {err}");
+                Progress::new(save_path(PROGRESS))
+            }),
+            entity_states: EntityStates::load(save_path(ENTITY_STATES)).unwrap_or_else(|err| {
+                log::warn!("could not read entity states: {err}");
+                EntityStates::new(save_path(ENTITY_STATES))
+            }),
+            achievement_menu: Menu::new("Achievements", Vec::new()),
+            lang: Lang::load()?,
+            new_best: false,
+            show_timer: true,
+            screenshot: false,
+            recording: None,
+            seed: args.randomize,
+            transforms: args.transforms.clone(),
+            campaigns,
+            campaign: None,
+            hub: false,
+            intermission: None,
+            conversation: None,
+            briefing: None,
+            failure: None,
+            menu: Menu::new("yaw", Vec::new()),
+            pause_menu: Menu::new("Paused", Vec::new()),
+            error_menu: Menu::new("Error", Vec::new()),
+            campaign_menu: Menu::new("Campaigns", Vec::new()),
+            maps,
+            map_menu: Menu::new("Maps", Vec::new()),
+            settings: Menu::new("Settings", Vec::new()),
+            random_menu: Menu::new("Random level", Vec::new()),
+            modifiers: Modifiers::default(),
+            modifier_menu: Menu::new("Modifiers", Vec::new()),
+            random_seed: TextField::new(MAX_SEED_DIGITS, |ch| ch.is_ascii_digit()),
+            random_layout: Layout::default(),
+            settings_return: GameState::Menu,
+            ignored: HashSet::new(),
+            loading: None,
+            entrance: None,
+            fps_frames: 0,
+            fps_since: Instant::now(),
+            frame_stats: FrameStats::new(),
+            fps: None,
+            title: TITLE.into(),
+            console: Console::default(),
+            free_camera: None,
+            spectator: None,
+            attract: Player::new(START_HEALTH),
+            menu_idle: 0,
+            scroll: ScrollText::new("", Vec::new()),
+            demo: None,
+            fov: Camera::DEFAULT_FOV,
+            visibility: Visibility::default(),
+            automap: Automap::default(),
+            minimap_seen: false,
+            minimap_view: MinimapView::default(),
+            crosshair: Crosshair::default(),
+            scaler: Scaler::new(RenderScale::default()),
+            faces: theme.status_bar.has_faces(),
+            theme: theme.clone(),
+            base_theme: theme,
+            config: Config::load(save_path(CONFIG)).unwrap_or_else(|err| {
+                log::warn!("could not read settings: {err}");
+                Config::new(save_path(CONFIG))
+            }),
+            update: true,
+        };
+        this.apply_theme();
+        if let Some(code) = args.lang.as_deref() {
+            if !this.lang.select(code) {
+                anyhow::bail!("unknown language: {code}");
+            }
+        }
+        this.random_seed.set(&random_seed().to_string());
+        this.translate_menus();
+        this.load_level(DEFAULT_MAP.into())?;
+        this.attract = this.player;
+
+        Ok(this)
+    }
+
+    /// transforms of the command line, then the one of the modifiers
+    fn level_transforms(&self) -> Vec<Transform> {
+        let mut transforms = self.transforms.clone();
+        transforms.extend(self.modifiers().transform());
+        transforms
+    }
+
+    /// modifiers the demo playing brings along, or else the picked ones
+    fn modifiers(&self) -> Modifiers {
+        self.demo
+            .as_ref()
+            .map_or(self.modifiers, |demo| demo.modifiers)
+    }
+
+    /// load a map right away and place the player on its spawn
+    fn load_level(&mut self, path: PathBuf) -> anyhow::Result<()> {
+        let (progress, _) = channel();
+        let map = loading::parse(&path, &self.level_transforms(), self.seed, &progress)?;
+        self.renderer.clear_cache();
+        for texture in map.textures() {
+            self.renderer.preload(&texture)?;
+        }
+        self.enter_level(path, map)
+    }
+
+    /// load a map behind a loading screen, going to `then` once it is done
+    fn start_loading(&mut self, path: PathBuf, then: GameState) {
+        self.renderer.clear_cache();
+        self.loading = Some((
+            Loader::start(path, self.level_transforms(), self.seed),
+            then,
+        ));
+        self.game_state = GameState::Loading;
+        self.update = true;
+    }
+
+    /// keep loading, switching to the level once it is loaded
+    fn loading_tick(&mut self) {
+        let Some((loader, _)) = self.loading.as_mut() else {
+            return;
+        };
+        self.update = true;
+        let Some(result) = loader.poll(self.renderer.as_mut()) else {
+            return;
+        };
+
+        let (loader, then) = self.loading.take().unwrap();
+        let path = loader.path.clone();
+        match result.and_then(|map| self.enter_level(loader.path, map)) {
+            Ok(()) => {
+                self.game_state = then;
+                if let (GameState::Playing, Some(path)) = (then, self.level_briefing()) {
+                    self.brief(&path, "briefing.title", then);
+                }
+            }
+            Err(err) => {
+                let err = err.context(format!("could not load {}", path.display()));
+                self.fail(&err, Retry::Load(path, then));
+            }
+        }
+    }
+
+    /// briefing of the level the campaign is at, or else the map's own one
+    fn level_briefing(&self) -> Option<PathBuf> {
+        let campaign = self
+            .campaign
+            .filter(|_| !self.hub)
+            .and_then(|(idx, step)| self.campaigns[idx].steps.get(step));
+        match campaign {
+            Some(Step::Level(Level {
+                briefing: Some(path),
+                ..
+            })) => Some(path.clone()),
+            _ => self.map.briefing_path(),
+        }
+    }
+
+    /// switch to a loaded map and place the player on its spawn
+    fn enter_level(&mut self, path: PathBuf, mut map: Map) -> anyhow::Result<()> {
+        let spawn = match self.entrance.take() {
+            Some(Entrance::Spawn(id)) => map.spawn_with_id(id).or_else(|| {
+                log::warn!("no spawn {id} in {}, using the main one", path.display());
+                map.get_spawn()
+            }),
+            Some(Entrance::Random) => map.random_spawn(random_seed()),
+            None => map.get_spawn(),
+        }
+        .context("no spawn in map")?;
+        // the second player starts on another spawn if there is one
+        let other = map
+            .spawns()
+            .into_iter()
+            .map(|(_, pos)| pos)
+            .find(|&pos| pos != spawn)
+            .unwrap_or(spawn);
+        // maps can tune how players move and replace the health they came with
+        let meta = map.player_meta();
+        let health = meta.health.map(|health| self.difficulty.health(health));
+        for (player, pos) in [Some(&mut self.player), self.second.as_mut()]
+            .into_iter()
+            .zip([spawn, other])
+            .filter_map(|(player, pos)| Some((player?, pos)))
+        {
+            player.pos = pos;
+            player.direction = map.spawn_facing(pos);
+            player.turning = 0.;
+            player.sliding = Vec2::ZERO;
+            player.stamina = MAX_STAMINA;
+            player.speed = meta.speed.unwrap_or(PLAYER_SPEED);
+            player.strafe_speed = meta
+                .strafe_speed
+                .or(self.movement.strafe_speed)
+                .or(self.config.strafe_speed)
+                .unwrap_or(STRAFE_SPEED);
+            player.turn_speed = meta
+                .turn_speed
+                .or(self.movement.turn_speed)
+                .or(self.config.turn_speed)
+                .unwrap_or(TURN_SPEED);
+            if let Some(health) = health {
+                player.health = health;
+            }
+        }
+        self.stats = LevelStats {
+            secret_total: map
+                .triggers
+                .iter()
+                .filter(|trigger| trigger.action == Action::Secret)
+                .count() as u32,
+            seed: self.seed,
+            modifiers: self.modifiers(),
+            ..LevelStats::default()
+        };
+        let crates = map.take_pushables();
+        let npcs = map.take_npcs();
+        self.automap = Automap::new(&map);
+        self.map = map;
+        self.rays_dirty = true;
+        self.level = path.clone();
+        self.free_camera = None;
+        self.spectator = None;
+        self.entities = World::default();
+        for (pos, sprite) in crates {
+            let entity = self.entities.spawn();
+            self.entities.positions.insert(entity, pos);
+            self.entities.origins.insert(entity, Self::tile_of(pos));
+            self.entities.sprites.insert(entity, sprite);
+            self.entities.colliders.insert(
+                entity,
+                Collider {
+                    radius: TILE_SIZE / 2.,
+                },
+            );
+            self.entities.pushable.insert(entity);
+        }
+        for (pos, sprite, dialogue) in npcs {
+            let entity = self.entities.spawn();
+            self.entities.positions.insert(entity, pos);
+            self.entities.origins.insert(entity, Self::tile_of(pos));
+            self.entities.sprites.insert(entity, sprite);
+            self.entities.colliders.insert(
+                entity,
+                Collider {
+                    radius: TILE_SIZE / 4.,
+                },
+            );
+            self.entities.dialogue.insert(entity, dialogue);
+        }
+        self.conversation = None;
+        self.particles = Particles::default();
+        self.triggers = Triggers::default();
+        self.script = Script::load(&path)?;
+        self.last_tile = Self::tile_of(spawn);
+        self.prompted = None;
+        self.checkpoint = None;
+        self.completed.clear();
+        self.lives = self.map.lives();
+        self.last_health = self.healths();
+        #[cfg(not(target_os = "emscripten"))]
+        {
+            self.peer = None;
+        }
+        let states = self.entity_states().get(&path.display().to_string());
+        for entity in self.entities.restore(&states) {
+            let (x, y) = Self::tile_of(self.entities.positions[&entity]);
+            let state = self.entities.state[&entity].clone();
+            let state = state
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()));
+            let args = (x as i64, y as i64, state.collect::<rhai::Map>());
+            self.run_script("on_restore", args);
+        }
+        self.run_script("on_load", ());
+
+        Ok(())
+    }
+
+    /// entity state the demo playing brings along, or else the saved one
+    fn entity_states(&mut self) -> &mut EntityStates {
+        match self.demo.as_mut() {
+            Some(demo) => &mut demo.states,
+            None => &mut self.entity_states,
+        }
+    }
+
+    /// health of the first and second player, or of the first twice
+    fn healths(&self) -> [u8; 2] {
+        [
+            self.player.health,
+            self.second.unwrap_or(self.player).health,
+        ]
+    }
+
+    /// tile a position is on
+    fn tile_of(pos: Vec2) -> (usize, usize) {
+        ((pos.x / TILE_SIZE) as usize, (pos.y / TILE_SIZE) as usize)
+    }
+
+    /// middle of a tile
+    fn center((x, y): (usize, usize)) -> Vec2 {
+        (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE
+    }
+
+    /// call back into the level script and do what it asks
+    fn run_script(&mut self, name: &str, args: impl rhai::FuncArgs) {
+        let Some(script) = self.script.as_mut() else {
+            return;
+        };
+
+        let state = State {
+            pos: self.player.pos,
+            health: self.player.health,
+            stamina: self.player.stamina,
+            ammo: self.player.ammo,
+            keys: self.player.keys,
+        };
+        script.set_entity_states(
+            self.entities
+                .positions
+                .iter()
+                .map(|(entity, pos)| {
+                    let state = self.entities.state.get(entity).cloned();
+                    (Self::tile_of(*pos), state.unwrap_or_default())
+                })
+                .collect(),
+        );
+        let commands = match script.call(name, args, state) {
+            Ok(commands) => commands,
+            Err(err) => {
+                log::error!("script error in {err}");
+                self.show_text("message.script_error");
+                // do not repeat the error every frame
+                self.script = None;
+                return;
+            }
+        };
+
+        for command in commands {
+            match command {
+                Command::Message(text) => self.show_message(text),
+                Command::SetTile { x, y, tile } => match tile {
+                    Tile::Custom(id) if !self.map.custom_tiles.contains_key(&id) => {
+                        log::warn!("script sets unknown tile: {id}");
+                    }
+                    tile => match self.map.tile_mut(x, y) {
+                        Some(old) => {
+                            *old = tile;
+                            self.rays_dirty = true;
+                        }
+                        None => log::warn!("script sets tile outside of the map: {x}, {y}"),
+                    },
+                },
+                Command::MoveEntity { from, to } => self.move_entity(from, to),
+                Command::KillEntity(at) => {
+                    if self.remove_entity(at) {
+                        self.stats.kills += 1;
+                    }
+                }
+                Command::PickUp(at) => {
+                    if self.remove_entity(at) {
+                        self.stats.items += 1;
+                    }
+                }
+                Command::Effect { at, effect } => self.particles.emit(effect, Self::center(at)),
+                Command::SetHealth(health) => self.player.health = health,
+                Command::SetStamina(stamina) => self.player.stamina = stamina.min(MAX_STAMINA),
+                Command::SetAmmo(ammo) => self.player.ammo = ammo,
+                Command::SetKeys(keys) => self.player.keys = keys,
+                Command::Damage(amount) => {
+                    let amount = self.difficulty.damage(amount);
+                    self.player.health = self.player.health.saturating_sub(amount);
+                }
+                Command::Heal(amount) => {
+                    let amount = self.difficulty.pickup(amount as u16).min(u8::MAX as u16) as u8;
+                    self.player.health = self.player.health.saturating_add(amount);
+                }
+                Command::GiveAmmo(amount) => {
+                    self.player.ammo = self
+                        .player
+                        .ammo
+                        .saturating_add(self.difficulty.pickup(amount));
+                }
+                Command::SetEntityState { at, key, value } => {
+                    match self.entities.at_tile(at.0, at.1) {
+                        Some(entity) => {
+                            let state = self.entities.state.entry(entity).or_default();
+                            state.insert(key, value);
+                        }
+                        None => log::warn!("no entity to keep state on at {at:?}"),
+                    }
+                }
+            }
+            self.update = true;
+        }
+    }
+
+    /// talk to whoever the first player faces, or else flip the switch in front of them and let
+    /// the level script know about the tile
+    fn use_tile(&mut self) {
+        if let Some(path) = self.facing_npc() {
+            match Dialogue::load(&path) {
+                Ok(dialogue) => {
+                    self.conversation = Some(Conversation::new(dialogue));
+                    self.game_state = GameState::Dialogue;
+                }
+                Err(err) => {
+                    log::error!("{err:#}");
+                    self.show_text("message.dialogue_failed");
+                }
+            }
+            return;
+        }
+
+        let (x, y) =
+            Self::tile_of(self.player.pos + (Vec2::from_angle(self.player.direction) * TILE_SIZE));
+        // doors wired to it follow on the next tick
+        self.map.flip_switch(x, y);
+        let id = match self.map.main_tiles.get((y * self.map.width) + x) {
+            // past the east edge the index would wrap around to the next row
+            Some(Tile::Custom(id)) if x < self.map.width => id.to_string(),
+            _ => String::new(),
+        };
+
+        let mut tile = rhai::Map::new();
+        tile.insert("x".into(), (x as i64).into());
+        tile.insert("y".into(), (y as i64).into());
+        tile.insert("id".into(), id.into());
+        self.run_script("on_use", (tile,));
+    }
+
+    /// dialogue file of the nearest friendly entity the first player faces within reach
+    fn facing_npc(&self) -> Option<PathBuf> {
+        let facing = Vec2::from_angle(self.player.direction);
+        self.entities
+            .dialogue
+            .iter()
+            .filter_map(|(entity, path)| {
+                let to = *self.entities.positions.get(entity)? - self.player.pos;
+                (to.length() <= USE_RANGE && facing.dot(to.normalize_or_zero()) >= NPC_FACING)
+                    .then_some((to.length(), path))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, path)| path.clone())
+    }
+
+    /// type out more of what is being said
+    fn dialogue_tick(&mut self) {
+        if self.game_state != GameState::Dialogue {
+            return;
+        }
+        if let Some(conversation) = self.conversation.as_mut() {
+            if conversation.tick() {
+                self.update = true;
+            }
+        }
+    }
+
+    /// handle key presses for while in "dialogue" state
+    fn dialogue_key_once(&mut self, key: Keycode) {
+        let talking = self
+            .conversation
+            .as_mut()
+            .is_some_and(|conversation| conversation.key(key));
+        if !talking {
+            self.conversation = None;
+            self.game_state = GameState::Playing;
+        }
+    }
+
+    /// draw the level with what is being said over it
+    fn dialogue_draw(&mut self) -> anyhow::Result<()> {
+        self.cast_rays();
+        self.playing_draw()?;
+        if let Some(conversation) = self.conversation.as_ref() {
+            conversation.draw(self.renderer.as_mut(), &self.theme)?;
+        }
+        Ok(())
+    }
+
+    /// tell the first player how to use the tile they look at, once per tile
+    fn prompt(&mut self) {
+        // rays from before the map changed, or from a camera, say nothing about it
+        if self.rays_dirty || self.free_camera.is_some() || self.spectator.is_some() {
+            return;
+        }
+
+        let facing = self
+            .slices
+            .get(self.slices.len() / 2)
+            .filter(|slice| slice.hit && slice.vec.length() <= USE_RANGE)
+            .and_then(|slice| {
+                let hit = self.player.pos + slice.vec + Vec2::from_angle(slice.angle);
+                let prompt = self.map.custom_tiles.get(&slice.tile)?.prompt.clone()?;
+                Some((Self::tile_of(hit), prompt))
+            });
+
+        match facing {
+            Some((tile, prompt)) if self.prompted != Some(tile) => {
+                self.prompted = Some(tile);
+                self.show_message(self.lang.fill("message.prompt", &[("action", &prompt)]));
+            }
+            Some(_) => {}
+            None => self.prompted = None,
+        }
+    }
+
+    /// hurt players caught in tiles that just closed
+    fn crush(&mut self) {
+        let damage = self.difficulty.damage(CRUSH_DAMAGE);
+        for player in [Some(&mut self.player), self.second.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            if !player.noclip && self.map.colliding(player.pos, true).is_some() {
+                player.health = player.health.saturating_sub(damage);
+            }
+        }
+    }
+
+    /// remember checkpoints players are on, telling them about new ones
+    fn touch_checkpoints(&mut self) {
+        for player in [Some(self.player), self.second].into_iter().flatten() {
+            let tile = Self::tile_of(player.pos);
+            if player.health == 0
+                || !self.map.is_checkpoint(player.pos)
+                || self
+                    .checkpoint
+                    .is_some_and(|checkpoint| checkpoint.tile == tile)
+            {
+                continue;
+            }
+            self.checkpoint = Some(Checkpoint {
+                tile,
+                direction: player.direction,
+                health: player.health,
+            });
+            self.show_text("message.checkpoint");
+        }
+    }
+
+    /// bring players without health back at the last checkpoint, or end the run when there is
+    /// none or no lives are left, returns whether the run goes on
+    fn die(&mut self) -> bool {
+        let Some(checkpoint) = self.checkpoint.filter(|_| self.lives != Some(0)) else {
+            self.show_text("message.game_over");
+            self.campaign = None;
+            self.reset_players();
+            if let Err(err) = self.load_level(DEFAULT_MAP.into()) {
+                log::error!("could not load map: {err}");
+            }
+            self.game_state = GameState::Menu;
+            self.update = true;
+            return false;
+        };
+
+        self.lives = self.lives.map(|lives| lives - 1);
+        for player in [Some(&mut self.player), self.second.as_mut()]
+            .into_iter()
+            .flatten()
+            .filter(|player| player.health == 0)
+        {
+            player.pos = Self::center(checkpoint.tile);
+            player.direction = checkpoint.direction;
+            player.turning = 0.;
+            player.sliding = Vec2::ZERO;
+            player.health = checkpoint.health;
+            player.stamina = MAX_STAMINA;
+        }
+        match self.lives {
+            Some(lives) => self.show_message(
+                self.lang
+                    .fill("message.respawned_lives", &[("lives", &lives)]),
+            ),
+            None => self.show_text("message.respawned"),
+        }
+
+        true
+    }
+
+    /// start over with fresh players
+    fn reset_players(&mut self) {
+        let health = self.start_health();
+        self.player = Player::new(health);
+        if self.second.is_some() {
+            self.second = Some(Player::new(health));
+        }
+    }
+
+    /// what the current map is called, or else its file
+    fn level_name(&self) -> String {
+        self.map.name.clone().unwrap_or_else(|| {
+            let name = self.level.file_stem().unwrap_or_default();
+            name.to_string_lossy().into_owned()
+        })
+    }
+
+    /// health players start the current map with
+    fn start_health(&self) -> u8 {
+        let health = self.map.player_meta().health.unwrap_or(START_HEALTH);
+        self.difficulty.health(health)
+    }
+
+    /// mark an objective of the level done, telling players the first time
+    fn complete_objective(&mut self, id: &str) {
+        let Some(objective) = self.map.objective(id) else {
+            return;
+        };
+        if self.completed.insert(objective.id.clone()) {
+            let text = self
+                .lang
+                .fill("message.objective", &[("objective", &objective.text)]);
+            self.show_message(text);
+        }
+    }
+
+    /// first objective of the level that is not done yet
+    fn objective(&self) -> Option<&Objective> {
+        self.map
+            .objectives
+            .iter()
+            .find(|objective| !self.completed.contains(&objective.id))
+    }
+
+    /// the player reached an exit, show how the level went
+    fn complete_level(&mut self) {
+        self.broadcast(self.lang.get("message.partner_finished"));
+        self.flash();
+        let level = self.level.display().to_string();
+        let states = self.entities.states();
+        if let Err(err) = self.entity_states().keep(&level, states) {
+            log::warn!("could not save entity states: {err}");
+        }
+        // demos and levels made easier or harder by modifiers do not count
+        if self.demo.is_some() || self.stats.modifiers != Modifiers::default() {
+            self.new_best = false;
+            self.game_state = GameState::Stats;
+            return;
+        }
+        self.new_best = self
+            .best_times
+            .record(&level, self.stats.frames)
+            .unwrap_or_else(|err| {
+                log::warn!("could not save best time: {err}");
+                false
+            });
+
+        match self.achievements.finish_level(&self.stats) {
+            Ok(unlocked) => {
+                for achievement in unlocked {
+                    self.show_message(self.lang.fill(
+                        "message.achievement",
+                        &[("name", &self.lang.get(achievement.keys().0))],
+                    ));
+                }
+                self.achievement_menu = self.achievement_menu();
+            }
+            Err(err) => log::warn!("could not save achievements: {err}"),
+        }
+        self.game_state = GameState::Stats;
+        if let Some(path) = self.map.debriefing_path() {
+            self.brief(&path, "debriefing.title", GameState::Stats);
+        }
+    }
+
+    /// show a text file of the map before going on to `then`, demos go on right away
+    fn brief(&mut self, path: &Path, title: &str, then: GameState) {
+        if self.demo.is_some() {
+            return;
+        }
+        let text = match assets::read(path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(err) => {
+                log::error!("could not read {}: {err}", path.display());
+                self.show_text("message.briefing_failed");
+                return;
+            }
+        };
+        let text = ScrollText::wrapped(
+            self.lang.get(title),
+            &text,
+            self.renderer.as_mut(),
+            &self.theme,
+        );
+        self.briefing = Some((text, then));
+        self.game_state = GameState::Briefing;
+        self.update = true;
+    }
+
+    /// handle key presses for while in "briefing" state, enter or escape go on
+    fn briefing_key_once(&mut self, key: Keycode) {
+        let Some((text, then)) = self.briefing.as_mut() else {
+            return;
+        };
+        if matches!(key, Keycode::Return | Keycode::Escape) {
+            self.game_state = *then;
+            self.briefing = None;
+        } else {
+            text.key(key);
+        }
+    }
+
+    fn briefing_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        if let Some((text, _)) = self.briefing.as_ref() {
+            text.draw(self.renderer.as_mut(), &self.theme)?;
+        }
+        Ok(())
+    }
+
+    /// go on from a finished level to the next one, the hub or back to the menu
+    fn leave_level(&mut self) -> anyhow::Result<()> {
+        match self.campaign {
+            Some((idx, step)) if self.campaigns[idx].hub.is_some() => {
+                // demos do not count
+                if !self.hub && self.demo.is_none() {
+                    let key = self.campaigns[idx].path.display().to_string();
+                    if let Err(err) = self.progress.clear(&key, step) {
+                        log::error!("could not save campaign progress: {err}");
+                    }
+                }
+                if !self.hub {
+                    self.show_text("message.level_complete");
+                }
+                self.return_to_hub(idx, step)
+            }
+            Some((idx, step)) => self.campaign_step(idx, step + 1),
+            None => {
+                self.show_text("message.level_complete");
+                self.reset_players();
+                self.load_level(DEFAULT_MAP.into())?;
+                self.game_state = GameState::Menu;
+                Ok(())
+            }
+        }
+    }
+
+    /// go to a step of a campaign, finishing it when there are no steps left
+    fn campaign_step(&mut self, idx: usize, step: usize) -> anyhow::Result<()> {
+        if let Some(audio) = self.audio.as_mut() {
+            audio.stop();
+        }
+        self.intermission = None;
+
+        let campaign = &self.campaigns[idx];
+        let key = campaign.path.display().to_string();
+        match campaign.steps.get(step).cloned() {
+            Some(Step::Level(level)) => {
+                // players come to levels of hub campaigns from the hub
+                let first = campaign.hub.is_none()
+                    && !campaign.steps[..step]
+                        .iter()
+                        .any(|step| matches!(step, Step::Level(_)));
+                let carry_ammo = campaign.carry_ammo;
+                let hub = campaign.hub.is_some();
+                if first || !campaign.carry_health {
+                    self.set_health(self.difficulty.health(campaign.health));
+                }
+                // levels one after another always keep it, hub campaigns can start each afresh
+                if hub && !carry_ammo {
+                    self.player.ammo = 0;
+                    if let Some(second) = self.second.as_mut() {
+                        second.ammo = 0;
+                    }
+                }
+
+                if let (Some(audio), Some(music)) = (self.audio.as_mut(), &level.music) {
+                    if let Err(err) = audio.play(music) {
+                        log::warn!("could not play music {}: {err}", music.display());
+                    }
+                }
+                // demos do not count
+                if self.demo.is_none() {
+                    if let Err(err) = self.progress.reach(&key, step) {
+                        log::error!("could not save campaign progress: {err}");
+                    }
+                }
+                self.menu = self.main_menu();
+
+                self.campaign = Some((idx, step));
+                self.hub = false;
+                self.start_loading(level.map, GameState::Playing);
+                if !first && !hub {
+                    self.show_text("message.level_complete");
+                }
+            }
+            Some(Step::Intermission(intermission)) => {
+                if let (Some(audio), Some(music)) = (self.audio.as_mut(), &intermission.music) {
+                    if let Err(err) = audio.play(music) {
+                        log::warn!("could not play music {}: {err}", music.display());
+                    }
+                }
+
+                self.campaign = Some((idx, step));
+                self.intermission = Some(intermission);
+                self.game_state = GameState::Intermission;
+            }
+            None => {
+                self.show_message(
+                    self.lang
+                        .fill("message.campaign_finished", &[("name", &campaign.name)]),
+                );
+                if self.demo.is_none() {
+                    if let Err(err) = self.progress.finish(&key) {
+                        log::error!("could not save campaign progress: {err}");
+                    }
+                }
+                // finishing it might unlock others
+                self.menu = self.main_menu();
+                self.campaign_menu = self.campaign_menu();
+                self.campaign = None;
+                self.reset_players();
+                self.load_level(DEFAULT_MAP.into())?;
+                self.game_state = GameState::Menu;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// go to the hub of a campaign from the level at a step, or finish the campaign once every
+    /// level of it is
+    fn return_to_hub(&mut self, idx: usize, step: usize) -> anyhow::Result<()> {
+        let campaign = &self.campaigns[idx];
+        let key = campaign.path.display().to_string();
+        let hub = campaign.hub.clone().context("campaign has no hub")?;
+        let steps = campaign.steps.len();
+        if (0..steps).all(|step| self.progress.cleared(&key, step)) {
+            return self.campaign_step(idx, steps);
+        }
+
+        if let Some(audio) = self.audio.as_mut() {
+            audio.stop();
+        }
+        // demos do not count
+        if self.demo.is_none() {
+            if let Err(err) = self.progress.reach(&key, step) {
+                log::error!("could not save campaign progress: {err}");
+            }
+        }
+        self.menu = self.main_menu();
+        self.campaign = Some((idx, step));
+        self.hub = true;
+        self.start_loading(hub, GameState::Playing);
+        Ok(())
+    }
+
+    /// go into the level of the campaign playing a map, from its hub
+    fn enter_from_hub(&mut self, map: &Path) {
+        let Some((idx, _)) = self.campaign.filter(|_| self.hub) else {
+            log::warn!("level trigger outside of a hub: {}", map.display());
+            return;
+        };
+        let campaign = &self.campaigns[idx];
+        let Some(step) = campaign.step_of(map) else {
+            log::warn!("no level {} in {}", map.display(), campaign.name);
+            return;
+        };
+        if self
+            .progress
+            .cleared(&campaign.path.display().to_string(), step)
+        {
+            self.show_text("message.level_cleared");
+            return;
+        }
+        if let Err(err) = self.campaign_step(idx, step) {
+            log::error!("could not load level: {err}");
+            self.show_text("message.next_level_failed");
+        }
+    }
+
+    /// give both players the same health
+    fn set_health(&mut self, health: u8) {
+        self.player.health = health;
+        if let Some(second) = self.second.as_mut() {
+            second.health = health;
+        }
+    }
+
+    /// work done once per frame regardless of input
+    pub fn tick(&mut self) {
+        self.demo_tick();
+        self.net_tick();
+        self.loading_tick();
+        self.attract_tick();
+        self.dialogue_tick();
+        if let Some(spectator) = self.spectator.as_mut() {
+            if spectator.turn() {
+                self.update = true;
+            }
+        }
+
+        if matches!(self.game_state, GameState::Playing | GameState::Minimap) {
+            self.stats.frames += 1;
+            if self.show_timer && self.stats.frames.is_multiple_of(TARGET_FPS) {
+                self.update = true;
+            }
+            self.map.steady = !self.config.effects;
+            if self.map.set_tick(self.stats.frames) {
+                self.rays_dirty = true;
+                self.update = true;
+                self.crush();
+            }
+            // flickering lights and the view of players in water look different every tick
+            if (!self.map.flickers.is_empty() && !self.map.steady)
+                || (!self.config.reduce_motion
+                    && [Some(self.player), self.second]
+                        .into_iter()
+                        .flatten()
+                        .any(|player| self.map.is_water(player.pos)))
+            {
+                self.update = true;
+            }
+            // turning goes on for a moment after turn keys are let go
+            for player in [
+                Some(&mut self.player),
+                self.second.as_mut(),
+                self.free_camera.as_mut(),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                if player.turn() {
+                    self.update = true;
+                }
+            }
+            // once a tick however many walking keys are held
+            for player in [Some(&mut self.player), self.second.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                player.tire();
+            }
+            // and sliding on slippery tiles after walking keys are
+            if self.slide() {
+                self.complete_level();
+                return;
+            }
+
+            let targets = [Some(&self.player), self.second.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|player| player.pos)
+                .collect::<Vec<_>>();
+            let crates = self
+                .entities
+                .pushable
+                .iter()
+                .filter_map(|entity| self.entities.positions.get(entity).copied())
+                .collect::<Vec<_>>();
+            let actions = self
+                .triggers
+                .update(&self.map.triggers, &targets, &crates)
+                .into_iter()
+                .cloned()
+                .collect::<Vec<_>>();
+            for action in actions {
+                self.run_action(action);
+                // a level trigger left this map, the rest of its actions are for it
+                if self.loading.is_some() {
+                    return;
+                }
+            }
+            let signals = self
+                .map
+                .update_signals(&[targets.as_slice(), crates.as_slice()].concat());
+            if !signals.is_empty() {
+                self.rays_dirty = true;
+                self.update = true;
+                self.crush();
+            }
+            for (signal, on) in signals {
+                self.run_script("on_signal", (signal, on));
+            }
+
+            self.run_script("on_tick", ());
+            self.prompt();
+            let tile = Self::tile_of(self.player.pos);
+            if tile != self.last_tile {
+                self.last_tile = tile;
+                self.run_script("on_enter", (tile.0 as i64, tile.1 as i64));
+            }
+            self.touch_checkpoints();
+
+            // enemies that reached a player hurt them every so often
+            if self.stats.frames.is_multiple_of(ENEMY_COOLDOWN) {
+                let damage = self.difficulty.damage(ENEMY_DAMAGE);
+                let enemies = self
+                    .entities
+                    .ai
+                    .keys()
+                    .filter_map(|entity| self.entities.positions.get(entity).copied())
+                    .collect::<Vec<_>>();
+                for player in [Some(&mut self.player), self.second.as_mut()]
+                    .into_iter()
+                    .flatten()
+                {
+                    if enemies
+                        .iter()
+                        .any(|pos| pos.distance(player.pos) <= ENEMY_REACH)
+                    {
+                        player.health = player.health.saturating_sub(damage);
+                    }
+                }
+            }
+            if self.healths().contains(&0) && !self.die() {
+                return;
+            }
+
+            let healths = self.healths();
+            let viewports = self.viewports();
+            let players = 1 + self.second.is_some() as usize;
+            for (idx, area) in [viewports.0, viewports.1]
+                .into_iter()
+                .enumerate()
+                .take(players)
+            {
+                let effect = match healths[idx].cmp(&self.last_health[idx]) {
+                    Ordering::Less => {
+                        self.stats.hurt = true;
+                        PostEffect::Damage
+                    }
+                    Ordering::Greater => PostEffect::Heal,
+                    Ordering::Equal => continue,
+                };
+                self.post.push(effect, Some(area));
+                self.update = true;
+            }
+            self.last_health = healths;
+
+            self.entities.tick(&self.map, &targets);
+            self.particles.tick(&self.map);
+            if !self.entities.ai.is_empty()
+                || !self.entities.routes.is_empty()
+                || !self.particles.is_empty()
+            {
+                self.update = true;
+            }
+        }
+    }
+
+    /// do what a trigger that fired says
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Message(text) => self.show_message(text),
+            Action::Sound(path) => {
+                if let Some(audio) = self.audio.as_mut() {
+                    if let Err(err) = audio.play(&path) {
+                        log::warn!("could not play {}: {err}", path.display());
+                    }
+                }
+            }
+            Action::Spawn { at, sprite, chase } => {
+                let entity = self.entities.spawn();
+                let pos = Self::center(at);
+                if chase {
+                    self.entities.ai.insert(
+                        entity,
+                        Ai::Chase {
+                            speed: ENEMY_SPEED
+                                * self.difficulty.enemy_speed()
+                                * self.modifiers().enemy_speed(),
+                            range: ENEMY_RANGE,
+                        },
+                    );
+                }
+                self.particles.emit(Effect::Teleport, pos);
+                self.entities.positions.insert(entity, pos);
+                self.entities.sprites.insert(entity, sprite);
+                self.entities.colliders.insert(
+                    entity,
+                    Collider {
+                        radius: TILE_SIZE / 4.,
+                    },
+                );
+            }
+            Action::SetTile { at, tile } => match self.map.tile_mut(at.0, at.1) {
+                Some(old) => {
+                    *old = tile;
+                    self.rays_dirty = true;
+                }
+                None => log::warn!("trigger sets tile outside of the map: {at:?}"),
+            },
+            Action::Move { from, to } => self.move_entity(from, to),
+            Action::Effect { at, effect } => self.particles.emit(effect, Self::center(at)),
+            // reached, the compass stops showing it if it only fires once
+            Action::Marker(_) => {}
+            Action::Secret => {
+                self.stats.secrets += 1;
+                self.show_text("message.secret");
+            }
+            Action::Complete(id) => self.complete_objective(&id),
+            Action::Level(map) => self.enter_from_hub(&map),
+        }
+        self.update = true;
+    }
+
+    /// send the entity on a tile walking to another tile
+    fn move_entity(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let Some(entity) = self.entities.at_tile(from.0, from.1) else {
+            log::warn!("no entity to move at {from:?}");
+            return;
+        };
+        let to = Self::center(to);
+        if !self.entities.walk_to(&self.map, entity, to, WALK_SPEED) {
+            log::warn!("no way for entity at {from:?} to get to {to}");
+        }
+    }
+
+    /// take the entity on a tile out of the level, returns whether there was one
+    fn remove_entity(&mut self, (x, y): (usize, usize)) -> bool {
+        match self.entities.at_tile(x, y) {
+            Some(entity) => {
+                self.entities.despawn(entity);
+                true
+            }
+            None => {
+                log::warn!("no entity to remove at {x}, {y}");
+                false
+            }
+        }
+    }
+
+    /// exchange states with the other player and keep their entity in sync
+    fn net_tick(&mut self) {
+        #[cfg(not(target_os = "emscripten"))]
+        if let Some(net) = self.net.as_mut() {
+            if matches!(self.game_state, GameState::Playing | GameState::Minimap) {
+                let state = Packet::State {
+                    pos: self.player.pos,
+                    direction: self.player.direction,
+                    health: self.player.health,
+                };
+                if let Err(err) = net.send(&state) {
+                    log::warn!("could not send state: {err}");
+                }
+            }
+
+            let events = match net.poll() {
+                Ok(events) => events,
+                Err(err) => {
+                    log::warn!("could not receive: {err}");
+                    return;
+                }
+            };
+            for event in events {
+                match event {
+                    Event::Joined => self.show_text("message.partner_joined"),
+                    Event::Left => self.show_text("message.partner_left"),
+                    Event::Moved => {}
+                    Event::Message(text) => self.show_message(text),
+                }
+                self.update = true;
+            }
+
+            let peer = self.net.as_ref().and_then(|net| net.peer);
+            match (peer, self.peer) {
+                (Some(peer), Some(entity)) => {
+                    self.entities.positions.insert(entity, peer.pos);
+                    self.entities.health.insert(entity, peer.health);
+                }
+                (Some(peer), None) => {
+                    let entity = self.entities.spawn();
+                    self.entities.positions.insert(entity, peer.pos);
+                    self.entities.sprites.insert(entity, PLAYER_SPRITE.into());
+                    self.entities.health.insert(entity, peer.health);
+                    self.peer = Some(entity);
+                }
+                (None, Some(entity)) => {
+                    self.entities.despawn(entity);
+                    self.peer = None;
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// tell the other player in co-op about something
+    #[cfg_attr(target_os = "emscripten", allow(unused_variables))]
+    fn broadcast(&self, text: &str) {
+        #[cfg(not(target_os = "emscripten"))]
+        if let Some(net) = self.net.as_ref() {
+            if let Err(err) = net.send(&Packet::Message(text.into())) {
+                log::warn!("could not send message: {err}");
+            }
+        }
+    }
+
+    /// show a message on screen for a few seconds
+    pub fn show_message(&mut self, msg: impl Into<String>) {
+        self.messages.push((msg.into(), MESSAGE_FRAMES));
+        self.update = true;
+    }
+
+    /// count a shown frame, and put the map being played and the frame rate in the window title
+    pub fn update_title(&mut self) {
+        self.frame_stats.frame(self.renderer.cache_stats());
+        self.fps_frames += 1;
+        let elapsed = self.fps_since.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.fps = Some((self.fps_frames as f32 / elapsed.as_secs_f32()).round() as u32);
+            (self.fps_frames, self.fps_since) = (0, Instant::now());
+        }
+
+        let mut title = TITLE.to_string();
+        if matches!(
+            self.game_state,
+            GameState::Playing
+                | GameState::Minimap
+                | GameState::Automap
+                | GameState::Dialogue
+                | GameState::Briefing
+                | GameState::Paused
+                | GameState::Stats
+                | GameState::Spectating
+        ) {
+            title.push_str(&format!(" - {}", self.level_name()));
+        }
+        if let Some(fps) = self.fps {
+            title.push_str(&format!(" - {fps} FPS"));
+        }
+
+        if title != self.title {
+            if let Err(err) = self.renderer.set_title(&title) {
+                log::warn!("could not set window title: {err}");
+            }
+            self.title = title;
+        }
+    }
+
+    /// get the attention of players away from the window, for things they would not want to miss
+    pub fn flash(&mut self) {
+        if let Err(err) = self.renderer.flash() {
+            log::warn!("could not flash window: {err}");
+        }
+    }
+
+    /// show the text of a language key on the hud
+    fn show_text(&mut self, key: &str) {
+        let text = self.lang.get(key).to_string();
+        self.show_message(text);
+    }
+
+    /// whether the screen keeps changing without any input
+    pub fn animating(&self) -> bool {
+        // recordings want every frame
+        !self.messages.is_empty() || !self.post.is_empty() || self.recording.is_some()
+    }
+
+    /// handle a key press in whatever state the game is in
+    pub fn key_pressed(&mut self, key: Keycode) {
+        self.menu_idle = 0;
+        if self.demo.is_some() {
+            // the spectator camera looks around the demo instead of stopping it
+            if key == SPECTATE_KEY {
+                self.toggle_spectator();
+            } else if self.spectator.is_none() || key == Keycode::Escape {
+                self.stop_demo();
+            } else {
+                self.key_down(key);
+            }
+            return;
+        }
+        self.press(key);
+    }
+
+    /// handle a key press that is not about a demo
+    fn press(&mut self, key: Keycode) {
+        if self.key_down(key) {
+            return;
+        }
+
+        if key == Keycode::Backquote {
+            self.console.toggle();
+            self.update = true;
+            return;
+        }
+        if self.console.open {
+            if let Some(line) = self.console.key(key) {
+                self.run_command(&line);
+            }
+            self.update = true;
+            return;
+        }
+
+        let was_playing = matches!(self.game_state, GameState::Playing | GameState::Minimap);
+        match self.game_state {
+            GameState::Menu => self.menu_key_once(key),
+            GameState::Playing | GameState::Minimap => self.playing_key_once(key),
+            GameState::Automap => self.automap_key_once(key),
+            GameState::Dialogue => self.dialogue_key_once(key),
+            GameState::Briefing => self.briefing_key_once(key),
+            GameState::Paused => self.pause_key_once(key),
+            GameState::Campaigns => self.campaigns_key_once(key),
+            GameState::Achievements => self.achievements_key_once(key),
+            GameState::MapSelect => self.map_select_key_once(key),
+            GameState::RandomLevel => self.random_key_once(key),
+            GameState::Settings => self.settings_key_once(key),
+            GameState::Modifiers => self.modifiers_key_once(key),
+            GameState::Intermission => self.intermission_key_once(key),
+            GameState::Stats => self.stats_key_once(key),
+            GameState::Spectating => self.spectating_key_once(key),
+            GameState::Help | GameState::Credits => self.scroll_key_once(key),
+            GameState::Error => self.error_key_once(key),
+            GameState::Loading | GameState::Exit => {}
+        }
+        if !was_playing
+            && matches!(
+                self.game_state,
+                GameState::Playing | GameState::Minimap | GameState::Loading
+            )
+        {
+            self.ignored.insert(key);
+        }
+
+        self.update = true;
+    }
+
+    /// handle keys being held down, once per frame
+    pub fn keys_held(&mut self, keys: &HashSet<Keycode>) {
+        if self.console.open {
+            return;
+        }
+
+        // the spectator camera flies with real keys, even while a demo plays
+        if let Some(mut spectator) = self.spectator {
+            for key in keys.iter() {
+                if !self.ignored.contains(key) {
+                    self.walk(&mut spectator, self.bindings[0], *key);
+                    self.update = true;
+                }
+            }
+            self.spectator = Some(spectator);
+            return;
+        }
+
+        // demos hold their own keys
+        if self.demo.is_none() {
+            self.hold_keys(keys);
+        }
+    }
+
+    /// handle held keys in whatever state the game is in
+    fn hold_keys(&mut self, keys: &HashSet<Keycode>) {
+        for key in keys.iter() {
+            if self.ignored.contains(key) {
+                continue;
+            }
+
+            match self.game_state {
+                GameState::Menu => {
+                    self.menu_key(*key);
+                    self.update = true;
+                }
+                GameState::Playing | GameState::Minimap => {
+                    self.playing_key(*key);
+                    self.update = true;
+                }
+                GameState::Automap
+                | GameState::Dialogue
+                | GameState::Briefing
+                | GameState::Paused
+                | GameState::Campaigns
+                | GameState::Achievements
+                | GameState::MapSelect
+                | GameState::RandomLevel
+                | GameState::Settings
+                | GameState::Modifiers
+                | GameState::Intermission
+                | GameState::Stats
+                | GameState::Spectating
+                | GameState::Help
+                | GameState::Credits
+                | GameState::Loading
+                | GameState::Error
+                | GameState::Exit => {}
+            }
+        }
+    }
+
+    /// whether anything takes typed text right now, text input is only on while it does
+    pub fn wants_text(&self) -> bool {
+        self.console.open || self.game_state == GameState::RandomLevel
+    }
+
+    /// handle typed text, the console and the seed of random levels take it
+    pub fn text_input(&mut self, text: &str) {
+        if self.console.open {
+            self.console.type_text(text);
+            self.update = true;
+        } else if self.game_state == GameState::RandomLevel {
+            self.random_seed.type_text(text);
+            self.random_menu = self.random_menu();
+            self.update = true;
+        }
+    }
+
+    /// run a line entered into the console
+    fn run_command(&mut self, line: &str) {
+        let command = match line.parse::<console::Command>() {
+            Ok(command) => command,
+            Err(err) => {
+                self.console.print(err.to_string());
+                return;
+            }
+        };
+
+        match command {
+            console::Command::Teleport(x, y) => {
+                if x >= self.map.width || y >= self.map.height {
+                    self.console.print("outside of the map");
+                    return;
+                }
+                self.player.pos = Self::center((x, y));
+                self.particles.emit(Effect::Teleport, self.player.pos);
+            }
+            console::Command::Noclip => {
+                self.player.noclip = !self.player.noclip;
+                self.console.print(format!(
+                    "noclip {}",
+                    if self.player.noclip { "on" } else { "off" }
+                ));
+            }
+            console::Command::FreeCamera => {
+                self.free_camera = match self.free_camera {
+                    Some(_) => None,
+                    None => Some(Player {
+                        noclip: true,
+                        ..self.player
+                    }),
+                };
+                self.console.print(format!(
+                    "free camera {}",
+                    if self.free_camera.is_some() {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ));
+            }
+            console::Command::GiveHealth(amount) => {
+                self.player.health = self.player.health.saturating_add(amount);
+            }
+            console::Command::Map(name, entrance) => {
+                let path = Path::new(MAP_DIR).join(name).with_extension("yaw");
+                if !assets::resolve(&path).exists() {
+                    self.console
+                        .print(format!("no such map: {}", path.display()));
+                    return;
+                }
+                self.campaign = None;
+                self.entrance = entrance;
+                self.start_loading(path, GameState::Playing);
+            }
+            console::Command::Fov(fov) => self.fov = fov.clamp(MIN_FOV, MAX_FOV),
+            console::Command::Clear => self.console.clear(),
+            console::Command::Help => self.console.print(console::HELP),
+        }
+    }
+
+    /// draw whatever state the game is in
+    pub fn draw(&mut self) -> anyhow::Result<()> {
+        let drawn = match self.game_state {
+            GameState::Menu => self.menu_draw(),
+            GameState::Playing | GameState::Minimap | GameState::Spectating => {
+                let start = Instant::now();
+                self.cast_rays();
+                let drawn = self.playing_draw();
+                // casting fewer rays next frame means casting again
+                if self.scaler.frame(start.elapsed()) {
+                    self.rays_dirty = true;
+                }
+                drawn
+            }
+            GameState::Automap => self.automap_draw(),
+            GameState::Dialogue => self.dialogue_draw(),
+            GameState::Briefing => self.briefing_draw(),
+            GameState::Paused => self.pause_draw(),
+            GameState::Campaigns => self.campaigns_draw(),
+            GameState::Achievements => self.achievements_draw(),
+            GameState::MapSelect => self.map_select_draw(),
+            GameState::RandomLevel => self.random_draw(),
+            GameState::Settings => self.settings_draw(),
+            GameState::Modifiers => self.modifiers_draw(),
+            GameState::Intermission => self.intermission_draw(),
+            GameState::Stats => self.stats_draw(),
+            GameState::Help | GameState::Credits => self.scroll_draw(),
+            GameState::Loading => self.loading_draw(),
+            GameState::Error => self.error_draw(),
+            GameState::Exit => Ok(()),
+        };
+        // only an error screen that cannot be drawn either ends the game
+        if let Err(err) = drawn {
+            if self.game_state == GameState::Error {
+                return Err(err);
+            }
+            let err = err.context(format!("could not draw {:?}", self.game_state));
+            self.fail(&err, Retry::Draw(self.game_state));
+            self.error_draw()?;
+        }
+
+        // overlays like the minimap and pause menu open without fading
+        let in_game = |state| {
+            matches!(
+                state,
+                GameState::Playing | GameState::Minimap | GameState::Dialogue | GameState::Paused
+            )
+        };
+        if self.game_state != self.last_state
+            && !(in_game(self.game_state) && in_game(self.last_state))
+        {
+            self.post.push(PostEffect::Fade, None);
+        }
+        self.last_state = self.game_state;
+        self.post.draw(
+            self.renderer.as_mut(),
+            Rect::new(0, 0, WIDTH as u32, HEIGHT as u32),
+        )?;
+
+        if self.console.open {
+            self.console
+                .draw(self.renderer.as_mut(), WIDTH as u32, &self.theme)?;
+        }
+        self.overlay_draw()
+    }
+
+    /// handle key presses, regardless of state, returns whether the key was used
+    fn key_down(&mut self, key: Keycode) -> bool {
+        match key {
+            // take a screenshot once the current frame is drawn
+            Keycode::F12 => {
+                self.screenshot = true;
+                self.update = true;
+                true
+            }
+            Keycode::F10 => {
+                self.toggle_recording();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// start recording a clip, or save the one being recorded
+    fn toggle_recording(&mut self) {
+        self.update = true;
+        let Some(recording) = self.recording.take() else {
+            self.recording = Some(Recording::new());
+            self.show_text("message.recording");
+            return;
+        };
+
+        match recording.save() {
+            Ok(path) => self.show_message(
+                self.lang
+                    .fill("message.recording_saved", &[("path", &path.display())]),
+            ),
+            Err(err) => {
+                log::error!("could not save recording: {err}");
+                self.show_text("message.recording_failed");
+            }
+        }
+    }
+
+    /// build every menu again in the current language
+    fn translate_menus(&mut self) {
+        self.menu = self.main_menu();
+        self.pause_menu = self.pause_menu();
+        self.error_menu = self.error_menu();
+        self.settings = self.settings_menu();
+        self.random_menu = self.random_menu();
+        self.modifier_menu = self.modifier_menu();
+        self.campaign_menu = self.campaign_menu();
+        self.achievement_menu = self.achievement_menu();
+        self.map_menu = self.map_menu();
+    }
+
+    /// main menu with the current difficulty, keeping the selected item
+    fn main_menu(&self) -> Menu<MenuItem> {
+        let lang = &self.lang;
+        let difficulty = lang.get(self.difficulty.key());
+        let mut items = vec![
+            (lang.get("menu.play").into(), MenuItem::Play),
+            (lang.get("menu.map_select").into(), MenuItem::MapSelect),
+            (lang.get("menu.random_level").into(), MenuItem::RandomLevel),
+            (lang.get("menu.campaigns").into(), MenuItem::Campaigns),
+            (lang.get("menu.achievements").into(), MenuItem::Achievements),
+            (
+                lang.fill("menu.difficulty", &[("difficulty", &difficulty)]),
+                MenuItem::Difficulty,
+            ),
+            (lang.get("menu.settings").into(), MenuItem::Settings),
+            (lang.get("menu.help").into(), MenuItem::Help),
+            (lang.get("menu.credits").into(), MenuItem::Credits),
+            (lang.get("menu.quit").into(), MenuItem::Quit),
+        ];
+        // a main campaign left in the middle can be picked up again
+        let resume = self.main_campaign().and_then(|idx| {
+            self.progress
+                .step(&self.campaigns[idx].path.display().to_string())
+        });
+        // unlocked by finishing any campaign
+        if self.progress.any_finished() {
+            let campaigns = items
+                .iter()
+                .position(|(_, item)| *item == MenuItem::Campaigns)
+                .unwrap_or_default();
+            items.insert(
+                campaigns + 1,
+                (lang.get("menu.modifiers").into(), MenuItem::Modifiers),
+            );
+        }
+        if resume.is_some() {
+            items.insert(0, (lang.get("menu.continue").into(), MenuItem::Continue));
+        }
+        // continue comes and goes in front of the rest, which would shift the selection onto
+        // another item
+        let mut menu = Menu::new("yaw", items);
+        menu.keep_selected(&self.menu);
+        menu
+    }
+
+    /// handle key presses for while in "menu" state
+    fn menu_key_once(&mut self, key: Keycode) {
+        let item = match key {
+            // shortcuts
+            Keycode::C => Some(MenuItem::Campaigns),
+            Keycode::Backspace => Some(MenuItem::Quit),
+            _ => self.menu.key(key),
+        };
+
+        match item {
+            Some(MenuItem::Continue) => {
+                if let Some(idx) = self.main_campaign() {
+                    let key = self.campaigns[idx].path.display().to_string();
+                    let step = self.progress.step(&key).unwrap_or_default();
+                    self.start_campaign(idx, step, GameState::Menu);
+                }
+            }
+            Some(MenuItem::Play) => match self.main_campaign() {
+                Some(idx) => self.start_campaign(idx, 0, GameState::Menu),
+                None => self.game_state = GameState::Playing,
+            },
+            Some(MenuItem::MapSelect) => self.game_state = GameState::MapSelect,
+            Some(MenuItem::RandomLevel) => self.game_state = GameState::RandomLevel,
+            Some(MenuItem::Campaigns) => self.game_state = GameState::Campaigns,
+            Some(MenuItem::Modifiers) => self.game_state = GameState::Modifiers,
+            Some(MenuItem::Achievements) => self.game_state = GameState::Achievements,
+            Some(MenuItem::Difficulty) => {
+                self.difficulty = self.difficulty.next();
+                // players in the menu have not started yet, so they start with the new health
+                let health = self.start_health();
+                self.player.health = health;
+                if let Some(second) = self.second.as_mut() {
+                    second.health = health;
+                }
+                self.last_health = self.healths();
+                self.menu = self.main_menu();
+            }
+            Some(MenuItem::Settings) => {
+                self.settings_return = GameState::Menu;
+                self.game_state = GameState::Settings;
+            }
+            Some(MenuItem::Help) => {
+                self.scroll = self.help();
+                self.game_state = GameState::Help;
+            }
+            Some(MenuItem::Credits) => {
+                self.scroll = ScrollText::new(
+                    self.lang.get("credits.title"),
+                    CREDITS.lines().map(String::from).collect(),
+                );
+                self.game_state = GameState::Credits;
+            }
+            Some(MenuItem::Quit) => self.game_state = GameState::Exit,
+            None => {}
+        }
+    }
+
+    /// handle key repeating for while in "menu" state
+    fn menu_key(&mut self, _key: Keycode) {}
+
+    /// draw menu over the level
+    fn menu_draw(&mut self) -> anyhow::Result<()> {
+        if let Err(err) = self.attract_draw() {
+            // the menu has to stay usable whatever is wrong with the level
+            log::debug!("could not draw the level behind the menu: {err:#}");
+            self.renderer.draw_rect(None, Color::BLACK)?;
+        }
+        self.renderer.draw_rect(None, MENU_DIM)?;
+        self.menu.draw(self.renderer.as_mut(), &self.theme)
+    }
+
+    /// turn the view behind the menu, and play the demo after a while without input
+    fn attract_tick(&mut self) {
+        if self.game_state != GameState::Menu || self.console.open {
+            self.menu_idle = 0;
+            return;
+        }
+
+        self.attract = Player {
+            direction: (self.attract.direction + ATTRACT_TURN) % (2. * PI),
+            ..self.player
+        };
+        self.update = true;
+
+        self.menu_idle += 1;
+        if self.menu_idle >= DEMO_IDLE && self.demo.is_none() {
+            self.menu_idle = 0;
+            match Demo::load() {
+                Ok(demo) => self.demo = demo,
+                Err(err) => log::warn!("could not load demo: {err:#}"),
+            }
+        }
+    }
+
+    /// draw the level as seen by the view behind the menu
+    fn attract_draw(&mut self) -> anyhow::Result<()> {
+        self.cast_rays();
+        let (view, _) = self.viewports();
+        let camera = self.camera(self.view(), view);
+        let mut visibility = Visibility::new(&self.map);
+        visibility.see(&self.map, camera.pos, &self.slices, &self.entities);
+        let sprites = visibility.sprites(&self.entities);
+
+        camera.draw(&self.map, &self.slices, self.renderer.as_mut(), view)?;
+        camera.draw_sprites(&self.depth, &sprites, self.renderer.as_mut(), view)
+    }
+
+    /// play the input of the demo for this tick, going back to the menu once it is over
+    fn demo_tick(&mut self) {
+        // levels loading hold the demo, however long that takes
+        if self.game_state == GameState::Loading {
+            return;
+        }
+        let Some(demo) = self.demo.as_mut() else {
+            return;
+        };
+        let Some(tick) = demo.next().cloned() else {
+            self.stop_demo();
+            return;
+        };
+
+        // past what a real key press does to a demo, so it keeps playing and does not count
+        for key in tick.pressed {
+            self.press(key);
+        }
+        self.hold_keys(&tick.held);
+        for key in tick.released {
+            self.release(key);
+        }
+    }
+
+    /// stop the demo, back on the menu with the level it started on
+    fn stop_demo(&mut self) {
+        self.demo = None;
+        self.spectator = None;
+        self.campaign = None;
+        self.console.open = false;
+        self.ignored.clear();
+        self.reset_players();
+        if let Err(err) = self.load_level(DEFAULT_MAP.into()) {
+            log::error!("could not load map: {err}");
+        }
+        self.game_state = GameState::Menu;
+        self.update = true;
+    }
+
+    /// settings with labels showing their current values
+    fn settings_menu(&self) -> Menu<Setting> {
+        let lang = &self.lang;
+        let on_off = |on: bool| lang.get(if on { "settings.on" } else { "settings.off" });
+        let setting = |key, value: &str| lang.fill(key, &[("value", &value)]);
+        let sound = self.audio.as_ref().is_some_and(|audio| !audio.muted);
+        let minimap = if self.minimap_seen {
+            "minimap.seen"
+        } else {
+            "minimap.all"
+        };
+        let minimap_view = match self.minimap_view {
+            MinimapView::Rays(stride) => lang.fill(self.minimap_view.key(), &[("stride", &stride)]),
+            MinimapView::Cone => lang.get(self.minimap_view.key()).into(),
+        };
+        let mut menu = Menu::new(
+            lang.get("settings.title"),
+            vec![
+                (setting("settings.sound", on_off(sound)), Setting::Sound),
+                (setting("settings.fov", &self.fov.to_string()), Setting::Fov),
+                (
+                    setting("settings.minimap", lang.get(minimap)),
+                    Setting::Minimap,
+                ),
+                (
+                    setting("settings.minimap_view", &minimap_view),
+                    Setting::MinimapView,
+                ),
+                (
+                    setting("settings.crosshair", lang.get(self.crosshair.key())),
+                    Setting::Crosshair,
+                ),
+                (
+                    setting("settings.render_scale", lang.get(self.scaler.setting.key())),
+                    Setting::RenderScale,
+                ),
+                (
+                    setting("settings.timer", on_off(self.show_timer)),
+                    Setting::Timer,
+                ),
+                (
+                    setting("settings.language", &lang.current().name),
+                    Setting::Language,
+                ),
+                (
+                    setting("settings.high_contrast", on_off(self.config.high_contrast)),
+                    Setting::HighContrast,
+                ),
+                (
+                    setting("settings.palette", lang.get(self.config.palette.key())),
+                    Setting::Palette,
+                ),
+                (
+                    setting("settings.effects", on_off(self.config.effects)),
+                    Setting::Effects,
+                ),
+                (
+                    setting("settings.reduce_motion", on_off(self.config.reduce_motion)),
+                    Setting::ReduceMotion,
+                ),
+                (lang.get("settings.back").into(), Setting::Back),
+            ],
+        );
+        menu.selected = self.settings.selected;
+        menu
+    }
+
+    /// handle key presses for while in "settings" state
+    fn settings_key_once(&mut self, key: Keycode) {
+        let setting = match key {
+            Keycode::Escape | Keycode::Backspace => Some(Setting::Back),
+            _ => self.settings.key(key),
+        };
+
+        match setting {
+            Some(Setting::Sound) => {
+                if let Some(audio) = self.audio.as_mut() {
+                    audio.muted = !audio.muted;
+                    if audio.muted {
+                        audio.stop();
+                    }
+                }
+            }
+            Some(Setting::Fov) => {
+                // wrap around to the narrowest after the widest
+                self.fov = if self.fov >= MAX_FOV {
+                    MIN_FOV
+                } else {
+                    (self.fov + FOV_STEP).min(MAX_FOV)
+                };
+            }
+            Some(Setting::Minimap) => self.minimap_seen = !self.minimap_seen,
+            Some(Setting::MinimapView) => self.minimap_view = self.minimap_view.next(),
+            Some(Setting::Crosshair) => self.crosshair = self.crosshair.next(),
+            Some(Setting::RenderScale) => {
+                self.scaler.setting = self.scaler.setting.next();
+                self.rays_dirty = true;
+            }
+            Some(Setting::Timer) => self.show_timer = !self.show_timer,
+            Some(Setting::Language) => {
+                self.lang.next();
+                self.translate_menus();
+            }
+            Some(Setting::HighContrast) => {
+                self.config.high_contrast = !self.config.high_contrast;
+                self.apply_theme();
+                self.save_config();
+            }
+            Some(Setting::Palette) => {
+                self.config.palette = self.config.palette.next();
+                self.apply_theme();
+                self.save_config();
+            }
+            Some(Setting::Effects) => {
+                self.config.effects = !self.config.effects;
+                self.save_config();
+            }
+            Some(Setting::ReduceMotion) => {
+                self.config.reduce_motion = !self.config.reduce_motion;
+                self.save_config();
+            }
+            Some(Setting::Back) => self.game_state = self.settings_return,
+            None => {}
+        }
+        self.settings = self.settings_menu();
+    }
+
+    /// draw with the theme as loaded, in the colors of the palette and maybe in high contrast
+    fn apply_theme(&mut self) {
+        let theme = self
+            .base_theme
+            .with_palette(self.config.palette)
+            .unwrap_or_else(|err| {
+                log::error!("{err:#}");
+                self.base_theme.clone()
+            });
+        self.theme = if self.config.high_contrast {
+            theme.high_contrast()
+        } else {
+            theme
+        };
+    }
+
+    fn save_config(&self) {
+        if let Err(err) = self.config.save() {
+            log::warn!("could not save settings: {err}");
+        }
+    }
+
+    /// draw settings
+    fn settings_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.settings.draw(self.renderer.as_mut(), &self.theme)
+    }
+
+    /// handle key presses for while in "map select" state
+    fn map_select_key_once(&mut self, key: Keycode) {
+        if matches!(key, Keycode::Escape | Keycode::Backspace) {
+            self.game_state = GameState::Menu;
+            return;
+        }
+
+        if let Some(path) = self.map_menu.key(key) {
+            self.play_map(path);
+        }
+    }
+
+    /// play a single map, outside of any campaign
+    pub fn play_map(&mut self, path: PathBuf) {
+        self.reset_players();
+        self.campaign = None;
+        self.start_loading(path, GameState::Playing);
+    }
+
+    /// every map with who made it and its par time
+    fn map_menu(&self) -> Menu<PathBuf> {
+        let lang = &self.lang;
+        let mut menu = Menu::new(
+            lang.get("maps.title"),
+            self.maps
+                .iter()
+                .map(|map| {
+                    let mut text = map_title(lang, &map.name, map.author.as_deref());
+                    if let Some(par) = map.par_time {
+                        let par = stats::clock(u64::from(par) * TARGET_FPS, false);
+                        text = lang.fill("maps.par", &[("map", &text), ("par", &par)]);
+                    }
+                    (text, map.path.clone())
+                })
+                .collect(),
+        );
+        menu.selected = self.map_menu.selected;
+        menu
+    }
+
+    /// draw map list, with the layout of the selected map in the corner
+    fn map_select_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.map_menu.draw(self.renderer.as_mut(), &self.theme)?;
+
+        let Some(map) = self.maps.get(self.map_menu.selected) else {
+            return Ok(());
+        };
+        let image = &map.thumbnail;
+        let (width, height) = (WIDTH as i32 / 3, HEIGHT as i32 / 3);
+        let size = (width / image.width.max(1) as i32)
+            .min(height / image.height.max(1) as i32)
+            .max(1);
+        let margin = self.theme.margin;
+        let corner = (
+            WIDTH as i32 - margin - (image.width as i32 * size),
+            HEIGHT as i32 - margin - (image.height as i32 * size),
+        );
+        for (idx, pixel) in image.pixels.iter().enumerate() {
+            let (x, y) = ((idx % image.width) as i32, (idx / image.width) as i32);
+            self.renderer.draw_rect(
+                Some(Rect::new(
+                    corner.0 + (x * size),
+                    corner.1 + (y * size),
+                    size as u32,
+                    size as u32,
+                )),
+                Color::from_argb(*pixel),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// random level options with labels showing their current values
+    fn random_menu(&self) -> Menu<RandomItem> {
+        let lang = &self.lang;
+        let layout = lang.get(match self.random_layout {
+            Layout::Rooms => "layout.rooms",
+            Layout::Maze => "layout.maze",
+        });
+        let mut menu = Menu::new(
+            lang.get("random.title"),
+            vec![
+                (
+                    lang.fill(
+                        "random.seed",
+                        &[("seed", &self.random_seed.with_cursor('_'))],
+                    ),
+                    RandomItem::Seed,
+                ),
+                (
+                    lang.fill("random.layout", &[("layout", &layout)]),
+                    RandomItem::Layout,
+                ),
+                (lang.get("random.play").into(), RandomItem::Play),
+                (lang.get("random.back").into(), RandomItem::Back),
+            ],
+        );
+        menu.selected = self.random_menu.selected;
+        menu
+    }
+
+    /// handle key presses for while in "random level" state
+    fn random_key_once(&mut self, key: Keycode) {
+        let item = match key {
+            Keycode::Escape => Some(RandomItem::Back),
+            _ if self.random_seed.key(key) => None,
+            _ => self.random_menu.key(key),
+        };
+
+        match item {
+            Some(RandomItem::Seed) => self.random_seed.set(&random_seed().to_string()),
+            Some(RandomItem::Layout) => self.random_layout = self.random_layout.next(),
+            Some(RandomItem::Play) => {
+                if let Err(err) = self.play_random_level() {
+                    log::error!("could not generate level: {err}");
+                    self.show_text("message.generate_failed");
+                }
+            }
+            Some(RandomItem::Back) => self.game_state = GameState::Menu,
+            None => {}
+        }
+        self.random_menu = self.random_menu();
+    }
+
+    /// generate a level from the template and load it like any other map
+    fn play_random_level(&mut self) -> anyhow::Result<()> {
+        // an empty seed is 0, like it was typed
+        let seed = self.random_seed.text().parse().unwrap_or_default();
+        let template = Map::load(MAPGEN_TEMPLATE.into())?;
+        let map = mapgen::generate(
+            &template,
+            RANDOM_WIDTH,
+            RANDOM_HEIGHT,
+            self.random_layout,
+            seed,
+        )?;
+
+        // the level only exists in memory, next to the template so its textures are found
+        let path =
+            template
+                .prefix()
+                .join(format!("random-{}-{}.yaw", self.random_layout.name(), seed));
+        assets::insert(&path, map.to_yaw().into_bytes());
+
+        self.reset_players();
+        self.campaign = None;
+        self.start_loading(path, GameState::Playing);
+        Ok(())
+    }
+
+    /// draw random level options
+    fn random_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.random_menu.draw(self.renderer.as_mut(), &self.theme)
+    }
+
+    /// modifier options with labels showing whether they are on
+    fn modifier_menu(&self) -> Menu<ModifierItem> {
+        let lang = &self.lang;
+        let on_off = |on: bool| lang.get(if on { "settings.on" } else { "settings.off" });
+        let mut menu = Menu::new(
+            lang.get("modifiers.title"),
+            vec![
+                (
+                    lang.fill(
+                        "modifiers.fast_enemies",
+                        &[("value", &on_off(self.modifiers.fast_enemies))],
+                    ),
+                    ModifierItem::FastEnemies,
+                ),
+                (
+                    lang.fill(
+                        "modifiers.mirrored",
+                        &[("value", &on_off(self.modifiers.mirrored))],
+                    ),
+                    ModifierItem::Mirrored,
+                ),
+                (lang.get("modifiers.back").into(), ModifierItem::Back),
+            ],
+        );
+        menu.selected = self.modifier_menu.selected;
+        menu
+    }
+
+    /// handle key presses for while in "modifiers" state
+    fn modifiers_key_once(&mut self, key: Keycode) {
+        let item = match key {
+            Keycode::Escape | Keycode::Backspace => Some(ModifierItem::Back),
+            _ => self.modifier_menu.key(key),
+        };
+
+        match item {
+            Some(ModifierItem::FastEnemies) => {
+                self.modifiers.fast_enemies = !self.modifiers.fast_enemies;
+            }
+            Some(ModifierItem::Mirrored) => self.modifiers.mirrored = !self.modifiers.mirrored,
+            Some(ModifierItem::Back) => self.game_state = GameState::Menu,
+            None => {}
+        }
+        self.modifier_menu = self.modifier_menu();
+    }
+
+    /// draw modifier options
+    fn modifiers_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.modifier_menu.draw(self.renderer.as_mut(), &self.theme)
+    }
+
+    /// handle key presses for while in "campaigns" state
+    fn campaigns_key_once(&mut self, key: Keycode) {
+        if matches!(key, Keycode::Escape | Keycode::Backspace) {
+            self.game_state = GameState::Menu;
+            return;
+        }
+
+        let Some(idx) = self.campaign_menu.key(key) else {
+            return;
+        };
+        if !self.locked(idx) {
+            self.start_campaign(idx, 0, GameState::Campaigns);
+        }
+    }
+
+    /// play a campaign from a step, going back to `back` when it cannot be started
+    fn start_campaign(&mut self, idx: usize, step: usize, back: GameState) {
+        self.reset_players();
+        let started = if self.campaigns[idx].hub.is_some() {
+            self.set_health(self.difficulty.health(self.campaigns[idx].health));
+            self.return_to_hub(idx, step)
+        } else {
+            self.campaign_step(idx, step)
+        };
+        if let Err(err) = started {
+            log::error!("could not start campaign: {err}");
+            self.show_text("message.campaign_failed");
+            self.campaign = None;
+            self.game_state = back;
+        }
+    }
+
+    /// index of the campaign play starts, when the data has one
+    fn main_campaign(&self) -> Option<usize> {
+        self.campaigns
+            .iter()
+            .position(|campaign| campaign.path == Path::new(MAIN_CAMPAIGN))
+    }
+
+    /// whether a campaign waits for another one to be finished first
+    fn locked(&self, idx: usize) -> bool {
+        self.campaigns[idx]
+            .unlock
+            .as_ref()
+            .is_some_and(|unlock| !self.progress.finished(&unlock.display().to_string()))
+    }
+
+    /// every campaign and how many levels it has
+    fn campaign_menu(&self) -> Menu<usize> {
+        let mut menu = Menu::new(
+            self.lang.get("campaigns.title"),
+            self.campaigns
+                .iter()
+                .enumerate()
+                .map(|(idx, campaign)| {
+                    let text = match &campaign.unlock {
+                        Some(unlock) if self.locked(idx) => {
+                            // by its file when there is no such campaign
+                            let name = self
+                                .campaigns
+                                .iter()
+                                .find(|other| &other.path == unlock)
+                                .map(|other| other.name.clone())
+                                .unwrap_or_else(|| unlock.display().to_string());
+                            self.lang.fill(
+                                "campaigns.locked",
+                                &[("name", &campaign.name), ("unlock", &name)],
+                            )
+                        }
+                        _ => self.lang.fill(
+                            "campaigns.campaign",
+                            &[("name", &campaign.name), ("levels", &campaign.levels())],
+                        ),
+                    };
+                    (text, idx)
+                })
+                .collect(),
+        );
+        menu.selected = self.campaign_menu.selected;
+        menu
+    }
+
+    /// draw campaign list
+    fn campaigns_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.campaign_menu.draw(self.renderer.as_mut(), &self.theme)
+    }
+
+    /// every achievement, marked by whether it is unlocked
+    fn achievement_menu(&self) -> Menu<Achievement> {
+        let mut menu = Menu::new(
+            self.lang.get("achievements.title"),
+            Achievement::ALL
+                .into_iter()
+                .map(|achievement| {
+                    let mark = if self.achievements.unlocked(achievement) {
+                        "[x]"
+                    } else {
+                        "[ ]"
+                    };
+                    let (name, description) = achievement.keys();
+                    let description = self.lang.fill(
+                        description,
+                        &[("seconds", &achievement.seconds().unwrap_or_default())],
+                    );
+                    (
+                        format!("{mark} {}: {description}", self.lang.get(name)),
+                        achievement,
+                    )
+                })
+                .collect(),
+        );
+        menu.selected = self.achievement_menu.selected;
+        menu
+    }
+
+    /// handle key presses for while in "achievements" state, only scrolling the list
+    fn achievements_key_once(&mut self, key: Keycode) {
+        if matches!(key, Keycode::Escape | Keycode::Backspace) {
+            self.game_state = GameState::Menu;
+            return;
+        }
+        self.achievement_menu.key(key);
+    }
+
+    /// draw the achievement list
+    fn achievements_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.achievement_menu
+            .draw(self.renderer.as_mut(), &self.theme)
+    }
+
+    /// keys of every player and the ones everyone shares, with what they do
+    fn help(&self) -> ScrollText {
+        let lang = &self.lang;
+        let line = |key: Keycode, action| format!("{:<14}{}", key.name(), lang.get(action));
+        let mut lines = Vec::new();
+        let players = if self.second.is_some() { 2 } else { 1 };
+        for (idx, bindings) in self.bindings.iter().take(players).enumerate() {
+            if players > 1 {
+                lines.push(lang.fill("help.player", &[("player", &(idx + 1))]));
+            }
+            lines.extend([
+                line(bindings.forward, "help.forward"),
+                line(bindings.back, "help.back"),
+                line(bindings.strafe_left, "help.strafe_left"),
+                line(bindings.strafe_right, "help.strafe_right"),
+                line(bindings.turn_left, "help.turn_left"),
+                line(bindings.turn_right, "help.turn_right"),
+                line(bindings.sprint, "help.sprint"),
+                String::new(),
+            ]);
+        }
+        lines.extend([
+            line(Keycode::E, "help.use"),
+            line(Keycode::F, "help.flashlight"),
+            line(Keycode::M, "help.minimap"),
+            line(Keycode::Tab, "help.automap"),
+            line(Keycode::Escape, "help.pause"),
+            line(Keycode::Backquote, "help.console"),
+            line(Keycode::F10, "help.recording"),
+            line(Keycode::F12, "help.screenshot"),
+        ]);
+
+        ScrollText::new(lang.get("help.title"), lines)
+    }
+
+    /// handle key presses for while in "help" or "credits" state
+    fn scroll_key_once(&mut self, key: Keycode) {
+        if matches!(key, Keycode::Escape | Keycode::Backspace) {
+            self.game_state = GameState::Menu;
+            return;
+        }
+        self.scroll.key(key);
+    }
+
+    /// draw the help or credits text
+    fn scroll_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.scroll.draw(self.renderer.as_mut(), &self.theme)
+    }
+
+    /// handle key presses for while in "intermission" state, any key continues
+    fn intermission_key_once(&mut self, _key: Keycode) {
+        if let Some((idx, step)) = self.campaign {
+            if let Err(err) = self.campaign_step(idx, step + 1) {
+                log::error!("could not load next level: {err}");
+                self.show_text("message.next_level_failed");
+                self.campaign = None;
+                self.game_state = GameState::Menu;
+            }
+        }
+    }
+
+    /// draw text screen between levels
+    fn intermission_draw(&mut self) -> anyhow::Result<()> {
+        let Some(intermission) = self.intermission.as_ref() else {
+            return Ok(());
+        };
+
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        if let Some(image) = intermission.image.as_ref() {
+            self.renderer.draw_image(image, None)?;
+        }
+
+        let style = self.theme.boxed();
+        let margin = self.theme.margin;
+        for (idx, line) in intermission.text.lines().enumerate() {
+            // empty lines cannot be rendered, but still take up space
+            if !line.is_empty() {
+                self.renderer.draw_text(
+                    line,
+                    &style,
+                    margin,
+                    margin + (idx as i32 * self.theme.line_height),
+                )?;
+            }
+        }
+
+        self.renderer.draw_text(
+            self.lang.get("continue"),
+            &style.italic(),
+            margin,
+            HEIGHT as i32 - 48,
+        )?;
+
+        Ok(())
+    }
+
+    /// handle key presses for while in "automap" state
+    fn automap_key_once(&mut self, key: Keycode) {
+        match key {
+            Keycode::Tab | Keycode::Escape => self.game_state = GameState::Playing,
+            key => {
+                self.automap.key(key, &self.map);
+            }
+        }
+    }
+
+    /// draw the automap with the items and players on it
+    fn automap_draw(&mut self) -> anyhow::Result<()> {
+        let items = self
+            .entities
+            .sprites
+            .keys()
+            .filter(|entity| {
+                !self.entities.ai.contains_key(entity)
+                    && !self.entities.pushable.contains(entity)
+                    && !self.entities.dialogue.contains_key(entity)
+            })
+            .filter_map(|entity| self.entities.positions.get(entity).copied())
+            .collect::<Vec<_>>();
+        let players = [Some(self.player), self.second]
+            .into_iter()
+            .flatten()
+            .map(|player| (player.pos, player.direction))
+            .collect::<Vec<_>>();
+        self.automap
+            .draw(self.renderer.as_mut(), &self.map, &items, &players)?;
+
+        // checklist of the objectives in the top left
+        let style = self.theme.boxed();
+        for (idx, objective) in self.map.objectives.iter().enumerate() {
+            let mark = if self.completed.contains(&objective.id) {
+                "x"
+            } else {
+                " "
+            };
+            self.renderer.draw_text(
+                &format!("[{mark}] {}", objective.text),
+                &style,
+                self.theme.margin,
+                self.theme.margin + (idx as i32 * self.theme.line_height),
+            )?;
+        }
 
-impl Player {
-    fn step(&self) -> Vec2 {
-        Vec2::from_angle(self.direction) * self.speed
+        self.renderer.draw_text(
+            self.lang.get("automap.hint"),
+            &self.theme.boxed(),
+            self.theme.margin,
+            HEIGHT as i32 - 32,
+        )?;
+        Ok(())
     }
-}
 
-const FOV: usize = 60;
-const DOF: usize = 24;
+    /// handle key presses for while in "stats" state, any key continues
+    fn stats_key_once(&mut self, key: Keycode) {
+        // look around the finished level before going on
+        if key == SPECTATE_KEY {
+            self.toggle_spectator();
+            self.game_state = GameState::Spectating;
+            return;
+        }
 
-pub(crate) struct Game {
-    map: Map,
-    player: Player,
-    pub game_state: GameState,
-    slices: Vec<RayCast>,
-    texture_creator: TextureCreator<WindowContext>,
-    pub canvas: Canvas<Window>,
-    font_ctx: Sdl2TtfContext,
-    pub update: bool,
-}
+        if let Err(err) = self.leave_level() {
+            log::error!("could not load next level: {err}");
+            self.show_text("message.next_level_failed");
+            self.campaign = None;
+            self.game_state = GameState::Menu;
+        }
+    }
 
-impl Game {
-    /// helper function to draw text
-    fn draw_text(
-        &mut self,
-        txt: impl AsRef<str>,
-        style: FontStyle,
-        size: u16,
-        fg: Color,
-        bg: Option<Color>,
-        bg_padding: Option<(u32, u32)>,
-        point: Point,
-    ) -> anyhow::Result<()> {
-        let mut font = self
-            .font_ctx
-            .load_font_from_rwops(RWops::from_bytes(super::FIXEDER_SYS).ah()?, size)
-            .ah()?;
-
-        font.set_style(style);
-        let texture = font
-            .render(txt.as_ref())
-            .solid(fg)?
-            .as_texture(&self.texture_creator)?;
-        let TextureQuery { width, height, .. } = texture.query();
-        let padding = bg_padding.unwrap_or((0, 0));
-        let rect = Rect::new(
-            point.x + padding.0 as i32,
-            point.y + padding.1 as i32,
-            width,
-            height,
-        );
-        if let Some(bg) = bg {
-            let prev_color = self.canvas.draw_color();
-            self.canvas.set_draw_color(bg);
-            self.canvas
-                .fill_rect(Rect::new(
-                    point.x,
-                    point.y,
-                    width + (padding.0 * 2),
-                    height + (padding.1 * 2),
-                ))
-                .ah()?;
-            self.canvas.set_draw_color(prev_color);
-        }
-        self.canvas.copy(&texture, None, rect).ah()?;
+    /// draw how the finished level went
+    fn stats_draw(&mut self) -> anyhow::Result<()> {
+        let name = self.level_name();
+        let stats = self.stats;
+        let lang = &self.lang;
+        let best = if self.new_best {
+            lang.get("stats.new_best").to_string()
+        } else {
+            let best = self.best_times.get(&self.level.display().to_string());
+            let time = stats::clock(best.unwrap_or_default(), true);
+            lang.fill("stats.best", &[("time", &time)])
+        };
+        let time = stats::clock(stats.frames, true);
+        let mut lines = vec![
+            lang.fill("stats.time", &[("time", &time), ("best", &best)]),
+            lang.fill("stats.kills", &[("kills", &stats.kills)]),
+            lang.fill(
+                "stats.secrets",
+                &[("found", &stats.secrets), ("total", &stats.secret_total)],
+            ),
+            lang.fill("stats.items", &[("items", &stats.items)]),
+        ];
+        if let Some(seed) = stats.seed {
+            lines.push(lang.fill("stats.seed", &[("seed", &seed)]));
+        }
+        let modifiers = stats.modifiers.names();
+        if !modifiers.is_empty() {
+            let modifiers = modifiers
+                .iter()
+                .map(|name| lang.get(&format!("modifier.{name}")).to_string())
+                .collect::<Vec<_>>();
+            lines.push(lang.fill("stats.modifiers", &[("modifiers", &modifiers.join(", "))]));
+        }
+        if let Some(par) = self.map.par_time {
+            let par = u64::from(par) * TARGET_FPS;
+            let result = if stats.frames <= par {
+                let time = stats::clock(par - stats.frames, true);
+                lang.fill("stats.under_par", &[("time", &time)])
+            } else {
+                let time = stats::clock(stats.frames - par, true);
+                lang.fill("stats.over_par", &[("time", &time)])
+            };
+            let par = stats::clock(par, false);
+            lines.insert(
+                1,
+                lang.fill("stats.par", &[("par", &par), ("result", &result)]),
+            );
+        }
+
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        let (theme, margin) = (&self.theme, self.theme.margin);
+        self.renderer.draw_text(
+            &self.lang.fill("stats.finished", &[("name", &name)]),
+            &theme.title(),
+            margin,
+            margin,
+        )?;
+        let style = theme.body();
+        for (idx, line) in lines.iter().enumerate() {
+            self.renderer.draw_text(
+                line,
+                &style,
+                margin,
+                theme.top + (idx as i32 * theme.line_height),
+            )?;
+        }
+        self.renderer.draw_text(
+            self.lang.get("stats.spectate"),
+            &style.italic(),
+            margin,
+            HEIGHT as i32 - 76,
+        )?;
+        self.renderer.draw_text(
+            self.lang.get("continue"),
+            &style.italic(),
+            margin,
+            HEIGHT as i32 - 48,
+        )?;
 
         Ok(())
     }
 
-    /// initialize game
-    pub fn new(canvas: Canvas<Window>, font_ctx: Sdl2TtfContext) -> anyhow::Result<Self> {
-        let map = Map::load("map/map.yaw".into())?;
-        let player = Player {
-            pos: map.get_spawn().context("no spawn in map")?,
-            direction: 0.,
-            speed: 2.,
-            health: 255,
-        };
-        let game_state = GameState::Menu;
-        let slices = Vec::<RayCast>::with_capacity(WIDTH);
+    /// handle key presses while flying around a finished level, the keys fly while held
+    fn spectating_key_once(&mut self, key: Keycode) {
+        if key == SPECTATE_KEY || key == Keycode::Escape {
+            self.toggle_spectator();
+            self.game_state = GameState::Stats;
+        }
+    }
 
-        Ok(Self {
-            map,
-            player,
-            game_state,
-            slices,
-            texture_creator: canvas.texture_creator(),
-            canvas,
-            font_ctx,
-            update: true,
-        })
+    /// detach a camera from the first player, or put it away again
+    fn toggle_spectator(&mut self) {
+        self.update = true;
+        if self.spectator.take().is_some() {
+            return;
+        }
+        self.spectator = Some(Player {
+            noclip: true,
+            sprinting: false,
+            turning: 0.,
+            ..*self.view()
+        });
+        self.show_text("message.spectating");
     }
 
-    /// handle key presses for while in "menu" state
-    pub fn menu_key_once(&mut self, key: Keycode) {
-        match key {
-            Keycode::Return => self.game_state = GameState::Playing,
-            Keycode::Backspace => self.game_state = GameState::Exit,
-            _ => {}
+    /// turn the spectator camera along with the mouse
+    pub fn mouse_moved(&mut self, dx: i32) {
+        if let Some(spectator) = self.spectator.as_mut() {
+            spectator.direction =
+                (spectator.direction + (dx as f32 * MOUSE_TURN)).rem_euclid(2. * PI);
+            self.update = true;
         }
     }
 
-    /// handle key repeating for while in "menu" state
-    pub fn menu_key(&mut self, _key: Keycode) {}
-
-    /// draw menu
-    pub fn menu_draw(&mut self) -> anyhow::Result<()> {
-        self.draw_text(
-            "Press enter to play, press backspace to exit",
-            FontStyle::ITALIC,
-            24,
-            Color::GREEN,
-            None,
-            None,
-            Point::new(16, 16),
+    /// whether the mouse turns the view right now, it is captured while it does
+    pub fn wants_mouse(&self) -> bool {
+        self.spectator.is_some()
+    }
+
+    // draw the loading screen
+    fn loading_draw(&mut self) -> anyhow::Result<()> {
+        let Some((loader, _)) = self.loading.as_ref() else {
+            return Ok(());
+        };
+        let (stage, progress) = loader.progress();
+        // named by its file until it is read
+        let name = match loader.map() {
+            Some(map) => map_title(
+                &self.lang,
+                &map.name.clone().unwrap_or_else(|| file_name(&loader.path)),
+                map.author.as_deref(),
+            ),
+            None => file_name(&loader.path),
+        };
+
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        let margin = self.theme.margin;
+        self.renderer.draw_text(
+            &self.lang.fill("loading.title", &[("name", &name)]),
+            &self.theme.title(),
+            margin,
+            margin,
+        )?;
+        self.renderer
+            .draw_text(self.lang.get(stage), &self.theme.body(), margin, 56)?;
+
+        let bar = Rect::new(16, HEIGHT as i32 - 48, WIDTH as u32 - 32, 16);
+        self.renderer.draw_rect(Some(bar), Color::RGB(0, 0x40, 0))?;
+        self.renderer.draw_rect(
+            Some(Rect {
+                width: (bar.width as f32 * progress.clamp(0., 1.)) as u32,
+                ..bar
+            }),
+            self.theme.text,
         )?;
 
         Ok(())
     }
 
     /// handle key presses for while in "playing" state
-    pub fn playing_key_once(&mut self, key: Keycode) {
+    fn playing_key_once(&mut self, key: Keycode) {
         match key {
             // minimap toggle
             Keycode::M => {
@@ -163,407 +3206,727 @@ impl Game {
                     self.game_state = GameState::Minimap;
                 }
             }
+            Keycode::Tab => {
+                self.automap.center_on(self.player.pos);
+                self.game_state = GameState::Automap;
+            }
             // pause game
-            Keycode::Escape => self.game_state = GameState::Paused,
-            _ => {}
+            Keycode::Escape => {
+                self.pause_menu.selected = 0;
+                self.game_state = GameState::Paused;
+            }
+            // interact with the tile in front
+            Keycode::E => self.use_tile(),
+            // flashlight toggle
+            Keycode::F => self.player.flashlight = !self.player.flashlight,
+            // start sprinting
+            _ => self.set_sprinting(key, true),
+        }
+    }
+
+    /// handle key releases, regardless of state
+    pub fn key_up(&mut self, key: Keycode) {
+        if self.demo.is_none() {
+            self.release(key);
+        }
+    }
+
+    /// handle a key release that is not about a demo
+    fn release(&mut self, key: Keycode) {
+        self.ignored.remove(&key);
+        self.set_sprinting(key, false);
+    }
+
+    /// start or stop sprinting for whoever has this key bound
+    fn set_sprinting(&mut self, key: Keycode, sprinting: bool) {
+        if key == self.bindings[0].sprint {
+            self.player.sprinting = sprinting;
+        }
+        if let Some(second) = self.second.as_mut() {
+            if key == self.bindings[1].sprint {
+                second.sprinting = sprinting;
+            }
         }
     }
 
     /// handle key repeating for while in "playing" state
-    pub fn playing_key(&mut self, key: Keycode) {
-        let mut step = Vec2::ZERO;
+    fn playing_key(&mut self, key: Keycode) {
+        for (idx, player) in [Some(self.player), self.second].into_iter().enumerate() {
+            let Some(mut player) = player else {
+                continue;
+            };
 
-        // define controls
-        match key {
-            Keycode::W => step = self.player.step(),
-            Keycode::D => step = self.player.step().perp(),
-            Keycode::S => step = -self.player.step(),
-            Keycode::A => step = -self.player.step().perp(),
-            Keycode::Left => self.player.direction -= 0.1,
-            Keycode::Right => self.player.direction += 0.1,
-            _ => {}
+            // the first player's keys fly the free camera instead
+            if let (0, Some(mut camera)) = (idx, self.free_camera) {
+                self.walk(&mut camera, self.bindings[idx], key);
+                self.free_camera = Some(camera);
+                continue;
+            }
+
+            let reached_exit = self.walk(&mut player, self.bindings[idx], key);
+            if idx == 0 {
+                self.player = player;
+            } else {
+                self.second = Some(player);
+            }
+
+            if reached_exit {
+                self.complete_level();
+                return;
+            }
         }
+    }
+
+    /// what the first viewport is seen from
+    fn view(&self) -> &Player {
+        if self.game_state == GameState::Menu {
+            return &self.attract;
+        }
+        self.spectator
+            .as_ref()
+            .or(self.free_camera.as_ref())
+            .unwrap_or(&self.player)
+    }
 
-        // fix player angle
-        while self.player.direction >= (2. * PI) {
-            self.player.direction -= 2. * PI;
+    /// whether a wall or a solid entity is in the way
+    fn blocked(&self, position: Vec2) -> bool {
+        self.map.colliding(position, true).is_some() || self.entities.colliding(position).is_some()
+    }
+
+    /// push the crate a player walked into at a position on by a tile, if it is one
+    fn push(&mut self, position: Vec2, direction: (i32, i32)) {
+        if self.map.colliding(position, true).is_some() {
+            return;
         }
-        while self.player.direction < 0. {
-            self.player.direction += 2. * PI;
+        let Some(entity) = self.entities.colliding(position) else {
+            return;
+        };
+        let players = [Some(self.player), self.second]
+            .into_iter()
+            .flatten()
+            .map(|player| player.pos)
+            .collect::<Vec<_>>();
+        self.entities
+            .push(&self.map, entity, direction, PUSH_SPEED, &players);
+    }
+
+    /// move one player for a held key, returns whether they reached an exit
+    fn walk(&mut self, player: &mut Player, bindings: Bindings, key: Keycode) -> bool {
+        let mut step = Vec2::ZERO;
+
+        // define controls
+        if key == bindings.forward {
+            step = player.step();
+        } else if key == bindings.strafe_right {
+            step = player.strafe();
+        } else if key == bindings.back {
+            step = -player.step();
+        } else if key == bindings.strafe_left {
+            step = -player.strafe();
+        } else if key == bindings.turn_left {
+            player.steer -= 1.;
+        } else if key == bindings.turn_right {
+            player.steer += 1.;
         }
 
         // collision
         if step != Vec2::ZERO {
-            if self
-                .map
-                .colliding(self.player.pos + Vec2::new(step.x, 0.), true)
-                .is_none()
-            {
-                self.player.pos.x += step.x
+            player.walked = true;
+
+            // tiles can speed players up or slow them down, on slippery ones walking only
+            // pushes them along
+            step *= self.map.speed_mul(player.pos);
+            if self.map.is_slippery(player.pos) && !player.noclip {
+                player.sliding += step * (1. - SLIDE_FRICTION);
+                return false;
+            }
+            let reached_exit = self.move_player(player, step);
+            // walking onto a slippery tile carries on at the same speed
+            if self.map.is_slippery(player.pos) && !player.noclip {
+                player.sliding = step;
             }
+            return reached_exit;
+        }
 
-            if self
-                .map
-                .colliding(self.player.pos + Vec2::new(0., step.y), true)
-                .is_none()
-            {
-                self.player.pos.y += step.y
+        false
+    }
+
+    /// slide players on slippery tiles for a tick, returns whether one reached an exit
+    fn slide(&mut self) -> bool {
+        for idx in 0..2 {
+            let Some(mut player) = [Some(self.player), self.second][idx] else {
+                continue;
+            };
+            if player.sliding == Vec2::ZERO {
+                continue;
+            }
+
+            let reached_exit = if self.map.is_slippery(player.pos) {
+                let step = player.sliding;
+                player.sliding *= SLIDE_FRICTION;
+                if player.sliding.length() < SLIDE_STOP {
+                    player.sliding = Vec2::ZERO;
+                }
+                self.update = true;
+                self.move_player(&mut player, step)
+            } else {
+                // grip comes back right away off the ice
+                player.sliding = Vec2::ZERO;
+                false
+            };
+            if idx == 0 {
+                self.player = player;
+            } else {
+                self.second = Some(player);
+            }
+            if reached_exit {
+                return true;
             }
         }
+
+        false
     }
 
-    /// raycasting
-    fn cast_rays(&mut self) {
-        // TODO: make iterator api, don't use Vec
-        self.slices.clear();
+    /// move a player unless something is in the way, returns whether they reached an exit
+    fn move_player(&mut self, player: &mut Player, mut step: Vec2) -> bool {
+        let prev_pos = player.pos;
+        let wading = self.map.is_water(player.pos);
+        if wading {
+            step *= WATER_SPEED;
+        }
 
-        let fov_rad = (FOV as f32).to_radians();
-        let ray_delta = fov_rad / WIDTH as f32;
+        if player.noclip || !self.blocked(player.pos + Vec2::new(step.x, 0.)) {
+            player.pos.x += step.x
+        } else if step.x != 0. {
+            self.push(
+                player.pos + Vec2::new(step.x, 0.),
+                (step.x.signum() as i32, 0),
+            );
+        }
 
-        // iterate through all angles rays need to be cast from
-        for ray_number in (-(WIDTH as isize) / 2)..(WIDTH as isize / 2) {
-            let mut angle = self.player.direction + ((ray_number as f32) * ray_delta);
+        if player.noclip || !self.blocked(player.pos + Vec2::new(0., step.y)) {
+            player.pos.y += step.y
+        } else if step.y != 0. {
+            self.push(
+                player.pos + Vec2::new(0., step.y),
+                (0, step.y.signum() as i32),
+            );
+        }
 
-            // fix angle
-            while angle < 0. {
-                angle += 2. * PI;
+        if self.map.is_exit(player.pos) {
+            if !self.map.exit_needs_objectives() || self.objective().is_none() {
+                return true;
             }
-            while angle >= 2. * PI {
-                angle -= 2. * PI;
+            // told once, as they step onto it
+            if !self.map.is_exit(prev_pos) {
+                self.show_text("message.objectives_left");
             }
+        }
 
-            // create a unit vector that is pointing in the direction of the angle
-            let angle_vec = Vec2::from_angle(angle);
-
-            // define ray start and step for rays that hit horizontal lines
-            let mut x = 'x: {
-                let (new_y, dy, cardinal) = if (angle > 0.) && (angle < PI) {
-                    // LOOKING DOWN
-                    (
-                        TILE_SIZE - (self.player.pos.y % TILE_SIZE),
-                        TILE_SIZE,
-                        Cardinal::North,
-                    )
-                } else if (angle > PI) && (angle < 2. * PI) {
-                    // LOOKING UP
-                    (
-                        -(self.player.pos.y % TILE_SIZE) - 0.0001,
-                        -TILE_SIZE,
-                        Cardinal::South,
-                    )
-                } else if (angle == 0.) || (angle == PI) {
-                    // LOOKING SIDEWAYS (parallel - will never hit)
-                    break 'x None;
-                } else {
-                    unreachable!()
-                };
+        // stepping into water splashes right away
+        if !wading && self.map.is_water(player.pos) {
+            self.step_distance = FOOTSTEP_DISTANCE;
+        }
 
-                // use the slope of angle_vec to calculate vectors that hit y-values while pointing
-                // in the required direction
-                let ray = Vec2::new((angle_vec.x / angle_vec.y) * new_y, new_y);
-                let step = Vec2::new((angle_vec.x / angle_vec.y) * dy, dy);
+        // play footsteps of the floor below the player, faster movement means more steps
+        self.step_distance += (player.pos - prev_pos).length();
+        if self.step_distance >= FOOTSTEP_DISTANCE {
+            self.step_distance = 0.;
+            if let (Some(audio), Some(sound)) =
+                (self.audio.as_mut(), self.map.step_sound(player.pos))
+            {
+                if let Err(err) = audio.play(&sound) {
+                    log::warn!("could not play footstep {}: {err}", sound.display());
+                }
+            }
+        }
 
-                Some((ray, step, cardinal))
-            };
+        false
+    }
 
-            // define ray start and step for rays that hit vertical lines
-            let mut y = 'y: {
-                let (new_x, dx, cardinal) = if (angle > FRAC_PI_2) && (angle < 3. * FRAC_PI_2) {
-                    // LOOKING LEFT
-                    (
-                        -(self.player.pos.x % TILE_SIZE) - 0.0001,
-                        -TILE_SIZE,
-                        Cardinal::East,
-                    )
-                } else if (angle == FRAC_PI_2) || (angle == 3. * FRAC_PI_2) {
-                    // LOOKING UP/DOWN (parallel - will never hit)
-                    break 'y None;
-                } else if (angle > 3. * FRAC_PI_2) || (angle < FRAC_PI_2) {
-                    // LOOKING RIGHT
-                    (
-                        TILE_SIZE - (self.player.pos.x % TILE_SIZE),
-                        TILE_SIZE,
-                        Cardinal::West,
-                    )
-                } else {
-                    unreachable!()
-                };
+    /// point the camera along the benchmark path, `t` goes from 0 to 1 over the whole run
+    pub fn bench_camera(&mut self, t: f32) -> anyhow::Result<()> {
+        // two full turns standing on the spawn
+        self.player.pos = self.map.get_spawn().context("no spawn in map")?;
+        self.player.direction = (t * 4. * PI) % (2. * PI);
+        Ok(())
+    }
 
-                // use the slope of angle_vec to calculate vectors that hit x-values while pointing
-                // in the required direction
-                let ray = Vec2::new(new_x, (angle_vec.y / angle_vec.x) * new_x);
-                let step = Vec2::new(dx, (angle_vec.y / angle_vec.x) * dx);
+    /// whether the screen is split between two players, the view behind the menu never is
+    fn split(&self) -> bool {
+        self.second.is_some() && self.game_state != GameState::Menu
+    }
 
-                Some((ray, step, cardinal))
-            };
+    /// areas of the screen the first and second player see, side by side in split-screen
+    fn viewports(&self) -> (Rect, Rect) {
+        if self.split() {
+            let half = WIDTH as u32 / 2;
+            (
+                Rect::new(0, 0, half, HEIGHT as u32),
+                Rect::new(half as i32, 0, WIDTH as u32 - half, HEIGHT as u32),
+            )
+        } else {
+            (
+                Rect::new(0, 0, WIDTH as u32, HEIGHT as u32),
+                Rect::default(),
+            )
+        }
+    }
 
-            let mut x_res = None;
-            let mut y_res = None;
-            for _ in 0..DOF {
-                if x_res.is_none() {
-                    if let Some((x_ray, x_step, cardinal)) = x.as_mut() {
-                        if let Some(tile) = self.map.colliding(self.player.pos + *x_ray, false) {
-                            // do not hit tiles that are half width (they are always along the
-                            // y-axis)
-                            if !self.map.custom_tiles[&tile].half_width {
-                                x_res = Some((
-                                    *x_ray
-                                        + if self.map.custom_tiles[&tile].half_height {
-                                            // if it's half heigt,
-                                            // add a little extra to the ray to make the object
-                                            // seem further
-                                            *x_step * 0.25
-                                        } else {
-                                            Vec2::ZERO
-                                        },
-                                    *cardinal,
-                                    tile,
-                                ));
-                            }
-                        }
-                        *x_ray += *x_step;
-                    }
-                }
-                if y_res.is_none() {
-                    if let Some((y_ray, y_step, cardinal)) = y.as_mut() {
-                        if let Some(tile) = self.map.colliding(self.player.pos + *y_ray, false) {
-                            // do not hit tiles that are half height (they are always along the
-                            // x-axis)
-                            if !self.map.custom_tiles[&tile].half_height {
-                                y_res = Some((
-                                    *y_ray
-                                        + if self.map.custom_tiles[&tile].half_width {
-                                            // if it's half width,
-                                            // add a little extra to the ray to make the object
-                                            // seem further
-                                            *y_step * 0.25
-                                        } else {
-                                            Vec2::ZERO
-                                        },
-                                    *cardinal,
-                                    tile,
-                                ));
-                            }
-                        }
-                        *y_ray += *y_step;
-                    }
-                }
-            }
+    /// camera at a player's eyes, narrower views keep the same field of view per pixel
+    fn camera(&self, player: &Player, viewport: Rect) -> Camera {
+        Camera {
+            fov: self.fov * (viewport.width as f32 / WIDTH as f32),
+            flashlight: player.flashlight,
+            wobble: (self.map.is_water(player.pos) && !self.config.reduce_motion)
+                .then_some(self.stats.frames as f32 * WOBBLE_SPEED),
+            height: self.map.floor_height(player.pos),
+            fog: self.config.effects,
+            ..Camera::new(player.pos, player.direction)
+        }
+    }
 
-            // find shortest ray
-            let (vec, cardinal, tile) = match (x_res, y_res) {
-                (Some((x, cardinal_x, tile_x)), Some((y, cardinal_y, tile_y))) => {
-                    if x.length_squared() < y.length_squared() {
-                        (x, cardinal_x, tile_x)
-                    } else {
-                        (y, cardinal_y, tile_y)
-                    }
-                }
-                (Some((ray, cardinal, tile)), None) | (None, Some((ray, cardinal, tile))) => {
-                    (ray, cardinal, tile)
-                }
-                (None, None) => (Vec2::INFINITY, Cardinal::North, '\0'),
-            };
+    /// raycasting, skipped when neither the map nor any camera changed since the last cast
+    pub fn cast_rays(&mut self) {
+        let (first, second) = self.viewports();
+        let mut cameras = vec![self.camera(self.view(), first)];
+        if let Some(player) = self.second.as_ref().filter(|_| self.split()) {
+            cameras.push(self.camera(player, second));
+        }
+        if !self.rays_dirty && cameras == self.cast_from {
+            return;
+        }
 
-            self.slices.push(RayCast {
-                vec,
-                angle,
-                face_direction: cardinal,
-                hit_where: match cardinal {
-                    Cardinal::North => TILE_SIZE - ((vec.x + self.player.pos.x) % TILE_SIZE),
-                    Cardinal::East => TILE_SIZE - ((vec.y + self.player.pos.y) % TILE_SIZE),
-                    Cardinal::South => (vec.x + self.player.pos.x) % TILE_SIZE,
-                    Cardinal::West => (vec.y + self.player.pos.y) % TILE_SIZE,
-                },
-                tile,
-            });
+        let start = Instant::now();
+        // each ray is drawn `scale` pixels across
+        let scale = self.scaler.scale();
+        self.columns
+            .fit(cameras[0].fov, (first.width / scale).max(1) as usize);
+        self.slices = cameras[0].cast_with(&self.map, &self.columns);
+        self.depth = cameras[0].depth(&self.slices);
+        if let Some(camera) = cameras.get(1) {
+            self.second_columns
+                .fit(camera.fov, (second.width / scale).max(1) as usize);
+            self.second_slices = camera.cast_with(&self.map, &self.second_columns);
+            self.second_depth = camera.depth(&self.second_slices);
         }
+        self.frame_stats.cast(start.elapsed());
+        self.cast_from = cameras;
+        self.rays_dirty = false;
     }
 
-    // draw while in "playing" state
+    // draw while in "playing" state, rays need to be cast before
     pub fn playing_draw(&mut self) -> anyhow::Result<()> {
-        self.cast_rays();
+        let (first, second) = self.viewports();
+        let camera = self.camera(self.view(), first);
 
-        // DRAW CEILING
-        self.canvas.set_draw_color(Color::WHITE);
-        self.canvas
-            .fill_rect(Rect::new(0, 0, WIDTH as u32, HEIGHT as u32 / 2))
-            .ah()?;
-
-        // DRAW FLOOR
-        self.canvas.set_draw_color(Color::WHITE);
-        self.canvas
-            .fill_rect(Rect::new(
-                0,
-                HEIGHT as i32 / 2,
-                WIDTH as u32,
-                HEIGHT as u32 / 2,
-            ))
-            .ah()?;
-
-        // DRAW WALLS
-        for (i, slice) in self.slices.iter().enumerate() {
-            // get height of line to draw (correcting fisheye effect)
-            let line_height = (TILE_SIZE * HEIGHT as f32)
-                / (slice.vec.length() * (self.player.direction - slice.angle).cos());
-
-            // sample correct area of wall texture to draw
-            #[cfg(not(target_os = "emscripten"))]
-            let texture = self
-                .map
-                .load_tex(slice.tile)
-                .context("could not load texture")?
-                .load_png()
-                .ah()?
-                .as_texture(&self.texture_creator)?;
-
-            #[cfg(target_os = "emscripten")]
-            let texture = self
-                .texture_creator
-                .load_texture(self.map.tex_path(slice.tile))
-                .ah()?;
-
-            let TextureQuery { width, height, .. } = texture.query();
-            let sample_rect = Rect::new(
-                ((width as i32 / 4)
-                    * match slice.face_direction {
-                        Cardinal::North => 0,
-                        Cardinal::East => 1,
-                        Cardinal::South => 2,
-                        Cardinal::West => 3,
-                    })
-                    + ((slice.hit_where / TILE_SIZE) * ((width as f32) / 4.)) as i32,
-                0,
-                (width / 4) / TILE_SIZE as u32,
-                height,
-            );
-            let dst_rect = Rect::new(
-                i as i32,
-                (HEIGHT as i32 - line_height as i32) / 2,
-                1,
-                line_height as u32,
-            );
-            self.canvas.copy(&texture, sample_rect, dst_rect).ah()?;
+        // entities behind walls are not drawn at all
+        let mut visibility = Visibility::new(&self.map);
+        visibility.see(&self.map, camera.pos, &self.slices, &self.entities);
+        if let Some(player) = self.second.as_ref() {
+            visibility.see(&self.map, player.pos, &self.second_slices, &self.entities);
+        }
+        self.visibility = visibility;
+        self.automap.explore(&self.visibility);
+        for player in [Some(self.player), self.second].into_iter().flatten() {
+            self.automap.visit(player.pos);
+        }
 
-            self.canvas.set_blend_mode(BlendMode::Blend);
-            self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 0));
-            if let Some(Meta::Fog { dof, color }) = self
-                .map
-                .meta
-                .iter()
-                .find(|item| matches!(item, Meta::Fog { .. }))
-            {
-                // add depth of field fog
-                self.canvas.set_draw_color(Color::RGBA(
-                    color.r,
-                    color.g,
-                    color.b,
-                    (0xff as f32 * (slice.vec.length() / ((*dof as f32) * TILE_SIZE)))
-                        .clamp(0., 255.) as u8,
-                ));
-            }
-            let color = self.canvas.draw_color();
-            // slightly discolor walls that face different directions for contrast
-            self.canvas.set_draw_color(Color::RGBA(
-                color.r,
-                color.g,
-                color.b,
-                color.a.saturating_add(match slice.face_direction {
-                    Cardinal::North | Cardinal::South => 0,
-                    Cardinal::East | Cardinal::West => 0x22,
-                }),
-            ));
-            self.canvas
-                .draw_line(
-                    Point::new(i as i32, (HEIGHT as i32 - line_height as i32) / 2),
-                    Point::new(
-                        i as i32,
-                        ((HEIGHT as i32 - line_height as i32) / 2) + line_height as i32,
-                    ),
+        let mut sprites = self.visibility.sprites(&self.entities);
+        if self.free_camera.is_some() || self.spectator.is_some() {
+            // show where the player was left
+            sprites.push(Sprite {
+                pos: self.player.pos,
+                texture: PLAYER_SPRITE.into(),
+            });
+        }
+        camera
+            .draw(&self.map, &self.slices, self.renderer.as_mut(), first)
+            .context("could not draw walls")?;
+        camera.draw_sprites(&self.depth, &sprites, self.renderer.as_mut(), first)?;
+        self.particles
+            .draw(&camera, &self.depth, self.renderer.as_mut(), first)?;
+        if let Some(player) = self.second.as_ref() {
+            let camera = self.camera(player, second);
+            camera
+                .draw(
+                    &self.map,
+                    &self.second_slices,
+                    self.renderer.as_mut(),
+                    second,
                 )
-                .ah()?;
-            self.canvas.set_blend_mode(BlendMode::None);
+                .context("could not draw walls")?;
+            camera.draw_sprites(&self.second_depth, &sprites, self.renderer.as_mut(), second)?;
+            self.particles
+                .draw(&camera, &self.second_depth, self.renderer.as_mut(), second)?;
+            self.renderer
+                .draw_line((second.x, 0), (second.x, HEIGHT as i32), Color::BLACK)?;
+        }
+
+        // the spectator camera shows nothing but the level, for screenshots
+        if self.spectator.is_some() {
+            return Ok(());
+        }
+
+        self.crosshair.draw(self.renderer.as_mut(), first)?;
+        if self.second.is_some() {
+            self.crosshair.draw(self.renderer.as_mut(), second)?;
         }
 
         // DRAW MINIMAP
         if self.game_state == GameState::Minimap {
-            self.canvas.set_blend_mode(BlendMode::Blend);
-            self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 0x77));
-            self.canvas.fill_rect(None).ah()?;
-            self.canvas.set_blend_mode(BlendMode::None);
-
-            let offset = Point::new(WIDTH as i32 / 2, HEIGHT as i32 / 2)
-                - Point::new(
-                    (self.map.width as i32 * TILE_SIZE as i32) / 2,
-                    (self.map.height as i32 * TILE_SIZE as i32) / 2,
-                );
-            // TODO: draw "YAWMAP v6666666666666666"
+            self.renderer
+                .draw_rect(None, self.theme.minimap.background)?;
 
-            self.canvas.set_draw_color(Color::GREEN);
-            for slice in self.slices.iter() {
-                if slice.vec.length() == f32::INFINITY {
-                    continue;
-                }
+            let offset = (
+                (WIDTH as i32 / 2) - ((self.map.width as i32 * TILE_SIZE as i32) / 2),
+                (HEIGHT as i32 / 2) - ((self.map.height as i32 * TILE_SIZE as i32) / 2),
+            );
+            // TODO: draw "YAWMAP v6666666666666666"
 
-                self.canvas
-                    .draw_line(
-                        Point::new(self.player.pos.x as i32, self.player.pos.y as i32) + offset,
-                        Point::new(
-                            (self.player.pos.x + slice.vec.x) as i32,
-                            (self.player.pos.y + slice.vec.y) as i32,
-                        ) + offset,
-                    )
-                    .ah()?;
-            }
+            let view = self.view().pos + Vec2::new(offset.0 as f32, offset.1 as f32);
+            self.minimap_view.draw(
+                self.renderer.as_mut(),
+                &self.theme.minimap,
+                view,
+                &self.slices,
+            )?;
 
-            self.canvas.set_draw_color(Color::RGB(0, 0xDD, 0));
             for (idx, tile) in self.map.main_tiles.iter().enumerate() {
                 let coord = self.map.idx_to_vec(idx);
+                let seen = self
+                    .visibility
+                    .tile(idx % self.map.width, idx / self.map.width);
+                if self.minimap_seen && !seen {
+                    continue;
+                }
                 if let Tile::Custom(id) = tile {
                     if self.map.custom_tiles[id].collidable {
-                        self.canvas
-                            .fill_rect(Rect::new(
-                                coord.x as i32 + offset.x(),
-                                coord.y as i32 + offset.y(),
+                        self.renderer.draw_rect(
+                            Some(Rect::new(
+                                coord.x as i32 + offset.0,
+                                coord.y as i32 + offset.1,
                                 TILE_SIZE as u32,
                                 TILE_SIZE as u32,
-                            ))
-                            .ah()?;
+                            )),
+                            self.theme.minimap.wall,
+                        )?;
                     }
                 }
             }
         }
 
         // DRAW HUD
-        self.draw_text(
-            format!("HEALTH: {}", self.player.health),
-            FontStyle::NORMAL,
-            16,
-            Color::GREEN,
-            Some(Color::BLACK),
-            Some((8, 4)),
-            Point::new(16, 16),
-        )?;
+        self.hud_draw(self.player, first)?;
+        let mut top_left = Rect::new(first.x + 16, 16, 0, 0);
+        if let Some(seed) = self.seed {
+            top_left = self.renderer.draw_text(
+                &self.lang.fill("hud.seed", &[("seed", &seed)]),
+                &self.theme.boxed(),
+                top_left.x,
+                top_left.y,
+            )?;
+        }
+        self.compass_draw(&camera, hud::compass_area(first, top_left))?;
+
+        if let Some(player) = self.second {
+            self.hud_draw(player, second)?;
+            self.compass_draw(
+                &self.camera(&player, second),
+                hud::compass_area(second, Rect::new(second.x + 16, 16, 0, 0)),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// draw the compass of a camera with the markers of the level not reached yet
+    fn compass_draw(&mut self, camera: &Camera, area: Rect) -> anyhow::Result<()> {
+        hud::draw_compass(
+            self.renderer.as_mut(),
+            camera,
+            &self.triggers.markers(&self.map.triggers),
+            area,
+            &self.theme,
+        )
+    }
+
+    /// draw the status bar of a player along the bottom of their viewport
+    fn hud_draw(&mut self, player: Player, viewport: Rect) -> anyhow::Result<()> {
+        let level = self.level.file_stem().unwrap_or_default().to_string_lossy();
+        let status = Status {
+            health: player.health,
+            stamina: player.stamina / MAX_STAMINA,
+            ammo: player.ammo,
+            keys: player.keys,
+            lives: self.lives,
+            time: self.show_timer.then_some(self.stats.frames),
+            level: &level,
+        };
+        self.theme.status_bar.draw(
+            self.renderer.as_mut(),
+            &status,
+            viewport,
+            self.faces,
+            &self.lang,
+        )
+    }
+
+    /// draw over every state, after the state itself was drawn
+    pub fn overlay_draw(&mut self) -> anyhow::Result<()> {
+        // screenshots do not include the overlay
+        if self.screenshot {
+            self.screenshot = false;
+            match self
+                .renderer
+                .capture()
+                .and_then(|image| screenshot::save(&image))
+            {
+                Ok(path) => self.show_message(
+                    self.lang
+                        .fill("message.screenshot_saved", &[("path", &path.display())]),
+                ),
+                Err(err) => {
+                    log::error!("could not save screenshot: {err}");
+                    self.show_text("message.screenshot_failed");
+                }
+            }
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            if let Err(err) = recording.capture(self.renderer.as_mut()) {
+                log::error!("could not record frame: {err}");
+                self.recording = None;
+                self.show_text("message.recording_failed");
+            }
+        }
+
+        // DRAW MESSAGES
+        let style = self
+            .theme
+            .boxed()
+            .wrapped(self.theme.wrap_width(WIDTH as u32));
+        let mut y = HEIGHT as i32 - 8;
+        if matches!(self.game_state, GameState::Playing | GameState::Minimap) {
+            // above the status bar
+            y -= self.theme.status_bar.height as i32;
+        }
+        // newest at the bottom, long ones wrap upwards
+        for (msg, _) in self.messages.iter().rev() {
+            let (_, height) = self.renderer.measure_text(msg, &style)?;
+            y -= height as i32;
+            self.renderer.draw_text(msg, &style, self.theme.margin, y)?;
+            y -= 4;
+        }
+        self.messages.retain_mut(|(_, frames)| {
+            *frames -= 1;
+            *frames > 0
+        });
 
         Ok(())
     }
 
+    fn pause_menu(&self) -> Menu<PauseItem> {
+        let lang = &self.lang;
+        let mut menu = Menu::new(
+            lang.get("pause.title"),
+            vec![
+                (lang.get("pause.resume").into(), PauseItem::Resume),
+                (lang.get("pause.restart").into(), PauseItem::Restart),
+                (lang.get("pause.settings").into(), PauseItem::Settings),
+                (lang.get("pause.quit").into(), PauseItem::QuitToMenu),
+            ],
+        );
+        menu.selected = self.pause_menu.selected;
+        menu
+    }
+
+    /// handle key presses for while in "paused" state
+    fn pause_key_once(&mut self, key: Keycode) {
+        let item = match key {
+            Keycode::Escape => Some(PauseItem::Resume),
+            _ => self.pause_menu.key(key),
+        };
+
+        match item {
+            Some(PauseItem::Resume) => self.game_state = GameState::Playing,
+            Some(PauseItem::Restart) => self.start_loading(self.level.clone(), GameState::Playing),
+            Some(PauseItem::Settings) => {
+                self.settings_return = GameState::Paused;
+                self.game_state = GameState::Settings;
+            }
+            Some(PauseItem::QuitToMenu) => {
+                self.campaign = None;
+                self.reset_players();
+                if let Err(err) = self.load_level(DEFAULT_MAP.into()) {
+                    log::error!("could not load map: {err}");
+                }
+                self.game_state = GameState::Menu;
+            }
+            None => {}
+        }
+    }
+
     // draw pause screen
-    pub fn pause_draw(&mut self) -> anyhow::Result<()> {
-        self.canvas.set_blend_mode(BlendMode::Blend);
-        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 0xDD));
-        self.canvas.fill_rect(None).ah()?;
-        self.draw_text(
-            "Paused - press any key to resume",
-            FontStyle::ITALIC,
-            24,
-            Color::GREEN,
-            None,
-            None,
-            Point::new(16, 16),
+    fn pause_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::RGBA(0, 0, 0, 0xDD))?;
+        self.pause_menu.draw(self.renderer.as_mut(), &self.theme)?;
+
+        // what to do next, at the bottom
+        let text = match self.objective() {
+            Some(objective) => self
+                .lang
+                .fill("pause.objective", &[("objective", &objective.text)]),
+            None if !self.map.objectives.is_empty() => {
+                self.lang.get("pause.objectives_done").into()
+            }
+            None => return Ok(()),
+        };
+        let style = self
+            .theme
+            .body()
+            .wrapped(self.theme.wrap_width(WIDTH as u32));
+        let (_, height) = self.renderer.measure_text(&text, &style)?;
+        self.renderer.draw_text(
+            &text,
+            &style,
+            self.theme.margin,
+            HEIGHT as i32 - self.theme.margin - height as i32,
         )?;
-        self.canvas.set_blend_mode(BlendMode::None);
+        Ok(())
+    }
+
+    fn error_menu(&self) -> Menu<ErrorItem> {
+        let lang = &self.lang;
+        let mut menu = Menu::new(
+            lang.get("error.title"),
+            vec![
+                (lang.get("error.retry").into(), ErrorItem::Retry),
+                (lang.get("pause.quit").into(), ErrorItem::QuitToMenu),
+            ],
+        );
+        menu.selected = self.error_menu.selected;
+        menu
+    }
+
+    /// show an error instead of quitting, with a way to try again
+    fn fail(&mut self, err: &anyhow::Error, retry: Retry) {
+        log::error!("{err:#}");
+        self.failure = Some(Failure {
+            lines: err.chain().map(|cause| cause.to_string()).collect(),
+            retry,
+        });
+        self.error_menu.selected = 0;
+        self.game_state = GameState::Error;
+        self.update = true;
+    }
+
+    /// handle key presses for while in "error" state
+    fn error_key_once(&mut self, key: Keycode) {
+        let item = match key {
+            Keycode::Escape => Some(ErrorItem::QuitToMenu),
+            _ => self.error_menu.key(key),
+        };
+        let Some(item) = item else {
+            return;
+        };
+
+        match (item, self.failure.take().map(|failure| failure.retry)) {
+            (ErrorItem::Retry, Some(Retry::Load(path, then))) => self.start_loading(path, then),
+            (ErrorItem::Retry, Some(Retry::Draw(state))) => self.game_state = state,
+            (ErrorItem::Retry, None) | (ErrorItem::QuitToMenu, _) => {
+                self.campaign = None;
+                self.reset_players();
+                if let Err(err) = self.load_level(DEFAULT_MAP.into()) {
+                    log::error!("could not load map: {err}");
+                }
+                self.game_state = GameState::Menu;
+            }
+        }
+    }
+
+    // draw error screen
+    fn error_draw(&mut self) -> anyhow::Result<()> {
+        self.renderer.draw_rect(None, Color::BLACK)?;
+        self.error_menu.draw(self.renderer.as_mut(), &self.theme)?;
+
+        let style = self.theme.body();
+        let top =
+            self.theme.top + (self.error_menu.options.len() as i32 * self.theme.line_height) + 24;
+        for (idx, line) in self
+            .failure
+            .iter()
+            .flat_map(|failure| &failure.lines)
+            .enumerate()
+        {
+            self.renderer
+                .draw_text(line, &style, self.theme.margin, top + (idx as i32 * 24))?;
+        }
 
         Ok(())
     }
 }
+
+/// seed that differs every time, for random levels nobody picked a seed for, short enough to
+/// be typed
+fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish() % 10_u64.pow(MAX_SEED_DIGITS as u32)
+}
+
+/// what a map is called and who made it, read ahead of playing it for map select
+struct MapInfo {
+    path: PathBuf,
+    name: String,
+    author: Option<String>,
+    par_time: Option<u32>,
+    /// layout of the map, a pixel per tile
+    thumbnail: Image,
+}
+
+impl MapInfo {
+    /// read the meta of a map, maps that cannot be read are named by their file
+    fn load(path: PathBuf) -> Self {
+        let map = Map::load(path.clone())
+            .inspect_err(|err| log::warn!("could not read {}: {err}", path.display()))
+            .unwrap_or_default();
+        Self {
+            thumbnail: thumbnail::render(&map, 1),
+            name: map.name.unwrap_or_else(|| file_name(&path)),
+            author: map.author,
+            par_time: map.par_time,
+            path,
+        }
+    }
+}
+
+/// name of a map file without its extension
+fn file_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// name of a map, with who made it when it says
+fn map_title(lang: &Lang, name: &str, author: Option<&str>) -> String {
+    match author {
+        Some(author) => lang.fill("maps.by", &[("name", &name), ("author", &author)]),
+        None => name.into(),
+    }
+}
+
+/// maps in a data directory, sorted by name
+fn list_maps(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    // paths stay relative, best times are kept by them
+    let mut maps = std::fs::read_dir(assets::resolve(dir))?
+        .map(|entry| entry.map(|entry| dir.join(entry.file_name())))
+        .filter(|path| {
+            path.as_ref().map_or(true, |path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "yaw" || ext == "yawb" || ext == "yawpak")
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    maps.sort();
+
+    Ok(maps)
+}
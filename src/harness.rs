@@ -0,0 +1,1485 @@
+//! drives `Game` with scripted input and without any window, for tests and `--headless`
+
+use crate::bindings::parse_key;
+use crate::cli::Args;
+use crate::game::{Game, GameState};
+use crate::modifiers::Modifiers;
+use crate::scale::RenderScale;
+use crate::temp::TempDir;
+use crate::{HEIGHT, WIDTH};
+use anyhow::Context;
+use sdl2::keyboard::Keycode;
+use std::collections::HashSet;
+use std::path::Path;
+use yaw_core::color::Color;
+use yaw_core::framebuffer::{Image, PixelRenderer};
+use yaw_core::map::TILE_SIZE;
+use yaw_core::render::{wrap_lines, CacheStats, Column, Rect, Renderer, TextStyle};
+
+/// draws into memory, text only takes up space since there is no font
+struct HeadlessRenderer(PixelRenderer);
+
+impl Renderer for HeadlessRenderer {
+    fn draw_column(&mut self, column: &Column) -> anyhow::Result<()> {
+        self.0.draw_column(column)
+    }
+
+    fn draw_rect(&mut self, rect: Option<Rect>, color: Color) -> anyhow::Result<()> {
+        self.0.draw_rect(rect, color)
+    }
+
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) -> anyhow::Result<()> {
+        self.0.draw_line(from, to, color)
+    }
+
+    fn draw_image(&mut self, path: &Path, rect: Option<Rect>) -> anyhow::Result<()> {
+        self.0.draw_image(path, rect)
+    }
+
+    fn draw_text(&mut self, text: &str, style: &TextStyle, x: i32, y: i32) -> anyhow::Result<Rect> {
+        let (width, height) = self.measure_text(text, style)?;
+        let rect = Rect::new(x, y, width, height);
+        if let Some(bg) = style.bg {
+            self.0.draw_rect(Some(rect), bg)?;
+        }
+
+        Ok(rect)
+    }
+
+    fn measure_text(&mut self, text: &str, style: &TextStyle) -> anyhow::Result<(u32, u32)> {
+        // roughly the size of the global font
+        let width = |line: &str| line.chars().count() as u32 * (style.size as u32 / 2);
+        let lines = wrap_lines(text, style, width);
+        Ok((
+            lines.iter().map(|line| width(line)).max().unwrap_or(0) + (style.padding.0 * 2),
+            (style.size as u32 * lines.len() as u32) + (style.padding.1 * 2),
+        ))
+    }
+
+    fn capture(&mut self) -> anyhow::Result<Image> {
+        self.0.capture()
+    }
+
+    fn present(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn preload(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.0.preload(path)
+    }
+
+    fn clear_cache(&mut self) {
+        self.0.clear_cache();
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.0.cache_stats()
+    }
+}
+
+/// condition on the game checked while playing back a script
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Expect {
+    State(GameState),
+    Health(u8),
+    /// the player stands on the tile at this column and row
+    Tile(usize, usize),
+}
+
+/// scripted input
+pub(crate) enum Action {
+    /// press and release a key within one tick
+    Tap(Keycode),
+    /// hold keys down for a number of ticks
+    Hold(Vec<Keycode>, usize),
+    /// let ticks pass without any input
+    Wait(usize),
+    /// type text, like into the console
+    Type(String),
+    /// fail unless the game is in this state
+    Expect(Expect),
+    /// play with these modifiers from now on
+    Modifiers(Modifiers),
+    /// give the entity a level places on a tile a value to start with
+    State {
+        level: String,
+        at: (usize, usize),
+        key: String,
+        value: String,
+    },
+}
+
+fn parse_state(name: &str) -> anyhow::Result<GameState> {
+    Ok(match name {
+        "menu" => GameState::Menu,
+        "playing" => GameState::Playing,
+        "minimap" => GameState::Minimap,
+        "automap" => GameState::Automap,
+        "dialogue" => GameState::Dialogue,
+        "briefing" => GameState::Briefing,
+        "paused" => GameState::Paused,
+        "campaigns" => GameState::Campaigns,
+        "achievements" => GameState::Achievements,
+        "map_select" => GameState::MapSelect,
+        "random_level" => GameState::RandomLevel,
+        "settings" => GameState::Settings,
+        "modifiers" => GameState::Modifiers,
+        "intermission" => GameState::Intermission,
+        "stats" => GameState::Stats,
+        "spectating" => GameState::Spectating,
+        "help" => GameState::Help,
+        "credits" => GameState::Credits,
+        "loading" => GameState::Loading,
+        "error" => GameState::Error,
+        "exit" => GameState::Exit,
+        _ => anyhow::bail!("unknown state: {name}"),
+    })
+}
+
+/// parse a script, one action per line:
+/// `tap <key>`, `hold <key>[+<key>...] <ticks>`, `wait <ticks>`, `type <text>`,
+/// `expect state <state>`, `expect health <health>`, `expect tile <column> <row>`,
+/// `modifiers <modifier>[,<modifier>...]` or `modifiers none`, or
+/// `state <level> <column> <row> <key> <value>`, `#` starts a comment
+pub(crate) fn parse_script(script: &str) -> anyhow::Result<Vec<Action>> {
+    let mut actions = Vec::new();
+
+    for (idx, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let action = match words.as_slice() {
+            ["tap", key] => parse_key(key).map(Action::Tap),
+            ["hold", keys, ticks] => keys
+                .split('+')
+                .map(parse_key)
+                .collect::<anyhow::Result<Vec<_>>>()
+                .and_then(|keys| Ok(Action::Hold(keys, ticks.parse()?))),
+            ["wait", ticks] => ticks.parse().map(Action::Wait).map_err(Into::into),
+            ["type", text @ ..] if !text.is_empty() => Ok(Action::Type(text.join(" "))),
+            ["expect", "state", state] => {
+                parse_state(state).map(|s| Action::Expect(Expect::State(s)))
+            }
+            ["expect", "health", health] => health
+                .parse()
+                .map(|health| Action::Expect(Expect::Health(health)))
+                .map_err(Into::into),
+            ["expect", "tile", x, y] => {
+                (|| Ok(Action::Expect(Expect::Tile(x.parse()?, y.parse()?))))()
+            }
+            ["modifiers", modifiers] => modifiers.parse().map(Action::Modifiers),
+            ["state", level, x, y, key, value @ ..] => (|| {
+                Ok(Action::State {
+                    level: level.to_string(),
+                    at: (x.parse()?, y.parse()?),
+                    key: key.to_string(),
+                    value: value.join(" "),
+                })
+            })(),
+            _ => Err(anyhow::anyhow!("unrecognized action")),
+        };
+        actions.push(action.with_context(|| format!("line {}: {line}", idx + 1))?);
+    }
+
+    Ok(actions)
+}
+
+/// play back a script file without a window, fails on the first unmet expectation
+pub(crate) fn run_script(args: &Args, path: &Path) -> anyhow::Result<()> {
+    let actions = parse_script(&std::fs::read_to_string(path)?)
+        .with_context(|| format!("could not parse {}", path.display()))?;
+    let mut harness = Harness::new(args)?;
+    harness.run(&actions)?;
+    println!("{}: {} actions ok", path.display(), actions.len());
+
+    Ok(())
+}
+
+pub(crate) struct Harness {
+    pub game: Game,
+    keys: HashSet<Keycode>,
+    /// where the game saves, so runs start from nothing and leave real saves alone, kept for
+    /// as long as the harness even though only tests look at it
+    #[cfg_attr(not(test), allow(dead_code))]
+    dir: TempDir,
+}
+
+impl Harness {
+    pub fn new(args: &Args) -> anyhow::Result<Self> {
+        let renderer = Box::new(HeadlessRenderer(PixelRenderer::new(WIDTH, HEIGHT)));
+        let dir = TempDir::new("harness")?;
+        let mut game = Game::new(renderer, None, args, Some(dir.path()))?;
+        // what is drawn should not depend on how fast tests run
+        game.scaler.setting = RenderScale::Fixed(1);
+
+        Ok(Self {
+            game,
+            keys: HashSet::new(),
+            dir,
+        })
+    }
+
+    /// check a condition on the game right now
+    pub fn check(&self, expect: Expect) -> anyhow::Result<()> {
+        let player = &self.game.player;
+        match expect {
+            Expect::State(state) => anyhow::ensure!(
+                self.game.game_state == state,
+                "expected state {state:?}, got {:?}",
+                self.game.game_state
+            ),
+            Expect::Health(health) => anyhow::ensure!(
+                player.health == health,
+                "expected health {health}, got {}",
+                player.health
+            ),
+            Expect::Tile(x, y) => {
+                let tile = (player.pos / TILE_SIZE).floor();
+                anyhow::ensure!(
+                    tile.x as usize == x && tile.y as usize == y,
+                    "expected to be on tile {x}, {y}, got {}, {}",
+                    tile.x,
+                    tile.y
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// play back actions in order
+    pub fn run(&mut self, actions: &[Action]) -> anyhow::Result<()> {
+        for action in actions {
+            match action {
+                Action::Tap(key) => {
+                    self.game.key_pressed(*key);
+                    self.keys.insert(*key);
+                    self.tick()?;
+                    self.keys.remove(key);
+                    self.game.key_up(*key);
+                }
+                Action::Hold(keys, ticks) => {
+                    for key in keys {
+                        self.game.key_pressed(*key);
+                        self.keys.insert(*key);
+                    }
+                    for _ in 0..*ticks {
+                        self.tick()?;
+                    }
+                    for key in keys {
+                        self.keys.remove(key);
+                        self.game.key_up(*key);
+                    }
+                }
+                Action::Wait(ticks) => {
+                    for _ in 0..*ticks {
+                        self.tick()?;
+                    }
+                }
+                Action::Type(text) => {
+                    self.game.text_input(text);
+                    self.tick()?;
+                }
+                Action::Expect(expect) => self.check(*expect)?,
+                Action::Modifiers(modifiers) => self.game.modifiers = *modifiers,
+                Action::State {
+                    level,
+                    at,
+                    key,
+                    value,
+                } => self.game.entity_states.set(level, *at, key, value),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// one iteration of the main loop, without frame pacing
+    fn tick(&mut self) -> anyhow::Result<()> {
+        self.game.keys_held(&self.keys);
+        self.game.tick();
+        // scripts go on once a level is loaded, however many frames that takes
+        while self.game.game_state == GameState::Loading {
+            self.game.tick();
+        }
+        if self.game.update {
+            self.game.draw()?;
+            self.game.renderer.present()?;
+            self.game.update = self.game.animating();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::achievements::Achievements;
+    use crate::bindings::Bindings;
+    use crate::campaign::Campaign;
+    use crate::entity_state::EntityStates;
+    use crate::game::MAIN_CAMPAIGN;
+    use crate::stats::BestTimes;
+    use crate::TARGET_FPS;
+    use glam::Vec2;
+    use std::f32::consts::PI;
+    use yaw_core::entity::Ai;
+    use yaw_core::map::Map;
+    use yaw_core::mapgen::{self, Layout};
+
+    /// a harness playing the default map, outside of the campaign play starts
+    fn playing() -> Harness {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        harness.game.play_map("map/map.yaw".into());
+        harness.run(&[Action::Wait(1)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        harness
+    }
+
+    impl Harness {
+        /// a harness with `files` written next to its saves, where maps can use `wall.png`
+        fn with_files(files: &[(&str, &str)]) -> Self {
+            let harness = Harness::new(&Args::default()).unwrap();
+            let dir = harness.dir.path();
+            std::fs::copy("map/wall.png", dir.join("wall.png")).unwrap();
+            for (name, text) in files {
+                std::fs::write(dir.join(name), text).unwrap();
+            }
+            harness
+        }
+
+        /// a harness playing a map written from `text`
+        fn with_map(text: &str) -> Self {
+            let mut harness = Self::with_files(&[("map.yaw", text)]);
+            harness.play("map.yaw");
+            harness
+        }
+
+        /// load one of the files written for the harness and play it
+        fn play(&mut self, name: &str) {
+            self.game.play_map(self.dir.path().join(name));
+            self.run(&[Action::Wait(1)]).unwrap();
+        }
+    }
+
+    #[test]
+    fn saves_are_kept_apart_and_removed_with_the_harness() {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        let dir = harness.dir.path().to_path_buf();
+        harness.game.best_times.record("map/map.yaw", 60).unwrap();
+        assert!(dir.join("best_times.txt").exists());
+
+        drop(harness);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn menu_starts_and_exits() {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+        harness.run(&[Action::Tap(Keycode::Backspace)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Exit);
+    }
+
+    #[test]
+    fn menu_options_are_navigable() {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        let tap = |keys: &[Keycode]| keys.iter().map(|key| Action::Tap(*key)).collect::<Vec<_>>();
+
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::MapSelect);
+        harness.run(&tap(&[Keycode::Escape])).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::RandomLevel);
+        harness.run(&tap(&[Keycode::Escape])).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Achievements);
+        harness.run(&tap(&[Keycode::Escape])).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+
+        // normal to hard halves the health players start with
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+        assert_eq!(harness.game.player.health, 127);
+
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Settings);
+        harness.run(&tap(&[Keycode::Escape])).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Help);
+        harness.run(&tap(&[Keycode::Escape])).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Credits);
+        harness.run(&tap(&[Keycode::Escape])).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+
+        harness
+            .run(&tap(&[Keycode::Down, Keycode::Return]))
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Exit);
+    }
+
+    #[test]
+    fn idle_menus_play_the_demo_until_a_key_is_pressed() {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        let spawn = harness.game.player.pos;
+        harness
+            .run(&[Action::Wait((TARGET_FPS * 30) as usize + 50)])
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_ne!(harness.game.player.pos, spawn);
+
+        // the spectator camera watches without stopping it
+        harness.run(&[Action::Tap(Keycode::Tab)]).unwrap();
+        assert!(harness.game.spectator.is_some());
+        harness.run(&[Action::Hold(vec![Keycode::S], 5)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+
+        harness.run(&[Action::Tap(Keycode::Escape)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+        assert_eq!(harness.game.player.pos, spawn);
+        assert!(harness.game.spectator.is_none());
+        // playing the campaign in the demo does not count as playing it
+        assert_eq!(harness.game.progress.step(MAIN_CAMPAIGN), None);
+    }
+
+    #[test]
+    fn map_select_plays_a_map() {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        harness
+            .run(&[
+                Action::Tap(Keycode::Down),
+                Action::Tap(Keycode::Return),
+                Action::Tap(Keycode::Return),
+            ])
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+    }
+
+    #[test]
+    fn random_levels_play_the_typed_seed() {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        let mut actions = parse_script("tap down\ntap down\ntap return").unwrap();
+        // clear the seed the menu came up with, one digit at a time
+        actions.extend((0..20).map(|_| Action::Tap(Keycode::Backspace)));
+        actions.extend(
+            parse_script(
+                "type 42
+                tap down
+                tap down
+                tap return
+                expect state playing",
+            )
+            .unwrap(),
+        );
+        harness.run(&actions).unwrap();
+
+        let template = Map::load("map/map.yaw".into()).unwrap();
+        let map = mapgen::generate(&template, 31, 21, Layout::Rooms, 42).unwrap();
+        assert_eq!(Some(harness.game.player.pos), map.get_spawn());
+    }
+
+    #[test]
+    fn console_commands_change_the_game() {
+        let mut harness = playing();
+        harness.game.player.health = 100;
+        let actions = parse_script(
+            "tap `
+            type tp 1 1
+            tap return
+            type give health 10
+            tap return
+            tap escape",
+        )
+        .unwrap();
+        harness.run(&actions).unwrap();
+
+        assert_eq!(harness.game.player.pos, Vec2::splat(48.));
+        assert_eq!(harness.game.player.health, 110);
+        assert_eq!(harness.game.game_state, GameState::Playing);
+
+        // held keys do nothing while the console is open
+        harness.run(&[Action::Tap(Keycode::Backquote)]).unwrap();
+        harness.run(&[Action::Hold(vec![Keycode::S], 10)]).unwrap();
+        assert_eq!(harness.game.player.pos, Vec2::splat(48.));
+    }
+
+    #[test]
+    fn noclip_and_free_camera_pass_through_walls() {
+        let mut harness = playing();
+        let spawn = harness.game.player.pos;
+        let console = |command: &str| {
+            parse_script(&format!("tap `\ntype {command}\ntap return\ntap escape")).unwrap()
+        };
+
+        harness.run(&console("freecam")).unwrap();
+        harness.run(&[Action::Hold(vec![Keycode::W], 60)]).unwrap();
+        assert_eq!(harness.game.player.pos, spawn);
+
+        harness.run(&console("freecam")).unwrap();
+        harness.run(&console("noclip")).unwrap();
+        harness.run(&[Action::Hold(vec![Keycode::W], 30)]).unwrap();
+        assert!(harness.game.player.pos.x > spawn.x + 32.);
+    }
+
+    #[test]
+    fn rays_are_cast_again_only_when_needed() {
+        let mut harness = playing();
+        assert!(!harness.game.rays_dirty);
+
+        // nothing moved, so whatever was cast last is kept
+        harness.game.slices.clear();
+        harness.game.cast_rays();
+        assert!(harness.game.slices.is_empty());
+
+        harness.game.player.direction += 0.1;
+        harness.game.cast_rays();
+        assert_eq!(harness.game.slices.len(), WIDTH);
+
+        harness.game.slices.clear();
+        harness.game.rays_dirty = true;
+        harness.game.cast_rays();
+        assert_eq!(harness.game.slices.len(), WIDTH);
+
+        // lower render scales cast a ray for every few pixels
+        harness.game.scaler.setting = RenderScale::Fixed(4);
+        harness.game.rays_dirty = true;
+        harness.game.cast_rays();
+        assert_eq!(harness.game.slices.len(), WIDTH / 4);
+    }
+
+    #[test]
+    fn walls_stop_the_player() {
+        let mut harness = playing();
+        let spawn = harness.game.player.pos;
+
+        // the spawn faces a wall one tile away
+        harness.run(&[Action::Hold(vec![Keycode::W], 60)]).unwrap();
+        let pos = harness.game.player.pos;
+        assert!(pos.x > spawn.x);
+        assert!(pos.x < spawn.x + 32.);
+        assert_eq!(pos.y, spawn.y);
+    }
+
+    #[test]
+    fn sprinting_is_faster() {
+        let mut walking = playing();
+        let mut sprinting = playing();
+
+        // turn around to face down the long corridor
+        let turn = Action::Hold(vec![Keycode::Left], (PI / 0.1).round() as usize);
+        walking.run(&[turn]).unwrap();
+        let turn = Action::Hold(vec![Keycode::Left], (PI / 0.1).round() as usize);
+        sprinting.run(&[turn]).unwrap();
+        let start = walking.game.player.pos;
+
+        walking.run(&[Action::Hold(vec![Keycode::W], 20)]).unwrap();
+        sprinting
+            .run(&[Action::Hold(vec![Keycode::W, Keycode::LShift], 20)])
+            .unwrap();
+
+        let walked = start.distance(walking.game.player.pos);
+        let sprinted = start.distance(sprinting.game.player.pos);
+        assert!(sprinted > walked * 1.5, "{sprinted} vs {walked}");
+        assert!(sprinting.game.player.stamina < walking.game.player.stamina);
+    }
+
+    #[test]
+    fn stamina_drains_once_a_tick_and_comes_back_standing_still() {
+        let mut ahead = playing();
+        let mut diagonal = playing();
+        let full = ahead.game.player.stamina;
+        ahead
+            .run(&[Action::Hold(vec![Keycode::W, Keycode::LShift], 10)])
+            .unwrap();
+        diagonal
+            .run(&[Action::Hold(
+                vec![Keycode::W, Keycode::D, Keycode::LShift],
+                10,
+            )])
+            .unwrap();
+        let tired = ahead.game.player.stamina;
+        assert!(tired < full);
+        assert_eq!(diagonal.game.player.stamina, tired);
+
+        ahead.run(&[Action::Wait(10)]).unwrap();
+        assert!(ahead.game.player.stamina > tired);
+    }
+
+    #[test]
+    fn turning_speeds_up_and_slows_down() {
+        let mut harness = playing();
+        let turn_speed = harness.game.player.turn_speed;
+
+        harness
+            .run(&[Action::Hold(vec![Keycode::Right], 1)])
+            .unwrap();
+        let turning = harness.game.player.turning;
+        assert!(turning > 0. && turning < turn_speed, "{turning}");
+        harness
+            .run(&[Action::Hold(vec![Keycode::Right], 10)])
+            .unwrap();
+        assert_eq!(harness.game.player.turning, turn_speed);
+
+        // letting go keeps turning for a moment
+        let released = harness.game.player.direction;
+        harness.run(&[Action::Wait(1)]).unwrap();
+        assert!(harness.game.player.direction > released);
+        harness.run(&[Action::Wait(5)]).unwrap();
+        assert_eq!(harness.game.player.turning, 0.);
+    }
+
+    #[test]
+    fn scripts_check_expectations() {
+        let actions = parse_script(
+            "# start the campaign
+            tap return
+            expect state playing
+            hold w+shift 3
+            expect health 200",
+        )
+        .unwrap();
+        assert_eq!(actions.len(), 4);
+        Harness::new(&Args::default())
+            .unwrap()
+            .run(&actions)
+            .unwrap();
+
+        let actions = parse_script("expect state exit").unwrap();
+        assert!(Harness::new(&Args::default())
+            .unwrap()
+            .run(&actions)
+            .is_err());
+        assert!(parse_script("hold w").is_err());
+    }
+
+    #[test]
+    fn split_screen_players_move_separately() {
+        let args = Args {
+            split: Some(Bindings::second()),
+            ..Args::default()
+        };
+        let mut harness = Harness::new(&args).unwrap();
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        let spawn = harness.game.player.pos;
+
+        harness
+            .run(&[
+                Action::Hold(vec![Keycode::W], 5),
+                Action::Hold(vec![Keycode::U], 5),
+            ])
+            .unwrap();
+        let second = harness.game.second.unwrap();
+        assert!(harness.game.player.pos.x > spawn.x);
+        assert_eq!(harness.game.player.direction, 0.);
+        assert_eq!(second.pos, spawn);
+        assert!(second.direction > PI);
+    }
+
+    #[test]
+    fn pausing_and_minimap() {
+        let mut harness = playing();
+
+        harness.run(&[Action::Tap(Keycode::M)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Minimap);
+        harness.run(&[Action::Tap(Keycode::M)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+
+        harness
+            .run(&[Action::Tap(Keycode::Escape), Action::Wait(5)])
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Paused);
+        // other keys do not resume
+        harness.run(&[Action::Tap(Keycode::Space)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Paused);
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+    }
+
+    #[test]
+    fn automap_pans_without_moving_players() {
+        let mut harness = playing();
+        let spawn = harness.game.player.pos;
+
+        harness.run(&[Action::Tap(Keycode::Tab)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Automap);
+        harness.run(&[Action::Hold(vec![Keycode::W], 5)]).unwrap();
+        assert_eq!(harness.game.player.pos, spawn);
+        harness.run(&[Action::Tap(Keycode::Escape)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+    }
+
+    #[test]
+    fn broken_maps_show_an_error_to_retry_or_leave() {
+        let path = std::env::temp_dir().join(format!("yaw-broken-{}", std::process::id()));
+        std::fs::write(path.with_extension("yaw"), "not a map").unwrap();
+        let mut harness = playing();
+        harness
+            .run(
+                &parse_script(&format!(
+                    "tap `\ntype map {}\ntap return\ntap escape",
+                    path.display()
+                ))
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Error);
+
+        // still broken when trying again
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Error);
+        std::fs::remove_file(path.with_extension("yaw")).unwrap();
+        harness.run(&[Action::Tap(Keycode::Escape)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+    }
+
+    #[test]
+    fn maps_set_how_players_move_and_their_health() {
+        let path = std::env::temp_dir().join(format!("yaw-player-{}.yaw", std::process::id()));
+        let wall = std::env::current_dir().unwrap().join("map/wall.png");
+        std::fs::write(
+            &path,
+            format!(
+                "!!!!META\nplayer,speed=120,strafe_speed=90,health=100,turn_speed=6\n\n\
+                 !!!!MAIN\n#{},collide\n\n#####\n#*  #\n#####",
+                wall.display()
+            ),
+        )
+        .unwrap();
+        let mut harness = playing();
+        assert_eq!(harness.game.player.speed, 60.);
+        harness
+            .run(&parse_script(&format!("tap `\ntype map {}\ntap return", path.display())).unwrap())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let player = harness.game.player;
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_eq!(
+            (
+                player.speed,
+                player.strafe_speed,
+                player.turn_speed,
+                player.health
+            ),
+            (120., 90., 6., 100)
+        );
+    }
+
+    #[test]
+    fn mud_slows_players_and_ice_slides_them() {
+        let mut harness = Harness::with_map(
+            "!!!!MAIN\n#wall.png,collide\n~wall.png,floor,speed_mul=0.5\n\
+             =wall.png,floor,speed_mul=1.5,slippery\n\n\
+             ##############\n#*~==========#\n##############",
+        );
+        let step = harness.game.player.speed / TARGET_FPS as f32;
+        let walk = |harness: &mut Harness, x: f32, ticks| {
+            harness.game.player.pos = Vec2::new(x, 1.5) * TILE_SIZE;
+            harness.game.player.direction = 0.;
+            harness
+                .run(&[Action::Hold(vec![Keycode::W], ticks)])
+                .unwrap();
+            harness.game.player.pos.x - (x * TILE_SIZE)
+        };
+
+        let mud = walk(&mut harness, 2.5, 1);
+        assert!((mud - (step * 0.5)).abs() < 0.001, "{mud}");
+        // ice gets going slowly, but then faster than walking
+        let first = walk(&mut harness, 3.5, 1);
+        assert!(first > 0. && first < step, "{first}");
+        harness.game.player.sliding = Vec2::ZERO;
+        let far = walk(&mut harness, 3.5, 60);
+        assert!(far > step * 60., "{far}");
+
+        // and keeps sliding for a while after letting go
+        let released = harness.game.player.pos;
+        harness.run(&[Action::Wait(1)]).unwrap();
+        assert!(harness.game.player.pos.x > released.x);
+        harness
+            .run(&[Action::Wait(TARGET_FPS as usize * 3)])
+            .unwrap();
+        assert_eq!(harness.game.player.sliding, Vec2::ZERO);
+    }
+
+    #[test]
+    fn walking_into_crates_pushes_them_onto_plates() {
+        let mut harness = Harness::with_map(
+            "!!!!MAIN\n#wall.png,collide\ncwall.png,collide,pushable\n\n\
+             #######\n#*c  ##\n#######\n\n\
+             !!!!TRIGGERS\nmessage,x=3,y=1,plate,text=Click",
+        );
+        let entities = &harness.game.entities;
+        let pushed = *entities.pushable.iter().next().unwrap();
+        assert_eq!(entities.positions[&pushed], Vec2::new(2.5, 1.5) * TILE_SIZE);
+
+        harness.game.player.pos = Vec2::splat(1.5) * TILE_SIZE;
+        harness.game.player.direction = 0.;
+        harness
+            .run(&[Action::Hold(vec![Keycode::W], 10), Action::Wait(20)])
+            .unwrap();
+        assert_eq!(
+            harness.game.entities.positions[&pushed],
+            Vec2::new(3.5, 1.5) * TILE_SIZE
+        );
+        assert!(harness
+            .game
+            .messages
+            .iter()
+            .any(|(message, _)| message == "Click"));
+
+        // the wall behind it stops it
+        harness
+            .run(&[Action::Hold(vec![Keycode::W], 40), Action::Wait(20)])
+            .unwrap();
+        assert_eq!(
+            harness.game.entities.positions[&pushed],
+            Vec2::new(4.5, 1.5) * TILE_SIZE
+        );
+        assert!(harness.game.player.pos.x < 4. * TILE_SIZE);
+    }
+
+    #[test]
+    fn entity_state_is_kept_once_a_level_is_finished() {
+        let mut harness = Harness::with_files(&[
+            (
+                "map.yaw",
+                "!!!!MAIN\n#wall.png,collide\ncwall.png,collide,pushable\n\
+                 Ewall.png,floor,exit\n\n\
+                 ######\n#*  E#\n#c####\n######",
+            ),
+            (
+                "map.rhai",
+                r#"
+                fn on_restore(x, y, state) { message(`restored ${x} ${y} ${state.seen}`); }
+                fn on_load() {
+                    if entity_state(1, 2, "seen") == "" {
+                        set_entity_state(1, 2, "seen", "once");
+                        message("first");
+                    }
+                }
+                "#,
+            ),
+        ]);
+        let messages = |harness: &Harness| {
+            let messages = harness.game.messages.iter();
+            messages.map(|(text, _)| text.clone()).collect::<Vec<_>>()
+        };
+        let finish = |harness: &mut Harness| {
+            harness.game.player.pos = Vec2::new(3.5, 1.5) * TILE_SIZE;
+            harness.game.player.direction = 0.;
+            harness.run(&[Action::Hold(vec![Keycode::W], 40)]).unwrap();
+            assert_eq!(harness.game.game_state, GameState::Stats);
+        };
+        harness.play("map.yaw");
+        assert!(messages(&harness).contains(&"first".into()));
+        finish(&mut harness);
+
+        let level = harness.dir.path().join("map.yaw").display().to_string();
+        let saved = EntityStates::load(harness.dir.path().join("entity_state.txt")).unwrap();
+        assert_eq!(saved.get(&level)[&(1, 2)]["seen"], "once");
+
+        harness.game.messages.clear();
+        harness.play("map.yaw");
+        assert_eq!(messages(&harness), ["restored 1 2 once"]);
+
+        // like demos bring along
+        harness
+            .run(&[Action::State {
+                level,
+                at: (1, 2),
+                key: "seen".into(),
+                value: "twice".into(),
+            }])
+            .unwrap();
+        harness.game.messages.clear();
+        harness.play("map.yaw");
+        assert_eq!(messages(&harness), ["restored 1 2 twice"]);
+    }
+
+    #[test]
+    fn switches_open_the_doors_wired_to_them() {
+        let mut harness = Harness::with_map(
+            "!!!!MAIN\n#wall.png,collide\n/wall.png,collide,switch=gate\n\
+             |wall.png,collide,door=gate\n\n\
+             #/####\n#*|  #\n######",
+        );
+        let walk = |harness: &mut Harness| {
+            harness.game.player.pos = Vec2::splat(1.5) * TILE_SIZE;
+            harness.game.player.direction = 0.;
+            harness.run(&[Action::Hold(vec![Keycode::W], 30)]).unwrap();
+            harness.game.player.pos.x
+        };
+
+        assert!(walk(&mut harness) < 2. * TILE_SIZE);
+        harness.game.player.direction = 1.5 * PI;
+        harness
+            .run(&[Action::Tap(Keycode::E), Action::Wait(1)])
+            .unwrap();
+        assert!(walk(&mut harness) > 3. * TILE_SIZE);
+    }
+
+    #[test]
+    fn talking_holds_the_level_until_the_dialogue_ends() {
+        let mut harness = Harness::with_files(&[
+            (
+                "map.yaw",
+                "!!!!MAIN\n#wall.png,collide\nnwall.png,collide,dialogue=npc.txt\n\n\
+                 #####\n#*n #\n#####",
+            ),
+            ("npc.txt", "Hello.\n> Bye\n"),
+        ]);
+        harness.play("map.yaw");
+        harness.game.player.pos = Vec2::splat(1.5) * TILE_SIZE;
+        harness.game.player.direction = 0.;
+
+        harness.run(&[Action::Tap(Keycode::E)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Dialogue);
+        let pos = harness.game.player.pos;
+        harness.run(&[Action::Hold(vec![Keycode::S], 10)]).unwrap();
+        assert_eq!(harness.game.player.pos, pos);
+
+        // the page is typed out by now, and the answer leads nowhere
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+    }
+
+    #[test]
+    fn exits_wait_for_every_objective() {
+        let mut harness = Harness::with_map(
+            "!!!!META\nexit=objectives\nobjective,hall,Walk down the hall\n\n\
+             !!!!MAIN\n#wall.png,collide\nEwall.png,floor,exit\n\n\
+             ######\n#*  E#\n######\n\n\
+             !!!!TRIGGERS\ncomplete,x=2,y=1,objective=hall",
+        );
+        let walk = |harness: &mut Harness, x: f32| {
+            harness.game.player.pos = Vec2::new(x, 1.5) * TILE_SIZE;
+            harness.game.player.direction = 0.;
+            harness.run(&[Action::Hold(vec![Keycode::W], 40)]).unwrap();
+        };
+
+        // past the hall, so the exit stays shut
+        walk(&mut harness, 3.5);
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        let messages = &harness.game.messages;
+        assert!(
+            messages
+                .iter()
+                .any(|(message, _)| message == "There is still something to do before leaving"),
+            "no message about objectives left in {messages:?}"
+        );
+
+        walk(&mut harness, 1.5);
+        assert!(harness
+            .game
+            .messages
+            .iter()
+            .any(|(message, _)| message == "Objective done: Walk down the hall"));
+        assert_eq!(harness.game.game_state, GameState::Stats);
+    }
+
+    #[test]
+    fn briefings_come_before_and_after_levels() {
+        let text = "word ".repeat(400);
+        let mut harness = Harness::with_files(&[
+            (
+                "map.yaw",
+                "!!!!META\nbriefing=briefing.txt\ndebriefing=briefing.txt\n\n\
+                 !!!!MAIN\n#wall.png,collide\nEwall.png,floor,exit\n\n\
+                 #####\n#* E#\n#####",
+            ),
+            ("briefing.txt", &text),
+        ]);
+        harness.play("map.yaw");
+        assert_eq!(harness.game.game_state, GameState::Briefing);
+        // long text is wrapped into lines that scroll
+        let (briefing, _) = harness.game.briefing.as_ref().unwrap();
+        assert!(briefing.lines.len() > 1);
+
+        let actions = parse_script(
+            "tap down
+            expect state briefing
+            tap return
+            expect state playing",
+        )
+        .unwrap();
+        harness.run(&actions).unwrap();
+        harness.game.player.pos = Vec2::splat(1.5) * TILE_SIZE;
+        harness.game.player.direction = 0.;
+        harness.run(&[Action::Hold(vec![Keycode::W], 30)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Briefing);
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Stats);
+    }
+
+    #[test]
+    fn play_starts_the_shipped_campaign() {
+        let mut harness = Harness::new(&Args::default()).unwrap();
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_eq!(harness.game.progress.step(MAIN_CAMPAIGN), Some(0));
+        // with the health the campaign starts players with
+        assert_eq!(harness.game.player.health, 200);
+    }
+
+    #[test]
+    fn play_starts_the_campaign_and_continue_resumes_it() {
+        let mut harness = Harness::with_files(&[
+            (
+                "level.yaw",
+                "!!!!MAIN\n#wall.png,collide\nEwall.png,floor,exit\n\n#####\n#* E#\n#####",
+            ),
+            ("level.txt", "Down the stairs."),
+            (
+                "campaign.yawc",
+                "!!!!LEVELS\nlevel.yaw\nlevel.yaw,briefing=level.txt\n",
+            ),
+        ]);
+        let mut campaign = Campaign::load(harness.dir.path().join("campaign.yawc")).unwrap();
+        campaign.path = MAIN_CAMPAIGN.into();
+        harness.game.campaigns = vec![campaign];
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_eq!(harness.game.progress.step(MAIN_CAMPAIGN), Some(0));
+
+        // the second level has a briefing of its own
+        harness.game.player.pos = Vec2::splat(1.5) * TILE_SIZE;
+        harness.game.player.direction = 0.;
+        harness.run(&[Action::Hold(vec![Keycode::W], 30)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Stats);
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Briefing);
+        assert_eq!(harness.game.progress.step(MAIN_CAMPAIGN), Some(1));
+
+        let actions = parse_script(
+            "tap return
+            tap escape
+            tap up
+            tap down
+            tap down
+            tap down
+            tap return
+            expect state menu
+            tap return
+            expect state briefing",
+        )
+        .unwrap();
+        harness.run(&actions).unwrap();
+        assert_eq!(harness.game.progress.step(MAIN_CAMPAIGN), Some(1));
+
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        harness.game.player.pos = Vec2::splat(1.5) * TILE_SIZE;
+        harness.game.player.direction = 0.;
+        harness.run(&[Action::Hold(vec![Keycode::W], 30)]).unwrap();
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+        assert!(harness.game.progress.finished(MAIN_CAMPAIGN));
+        assert_eq!(harness.game.progress.step(MAIN_CAMPAIGN), None);
+    }
+
+    #[test]
+    fn hubs_lead_to_levels_until_every_one_is_finished() {
+        let level = "!!!!MAIN\n#wall.png,collide\nEwall.png,floor,exit\n\n#####\n#* E#\n#####";
+        let mut harness = Harness::with_files(&[
+            ("a.yaw", level),
+            ("b.yaw", level),
+            (
+                "hub.yaw",
+                "!!!!MAIN\n#wall.png,collide\n\n#####\n#*  #\n#####\n\n\
+                 !!!!TRIGGERS\nlevel,x=2,y=1,map=a.yaw\nlevel,x=3,y=1,map=b.yaw\n\
+                 message,x=3,y=1,text=Still in the hub",
+            ),
+            (
+                "campaign.yawc",
+                "!!!!META\nhub=hub.yaw\nhealth=100\ncarry=health,ammo\n\n\
+                 !!!!LEVELS\na.yaw\nb.yaw\n",
+            ),
+        ]);
+        let dir = harness.dir.path().to_path_buf();
+        let mut campaign = Campaign::load(dir.join("campaign.yawc")).unwrap();
+        campaign.path = MAIN_CAMPAIGN.into();
+        harness.game.campaigns = vec![campaign];
+        let go = |harness: &mut Harness, x: f32| {
+            harness.game.player.pos = Vec2::new(x, 1.5) * TILE_SIZE;
+            harness.game.player.direction = 0.;
+            harness.run(&[Action::Wait(1)]).unwrap();
+        };
+        let finish = |harness: &mut Harness| {
+            go(harness, 1.5);
+            harness.run(&[Action::Hold(vec![Keycode::W], 30)]).unwrap();
+            assert_eq!(harness.game.game_state, GameState::Stats);
+            harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        };
+
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.level, dir.join("hub.yaw"));
+        assert_eq!(harness.game.player.health, 100);
+        harness.game.player.health = 50;
+        harness.game.player.ammo = 7;
+
+        // any level first, keeping what players have and leaving the hub's actions behind
+        go(&mut harness, 3.5);
+        assert_eq!(harness.game.level, dir.join("b.yaw"));
+        assert!(!harness
+            .game
+            .messages
+            .iter()
+            .any(|(message, _)| message == "Still in the hub"));
+        assert_eq!(
+            (harness.game.player.health, harness.game.player.ammo),
+            (50, 7)
+        );
+        finish(&mut harness);
+        assert_eq!(harness.game.level, dir.join("hub.yaw"));
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert!(harness.game.progress.cleared(MAIN_CAMPAIGN, 1));
+        assert!(!harness.game.progress.cleared(MAIN_CAMPAIGN, 0));
+        assert_eq!(harness.game.player.ammo, 7);
+
+        // finished levels stay shut
+        go(&mut harness, 3.5);
+        assert_eq!(harness.game.level, dir.join("hub.yaw"));
+        assert!(harness
+            .game
+            .messages
+            .iter()
+            .any(|(message, _)| message == "You already finished this level"));
+
+        go(&mut harness, 2.5);
+        assert_eq!(harness.game.level, dir.join("a.yaw"));
+        finish(&mut harness);
+        assert_eq!(harness.game.game_state, GameState::Menu);
+        assert!(harness.game.progress.finished(MAIN_CAMPAIGN));
+        assert!(!harness.game.progress.cleared(MAIN_CAMPAIGN, 1));
+    }
+
+    #[test]
+    fn finishing_a_campaign_unlocks_modifiers() {
+        let mut harness = Harness::with_files(&[
+            (
+                "level.yaw",
+                "!!!!MAIN\n#wall.png,collide\nEwall.png,floor,exit\n\n\
+                 #######\n#*   E#\n#######\n\n\
+                 !!!!TRIGGERS\nspawn,x=2,y=1,at=3:1,sprite=wall.png,chase",
+            ),
+            ("campaign.yawc", "!!!!LEVELS\nlevel.yaw\n"),
+        ]);
+        let dir = harness.dir.path().to_path_buf();
+        let mut campaign = Campaign::load(dir.join("campaign.yawc")).unwrap();
+        campaign.path = MAIN_CAMPAIGN.into();
+        harness.game.campaigns = vec![campaign];
+        let go = |harness: &mut Harness, x: f32| {
+            harness.game.player.pos = Vec2::new(x, 1.5) * TILE_SIZE;
+            harness.run(&[Action::Wait(1)]).unwrap();
+        };
+        let enemy_speed = |harness: &Harness| match harness.game.entities.ai.values().next() {
+            Some(Ai::Chase { speed, .. }) => *speed,
+            other => panic!("no enemy but {other:?}"),
+        };
+        // the item after campaigns, once there is one
+        let modifiers = "tap down\ntap down\ntap down\ntap down\ntap return";
+
+        harness.run(&parse_script(modifiers).unwrap()).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Achievements);
+        let actions = parse_script(
+            "tap escape
+            tap up
+            tap up
+            tap up
+            tap up
+            tap return
+            expect state playing",
+        )
+        .unwrap();
+        harness.run(&actions).unwrap();
+        go(&mut harness, 2.5);
+        let speed = enemy_speed(&harness);
+        harness.game.player.direction = 0.;
+        go(&mut harness, 4.9);
+        harness.run(&[Action::Hold(vec![Keycode::W], 10)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Stats);
+        assert_eq!(harness.game.stats.modifiers, Modifiers::default());
+        let level = dir.join("level.yaw").display().to_string();
+        let best = harness.game.best_times.get(&level);
+        assert!(best.is_some());
+        harness.run(&[Action::Tap(Keycode::Return)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+
+        let actions = parse_script(&format!(
+            "{modifiers}
+            expect state modifiers
+            tap return
+            tap down
+            tap return
+            tap escape
+            expect state menu"
+        ))
+        .unwrap();
+        harness.run(&actions).unwrap();
+        assert!(harness.game.modifiers.fast_enemies && harness.game.modifiers.mirrored);
+
+        // the spawn is at the other end now, and so is the trigger
+        harness.game.play_map(dir.join("level.yaw"));
+        harness.run(&[Action::Wait(1)]).unwrap();
+        harness.check(Expect::Tile(5, 1)).unwrap();
+        assert_eq!(harness.game.stats.modifiers, harness.game.modifiers);
+        go(&mut harness, 4.5);
+        assert_eq!(enemy_speed(&harness), speed * 1.5);
+
+        // however fast, the time is not kept
+        harness.game.player.direction = std::f32::consts::PI;
+        go(&mut harness, 2.1);
+        harness.game.stats.frames = 0;
+        harness.run(&[Action::Hold(vec![Keycode::W], 10)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Stats);
+        assert_eq!(harness.game.best_times.get(&level), best);
+    }
+
+    #[test]
+    fn pause_menu_restarts_and_quits() {
+        let mut harness = playing();
+        let spawn = harness.game.player.pos;
+        harness.run(&[Action::Hold(vec![Keycode::W], 5)]).unwrap();
+        assert_ne!(harness.game.player.pos, spawn);
+
+        harness
+            .run(&[
+                Action::Tap(Keycode::Escape),
+                Action::Tap(Keycode::Down),
+                Action::Tap(Keycode::Return),
+            ])
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_eq!(harness.game.player.pos, spawn);
+
+        harness
+            .run(&[
+                Action::Tap(Keycode::Escape),
+                Action::Tap(Keycode::Up),
+                Action::Tap(Keycode::Down),
+                Action::Tap(Keycode::Down),
+                Action::Tap(Keycode::Down),
+                Action::Tap(Keycode::Return),
+            ])
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+    }
+
+    #[test]
+    fn losing_health_flashes_the_screen() {
+        let mut harness = playing();
+        // let the fade into the level finish
+        harness.run(&[Action::Wait(30)]).unwrap();
+        // on the edge of the view, far above the status bar
+        let corner = |harness: &mut Harness| {
+            let image = harness.game.renderer.capture().unwrap();
+            Color::from_argb(image.pixels[(HEIGHT / 4) * image.width])
+        };
+        // bright pixels lose some red under the flash too, but never more than the rest
+        let redness = |color: Color| i32::from(color.r) - i32::from(color.g.max(color.b));
+        let before = corner(&mut harness);
+
+        harness.game.player.health -= 10;
+        harness.run(&[Action::Wait(1)]).unwrap();
+        let flash = corner(&mut harness);
+        assert!(redness(flash) > redness(before), "{flash:?} vs {before:?}");
+        assert!(harness.game.animating());
+
+        harness.run(&[Action::Wait(30)]).unwrap();
+        assert!(!harness.game.animating());
+        assert_eq!(corner(&mut harness), before);
+    }
+
+    #[test]
+    fn dying_goes_back_to_the_last_checkpoint() {
+        let has_message = |harness: &Harness, text: &str| {
+            harness
+                .game
+                .messages
+                .iter()
+                .any(|(message, _)| message == text)
+        };
+
+        // without a checkpoint the run is over
+        let mut harness = playing();
+        harness.game.player.health = 0;
+        harness.run(&[Action::Wait(1)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Menu);
+        assert!(has_message(&harness, "Game over"));
+
+        let mut harness = playing();
+        let actions = parse_script(
+            "tap `
+            type tp 5 6
+            tap return
+            tap `
+            wait 1",
+        )
+        .unwrap();
+        harness.run(&actions).unwrap();
+        assert!(has_message(&harness, "Checkpoint reached"));
+        let checkpoint = harness.game.player.pos;
+
+        harness.run(&[Action::Hold(vec![Keycode::S], 10)]).unwrap();
+        harness.game.player.health = 0;
+        harness.run(&[Action::Wait(1)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_eq!(harness.game.player.pos, checkpoint);
+        assert_eq!(harness.game.player.health, 255);
+        assert!(has_message(&harness, "Back at the checkpoint"));
+    }
+
+    #[test]
+    fn looking_at_usable_tiles_prompts() {
+        let mut harness = playing();
+        let prompts = |harness: &Harness| {
+            harness
+                .game
+                .messages
+                .iter()
+                .filter(|(text, _)| text == "Press E to examine")
+                .count()
+        };
+
+        // the cellar spawn has a wall right behind it
+        harness
+            .run(&parse_script("tap `\ntype map cellar\ntap return\ntap `").unwrap())
+            .unwrap();
+        assert_eq!(prompts(&harness), 0);
+        let turn = Action::Hold(vec![Keycode::Left], (PI / 0.1).round() as usize);
+        harness.run(&[turn, Action::Wait(2)]).unwrap();
+        let seen = prompts(&harness);
+        assert!(seen > 0);
+        assert_eq!(
+            harness.game.messages.last().unwrap().0,
+            "Press E to examine"
+        );
+
+        // only once while looking at the same wall
+        harness.run(&[Action::Wait(5)]).unwrap();
+        assert_eq!(prompts(&harness), seen);
+    }
+
+    #[test]
+    fn finishing_a_level_shows_stats() {
+        let mut harness = playing();
+        let path = std::env::temp_dir().join(format!("yaw-harness-{}.txt", std::process::id()));
+        harness.game.best_times = BestTimes::new(path.clone());
+        let unlocked = path.with_extension("achievements");
+        harness.game.achievements = Achievements::new(unlocked.clone());
+
+        // the exit is right above this tile
+        let actions = parse_script(
+            "tap `
+            type tp 10 2
+            tap return
+            tap `
+            hold left 16
+            hold w 30
+            expect state stats",
+        )
+        .unwrap();
+        harness.run(&actions).unwrap();
+        // quick and without a scratch
+        let toasts = harness
+            .game
+            .messages
+            .iter()
+            .filter(|(text, _)| text.starts_with("Achievement unlocked"))
+            .count();
+        assert_eq!(toasts, 3);
+
+        // fly around the finished level without moving the player
+        let player = harness.game.player;
+        harness
+            .run(&parse_script("tap tab\nhold w 10").unwrap())
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Spectating);
+        let spectator = harness.game.spectator.unwrap();
+        assert_ne!(spectator.pos, player.pos);
+        harness.game.mouse_moved(100);
+        assert_ne!(
+            harness.game.spectator.unwrap().direction,
+            spectator.direction
+        );
+        assert_eq!(harness.game.player.pos, player.pos);
+        harness.run(&[Action::Tap(Keycode::Tab)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Stats);
+        assert!(!harness.game.wants_mouse());
+
+        let actions = parse_script(
+            "tap space
+            expect state menu",
+        )
+        .unwrap();
+        harness.run(&actions).unwrap();
+
+        let best = BestTimes::load(path.clone()).unwrap().get("map/map.yaw");
+        assert!(best.is_some_and(|frames| frames > 0));
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(unlocked).unwrap();
+    }
+
+    #[test]
+    fn stats_keep_the_randomize_seed() {
+        let args = Args {
+            randomize: Some(42),
+            ..Args::default()
+        };
+        let mut harness = Harness::new(&args).unwrap();
+        harness.game.play_map("map/map.yaw".into());
+        harness.run(&[Action::Wait(1)]).unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_eq!(harness.game.stats.seed, Some(42));
+
+        assert_eq!(playing().game.stats.seed, None);
+    }
+
+    #[test]
+    fn keys_leaving_menus_do_not_move_the_player() {
+        let args = Args {
+            keys: Bindings::parse("return,s,a,d,left,right,lshift").unwrap(),
+            ..Args::default()
+        };
+        let mut harness = Harness::new(&args).unwrap();
+        let spawn = harness.game.player.pos;
+
+        harness
+            .run(&[Action::Hold(vec![Keycode::Return], 10)])
+            .unwrap();
+        assert_eq!(harness.game.game_state, GameState::Playing);
+        assert_eq!(harness.game.player.pos, spawn);
+
+        // once released it walks again
+        harness
+            .run(&[Action::Hold(vec![Keycode::Return], 10)])
+            .unwrap();
+        assert_ne!(harness.game.player.pos, spawn);
+    }
+}
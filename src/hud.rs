@@ -0,0 +1,550 @@
+//! parts of the hud drawn over the view of a player
+
+use crate::lang::Lang;
+use crate::stats;
+use crate::theme::HudTheme;
+use glam::Vec2;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+use yaw_core::assets;
+use yaw_core::camera::Camera;
+use yaw_core::color::Color;
+use yaw_core::ray::RayCast;
+use yaw_core::render::{Rect, Renderer, TextStyle};
+
+/// size of the compass strip in pixels
+const COMPASS_WIDTH: u32 = 200;
+const COMPASS_HEIGHT: u32 = 24;
+/// how much of the heading the compass shows at once
+const COMPASS_SPAN: f32 = PI;
+/// headings of the letters on the compass, north is up on the map
+const CARDINALS: [(&str, f32); 4] = [("N", -FRAC_PI_2), ("E", 0.), ("S", FRAC_PI_2), ("W", PI)];
+const MARKER_COLOR: Color = Color::RGB(0xff, 0xdd, 0);
+/// rays drawn on the minimap by default, one in this many
+const RAY_STRIDE: usize = 8;
+/// rays the outline of the view cone follows, one in this many
+const CONE_STRIDE: usize = 4;
+
+/// mark in the middle of the view
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum Crosshair {
+    Off,
+    Dot,
+    #[default]
+    Cross,
+}
+
+impl Crosshair {
+    /// language key of its name
+    pub fn key(self) -> &'static str {
+        match self {
+            Crosshair::Off => "crosshair.off",
+            Crosshair::Dot => "crosshair.dot",
+            Crosshair::Cross => "crosshair.cross",
+        }
+    }
+
+    /// the next style, for menus that cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            Crosshair::Off => Crosshair::Dot,
+            Crosshair::Dot => Crosshair::Cross,
+            Crosshair::Cross => Crosshair::Off,
+        }
+    }
+
+    /// draw it in the middle of a viewport
+    pub fn draw(self, renderer: &mut dyn Renderer, viewport: Rect) -> anyhow::Result<()> {
+        let (x, y) = (
+            viewport.x + (viewport.width as i32 / 2),
+            viewport.y + (viewport.height as i32 / 2),
+        );
+        let color = Color::RGBA(0xff, 0xff, 0xff, 0xc0);
+
+        match self {
+            Crosshair::Off => {}
+            Crosshair::Dot => renderer.draw_rect(Some(Rect::new(x - 1, y - 1, 3, 3)), color)?,
+            Crosshair::Cross => {
+                // gap in the middle so the target stays visible
+                for (from, to) in [(-8, -3), (3, 8)] {
+                    renderer.draw_line((x + from, y), (x + to, y), color)?;
+                    renderer.draw_line((x, y + from), (x, y + to), color)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// how the minimap shows what the player sees
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum MinimapView {
+    /// filled out to the walls
+    #[default]
+    Cone,
+    /// a line along one in this many rays that hit a wall
+    Rays(usize),
+}
+
+impl MinimapView {
+    /// language key of its name, filled in with `stride`
+    pub fn key(self) -> &'static str {
+        match self {
+            MinimapView::Cone => "minimap_view.cone",
+            MinimapView::Rays(1) => "minimap_view.all",
+            MinimapView::Rays(_) => "minimap_view.rays",
+        }
+    }
+
+    /// the next way, for menus that cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            MinimapView::Cone => MinimapView::Rays(RAY_STRIDE),
+            MinimapView::Rays(1) => MinimapView::Cone,
+            MinimapView::Rays(_) => MinimapView::Rays(1),
+        }
+    }
+
+    /// draw the rays cast from `from` on the minimap
+    pub fn draw(
+        self,
+        renderer: &mut dyn Renderer,
+        style: &MinimapStyle,
+        from: Vec2,
+        slices: &[RayCast],
+    ) -> anyhow::Result<()> {
+        let point = |vec: Vec2| ((from.x + vec.x) as i32, (from.y + vec.y) as i32);
+        match self {
+            MinimapView::Cone => {
+                // every ray out to the sides of the view, even those that hit nothing
+                let mut outline = vec![from];
+                outline.extend(
+                    slices
+                        .iter()
+                        .step_by(CONE_STRIDE)
+                        .map(|slice| from + slice.vec),
+                );
+                outline.extend(slices.last().map(|slice| from + slice.vec));
+                fill_polygon(renderer, &outline, style.cone)?;
+            }
+            MinimapView::Rays(stride) => {
+                for slice in slices.iter().step_by(stride.max(1)) {
+                    if slice.hit {
+                        renderer.draw_line(point(Vec2::ZERO), point(slice.vec), style.ray)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// how the minimap looks
+#[derive(Clone)]
+pub(crate) struct MinimapStyle {
+    /// drawn over the view behind the map
+    pub background: Color,
+    pub wall: Color,
+    /// what the player sees, in each [`MinimapView`]
+    pub ray: Color,
+    pub cone: Color,
+}
+
+impl Default for MinimapStyle {
+    fn default() -> Self {
+        Self {
+            background: Color::RGBA(0, 0, 0, 0x77),
+            wall: Color::RGB(0, 0xdd, 0),
+            ray: Color::GREEN,
+            cone: Color::RGBA(0, 0xff, 0, 0x60),
+        }
+    }
+}
+
+/// fill the inside of a polygon one row at a time, every pixel is drawn once
+fn fill_polygon(renderer: &mut dyn Renderer, points: &[Vec2], color: Color) -> anyhow::Result<()> {
+    let (top, bottom) = points
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(top, bottom), point| {
+            (top.min(point.y), bottom.max(point.y))
+        });
+    if top > bottom {
+        return Ok(());
+    }
+
+    for y in top.floor() as i32..=bottom.ceil() as i32 {
+        for (from, to) in spans(points, y as f32 + 0.5) {
+            let (from, to) = (from.round() as i32, to.round() as i32 - 1);
+            if from <= to {
+                renderer.draw_line((from, y), (to, y), color)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// where a row crosses into and out of a polygon, from left to right
+fn spans(points: &[Vec2], y: f32) -> Vec<(f32, f32)> {
+    let mut crossings = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .filter(|(a, b)| (a.y <= y) != (b.y <= y))
+        .map(|(a, b)| a.x + ((y - a.y) / (b.y - a.y) * (b.x - a.x)))
+        .collect::<Vec<_>>();
+    crossings.sort_by(f32::total_cmp);
+    crossings
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect()
+}
+
+/// what a part of the status bar shows
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Field {
+    Health,
+    Stamina,
+    Ammo,
+    Keys,
+    /// lives left, on levels that count them
+    Lives,
+    /// time spent in the level, when the timer is on
+    Time,
+    /// face of the player, looking worse the less health is left
+    Face,
+    /// name of the map being played
+    Level,
+}
+
+impl Field {
+    /// language key of the label above it, none for no label
+    fn label(self) -> Option<&'static str> {
+        match self {
+            Field::Health => Some("hud.health"),
+            Field::Stamina => Some("hud.stamina"),
+            Field::Ammo => Some("hud.ammo"),
+            Field::Keys => Some("hud.keys"),
+            Field::Lives => Some("hud.lives"),
+            Field::Time => Some("hud.time"),
+            Field::Face => None,
+            Field::Level => Some("hud.level"),
+        }
+    }
+
+    /// a field by the name themes use for it
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "health" => Some(Field::Health),
+            "stamina" => Some(Field::Stamina),
+            "ammo" => Some(Field::Ammo),
+            "keys" => Some(Field::Keys),
+            "lives" => Some(Field::Lives),
+            "time" => Some(Field::Time),
+            "face" => Some(Field::Face),
+            "level" => Some(Field::Level),
+            _ => None,
+        }
+    }
+}
+
+/// how the status bar at the bottom of each view looks
+#[derive(Clone)]
+pub(crate) struct StatusBar {
+    pub height: u32,
+    pub background: Color,
+    /// color and size of labels, and of values and bars
+    pub label: Color,
+    pub value: Color,
+    pub label_size: u16,
+    pub value_size: u16,
+    /// what is shown where, from and to which fraction of the width of the bar
+    pub slots: Vec<(Field, f32, f32)>,
+    /// face shown from each amount of health up, highest first
+    pub faces: &'static [(u8, &'static str)],
+}
+
+impl Default for StatusBar {
+    fn default() -> Self {
+        Self {
+            height: 48,
+            background: Color::BLACK,
+            label: Color::RGB(0, 0x80, 0),
+            value: Color::GREEN,
+            label_size: 12,
+            value_size: 16,
+            slots: vec![
+                (Field::Health, 0., 0.13),
+                (Field::Stamina, 0.13, 0.3),
+                (Field::Face, 0.3, 0.4),
+                (Field::Ammo, 0.4, 0.5),
+                (Field::Keys, 0.5, 0.59),
+                (Field::Lives, 0.59, 0.68),
+                (Field::Time, 0.68, 0.82),
+                (Field::Level, 0.82, 1.),
+            ],
+            faces: &[
+                (170, "images/face-healthy.png"),
+                (85, "images/face-hurt.png"),
+                (0, "images/face-dying.png"),
+            ],
+        }
+    }
+}
+
+/// what the status bar shows for one player
+pub(crate) struct Status<'a> {
+    pub health: u8,
+    /// from 0 to 1
+    pub stamina: f32,
+    pub ammo: u16,
+    pub keys: u8,
+    /// none to leave the lives out
+    pub lives: Option<u8>,
+    /// frames spent in the level, none to leave the time out
+    pub time: Option<u64>,
+    pub level: &'a str,
+}
+
+impl StatusBar {
+    /// whether every face image is there, the face is left out otherwise
+    pub fn has_faces(&self) -> bool {
+        self.faces
+            .iter()
+            .all(|(_, path)| assets::read(path.as_ref()).is_ok())
+    }
+
+    /// image for an amount of health
+    fn face(&self, health: u8) -> Option<&'static str> {
+        self.faces
+            .iter()
+            .find(|(from, _)| health >= *from)
+            .map(|(_, path)| *path)
+    }
+
+    /// draw the bar along the bottom of a viewport
+    pub fn draw(
+        &self,
+        renderer: &mut dyn Renderer,
+        status: &Status,
+        viewport: Rect,
+        faces: bool,
+        lang: &Lang,
+    ) -> anyhow::Result<()> {
+        let bar = Rect::new(
+            viewport.x,
+            viewport.bottom() - self.height as i32,
+            viewport.width,
+            self.height,
+        );
+        renderer.draw_rect(Some(bar), self.background)?;
+
+        let label_style = TextStyle::new(self.label_size, self.label);
+        let value_style = TextStyle::new(self.value_size, self.value);
+        for (field, from, to) in self.slots.iter() {
+            if (*field == Field::Time && status.time.is_none())
+                || (*field == Field::Lives && status.lives.is_none())
+            {
+                continue;
+            }
+            let slot = Rect::new(
+                bar.x + (from * bar.width as f32) as i32 + 8,
+                bar.y + 4,
+                ((to - from) * bar.width as f32) as u32 - 16,
+                bar.height - 8,
+            );
+            if let Some(label) = field.label() {
+                renderer.draw_text(lang.get(label), &label_style, slot.x, slot.y)?;
+            }
+            let value_y = slot.y + self.label_size as i32 + 6;
+
+            let value = match field {
+                Field::Health => status.health.to_string(),
+                Field::Ammo => status.ammo.to_string(),
+                Field::Keys => status.keys.to_string(),
+                Field::Lives => status.lives.unwrap_or_default().to_string(),
+                Field::Time => stats::clock(status.time.unwrap_or_default(), false),
+                Field::Level => status.level.to_string(),
+                Field::Stamina => {
+                    let outline = Rect::new(slot.x, value_y, slot.width, self.value_size as u32);
+                    renderer.draw_rect(Some(outline), self.label)?;
+                    renderer.draw_rect(
+                        Some(Rect::new(
+                            outline.x + 2,
+                            outline.y + 2,
+                            ((outline.width - 4) as f32 * status.stamina.clamp(0., 1.)) as u32,
+                            outline.height - 4,
+                        )),
+                        self.value,
+                    )?;
+                    continue;
+                }
+                Field::Face => {
+                    if let (true, Some(face)) = (faces, self.face(status.health)) {
+                        // square, as big as the bar allows
+                        let size = slot.width.min(slot.height);
+                        let x = slot.x + ((slot.width - size) / 2) as i32;
+                        renderer
+                            .draw_image(face.as_ref(), Some(Rect::new(x, slot.y, size, size)))?;
+                    }
+                    continue;
+                }
+            };
+            renderer.draw_text(&value, &value_style, slot.x, value_y)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// where a heading is on a strip `width` pixels wide, looking along `direction`, none when
+/// it is more than half of `span` away
+fn strip_x(direction: f32, heading: f32, span: f32, width: u32) -> Option<i32> {
+    // shortest way round, from -pi to pi
+    let diff = (heading - direction + PI).rem_euclid(TAU) - PI;
+    (diff.abs() <= span / 2.).then(|| (((diff / span) + 0.5) * width as f32).round() as i32)
+}
+
+/// compass strip centered at the top of a viewport, under `hud`, whatever is in its top left
+/// corner, if it would overlap it
+pub(crate) fn compass_area(viewport: Rect, hud: Rect) -> Rect {
+    let area = Rect::new(
+        viewport.x + ((viewport.width as i32 - COMPASS_WIDTH as i32) / 2),
+        hud.y,
+        COMPASS_WIDTH,
+        COMPASS_HEIGHT,
+    );
+    if area.x <= hud.right() + 8 {
+        Rect {
+            y: hud.bottom() + 8,
+            ..area
+        }
+    } else {
+        area
+    }
+}
+
+/// draw which way a camera looks, and the markers within its field of view
+pub(crate) fn draw_compass(
+    renderer: &mut dyn Renderer,
+    camera: &Camera,
+    markers: &[(Vec2, &str)],
+    area: Rect,
+    theme: &HudTheme,
+) -> anyhow::Result<()> {
+    let style = theme.body();
+    renderer.draw_rect(Some(area), theme.background)?;
+
+    // a tick every eighth of a turn, letters over the ones pointing north, east, south, west
+    for idx in 0..8 {
+        let heading = idx as f32 * FRAC_PI_4;
+        if let Some(x) = strip_x(camera.direction, heading, COMPASS_SPAN, area.width) {
+            let x = area.x + x;
+            renderer.draw_line(
+                (x, area.bottom() - 6),
+                (x, area.bottom() - 1),
+                Color::RGB(0, 0x80, 0),
+            )?;
+        }
+    }
+    for (letter, heading) in CARDINALS {
+        if let Some(x) = strip_x(camera.direction, heading, COMPASS_SPAN, area.width) {
+            // letters are about half as wide as they are high
+            renderer.draw_text(letter, &style, area.x + x - (style.size as i32 / 4), area.y)?;
+        }
+    }
+
+    let fov = camera.fov.to_radians();
+    for (pos, text) in markers {
+        let heading = (*pos - camera.pos).to_angle();
+        if strip_x(camera.direction, heading, fov, area.width).is_none() {
+            continue;
+        }
+        let Some(x) = strip_x(camera.direction, heading, COMPASS_SPAN, area.width) else {
+            continue;
+        };
+
+        let x = area.x + x;
+        renderer.draw_rect(
+            Some(Rect::new(x - 3, area.bottom() - 6, 6, 6)),
+            MARKER_COLOR,
+        )?;
+        renderer.draw_text(
+            text,
+            &theme
+                .style(12, MARKER_COLOR)
+                .boxed(theme.background, (4, 2)),
+            x - (text.chars().count() as i32 * 3),
+            area.bottom() + 2,
+        )?;
+    }
+
+    // the way the camera looks
+    let center = area.x + (area.width as i32 / 2);
+    renderer.draw_line((center, area.y), (center, area.bottom() - 1), Color::WHITE)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headings_wrap_around_the_strip() {
+        // looking east, north is to the left and south to the right
+        assert_eq!(strip_x(0., 0., PI, 200), Some(100));
+        assert_eq!(strip_x(0., -FRAC_PI_4, PI, 200), Some(50));
+        assert_eq!(strip_x(0., FRAC_PI_4, PI, 200), Some(150));
+        assert_eq!(strip_x(0., PI, PI, 200), None);
+
+        // turned a little west of north, west is at the left edge
+        let x = strip_x(TAU - FRAC_PI_2 - 0.1, PI, PI, 200).unwrap();
+        assert!(x < 10, "{x}");
+    }
+
+    #[test]
+    fn faces_follow_health() {
+        let bar = StatusBar::default();
+        assert!(bar.has_faces());
+        assert_eq!(bar.face(255), Some("images/face-healthy.png"));
+        assert_eq!(bar.face(100), Some("images/face-hurt.png"));
+        assert_eq!(bar.face(0), Some("images/face-dying.png"));
+
+        // slots fill the bar from left to right
+        for pair in bar.slots.windows(2) {
+            assert_eq!(pair[0].2, pair[1].1);
+        }
+    }
+
+    #[test]
+    fn polygons_fill_between_their_edges() {
+        let triangle = [Vec2::new(0., 0.), Vec2::new(10., 10.), Vec2::new(0., 10.)];
+        assert_eq!(spans(&triangle, 5.), [(0., 5.)]);
+        assert!(spans(&triangle, 11.).is_empty());
+
+        // rows through a notch are split in two
+        let notched = [
+            Vec2::new(0., 0.),
+            Vec2::new(10., 0.),
+            Vec2::new(10., 10.),
+            Vec2::new(5., 2.),
+            Vec2::new(0., 10.),
+        ];
+        assert_eq!(spans(&notched, 6.).len(), 2);
+        assert_eq!(spans(&notched, 1.), [(0., 10.)]);
+
+        assert_eq!(MinimapView::Cone.next().next().next(), MinimapView::Cone);
+    }
+
+    #[test]
+    fn compass_moves_under_the_hud() {
+        let viewport = Rect::new(0, 0, 640, 480);
+        let area = compass_area(viewport, Rect::new(16, 16, 100, 24));
+        assert_eq!((area.x, area.y), (220, 16));
+
+        let viewport = Rect::new(320, 0, 320, 480);
+        let area = compass_area(viewport, Rect::new(336, 16, 200, 24));
+        assert_eq!((area.x, area.y), (380, 48));
+    }
+}
@@ -0,0 +1,179 @@
+//! text shown to players, looked up by key in a table per language
+//!
+//! tables are embedded from `lang/<code>.toml`, one `key = "text"` per line. `{name}` in a
+//! text is filled in by [`Lang::fill`], and keys a table lacks fall back to english
+
+use anyhow::Context;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// code and table of every language, english first since it is the fallback
+const TABLES: [(&str, &str); 2] = [
+    ("en", include_str!("../lang/en.toml")),
+    ("de", include_str!("../lang/de.toml")),
+];
+
+/// one table of texts
+pub(crate) struct Language {
+    pub code: &'static str,
+    /// what the language calls itself, from its `language` key
+    pub name: String,
+    texts: HashMap<String, String>,
+}
+
+impl Language {
+    fn parse(code: &'static str, table: &str) -> anyhow::Result<Self> {
+        let mut texts = HashMap::new();
+        for (idx, line) in table.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, text) = parse_line(line)
+                .with_context(|| format!("lang/{code}.toml line {}: {line}", idx + 1))?;
+            texts.insert(key.to_string(), text);
+        }
+
+        Ok(Self {
+            code,
+            name: texts
+                .get("language")
+                .cloned()
+                .with_context(|| format!("lang/{code}.toml has no language name"))?,
+            texts,
+        })
+    }
+
+    /// every text, to check the font can draw them
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.texts.values().map(String::as_str)
+    }
+}
+
+/// `key = "text"`, with `\"`, `\\` and `\n` escapes in the text
+fn parse_line(line: &str) -> anyhow::Result<(&str, String)> {
+    let (key, value) = line.split_once('=').context("expected key = \"text\"")?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .context("text must be quoted")?;
+
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some(c @ ('"' | '\\')) => text.push(c),
+                other => anyhow::bail!("invalid escape: \\{}", other.unwrap_or(' ')),
+            },
+            '"' => anyhow::bail!("unescaped quote"),
+            c => text.push(c),
+        }
+    }
+
+    Ok((key.trim(), text))
+}
+
+/// every language and the one being shown
+pub(crate) struct Lang {
+    languages: Vec<Language>,
+    current: usize,
+}
+
+impl Lang {
+    /// read the embedded tables, showing english
+    pub fn load() -> anyhow::Result<Self> {
+        Ok(Self {
+            languages: TABLES
+                .into_iter()
+                .map(|(code, table)| Language::parse(code, table))
+                .collect::<anyhow::Result<_>>()?,
+            current: 0,
+        })
+    }
+
+    pub fn languages(&self) -> &[Language] {
+        &self.languages
+    }
+
+    pub fn current(&self) -> &Language {
+        &self.languages[self.current]
+    }
+
+    /// show the next language, for menus that cycle through them
+    pub fn next(&mut self) {
+        self.current = (self.current + 1) % self.languages.len();
+    }
+
+    /// show a language by its code, returns whether there is one
+    pub fn select(&mut self, code: &str) -> bool {
+        match self.languages.iter().position(|lang| lang.code == code) {
+            Some(idx) => {
+                self.current = idx;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// text of a key, in english if the current language lacks it, or the key itself
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        [self.current(), &self.languages[0]]
+            .into_iter()
+            .find_map(|lang| lang.texts.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    /// text of a key with each `{name}` replaced by its value
+    pub fn fill(&self, key: &str, values: &[(&str, &dyn Display)]) -> String {
+        values
+            .iter()
+            .fold(self.get(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_fall_back_to_english() {
+        let mut lang = Lang::load().unwrap();
+        assert_eq!(lang.current().name, "English");
+        assert_eq!(lang.get("menu.play"), "Play");
+        assert_eq!(lang.get("no.such.key"), "no.such.key");
+        assert_eq!(
+            lang.fill("stats.secrets", &[("found", &1), ("total", &3)]),
+            "Secrets: 1 / 3"
+        );
+
+        assert!(lang.select("de"));
+        assert_eq!(lang.get("menu.play"), "Spielen");
+        assert!(!lang.select("xx"));
+        lang.next();
+        assert_eq!(lang.current().code, "en");
+
+        // other tables only translate what english has
+        let english = &lang.languages()[0];
+        for language in &lang.languages()[1..] {
+            for key in language.texts.keys() {
+                assert!(english.texts.contains_key(key), "{}: {key}", language.code);
+            }
+        }
+    }
+
+    #[test]
+    fn lines_are_quoted_text() {
+        assert_eq!(
+            parse_line(r#"a.b = "say \"hi\"\n""#).unwrap(),
+            ("a.b", "say \"hi\"\n".to_string())
+        );
+        assert!(parse_line("a = hi").is_err());
+        assert!(parse_line(r#"a = "x"y""#).is_err());
+        assert!(parse_line("just text").is_err());
+    }
+}
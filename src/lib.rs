@@ -0,0 +1,34 @@
+//! Raycast renderer for `.yaw` maps, usable outside of the game.
+//!
+//! Load a [`Map`], point a [`Camera`] at it and draw frames with any [`Renderer`].
+//! [`PixelRenderer`] draws into a plain `0xAARRGGBB` pixel buffer:
+//!
+//! ```
+//! use yaw::{Camera, Map, PixelRenderer, Rect};
+//!
+//! let map = Map::load("map/map.yaw".into())?;
+//! let camera = Camera::new(map.get_spawn().unwrap(), 0.);
+//! let mut renderer = PixelRenderer::new(640, 480);
+//! camera.render(&map, &mut renderer, Rect::new(0, 0, 640, 480))?;
+//!
+//! let pixels: &[u32] = &renderer.framebuffer.pixels;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Tools like editors and generators can put maps together with [`MapBuilder`] and save them
+//! with [`Map::to_yaw`], or [`Map::compile`] for the binary format.
+//!
+//! Implement [`Renderer`] to draw with something else, like a gpu or a terminal.
+
+pub use yaw_core::assets;
+pub use yaw_core::camera::{Camera, Sprite, ZBuffer};
+pub use yaw_core::color::Color;
+pub use yaw_core::entity::{Ai, Collider, Entity, Route, StateBag, World};
+pub use yaw_core::framebuffer::{Framebuffer, Image, PixelRenderer};
+pub use yaw_core::map::{
+    Corner, CustomTile, Map, MapBuilder, Meta, Shape, Tile, Transform, TILE_SIZE,
+};
+pub use yaw_core::particle::{Effect, Particle, Particles};
+pub use yaw_core::ray::{cast_ray, march, Cardinal, Hit, RayCast};
+pub use yaw_core::render::{Column, Rect, Renderer, TextStyle};
+pub use yaw_core::trigger::{Action, Trigger, Triggers};
@@ -0,0 +1,173 @@
+//! maps are read on a worker thread while a progress bar is drawn, their textures are then
+//! uploaded one per frame since only the main thread may use the renderer
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use yaw_core::map::{Map, Transform};
+use yaw_core::render::Renderer;
+
+/// what the worker reports while it reads a map
+pub(crate) enum LoadProgress {
+    /// `done` out of `total` steps are finished, the next one is `stage`, a language key
+    Step {
+        stage: &'static str,
+        done: usize,
+        total: usize,
+    },
+    /// the map is ready for its textures, or could not be read
+    Parsed(anyhow::Result<Box<Map>>),
+}
+
+/// read a map and apply transforms and randomization, reporting each step
+pub(crate) fn parse(
+    path: &Path,
+    transforms: &[Transform],
+    seed: Option<u64>,
+    progress: &Sender<LoadProgress>,
+) -> anyhow::Result<Map> {
+    let total = 1 + transforms.len() + seed.is_some() as usize;
+    let step = |stage, done| {
+        // nobody might be listening, e.g. when loading without a loading screen
+        let _ = progress.send(LoadProgress::Step { stage, done, total });
+    };
+
+    step("loading.reading", 0);
+    let mut map = Map::load(path.into())?;
+    for (idx, transform) in transforms.iter().enumerate() {
+        step("loading.transforming", idx + 1);
+        map.transform(*transform);
+    }
+    if let Some(seed) = seed {
+        step("loading.randomizing", total - 1);
+        map.randomize(seed);
+    }
+
+    Ok(map)
+}
+
+/// loads a level a bit at a time, see [`Loader::poll`]
+pub(crate) struct Loader {
+    pub path: PathBuf,
+    progress: Receiver<LoadProgress>,
+    /// latest step the worker reported
+    stage: &'static str,
+    done: usize,
+    total: usize,
+    map: Option<Map>,
+    /// textures of the map still to upload, and how many there were
+    textures: Vec<PathBuf>,
+    texture_count: usize,
+}
+
+impl Loader {
+    pub fn start(path: PathBuf, transforms: Vec<Transform>, seed: Option<u64>) -> Self {
+        let (sender, progress) = channel();
+        let worker_path = path.clone();
+        let work = move || {
+            let map = parse(&worker_path, &transforms, seed, &sender);
+            let _ = sender.send(LoadProgress::Parsed(map.map(Box::new)));
+        };
+
+        // the web build has no threads, the map is read right away there
+        #[cfg(not(target_os = "emscripten"))]
+        std::thread::spawn(work);
+        #[cfg(target_os = "emscripten")]
+        work();
+
+        Self {
+            path,
+            progress,
+            stage: "loading.reading",
+            done: 0,
+            total: 1,
+            map: None,
+            textures: Vec::new(),
+            texture_count: 0,
+        }
+    }
+
+    /// what is being done and how far along loading is, from 0 to 1
+    pub fn progress(&self) -> (&'static str, f32) {
+        let parsed = self.done as f32 / self.total as f32;
+        let uploaded =
+            (self.texture_count - self.textures.len()) as f32 / self.texture_count.max(1) as f32;
+        (self.stage, (parsed + uploaded) / 2.)
+    }
+
+    /// the map once the worker has read it, while its textures are uploaded
+    pub fn map(&self) -> Option<&Map> {
+        self.map.as_ref()
+    }
+
+    /// pick up progress and upload a texture, returns the map once everything is loaded
+    pub fn poll(&mut self, renderer: &mut dyn Renderer) -> Option<anyhow::Result<Map>> {
+        if self.map.is_none() {
+            loop {
+                match self.progress.try_recv() {
+                    Ok(LoadProgress::Step { stage, done, total }) => {
+                        (self.stage, self.done, self.total) = (stage, done, total);
+                    }
+                    Ok(LoadProgress::Parsed(Ok(map))) => {
+                        (self.stage, self.done) = ("loading.textures", self.total);
+                        self.textures = map.textures().into_iter().collect();
+                        self.texture_count = self.textures.len();
+                        self.map = Some(*map);
+                        // show the finished progress before uploading anything
+                        return None;
+                    }
+                    Ok(LoadProgress::Parsed(Err(err))) => return Some(Err(err)),
+                    Err(TryRecvError::Empty) => return None,
+                    Err(TryRecvError::Disconnected) => {
+                        return Some(Err(anyhow::anyhow!("map loader stopped")));
+                    }
+                }
+            }
+        }
+
+        if let Some(texture) = self.textures.pop() {
+            if let Err(err) = renderer.preload(&texture) {
+                return Some(Err(err));
+            }
+            return None;
+        }
+
+        self.map.take().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaw_core::framebuffer::PixelRenderer;
+
+    fn finish(loader: &mut Loader) -> anyhow::Result<Map> {
+        let mut renderer = PixelRenderer::new(1, 1);
+        let mut last = 0.;
+        loop {
+            let (_, progress) = loader.progress();
+            assert!(
+                progress >= last,
+                "progress went back from {last} to {progress}"
+            );
+            last = progress;
+
+            if let Some(map) = loader.poll(&mut renderer) {
+                return map;
+            }
+        }
+    }
+
+    #[test]
+    fn loads_maps_and_their_textures() {
+        let mut loader = Loader::start("map/map.yaw".into(), vec![Transform::FlipH], Some(1));
+        let map = finish(&mut loader).unwrap();
+        assert!(map.get_spawn().is_some());
+        assert_eq!(loader.progress().1, 1.);
+    }
+
+    #[test]
+    fn reports_missing_maps() {
+        let mut loader = Loader::start("map/nowhere.yaw".into(), Vec::new(), None);
+        assert!(finish(&mut loader).is_err());
+    }
+}
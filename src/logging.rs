@@ -0,0 +1,166 @@
+//! log messages on the terminal, filtered by `YAW_LOG`, and optionally as json lines in a file
+//!
+//! every line of the file is an object with the time in seconds since the epoch, the level,
+//! target and message, and the key-value fields of the record, like the frame stats logged at
+//! trace level
+
+use anyhow::Context;
+use log::kv::{Key, Value, VisitSource};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// environment variable with the filters, like `info` or `yaw=trace`
+const ENV: &str = "YAW_LOG";
+
+struct Logger {
+    terminal: Box<dyn Log>,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.terminal.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.terminal.log(record);
+
+        if let Some(file) = self.file.as_ref() {
+            let line = json_line(record, SystemTime::now());
+            let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+            // there is nowhere left to report failing to log
+            let _ = file.write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        self.terminal.flush();
+    }
+}
+
+/// log to the terminal, and to `file` too if there is one
+///
+/// without `YAW_LOG` only errors are shown, or everything from info up when logging to a file
+pub(crate) fn init(file: Option<&Path>) -> anyhow::Result<()> {
+    let mut builder = pretty_env_logger::formatted_builder();
+    match std::env::var(ENV) {
+        Ok(filters) => {
+            builder.parse_filters(&filters);
+        }
+        Err(_) if file.is_some() => {
+            builder.filter_level(LevelFilter::Info);
+        }
+        Err(_) => {
+            builder.filter_level(LevelFilter::Error);
+        }
+    }
+    let terminal = builder.build();
+    let max_level = terminal.filter();
+
+    let file = file
+        .map(|path| {
+            File::create(path).with_context(|| format!("could not create {}", path.display()))
+        })
+        .transpose()?
+        .map(Mutex::new);
+    log::set_boxed_logger(Box::new(Logger {
+        terminal: Box::new(terminal),
+        file,
+    }))?;
+    log::set_max_level(max_level);
+
+    Ok(())
+}
+
+/// a record as one line of json
+fn json_line(record: &Record, time: SystemTime) -> String {
+    let time = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut line = format!(
+        "{{\"time\":{time:.3},\"level\":\"{}\",\"target\":{},\"message\":{}",
+        record.level(),
+        json_string(record.target()),
+        json_string(&record.args().to_string())
+    );
+    // fields that fail to visit are left out
+    let _ = record.key_values().visit(&mut Fields(&mut line));
+    line.push_str("}\n");
+    line
+}
+
+/// appends key-value fields to a json object
+struct Fields<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        // numbers and bools stay what they are, anything else is written as a string
+        let value = if let Some(value) = value.to_u64() {
+            value.to_string()
+        } else if let Some(value) = value.to_i64() {
+            value.to_string()
+        } else if let Some(value) = value.to_f64().filter(|value| value.is_finite()) {
+            value.to_string()
+        } else if let Some(value) = value.to_bool() {
+            value.to_string()
+        } else {
+            json_string(&value.to_string())
+        };
+        self.0
+            .push_str(&format!(",{}:{value}", json_string(key.as_str())));
+        Ok(())
+    }
+}
+
+/// quoted and escaped
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            ch if ch.is_control() => json.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => json.push(ch),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+    use std::time::Duration;
+
+    #[test]
+    fn records_are_json_lines() {
+        let fields = [("frames", Value::from(30)), ("average", Value::from(1.5))];
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("said \"hi\"\n"))
+                .level(Level::Trace)
+                .target("yaw::frames")
+                .key_values(&fields)
+                .build(),
+            UNIX_EPOCH + Duration::from_millis(1_500),
+        );
+        assert_eq!(
+            line,
+            "{\"time\":1.500,\"level\":\"TRACE\",\"target\":\"yaw::frames\",\
+             \"message\":\"said \\\"hi\\\"\\n\",\"frames\":30,\"average\":1.5}\n"
+        );
+
+        assert_eq!(json_string("a\u{1}\\"), "\"a\\u0001\\\\\"");
+    }
+}
@@ -1,18 +1,58 @@
+use anyhow::Context;
+use audio::Audio;
+use cli::{Args, Backend};
 use game::{Game, GameState};
+use pacing::{FrameRate, Pacer};
+use render::{Fonts, SdlRenderer, SoftwareRenderer};
 use sdl2::event::Event;
 use std::collections::HashSet;
 
+#[cfg(not(target_os = "emscripten"))]
+mod bench;
 #[cfg(target_os = "emscripten")]
 mod emscripten;
-
-#[cfg(not(target_os = "emscripten"))]
-use std::time::{Duration, Instant};
+use std::time::Instant;
+mod achievements;
+mod audio;
+mod automap;
+mod bindings;
+mod campaign;
+mod cli;
+mod config;
+mod console;
+mod demo;
+mod dialogue;
+mod difficulty;
+mod entity_state;
 mod game;
-mod map;
-mod ray;
+mod harness;
+mod hud;
+mod lang;
+mod loading;
+mod logging;
+mod menu;
+mod modifiers;
+#[cfg(not(target_os = "emscripten"))]
+mod net;
+mod pacing;
+mod postfx;
+mod profile;
+mod progress;
+mod recording;
+mod render;
+mod scale;
+mod screenshot;
+mod script;
+mod scroll;
+mod stats;
+mod temp;
+mod textfield;
+mod theme;
+#[cfg(not(target_os = "emscripten"))]
+mod tty;
 
 // global font
-const FIXEDER_SYS: &'static [u8] = include_bytes!("tom7.ttf");
+const FIXEDER_SYS: &[u8] = include_bytes!("tom7.ttf");
 
 // helper trait to convert strings into std::error types
 trait StringToAnyhow<T> {
@@ -25,6 +65,9 @@ impl<T> StringToAnyhow<T> for Result<T, String> {
     }
 }
 
+// title of the window, the map and frame rate are added while playing
+const TITLE: &str = "YAW";
+
 // dimensions of screen
 const WIDTH: usize = 640;
 const HEIGHT: usize = 480;
@@ -32,7 +75,68 @@ const HEIGHT: usize = 480;
 const TARGET_FPS: u64 = 30;
 
 fn main() -> anyhow::Result<()> {
-    pretty_env_logger::init_custom_env("YAW_LOG");
+    let args = Args::parse()?;
+    logging::init(args.log_file.as_deref())?;
+
+    if let Some(compile) = args.compile.as_ref() {
+        let bytes = if compile.pack {
+            yaw_core::map::Map::pack(&compile.from)?
+        } else {
+            yaw_core::map::Map::load(compile.from.clone())?.compile(compile.embed)?
+        };
+        std::fs::write(&compile.to, bytes)?;
+        println!(
+            "wrote {} to {}",
+            compile.from.display(),
+            compile.to.display()
+        );
+        return Ok(());
+    }
+
+    if let Some(path) = args.lint.as_ref() {
+        let map = yaw_core::map::Map::load(path.clone())
+            .with_context(|| format!("{} could not be read", path.display()))?;
+        let report = yaw_core::lint::check(&map);
+        for warning in &report.warnings {
+            println!("{}: warning: {warning}", path.display());
+        }
+        for error in &report.errors {
+            println!("{}: error: {error}", path.display());
+        }
+        anyhow::ensure!(
+            report.errors.is_empty(),
+            "{} has {} errors",
+            path.display(),
+            report.errors.len()
+        );
+        if report.warnings.is_empty() {
+            println!("{}: nothing found", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(thumbnail) = args.thumbnail.as_ref() {
+        let map = yaw_core::map::Map::load(thumbnail.map.clone())?;
+        let image = yaw_core::thumbnail::render(&map, yaw_core::thumbnail::TILE_PIXELS);
+        std::fs::write(&thumbnail.to, image.to_png()?)?;
+        println!(
+            "wrote {} to {}",
+            thumbnail.map.display(),
+            thumbnail.to.display()
+        );
+        return Ok(());
+    }
+
+    if let Some(script) = args.headless.as_ref() {
+        return harness::run_script(&args, script);
+    }
+
+    // the terminal needs no sdl window at all
+    #[cfg(not(target_os = "emscripten"))]
+    if args.backend == Backend::Tty {
+        return tty::run(&args);
+    }
+
     // sdl boilerplate
     log::info!("initializing sdl2");
     let sdl_ctx = sdl2::init().ah()?;
@@ -41,7 +145,7 @@ fn main() -> anyhow::Result<()> {
 
     log::info!("initializing window");
     let mut window = video
-        .window("YAW", WIDTH as u32, HEIGHT as u32)
+        .window(TITLE, WIDTH as u32, HEIGHT as u32)
         .position_centered()
         .opengl()
         .build()?;
@@ -62,29 +166,55 @@ fn main() -> anyhow::Result<()> {
     window.set_maximum_size(WIDTH as u32, HEIGHT as u32)?;
     window.set_minimum_size(WIDTH as u32, HEIGHT as u32)?;
     log::info!("creating canvas");
-    let canvas = window.into_canvas().build()?;
+    let mut canvas = window.into_canvas();
+    if args.frame_rate == FrameRate::Vsync {
+        canvas = canvas.present_vsync();
+    }
+    let canvas = canvas.build()?;
     log::info!("pumping events");
     let mut events = sdl_ctx.event_pump().ah()?;
 
     // load font context
     log::info!("initializing font context");
-    let font_ctx = sdl2::ttf::init()?;
+    // the one of the command line, else the one of the settings
+    let font = args.font.clone().or_else(|| {
+        let path = yaw_core::assets::save_path(game::CONFIG.as_ref());
+        config::Config::load(path).ok()?.font
+    });
+    let fonts = Fonts::new(sdl2::ttf::init()?, font.as_deref());
+    fonts.check_glyphs()?;
+
+    // audio is optional, the game is still playable without it
+    log::info!("initializing audio");
+    let audio = match sdl_ctx.audio().ah().and_then(|audio| Audio::new(&audio)) {
+        Ok(audio) => Some(audio),
+        Err(err) => {
+            log::warn!("could not initialize audio: {err}");
+            None
+        }
+    };
 
     let mut keys = HashSet::new();
+    let text_input = video.text_input();
+    let mouse = sdl_ctx.mouse();
 
     // initialize game
     log::info!("initializing game state");
-    let mut game = Game::new(canvas, font_ctx)?;
-
-    let delta = 1_000 / TARGET_FPS;
+    let renderer: Box<dyn yaw_core::render::Renderer> = match args.backend {
+        Backend::Sdl => Box::new(SdlRenderer::new(canvas, fonts)),
+        Backend::Software => Box::new(SoftwareRenderer::new(canvas, fonts)?),
+        Backend::Tty => anyhow::bail!("the tty backend is not available here"),
+    };
+    let mut game = Game::new(renderer, audio, &args, None)?;
 
-    'main_loop: loop {
-        #[cfg(not(target_os = "emscripten"))]
-        let prev = Instant::now();
+    #[cfg(not(target_os = "emscripten"))]
+    if let Some(frames) = args.bench {
+        return bench::run(&mut game, frames);
+    }
 
-        #[cfg(target_os = "emscripten")]
-        let prev = unsafe { emscripten::emscripten_get_now() };
+    let mut pacer = Pacer::new(args.frame_rate);
 
+    'main_loop: loop {
         // handle events
         for ev in events.poll_iter() {
             match ev {
@@ -97,83 +227,58 @@ fn main() -> anyhow::Result<()> {
                     keys.insert(k);
 
                     if !repeat {
-                        match game.game_state {
-                            GameState::Menu => game.menu_key_once(k),
-                            GameState::Playing | GameState::Minimap => game.playing_key_once(k),
-                            GameState::Paused => game.game_state = GameState::Playing,
-                            GameState::Exit => break 'main_loop,
-                        }
-
-                        game.update = true;
+                        game.key_pressed(k);
                     }
                 }
                 Event::KeyUp {
                     keycode: Some(k), ..
                 } => {
                     keys.remove(&k);
+                    game.key_up(k);
                 }
+                Event::TextInput { text, .. } => game.text_input(&text),
+                Event::MouseMotion { xrel, .. } => game.mouse_moved(xrel),
                 _ => {}
             }
         }
 
-        for k in keys.iter() {
-            match game.game_state {
-                GameState::Menu => {
-                    game.menu_key(*k);
-                    game.update = true;
-                }
-                GameState::Playing | GameState::Minimap => {
-                    game.playing_key(*k);
-                    game.update = true;
-                }
-                GameState::Paused => {}
-                GameState::Exit => break 'main_loop,
+        // text input brings up on-screen keyboards on some platforms, so it is only on while
+        // something takes text
+        if game.wants_text() != text_input.is_active() {
+            if game.wants_text() {
+                text_input.start();
+            } else {
+                text_input.stop();
             }
         }
 
+        // the mouse is held in the window while it turns the view
+        if game.wants_mouse() != mouse.relative_mouse_mode() {
+            mouse.set_relative_mouse_mode(game.wants_mouse());
+        }
+
+        // the game advances at the same rate however often frames are drawn
+        for _ in 0..pacer.ticks(Instant::now()) {
+            game.keys_held(&keys);
+            game.tick();
+        }
+        if game.game_state == GameState::Exit {
+            break;
+        }
+
         // draw game
-        if game.update {
-            if let Err(err) = match game.game_state {
-                GameState::Menu => game.menu_draw(),
-                GameState::Playing | GameState::Minimap => game.playing_draw(),
-                GameState::Paused => game.pause_draw(),
-                GameState::Exit => break,
-            } {
+        let drew = game.update || pacer.redraws();
+        if drew {
+            if let Err(err) = game.draw() {
                 log::error!("error while in game state {:?}: {err}", game.game_state);
                 Err(err)?;
             }
-            game.canvas.present();
-
-            game.update = false;
-
-            #[cfg(not(target_os = "emscripten"))]
-            {
-                let after = Instant::now();
-                let diff = after - prev;
-
-                if diff < Duration::from_millis(delta) {
-                    std::thread::sleep(Duration::from_millis(delta) - diff);
-                }
-            }
-
-            #[cfg(target_os = "emscripten")]
-            {
-                let after = unsafe { emscripten::emscripten_get_now() };
-                let diff = after - prev;
+            game.renderer.present()?;
+            game.update_title();
 
-                unsafe {
-                    emscripten::emscripten_sleep(
-                        (delta as std::os::raw::c_double - diff).clamp(0., f64::INFINITY)
-                            as std::os::raw::c_uint,
-                    );
-                }
-            }
-        } else {
-            #[cfg(target_os = "emscripten")]
-            unsafe {
-                emscripten::emscripten_sleep(0);
-            }
+            game.update = game.animating();
         }
+        pacer.wait(drew);
     }
 
     Ok(())
@@ -0,0 +1,125 @@
+use crate::theme::HudTheme;
+use crate::{HEIGHT, WIDTH};
+use sdl2::keyboard::Keycode;
+use yaw_core::render::{Renderer, TextStyle};
+
+/// list of options, picked with up/down and enter
+pub(crate) struct Menu<T> {
+    pub title: String,
+    pub options: Vec<(String, T)>,
+    pub selected: usize,
+}
+
+impl<T: Clone> Menu<T> {
+    pub fn new(title: impl Into<String>, options: Vec<(String, T)>) -> Self {
+        Self {
+            title: title.into(),
+            options,
+            selected: 0,
+        }
+    }
+
+    /// move the selection, returns the option enter picked
+    pub fn key(&mut self, key: Keycode) -> Option<T> {
+        match key {
+            Keycode::Up => self.selected = self.selected.saturating_sub(1),
+            Keycode::Down => {
+                self.selected = (self.selected + 1).min(self.options.len().saturating_sub(1))
+            }
+            Keycode::Return => return self.options.get(self.selected).map(|(_, o)| o.clone()),
+            _ => {}
+        }
+
+        None
+    }
+
+    /// keep the selection of `other` if it has the same options, which may be labeled
+    /// differently, or else start over at the first one
+    pub fn keep_selected(&mut self, other: &Menu<T>)
+    where
+        T: PartialEq,
+    {
+        let same = self.options.len() == other.options.len()
+            && self
+                .options
+                .iter()
+                .zip(&other.options)
+                .all(|((_, a), (_, b))| a == b);
+        self.selected = if same { other.selected } else { 0 };
+    }
+
+    /// draw the title and options, highlighting the selected one, long ones wrap and push the
+    /// rest down. options that do not fit scroll up as the selection moves past them
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &HudTheme) -> anyhow::Result<()> {
+        let wrap = theme.wrap_width(WIDTH as u32);
+        let title = renderer.draw_text(
+            &self.title,
+            &theme.title().wrapped(wrap),
+            theme.margin,
+            theme.margin,
+        )?;
+
+        let mut y = theme.top.max(title.bottom() + (theme.line_height / 2));
+        let fit = ((HEIGHT as i32 - theme.margin - y) / theme.line_height).max(1) as usize;
+        let first = (self.selected + 1).saturating_sub(fit);
+        for (idx, (label, _)) in self.options.iter().enumerate().skip(first) {
+            let selected = idx == self.selected;
+            let style = if selected {
+                theme
+                    .style(theme.text_size, theme.selected)
+                    .boxed(theme.selected_background, theme.padding)
+            } else {
+                TextStyle {
+                    padding: theme.padding,
+                    ..theme.body()
+                }
+            };
+            let rect = renderer.draw_text(
+                &format!("{} {label}", if selected { ">" } else { " " }),
+                &style.wrapped(wrap),
+                theme.margin,
+                y,
+            )?;
+            y += theme.line_height.max(rect.height as i32);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_stays_in_bounds() {
+        let mut menu = Menu::new("", vec![("a".into(), 'a'), ("b".into(), 'b')]);
+        assert_eq!(menu.key(Keycode::Up), None);
+        assert_eq!(menu.key(Keycode::Return), Some('a'));
+        menu.key(Keycode::Down);
+        menu.key(Keycode::Down);
+        assert_eq!(menu.key(Keycode::Return), Some('b'));
+
+        let mut empty = Menu::<char>::new("", Vec::new());
+        empty.key(Keycode::Down);
+        assert_eq!(empty.key(Keycode::Return), None);
+    }
+
+    #[test]
+    fn selections_are_kept_while_the_options_stay() {
+        let mut old = Menu::new("", vec![("a".into(), 'a'), ("b".into(), 'b')]);
+        old.key(Keycode::Down);
+
+        let mut relabeled = Menu::new("", vec![("A".into(), 'a'), ("B".into(), 'b')]);
+        relabeled.keep_selected(&old);
+        assert_eq!(relabeled.key(Keycode::Return), Some('b'));
+
+        // an option showing up in front would otherwise move the selection to another one
+        let mut menu = Menu::new(
+            "",
+            vec![("c".into(), 'c'), ("a".into(), 'a'), ("b".into(), 'b')],
+        );
+        menu.keep_selected(&old);
+        assert_eq!(menu.key(Keycode::Return), Some('c'));
+    }
+}
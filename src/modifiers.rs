@@ -0,0 +1,83 @@
+//! gameplay changes unlocked by finishing a campaign, kept in the stats of each level and
+//! brought along by demos
+//!
+//! there is no pistol-only or low gravity modifier yet, as there are no weapons to pick between
+//! and players cannot jump
+
+use std::str::FromStr;
+use yaw_core::map::Transform;
+
+/// how much faster enemies chase players with fast enemies on
+const FAST_ENEMY_SPEED: f32 = 1.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub(crate) struct Modifiers {
+    pub fast_enemies: bool,
+    /// maps are flipped left to right
+    pub mirrored: bool,
+}
+
+impl Modifiers {
+    /// what the speed of enemies is multiplied by
+    pub fn enemy_speed(self) -> f32 {
+        if self.fast_enemies {
+            FAST_ENEMY_SPEED
+        } else {
+            1.
+        }
+    }
+
+    /// applied to maps after the ones from the command line
+    pub fn transform(self) -> Option<Transform> {
+        self.mirrored.then_some(Transform::FlipH)
+    }
+
+    /// names of the ones that are on, as demos list them
+    pub fn names(self) -> Vec<&'static str> {
+        [
+            (self.fast_enemies, "fast_enemies"),
+            (self.mirrored, "mirrored"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect()
+    }
+}
+
+/// names joined by commas, or `none`
+impl FromStr for Modifiers {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut modifiers = Self::default();
+        for name in s.split(',').filter(|name| *name != "none") {
+            match name {
+                "fast_enemies" => modifiers.fast_enemies = true,
+                "mirrored" => modifiers.mirrored = true,
+                other => anyhow::bail!("unrecognized modifier: {other}"),
+            }
+        }
+        Ok(modifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_read_back() {
+        let modifiers: Modifiers = "fast_enemies,mirrored".parse().unwrap();
+        assert_eq!(modifiers.enemy_speed(), FAST_ENEMY_SPEED);
+        assert_eq!(modifiers.transform(), Some(Transform::FlipH));
+        assert_eq!(
+            modifiers.names().join(",").parse::<Modifiers>().unwrap(),
+            modifiers
+        );
+
+        let none: Modifiers = "none".parse().unwrap();
+        assert_eq!(none, Modifiers::default());
+        assert_eq!((none.enemy_speed(), none.transform()), (1., None));
+        assert!("pistol_only".parse::<Modifiers>().is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! co-op over udp, both sides send their position every frame and see each other as billboards
+//!
+//! packets are single lines of text: `state <x> <y> <direction> <health>`, `message <text>` and `bye`
+
+use glam::Vec2;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+pub(crate) const DEFAULT_PORT: u16 = 7777;
+
+/// peers that were not heard from for this long are gone
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// which side of the connection this instance is
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Role {
+    /// wait for someone to connect on this port
+    Host(u16),
+    /// connect to a host at this address
+    Connect(String),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Packet {
+    /// where the sender is right now
+    State {
+        pos: Vec2,
+        direction: f32,
+        health: u8,
+    },
+    /// text shown to the other side
+    Message(String),
+    /// the sender is leaving
+    Bye,
+}
+
+impl Packet {
+    fn encode(&self) -> String {
+        match self {
+            Self::State {
+                pos,
+                direction,
+                health,
+            } => format!("state {} {} {direction} {health}", pos.x, pos.y),
+            Self::Message(text) => format!("message {text}"),
+            Self::Bye => "bye".into(),
+        }
+    }
+
+    fn decode(packet: &str) -> anyhow::Result<Self> {
+        let (kind, rest) = packet.split_once(' ').unwrap_or((packet, ""));
+        match kind {
+            "state" => {
+                let fields = rest.split(' ').collect::<Vec<_>>();
+                let [x, y, direction, health] = fields[..] else {
+                    anyhow::bail!("expected 4 fields, got {}", fields.len());
+                };
+
+                Ok(Self::State {
+                    pos: Vec2::new(x.parse()?, y.parse()?),
+                    direction: direction.parse()?,
+                    health: health.parse()?,
+                })
+            }
+            "message" => Ok(Self::Message(rest.into())),
+            "bye" => Ok(Self::Bye),
+            _ => anyhow::bail!("unknown packet: {kind}"),
+        }
+    }
+}
+
+/// the other player, as last heard from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Peer {
+    pub pos: Vec2,
+    pub direction: f32,
+    pub health: u8,
+    last_seen: Instant,
+}
+
+/// something that happened on the other side
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Event {
+    Joined,
+    Left,
+    Moved,
+    Message(String),
+}
+
+pub(crate) struct Net {
+    socket: UdpSocket,
+    /// where packets go, hosts learn it from the first packet they receive
+    addr: Option<SocketAddr>,
+    /// hosts forget that address once the peer leaves, so someone else can join
+    host: bool,
+    pub peer: Option<Peer>,
+}
+
+impl Net {
+    pub fn new(role: &Role) -> anyhow::Result<Self> {
+        let (socket, addr) = match role {
+            Role::Host(port) => {
+                log::info!("hosting on port {port}");
+                (UdpSocket::bind(("0.0.0.0", *port))?, None)
+            }
+            Role::Connect(addr) => {
+                log::info!("connecting to {addr}");
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(addr)?;
+                let addr = socket.peer_addr()?;
+                (socket, Some(addr))
+            }
+        };
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            addr,
+            host: matches!(role, Role::Host(_)),
+            peer: None,
+        })
+    }
+
+    /// the peer left or timed out
+    fn forget_peer(&mut self, events: &mut Vec<Event>) {
+        self.peer = None;
+        if self.host {
+            self.addr = None;
+        }
+        events.push(Event::Left);
+    }
+
+    /// send a packet to the other side, if there is one yet
+    pub fn send(&self, packet: &Packet) -> anyhow::Result<()> {
+        if let Some(addr) = self.addr {
+            self.socket.send_to(packet.encode().as_bytes(), addr)?;
+        }
+
+        Ok(())
+    }
+
+    /// read every packet that arrived since the last call
+    pub fn poll(&mut self) -> anyhow::Result<Vec<Event>> {
+        let mut events = Vec::new();
+        let mut buf = [0; 1024];
+
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                // nobody listening on the other side yet
+                Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => break,
+                Err(err) => return Err(err.into()),
+            };
+            if self.addr.is_some_and(|addr| addr != from) {
+                log::warn!("ignoring packet from {from}");
+                continue;
+            }
+
+            let packet = match std::str::from_utf8(&buf[..len])
+                .map_err(anyhow::Error::from)
+                .and_then(Packet::decode)
+            {
+                Ok(packet) => packet,
+                Err(err) => {
+                    log::warn!("bad packet from {from}: {err}");
+                    continue;
+                }
+            };
+            self.addr = Some(from);
+
+            match packet {
+                Packet::State {
+                    pos,
+                    direction,
+                    health,
+                } => {
+                    if self.peer.is_none() {
+                        events.push(Event::Joined);
+                    }
+                    self.peer = Some(Peer {
+                        pos,
+                        direction,
+                        health,
+                        last_seen: Instant::now(),
+                    });
+                    events.push(Event::Moved);
+                }
+                Packet::Message(text) => events.push(Event::Message(text)),
+                Packet::Bye if self.peer.is_some() => self.forget_peer(&mut events),
+                // nobody joined yet, so nobody left and there is nobody to answer
+                Packet::Bye if self.host => self.addr = None,
+                Packet::Bye => {}
+            }
+        }
+
+        if self
+            .peer
+            .is_some_and(|peer| peer.last_seen.elapsed() > TIMEOUT)
+        {
+            self.forget_peer(&mut events);
+        }
+
+        Ok(events)
+    }
+}
+
+impl Drop for Net {
+    fn drop(&mut self) {
+        let _ = self.send(&Packet::Bye);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_round_trip() {
+        for packet in [
+            Packet::State {
+                pos: Vec2::new(80.5, -3.),
+                direction: 1.25,
+                health: 200,
+            },
+            Packet::Message("reached the exit".into()),
+            Packet::Bye,
+        ] {
+            assert_eq!(Packet::decode(&packet.encode()).unwrap(), packet);
+        }
+
+        assert!(Packet::decode("state 1 2").is_err());
+        assert!(Packet::decode("hello").is_err());
+    }
+
+    #[test]
+    fn peers_see_each_other() {
+        let mut host = Net::new(&Role::Host(0)).unwrap();
+        let port = host.socket.local_addr().unwrap().port();
+        let mut client = Net::new(&Role::Connect(format!("127.0.0.1:{port}"))).unwrap();
+
+        let state = Packet::State {
+            pos: Vec2::new(1., 2.),
+            direction: 0.5,
+            health: 100,
+        };
+        client.send(&state).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(host.poll().unwrap(), [Event::Joined, Event::Moved]);
+        assert_eq!(host.peer.unwrap().pos, Vec2::new(1., 2.));
+
+        // the host learned where to answer from the first packet
+        host.send(&Packet::Message("hi".into())).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(client.poll().unwrap(), [Event::Message("hi".into())]);
+
+        drop(client);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(host.poll().unwrap(), [Event::Left]);
+
+        // someone else can join once they left
+        let mut second = Net::new(&Role::Connect(format!("127.0.0.1:{port}"))).unwrap();
+        second.send(&state).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(host.poll().unwrap(), [Event::Joined, Event::Moved]);
+        host.send(&Packet::Message("welcome".into())).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(second.poll().unwrap(), [Event::Message("welcome".into())]);
+    }
+
+    #[test]
+    fn hosts_ignore_bad_packets_and_byes_from_nobody() {
+        let mut host = Net::new(&Role::Host(0)).unwrap();
+        let port = host.socket.local_addr().unwrap().port();
+        let stranger = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        for text in ["hello", "bye"] {
+            stranger
+                .send_to(text.as_bytes(), ("127.0.0.1", port))
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            assert_eq!(host.poll().unwrap(), []);
+            assert_eq!(host.addr, None);
+        }
+
+        let client = Net::new(&Role::Connect(format!("127.0.0.1:{port}"))).unwrap();
+        client.send(&Packet::Message("hi".into())).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(host.poll().unwrap(), [Event::Message("hi".into())]);
+        let client_port = client.socket.local_addr().unwrap().port();
+        assert_eq!(host.addr.map(|addr| addr.port()), Some(client_port));
+    }
+}
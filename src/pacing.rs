@@ -0,0 +1,162 @@
+//! keeping the main loop at a steady pace
+//!
+//! the game advances in ticks of a fixed length, `TARGET_FPS` of them a second, however often
+//! frames are shown, so picking another frame rate does not speed up or slow down the game
+
+#[cfg(target_os = "emscripten")]
+use crate::emscripten;
+use crate::TARGET_FPS;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// length of a tick
+const TICK: Duration = Duration::from_nanos(1_000_000_000 / TARGET_FPS);
+/// most ticks caught up with at once, time lost to longer stalls is skipped
+const MAX_TICKS: u32 = 5;
+/// sleeping wakes up late by up to about this much, so the rest is waited for busily
+#[cfg(not(target_os = "emscripten"))]
+const SPIN: Duration = Duration::from_millis(1);
+/// highest frame rate that can be asked for
+const MAX_FPS: u32 = 1_000;
+
+/// how often frames are shown
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum FrameRate {
+    /// at most this many a second
+    Fps(u32),
+    /// whenever the display refreshes, presenting waits for it
+    Vsync,
+    /// as often as they can be drawn, for benchmarking
+    Uncapped,
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        Self::Fps(TARGET_FPS as u32)
+    }
+}
+
+impl FromStr for FrameRate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vsync" => Ok(Self::Vsync),
+            "uncapped" => Ok(Self::Uncapped),
+            _ => match s.parse() {
+                Ok(fps @ 1..=MAX_FPS) => Ok(Self::Fps(fps)),
+                _ => anyhow::bail!("frame rate must be 1 to {MAX_FPS}, vsync or uncapped: {s}"),
+            },
+        }
+    }
+}
+
+pub(crate) struct Pacer {
+    rate: FrameRate,
+    /// when the next tick and frame are due
+    next_tick: Instant,
+    next_frame: Instant,
+}
+
+impl Pacer {
+    pub fn new(rate: FrameRate) -> Self {
+        let now = Instant::now();
+        Self {
+            rate,
+            next_tick: now,
+            next_frame: now,
+        }
+    }
+
+    /// whether every frame is drawn, even when nothing changed
+    pub fn redraws(&self) -> bool {
+        self.rate == FrameRate::Uncapped
+    }
+
+    /// how many ticks are due by `now`
+    pub fn ticks(&mut self, now: Instant) -> u32 {
+        let mut ticks = 0;
+        while self.next_tick <= now && ticks < MAX_TICKS {
+            self.next_tick += TICK;
+            ticks += 1;
+        }
+        if self.next_tick <= now {
+            self.next_tick = now + TICK;
+        }
+        ticks
+    }
+
+    /// when the loop should go on, after drawing a frame or not
+    fn due(&mut self, now: Instant, drew: bool) -> Instant {
+        match self.rate {
+            FrameRate::Fps(fps) => {
+                // frames running late start the schedule over instead of rushing to catch up
+                self.next_frame = (self.next_frame + (Duration::from_secs(1) / fps)).max(now);
+                self.next_frame
+            }
+            // presenting already waited for the display, otherwise there is nothing to do
+            // before the next tick
+            FrameRate::Vsync if drew => now,
+            FrameRate::Vsync => self.next_tick,
+            FrameRate::Uncapped => now,
+        }
+    }
+
+    /// wait until the next frame is due
+    pub fn wait(&mut self, drew: bool) {
+        let due = self.due(Instant::now(), drew);
+
+        #[cfg(not(target_os = "emscripten"))]
+        {
+            let left = due.saturating_duration_since(Instant::now());
+            if left > SPIN {
+                std::thread::sleep(left - SPIN);
+            }
+            while Instant::now() < due {
+                std::hint::spin_loop();
+            }
+        }
+
+        // the browser only gets to run while sleeping, so this sleeps even when late
+        #[cfg(target_os = "emscripten")]
+        unsafe {
+            let left = due.saturating_duration_since(Instant::now());
+            emscripten::emscripten_sleep(left.as_millis() as std::os::raw::c_uint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_keep_their_rate_whatever_the_frame_rate() {
+        assert_eq!("vsync".parse::<FrameRate>().unwrap(), FrameRate::Vsync);
+        assert_eq!("60".parse::<FrameRate>().unwrap(), FrameRate::Fps(60));
+        assert!("0".parse::<FrameRate>().is_err());
+        assert!("fast".parse::<FrameRate>().is_err());
+
+        let mut pacer = Pacer::new(FrameRate::Fps(TARGET_FPS as u32 * 2));
+        let start = pacer.next_tick;
+        assert_eq!(pacer.ticks(start), 1);
+        // two frames for every tick
+        let frame = pacer.due(start, true);
+        assert!(frame < start + TICK);
+        assert_eq!(pacer.ticks(frame), 0);
+        assert_eq!(pacer.ticks(start + TICK), 1);
+
+        // long stalls are not caught up with all at once
+        let late = start + (TICK * 100);
+        assert_eq!(pacer.ticks(late), MAX_TICKS);
+        assert_eq!(pacer.ticks(late), 0);
+        assert_eq!(pacer.due(late, true), late);
+
+        let mut pacer = Pacer::new(FrameRate::Vsync);
+        pacer.ticks(start);
+        assert_eq!(pacer.due(start, true), start);
+        assert_eq!(pacer.due(start, false), pacer.next_tick);
+        assert!(!pacer.redraws());
+        assert!(Pacer::new(FrameRate::Uncapped).redraws());
+    }
+}
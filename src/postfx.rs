@@ -0,0 +1,176 @@
+//! tints drawn over the finished frame, like a red flash when hurt or fading in from black
+
+use crate::TARGET_FPS;
+use yaw_core::color::Color;
+use yaw_core::render::{Rect, Renderer};
+
+/// how many rings the vignette of a damage flash is made of
+const VIGNETTE_RINGS: u32 = 8;
+
+/// kinds of screen effects
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PostEffect {
+    /// red around the edges, fading away
+    Damage,
+    /// green tint over everything, fading away
+    Heal,
+    /// cut to black and fade back in, between screens
+    Fade,
+}
+
+impl PostEffect {
+    /// how many frames the effect lasts
+    fn frames(self) -> u64 {
+        match self {
+            PostEffect::Damage => TARGET_FPS / 2,
+            PostEffect::Heal => TARGET_FPS / 2,
+            PostEffect::Fade => TARGET_FPS / 3,
+        }
+    }
+}
+
+/// an effect in the queue, over `area` or the whole screen
+struct Active {
+    effect: PostEffect,
+    area: Option<Rect>,
+    frames: u64,
+}
+
+/// effects being shown, drawn in the order they were added
+#[derive(Default)]
+pub(crate) struct PostEffects {
+    queue: Vec<Active>,
+}
+
+impl PostEffects {
+    /// start an effect over an area of the screen, or all of it
+    pub fn push(&mut self, effect: PostEffect, area: Option<Rect>) {
+        // the same effect again starts over instead of stacking
+        self.queue
+            .retain(|active| active.effect != effect || active.area != area);
+        self.queue.push(Active {
+            effect,
+            area,
+            frames: effect.frames(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// draw every effect over `screen` and advance them a frame
+    pub fn draw(&mut self, renderer: &mut dyn Renderer, screen: Rect) -> anyhow::Result<()> {
+        for active in self.queue.iter() {
+            let area = active.area.unwrap_or(screen);
+            // close to 1 when the effect starts, down to 0 on its last frame so nothing is left
+            let strength = (active.frames - 1) as f32 / active.effect.frames() as f32;
+
+            match active.effect {
+                PostEffect::Damage => {
+                    let width = (area.width.min(area.height) / 3) / VIGNETTE_RINGS;
+                    for ring in 0..VIGNETTE_RINGS {
+                        // strongest at the edges
+                        let alpha = strength * 0xa0 as f32 * (VIGNETTE_RINGS - ring) as f32
+                            / VIGNETTE_RINGS as f32;
+                        draw_ring(
+                            renderer,
+                            area,
+                            ring * width,
+                            width,
+                            Color::RGBA(0xcc, 0, 0, alpha as u8),
+                        )?;
+                    }
+                }
+                PostEffect::Heal => {
+                    renderer.draw_rect(
+                        Some(area),
+                        Color::RGBA(0, 0xcc, 0, (strength * 0x60 as f32) as u8),
+                    )?;
+                }
+                PostEffect::Fade => {
+                    renderer.draw_rect(
+                        Some(area),
+                        Color::RGBA(0, 0, 0, (strength * 0xff as f32) as u8),
+                    )?;
+                }
+            }
+        }
+
+        self.queue.retain_mut(|active| {
+            active.frames -= 1;
+            active.frames > 0
+        });
+
+        Ok(())
+    }
+}
+
+/// fill a frame `width` pixels wide, `inset` pixels inside of `area`
+fn draw_ring(
+    renderer: &mut dyn Renderer,
+    area: Rect,
+    inset: u32,
+    width: u32,
+    color: Color,
+) -> anyhow::Result<()> {
+    let (x, y) = (area.x + inset as i32, area.y + inset as i32);
+    let outer_width = area.width.saturating_sub(inset * 2);
+    let outer_height = area.height.saturating_sub(inset * 2);
+    let side_height = outer_height.saturating_sub(width * 2);
+
+    for rect in [
+        Rect::new(x, y, outer_width, width),
+        Rect::new(x, y + (outer_height - width) as i32, outer_width, width),
+        Rect::new(x, y + width as i32, width, side_height),
+        Rect::new(
+            x + (outer_width - width) as i32,
+            y + width as i32,
+            width,
+            side_height,
+        ),
+    ] {
+        renderer.draw_rect(Some(rect), color)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaw_core::framebuffer::PixelRenderer;
+
+    fn pixel(renderer: &PixelRenderer, x: usize, y: usize) -> Color {
+        Color::from_argb(renderer.framebuffer.pixels[(y * 64) + x])
+    }
+
+    #[test]
+    fn effects_tint_the_screen_and_wear_off() {
+        let screen = Rect::new(0, 0, 64, 64);
+        let mut effects = PostEffects::default();
+        effects.push(PostEffect::Damage, None);
+        // pushing again restarts it instead of stacking
+        effects.push(PostEffect::Damage, None);
+
+        let mut renderer = PixelRenderer::new(64, 64);
+        renderer.draw_rect(None, Color::WHITE).unwrap();
+        effects.draw(&mut renderer, screen).unwrap();
+        let edge = pixel(&renderer, 0, 32);
+        assert!(edge.r > edge.g && edge.g == edge.b, "{edge:?}");
+        assert_eq!(pixel(&renderer, 32, 32), Color::WHITE);
+
+        for _ in 1..PostEffect::Damage.frames() {
+            renderer.draw_rect(None, Color::WHITE).unwrap();
+            effects.draw(&mut renderer, screen).unwrap();
+        }
+        assert!(effects.is_empty());
+        assert_eq!(pixel(&renderer, 0, 32), Color::WHITE);
+
+        effects.push(PostEffect::Fade, Some(Rect::new(0, 0, 32, 64)));
+        renderer.draw_rect(None, Color::WHITE).unwrap();
+        effects.draw(&mut renderer, screen).unwrap();
+        assert!(pixel(&renderer, 16, 32).r < 0x40);
+        assert_eq!(pixel(&renderer, 48, 32), Color::WHITE);
+    }
+}
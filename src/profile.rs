@@ -0,0 +1,63 @@
+//! numbers about rendering, summed up and logged once a second at trace level
+
+use std::time::{Duration, Instant};
+use yaw_core::render::CacheStats;
+
+pub(crate) struct FrameStats {
+    since: Instant,
+    /// frames shown since `since`
+    frames: u32,
+    /// times rays were cast since `since`, and how long it took altogether
+    casts: u32,
+    casting: Duration,
+    /// texture cache lookups of the renderer when the last stats were logged
+    cache: CacheStats,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            frames: 0,
+            casts: 0,
+            casting: Duration::ZERO,
+            cache: CacheStats::default(),
+        }
+    }
+
+    /// count rays being cast
+    pub fn cast(&mut self, took: Duration) {
+        self.casts += 1;
+        self.casting += took;
+    }
+
+    /// count a shown frame, logging and starting over once a second passed
+    pub fn frame(&mut self, cache: CacheStats) {
+        self.frames += 1;
+        let elapsed = self.since.elapsed();
+        if elapsed < Duration::from_secs(1) {
+            return;
+        }
+
+        let average = self.casting.checked_div(self.casts).unwrap_or_default();
+        let hits = cache.hits.saturating_sub(self.cache.hits);
+        let misses = cache.misses.saturating_sub(self.cache.misses);
+        log::trace!(
+            frames = self.frames,
+            seconds = elapsed.as_secs_f32(),
+            casts = self.casts,
+            cast_micros = average.as_micros() as u64,
+            cache_hits = hits,
+            cache_misses = misses;
+            "{} frames in {elapsed:.2?}, {} casts taking {average:.2?} on average, \
+             {hits} texture cache hits and {misses} misses",
+            self.frames,
+            self.casts,
+        );
+
+        *self = Self {
+            cache,
+            ..Self::new()
+        };
+    }
+}
@@ -0,0 +1,181 @@
+//! how far each campaign got, kept in a file between runs
+//!
+//! one `<step> <campaign>` line for campaigns being played and `done <campaign>` for finished
+//! ones, which can have both when played again, and `cleared <step> <campaign>` for each level
+//! finished in a campaign with a hub until the campaign is
+
+use anyhow::Context;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// word saved in place of a step for finished campaigns
+const DONE: &str = "done";
+/// word saved before the step of finished levels
+const CLEARED: &str = "cleared";
+
+pub(crate) struct Progress {
+    path: PathBuf,
+    /// step of the level each campaign is at
+    steps: BTreeMap<String, usize>,
+    finished: BTreeSet<String>,
+    /// steps of the levels finished in each campaign played from a hub
+    cleared: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl Progress {
+    /// nothing played yet, saved to `path` once something is
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            steps: BTreeMap::new(),
+            finished: BTreeSet::new(),
+            cleared: BTreeMap::new(),
+        }
+    }
+
+    /// read the progress saved at `path`, none when there is no file yet
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let mut progress = Self::new(path);
+        match std::fs::read_to_string(&progress.path) {
+            Ok(text) => {
+                for line in text.lines().filter(|line| !line.is_empty()) {
+                    let (step, campaign) = line
+                        .split_once(' ')
+                        .with_context(|| format!("invalid progress: {line}"))?;
+                    if step == DONE {
+                        progress.finished.insert(campaign.to_string());
+                    } else if step == CLEARED {
+                        let (step, campaign) = campaign
+                            .split_once(' ')
+                            .with_context(|| format!("invalid progress: {line}"))?;
+                        let cleared = progress.cleared.entry(campaign.to_string()).or_default();
+                        cleared.insert(step.parse()?);
+                    } else {
+                        progress.steps.insert(campaign.to_string(), step.parse()?);
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(progress)
+    }
+
+    /// step of the level a campaign was left at, none when it was not started or played to
+    /// the end
+    pub fn step(&self, campaign: &str) -> Option<usize> {
+        self.steps.get(campaign).copied()
+    }
+
+    /// whether a campaign was ever played to the end
+    pub fn finished(&self, campaign: &str) -> bool {
+        self.finished.contains(campaign)
+    }
+
+    /// whether any campaign was ever played to the end
+    pub fn any_finished(&self) -> bool {
+        !self.finished.is_empty()
+    }
+
+    /// whether the level at a step of a campaign was finished since the campaign last was
+    pub fn cleared(&self, campaign: &str, step: usize) -> bool {
+        self.cleared
+            .get(campaign)
+            .is_some_and(|cleared| cleared.contains(&step))
+    }
+
+    /// remember the level at a step of a campaign was finished and save
+    pub fn clear(&mut self, campaign: &str, step: usize) -> anyhow::Result<()> {
+        let cleared = self.cleared.entry(campaign.to_string()).or_default();
+        cleared.insert(step);
+        self.save()
+    }
+
+    /// remember the level a campaign is at and save
+    pub fn reach(&mut self, campaign: &str, step: usize) -> anyhow::Result<()> {
+        self.steps.insert(campaign.to_string(), step);
+        self.save()
+    }
+
+    /// remember a campaign was played to the end and save
+    pub fn finish(&mut self, campaign: &str) -> anyhow::Result<()> {
+        self.steps.remove(campaign);
+        self.cleared.remove(campaign);
+        self.finished.insert(campaign.to_string());
+        self.save()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let text = self
+            .finished
+            .iter()
+            .map(|campaign| format!("{DONE} {campaign}\n"))
+            .chain(
+                self.steps
+                    .iter()
+                    .map(|(campaign, step)| format!("{step} {campaign}\n")),
+            )
+            .chain(self.cleared.iter().flat_map(|(campaign, cleared)| {
+                cleared
+                    .iter()
+                    .map(move |step| format!("{CLEARED} {step} {campaign}\n"))
+            }))
+            .collect::<String>();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, text)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn progress_is_kept_between_runs() {
+        let dir = TempDir::new("progress").unwrap();
+        let path = dir.path().join("progress.txt");
+
+        let mut progress = Progress::load(path.clone()).unwrap();
+        assert_eq!(progress.step("map/campaign.yawc"), None);
+        progress.reach("map/campaign.yawc", 2).unwrap();
+        progress.reach("map/other campaign.yawc", 0).unwrap();
+        progress.finish("map/other campaign.yawc").unwrap();
+
+        let mut progress = Progress::load(path.clone()).unwrap();
+        assert_eq!(progress.step("map/campaign.yawc"), Some(2));
+        assert!(!progress.finished("map/campaign.yawc"));
+        assert!(progress.finished("map/other campaign.yawc"));
+        assert!(progress.any_finished());
+        assert_eq!(progress.step("map/other campaign.yawc"), None);
+
+        // playing it again keeps it finished
+        progress.reach("map/other campaign.yawc", 1).unwrap();
+        let progress = Progress::load(path.clone()).unwrap();
+        assert!(progress.finished("map/other campaign.yawc"));
+        assert_eq!(progress.step("map/other campaign.yawc"), Some(1));
+
+        // levels finished from a hub until the campaign is
+        let mut progress = Progress::load(path.clone()).unwrap();
+        progress.clear("map/hub campaign.yawc", 2).unwrap();
+        progress.clear("map/hub campaign.yawc", 0).unwrap();
+        let mut progress = Progress::load(path.clone()).unwrap();
+        assert!(progress.cleared("map/hub campaign.yawc", 2));
+        assert!(progress.cleared("map/hub campaign.yawc", 0));
+        assert!(!progress.cleared("map/hub campaign.yawc", 1));
+        assert!(!progress.cleared("map/campaign.yawc", 2));
+        progress.finish("map/hub campaign.yawc").unwrap();
+        let progress = Progress::load(path.clone()).unwrap();
+        assert!(!progress.cleared("map/hub campaign.yawc", 2));
+
+        std::fs::write(&path, "cleared map/campaign.yawc\n").unwrap();
+        assert!(Progress::load(path.clone()).is_err());
+        std::fs::write(&path, "later map/campaign.yawc\n").unwrap();
+        assert!(Progress::load(path.clone()).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! clips of gameplay, toggled with f10
+//!
+//! while recording the last few seconds of drawn frames are kept in a ring buffer, halved in
+//! size, and saved next to screenshots as an animated gif once recording stops
+
+use crate::screenshot;
+use crate::TARGET_FPS;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use yaw_core::color::Color;
+use yaw_core::framebuffer::Image;
+use yaw_core::render::Renderer;
+
+/// longest clip, older frames are dropped
+const MAX_SECONDS: u64 = 10;
+/// every how many drawn frames one is kept
+const FRAME_STEP: u64 = 2;
+/// how much smaller kept frames are than the screen
+const DOWNSCALE: usize = 2;
+/// speed of the color quantization, 1 is best and slowest and 30 fastest
+const QUANTIZE_SPEED: i32 = 20;
+
+pub(crate) struct Recording {
+    frames: VecDeque<Image>,
+    /// frames drawn since recording started
+    drawn: u64,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            drawn: 0,
+        }
+    }
+
+    /// most frames kept at once
+    fn capacity() -> usize {
+        (MAX_SECONDS * TARGET_FPS / FRAME_STEP) as usize
+    }
+
+    /// keep what was drawn this frame, if it is one of the kept ones
+    pub fn capture(&mut self, renderer: &mut dyn Renderer) -> anyhow::Result<()> {
+        self.drawn += 1;
+        if (self.drawn - 1).is_multiple_of(FRAME_STEP) {
+            self.push(&renderer.capture()?);
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, image: &Image) {
+        if self.frames.len() >= Self::capacity() {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(shrink(image));
+    }
+
+    /// write the clip to a timestamped gif
+    pub fn save(self) -> anyhow::Result<PathBuf> {
+        anyhow::ensure!(!self.frames.is_empty(), "nothing was recorded");
+
+        let path = screenshot::path("gif")?;
+        log::info!(
+            "saving {} frames of recording to {}",
+            self.frames.len(),
+            path.display()
+        );
+        let mut file = BufWriter::new(File::create(&path)?);
+        encode(&self.frames, &mut file)?;
+        file.flush()?;
+        screenshot::download(&path, "image/gif")?;
+
+        Ok(path)
+    }
+}
+
+/// every `DOWNSCALE`th pixel of every `DOWNSCALE`th row
+fn shrink(image: &Image) -> Image {
+    let (width, height) = (image.width / DOWNSCALE, image.height / DOWNSCALE);
+    Image {
+        width,
+        height,
+        pixels: (0..width * height)
+            .map(|idx| {
+                let (x, y) = ((idx % width) * DOWNSCALE, (idx / width) * DOWNSCALE);
+                image.pixels[(y * image.width) + x]
+            })
+            .collect(),
+    }
+}
+
+/// frames of the same size as a looping gif
+fn encode<'a>(
+    frames: impl IntoIterator<Item = &'a Image>,
+    writer: impl Write,
+) -> anyhow::Result<()> {
+    let mut frames = frames.into_iter().peekable();
+    let Some(first) = frames.peek() else {
+        anyhow::bail!("a gif needs at least one frame");
+    };
+    let size = (first.width, first.height);
+    let (width, height) = (u16::try_from(size.0)?, u16::try_from(size.1)?);
+
+    let mut encoder = gif::Encoder::new(writer, width, height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    // gifs count in hundredths of seconds
+    let delay = ((100 * FRAME_STEP) as f32 / TARGET_FPS as f32).round() as u16;
+    for image in frames {
+        anyhow::ensure!((image.width, image.height) == size, "frames differ in size");
+        let mut rgba: Vec<u8> = image
+            .pixels
+            .iter()
+            .flat_map(|px| {
+                let Color { r, g, b, .. } = Color::from_argb(*px);
+                [r, g, b, 0xff]
+            })
+            .collect();
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, QUANTIZE_SPEED);
+        frame.delay = delay;
+        encoder.write_frame(&frame)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: usize, height: usize, argb: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![argb; width * height],
+        }
+    }
+
+    #[test]
+    fn clips_keep_the_last_frames_as_a_gif() {
+        let mut recording = Recording::new();
+        for frame in 0..Recording::capacity() + 3 {
+            recording.push(&image(8, 6, frame as u32));
+        }
+        assert_eq!(recording.frames.len(), Recording::capacity());
+        assert_eq!(recording.frames[0].pixels[0], 3);
+        assert_eq!(
+            (recording.frames[0].width, recording.frames[0].height),
+            (4, 3)
+        );
+
+        let mut gif = Vec::new();
+        encode(&recording.frames, &mut gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+
+        assert!(encode(&[], &mut Vec::new()).is_err());
+        assert!(encode(&[image(4, 4, 0), image(2, 2, 0)], &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,596 @@
+use crate::lang::Lang;
+use crate::StringToAnyhow;
+use sdl2::image::LoadTexture;
+use sdl2::pixels::{Color as SdlColor, PixelFormatEnum};
+use sdl2::rect::{Point, Rect as SdlRect};
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator, TextureQuery};
+use sdl2::rwops::RWops;
+use sdl2::surface::Surface;
+use sdl2::ttf::{Font, FontStyle, Sdl2TtfContext};
+use sdl2::video::{FlashOperation, Window, WindowContext};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use yaw_core::assets;
+use yaw_core::color::Color;
+use yaw_core::framebuffer::{Image, PixelRenderer};
+use yaw_core::render::{CacheStats, Column, Rect, Renderer, TextStyle};
+
+fn sdl_color(color: Color) -> SdlColor {
+    SdlColor::RGBA(color.r, color.g, color.b, color.a)
+}
+
+fn sdl_rect(rect: Rect) -> SdlRect {
+    SdlRect::new(rect.x, rect.y, rect.width, rect.height)
+}
+
+/// the font all text is drawn in
+pub(crate) struct Fonts {
+    ctx: Sdl2TtfContext,
+    /// contents of the font file, kept for as long as the program runs since fonts read from it
+    data: &'static [u8],
+}
+
+impl Fonts {
+    /// the font file at `path`, or the global font without one or if it cannot be loaded
+    pub fn new(ctx: Sdl2TtfContext, path: Option<&Path>) -> Self {
+        let mut this = Self {
+            ctx,
+            data: crate::FIXEDER_SYS,
+        };
+        let Some(path) = path else {
+            return this;
+        };
+
+        let path = assets::resolve(path);
+        match std::fs::read(&path) {
+            Ok(data) => {
+                let global = std::mem::replace(&mut this.data, Box::leak(data.into_boxed_slice()));
+                let loaded = this.load(&TextStyle::new(16, Color::WHITE)).map(drop);
+                if let Err(err) = loaded {
+                    log::warn!("could not load font {}: {err}", path.display());
+                    this.data = global;
+                }
+            }
+            Err(err) => log::warn!("could not read font {}: {err}", path.display()),
+        }
+        this
+    }
+
+    /// the font at the size and style of some text
+    fn load(&self, style: &TextStyle) -> anyhow::Result<Font<'_, 'static>> {
+        let mut font = self
+            .ctx
+            .load_font_from_rwops(RWops::from_bytes(self.data).ah()?, style.size)
+            .ah()?;
+        font.set_style(if style.italic {
+            FontStyle::ITALIC
+        } else {
+            FontStyle::NORMAL
+        });
+
+        Ok(font)
+    }
+
+    /// warn about text of any language the font has no glyphs for
+    pub fn check_glyphs(&self) -> anyhow::Result<()> {
+        let font = self.load(&TextStyle::new(16, Color::WHITE))?;
+        for language in Lang::load()?.languages() {
+            let missing = language
+                .texts()
+                .flat_map(str::chars)
+                .filter(|c| !c.is_control() && font.find_glyph(*c).is_none())
+                .collect::<BTreeSet<_>>();
+            if !missing.is_empty() {
+                log::warn!(
+                    "font cannot draw {} text: {}",
+                    language.name,
+                    missing.into_iter().collect::<String>()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// text rendered one surface per line, none for empty lines which cannot be rendered
+struct Lines {
+    surfaces: Vec<Option<Surface<'static>>>,
+    /// size of all lines together, and how far apart lines are
+    width: u32,
+    height: u32,
+    spacing: i32,
+}
+
+fn font_lines<'a>(font: &Font, text: &'a str, style: &TextStyle) -> Vec<&'a str> {
+    yaw_core::render::wrap_lines(text, style, |line| {
+        font.size_of(line).map_or(0, |(width, _)| width)
+    })
+}
+
+/// height of some lines of text together
+fn lines_height(font: &Font, lines: usize) -> u32 {
+    (font.height() + (font.recommended_line_spacing() * (lines as i32 - 1))) as u32
+}
+
+/// size of text and its box, as [`render_lines`] would draw it
+fn measure_lines(font: &Font, text: &str, style: &TextStyle) -> (u32, u32) {
+    let lines = font_lines(font, text, style);
+    let width = lines
+        .iter()
+        .map(|line| font.size_of(line).map_or(0, |(width, _)| width))
+        .max()
+        .unwrap_or(0);
+    let (_, bg_rect) = text_rects(style, 0, 0, width, lines_height(font, lines.len()));
+    (bg_rect.width, bg_rect.height)
+}
+
+fn render_lines(font: &Font, text: &str, style: &TextStyle) -> anyhow::Result<Lines> {
+    let lines = font_lines(font, text, style);
+    let surfaces = lines
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                return Ok(None);
+            }
+            let rendering = font.render(line);
+            let surface = if style.blended {
+                rendering.blended(sdl_color(style.fg))?
+            } else {
+                rendering.solid(sdl_color(style.fg))?
+            };
+            Ok(Some(surface))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Lines {
+        width: surfaces
+            .iter()
+            .flatten()
+            .map(|surface| surface.width())
+            .max()
+            .unwrap_or(0),
+        height: lines_height(font, surfaces.len()),
+        surfaces,
+        spacing: font.recommended_line_spacing(),
+    })
+}
+
+/// area taken by text of a given size and its box
+fn text_rects(style: &TextStyle, x: i32, y: i32, width: u32, height: u32) -> (Rect, Rect) {
+    let (pad_x, pad_y) = style.padding;
+    (
+        Rect::new(x + pad_x as i32, y + pad_y as i32, width, height),
+        Rect::new(x, y, width + (pad_x * 2), height + (pad_y * 2)),
+    )
+}
+
+/// copy a surface into an image, converting it first
+fn surface_to_image(surface: &Surface) -> anyhow::Result<Image> {
+    let surface = surface.convert_format(PixelFormatEnum::ARGB8888).ah()?;
+    let (width, height) = (surface.width() as usize, surface.height() as usize);
+    let pitch = surface.pitch() as usize;
+    let pixels = surface.with_lock(|bytes| pixels_from_bytes(bytes, pitch, width, height));
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// read `0xAARRGGBB` pixels out of rows of native endian bytes
+fn pixels_from_bytes(bytes: &[u8], pitch: usize, width: usize, height: usize) -> Vec<u32> {
+    bytes
+        .chunks(pitch)
+        .take(height)
+        .flat_map(|row| {
+            row[..width * 4]
+                .chunks_exact(4)
+                .map(|px| u32::from_ne_bytes([px[0], px[1], px[2], px[3]]))
+        })
+        .collect()
+}
+
+/// adjacent wall columns of the same face, drawn with a single copy
+#[derive(Clone, Debug)]
+struct Batch {
+    texture: PathBuf,
+    tint: Color,
+    overlay: Color,
+    x: i32,
+    width: u32,
+    top: i32,
+    height: u32,
+    /// texture position of the first and last column
+    first: f32,
+    last: f32,
+}
+
+impl Batch {
+    fn new(column: &Column) -> Self {
+        Self {
+            texture: column.texture.into(),
+            tint: column.tint,
+            overlay: column.overlay,
+            x: column.x,
+            width: column.width,
+            top: column.top,
+            height: column.height,
+            first: column.texture_x,
+            last: column.texture_x,
+        }
+    }
+
+    /// grow the batch by a column continuing it, returns whether it did
+    fn extend(&mut self, column: &Column) -> bool {
+        // anything further than a few texels apart is another tile
+        let continues = (0. ..0.1).contains(&(column.texture_x - self.last));
+        if !continues
+            || column.x != self.x + self.width as i32
+            || column.top != self.top
+            || column.height != self.height
+            || column.tint != self.tint
+            || column.overlay != self.overlay
+            || column.texture != self.texture
+        {
+            return false;
+        }
+
+        self.width += column.width;
+        self.last = column.texture_x;
+        true
+    }
+}
+
+/// draws straight to the sdl canvas, copying one texture strip per run of similar wall columns
+pub(crate) struct SdlRenderer {
+    canvas: Canvas<Window>,
+    /// lives as long as the program, so textures made by it can be cached
+    texture_creator: &'static TextureCreator<WindowContext>,
+    fonts: Fonts,
+    /// decoded and uploaded image files
+    textures: HashMap<PathBuf, Texture<'static>>,
+    /// columns waiting to be drawn, flushed before anything else is
+    batch: Option<Batch>,
+    /// textures that failed to load and were already warned about
+    missing: HashSet<PathBuf>,
+    cache_stats: CacheStats,
+}
+
+impl SdlRenderer {
+    pub fn new(canvas: Canvas<Window>, fonts: Fonts) -> Self {
+        Self {
+            texture_creator: Box::leak(Box::new(canvas.texture_creator())),
+            canvas,
+            fonts,
+            textures: HashMap::new(),
+            batch: None,
+            missing: HashSet::new(),
+            cache_stats: CacheStats::default(),
+        }
+    }
+
+    /// upload an image file as a texture, cached, files that fail to load are drawn as
+    /// [`Image::missing`]
+    fn load(&mut self, path: &Path) -> anyhow::Result<()> {
+        if self.textures.contains_key(path) {
+            self.cache_stats.hits += 1;
+        } else {
+            self.cache_stats.misses += 1;
+            let texture = match assets::read(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| self.texture_creator.load_texture_bytes(&bytes).ah())
+            {
+                Ok(texture) => texture,
+                Err(err) => {
+                    if self.missing.insert(path.into()) {
+                        log::warn!("could not load {}: {err}", path.display());
+                    }
+                    image_texture(self.texture_creator, &Image::missing())?
+                }
+            };
+            self.textures.insert(path.into(), texture);
+        }
+
+        Ok(())
+    }
+
+    /// draw the pending columns
+    fn flush(&mut self) -> anyhow::Result<()> {
+        let Some(batch) = self.batch.take() else {
+            return Ok(());
+        };
+
+        self.load(&batch.texture)?;
+        let texture = self.textures.get_mut(&batch.texture).unwrap();
+        let TextureQuery { width, height, .. } = texture.query();
+        texture.set_color_mod(batch.tint.r, batch.tint.g, batch.tint.b);
+        let first = (batch.first * width as f32) as i32;
+        let last = ((batch.last * width as f32) as i32).max(first);
+        let sample_rect = SdlRect::new(first, 0, (last - first + 1) as u32, height);
+        let dst_rect = SdlRect::new(batch.x, batch.top, batch.width, batch.height);
+        self.canvas.copy(texture, sample_rect, dst_rect).ah()?;
+
+        if batch.overlay.a == 0 {
+            return Ok(());
+        }
+        fill(
+            &mut self.canvas,
+            Some(Rect::new(batch.x, batch.top, batch.width, batch.height)),
+            batch.overlay,
+        )
+    }
+}
+
+/// upload a decoded image
+fn image_texture<'tex>(
+    texture_creator: &'tex TextureCreator<WindowContext>,
+    image: &Image,
+) -> anyhow::Result<Texture<'tex>> {
+    let mut texture = texture_creator.create_texture_static(
+        PixelFormatEnum::ARGB8888,
+        image.width as u32,
+        image.height as u32,
+    )?;
+    let bytes = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_ne_bytes())
+        .collect::<Vec<_>>();
+    texture.update(None, &bytes, image.width * 4)?;
+
+    Ok(texture)
+}
+
+/// fill an area of the canvas, blended
+fn fill(canvas: &mut Canvas<Window>, rect: Option<Rect>, color: Color) -> anyhow::Result<()> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(sdl_color(color));
+    canvas.fill_rect(rect.map(sdl_rect)).ah()
+}
+
+/// draw a line on the canvas, blended
+fn line(
+    canvas: &mut Canvas<Window>,
+    from: (i32, i32),
+    to: (i32, i32),
+    color: Color,
+) -> anyhow::Result<()> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(sdl_color(color));
+    canvas.draw_line(Point::from(from), Point::from(to)).ah()
+}
+
+impl Renderer for SdlRenderer {
+    fn draw_column(&mut self, column: &Column) -> anyhow::Result<()> {
+        if let Some(batch) = self.batch.as_mut() {
+            if batch.extend(column) {
+                return Ok(());
+            }
+        }
+
+        self.flush()?;
+        self.batch = Some(Batch::new(column));
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: Option<Rect>, color: Color) -> anyhow::Result<()> {
+        self.flush()?;
+        fill(&mut self.canvas, rect, color)
+    }
+
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) -> anyhow::Result<()> {
+        self.flush()?;
+        line(&mut self.canvas, from, to, color)
+    }
+
+    fn draw_image(&mut self, path: &Path, rect: Option<Rect>) -> anyhow::Result<()> {
+        self.flush()?;
+        self.load(path)?;
+        let texture = self.textures.get_mut(path).unwrap();
+        texture.set_color_mod(0xff, 0xff, 0xff);
+        self.canvas.copy(texture, None, rect.map(sdl_rect)).ah()
+    }
+
+    fn draw_text(&mut self, text: &str, style: &TextStyle, x: i32, y: i32) -> anyhow::Result<Rect> {
+        self.flush()?;
+        let lines = render_lines(&self.fonts.load(style)?, text, style)?;
+        let (rect, bg_rect) = text_rects(style, x, y, lines.width, lines.height);
+        if let Some(bg) = style.bg {
+            fill(&mut self.canvas, Some(bg_rect), bg)?;
+        }
+        for (idx, surface) in lines.surfaces.iter().enumerate() {
+            let Some(surface) = surface else {
+                continue;
+            };
+            let texture = surface.as_texture(self.texture_creator)?;
+            let TextureQuery { width, height, .. } = texture.query();
+            let line = Rect::new(rect.x, rect.y + (idx as i32 * lines.spacing), width, height);
+            self.canvas.copy(&texture, None, sdl_rect(line)).ah()?;
+        }
+
+        Ok(bg_rect)
+    }
+
+    fn measure_text(&mut self, text: &str, style: &TextStyle) -> anyhow::Result<(u32, u32)> {
+        Ok(measure_lines(&self.fonts.load(style)?, text, style))
+    }
+
+    fn capture(&mut self) -> anyhow::Result<Image> {
+        self.flush()?;
+        let (width, height) = self.canvas.output_size().ah()?;
+        let format = PixelFormatEnum::ARGB8888;
+        let bytes = self.canvas.read_pixels(None, format).ah()?;
+        let pitch = format.byte_size_of_pixels(width as usize);
+
+        Ok(Image {
+            width: width as usize,
+            height: height as usize,
+            pixels: pixels_from_bytes(&bytes, pitch, width as usize, height as usize),
+        })
+    }
+
+    fn present(&mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        self.canvas.present();
+        Ok(())
+    }
+
+    fn preload(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.load(path)
+    }
+
+    fn clear_cache(&mut self) {
+        self.batch = None;
+        self.textures.clear();
+        self.missing.clear();
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.cache_stats
+    }
+
+    fn set_title(&mut self, title: &str) -> anyhow::Result<()> {
+        Ok(self.canvas.window_mut().set_title(title)?)
+    }
+
+    fn flash(&mut self) -> anyhow::Result<()> {
+        self.canvas
+            .window_mut()
+            .flash(FlashOperation::UntilFocused)
+            .ah()
+    }
+}
+
+/// draws every pixel into a framebuffer on the cpu, uploaded as one texture per frame
+pub(crate) struct SoftwareRenderer {
+    pixels: PixelRenderer,
+    canvas: Canvas<Window>,
+    /// lives as long as the program, so the frame texture made by it can be kept
+    texture_creator: &'static TextureCreator<WindowContext>,
+    fonts: Fonts,
+    /// frames are uploaded to this, made on the first one and again when the size changes
+    texture: Option<Texture<'static>>,
+    /// the frame as bytes, kept so it is not allocated every frame
+    bytes: Vec<u8>,
+}
+
+impl SoftwareRenderer {
+    pub fn new(canvas: Canvas<Window>, fonts: Fonts) -> anyhow::Result<Self> {
+        let (width, height) = canvas.output_size().ah()?;
+
+        Ok(Self {
+            pixels: PixelRenderer::new(width as usize, height as usize),
+            texture_creator: Box::leak(Box::new(canvas.texture_creator())),
+            canvas,
+            fonts,
+            texture: None,
+            bytes: Vec::new(),
+        })
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn draw_column(&mut self, column: &Column) -> anyhow::Result<()> {
+        self.pixels.draw_column(column)
+    }
+
+    fn draw_rect(&mut self, rect: Option<Rect>, color: Color) -> anyhow::Result<()> {
+        self.pixels.draw_rect(rect, color)
+    }
+
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) -> anyhow::Result<()> {
+        self.pixels.draw_line(from, to, color)
+    }
+
+    fn draw_image(&mut self, path: &Path, rect: Option<Rect>) -> anyhow::Result<()> {
+        self.pixels.draw_image(path, rect)
+    }
+
+    fn draw_text(&mut self, text: &str, style: &TextStyle, x: i32, y: i32) -> anyhow::Result<Rect> {
+        // the framebuffer blends every pixel anyway
+        let style = &TextStyle {
+            blended: true,
+            ..*style
+        };
+        let lines = render_lines(&self.fonts.load(style)?, text, style)?;
+        let (rect, bg_rect) = text_rects(style, x, y, lines.width, lines.height);
+        if let Some(bg) = style.bg {
+            self.pixels.draw_rect(Some(bg_rect), bg)?;
+        }
+        for (idx, surface) in lines.surfaces.iter().enumerate() {
+            let Some(surface) = surface else {
+                continue;
+            };
+            let image = surface_to_image(surface)?;
+            self.pixels.framebuffer.draw_image(
+                &image,
+                rect.x,
+                rect.y + (idx as i32 * lines.spacing),
+                image.width as u32,
+                image.height as u32,
+            );
+        }
+
+        Ok(bg_rect)
+    }
+
+    fn measure_text(&mut self, text: &str, style: &TextStyle) -> anyhow::Result<(u32, u32)> {
+        Ok(measure_lines(&self.fonts.load(style)?, text, style))
+    }
+
+    fn capture(&mut self) -> anyhow::Result<Image> {
+        self.pixels.capture()
+    }
+
+    fn present(&mut self) -> anyhow::Result<()> {
+        let framebuffer = &self.pixels.framebuffer;
+        let (width, height) = (framebuffer.width as u32, framebuffer.height as u32);
+        let texture = match &mut self.texture {
+            Some(texture) if (texture.query().width, texture.query().height) == (width, height) => {
+                texture
+            }
+            texture => texture.insert(self.texture_creator.create_texture_streaming(
+                PixelFormatEnum::ARGB8888,
+                width,
+                height,
+            )?),
+        };
+        self.bytes.clear();
+        self.bytes.extend(
+            framebuffer
+                .pixels
+                .iter()
+                .flat_map(|pixel| pixel.to_ne_bytes()),
+        );
+        texture.update(None, &self.bytes, framebuffer.width * 4)?;
+        self.canvas.copy(texture, None, None).ah()?;
+        self.canvas.present();
+
+        Ok(())
+    }
+
+    fn preload(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.pixels.preload(path)
+    }
+
+    fn clear_cache(&mut self) {
+        self.pixels.clear_cache();
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.pixels.cache_stats()
+    }
+
+    fn set_title(&mut self, title: &str) -> anyhow::Result<()> {
+        Ok(self.canvas.window_mut().set_title(title)?)
+    }
+
+    fn flash(&mut self) -> anyhow::Result<()> {
+        self.canvas
+            .window_mut()
+            .flash(FlashOperation::UntilFocused)
+            .ah()
+    }
+}
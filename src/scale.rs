@@ -0,0 +1,140 @@
+//! how many pixels across each ray of the view is drawn, so slow machines cast fewer rays
+//!
+//! picked in the settings, or adjusted while playing to keep frames within their time budget
+
+use crate::TARGET_FPS;
+use std::time::Duration;
+
+/// the widest columns get, in pixels
+const MAX_SCALE: u32 = 4;
+/// frames taking longer than this part of the budget are slow, shorter than the other fast
+const SLOW: f32 = 0.8;
+const FAST: f32 = 0.3;
+/// how many slow frames in a row make columns wider, and how many fast ones narrower again
+const SLOW_FRAMES: u32 = TARGET_FPS as u32 / 2;
+const FAST_FRAMES: u32 = TARGET_FPS as u32 * 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum RenderScale {
+    /// as wide as frames need to be drawn in time
+    #[default]
+    Auto,
+    /// always this many pixels
+    Fixed(u32),
+}
+
+impl RenderScale {
+    /// language key of its name
+    pub fn key(self) -> &'static str {
+        match self {
+            RenderScale::Auto => "scale.auto",
+            RenderScale::Fixed(1) => "scale.full",
+            RenderScale::Fixed(2) => "scale.half",
+            RenderScale::Fixed(_) => "scale.quarter",
+        }
+    }
+
+    /// the next one, for menus that cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            RenderScale::Auto => RenderScale::Fixed(1),
+            RenderScale::Fixed(scale) if scale < MAX_SCALE => RenderScale::Fixed(scale * 2),
+            RenderScale::Fixed(_) => RenderScale::Auto,
+        }
+    }
+}
+
+/// the scale in use, following the setting or the time frames take
+#[derive(Debug)]
+pub(crate) struct Scaler {
+    pub setting: RenderScale,
+    /// pixels per ray picked automatically
+    auto: u32,
+    /// slow or fast frames in a row
+    slow: u32,
+    fast: u32,
+}
+
+impl Scaler {
+    pub fn new(setting: RenderScale) -> Self {
+        Self {
+            setting,
+            auto: 1,
+            slow: 0,
+            fast: 0,
+        }
+    }
+
+    /// pixels across each ray is drawn
+    pub fn scale(&self) -> u32 {
+        match self.setting {
+            RenderScale::Auto => self.auto,
+            RenderScale::Fixed(scale) => scale,
+        }
+    }
+
+    /// count how long drawing a frame took, returns whether the scale changed
+    pub fn frame(&mut self, took: Duration) -> bool {
+        let budget = Duration::from_secs(1) / TARGET_FPS as u32;
+        if took > budget.mul_f32(SLOW) {
+            (self.slow, self.fast) = (self.slow + 1, 0);
+        } else if took < budget.mul_f32(FAST) {
+            (self.slow, self.fast) = (0, self.fast + 1);
+        } else {
+            (self.slow, self.fast) = (0, 0);
+        }
+
+        let auto = if self.slow >= SLOW_FRAMES {
+            (self.auto * 2).min(MAX_SCALE)
+        } else if self.fast >= FAST_FRAMES {
+            (self.auto / 2).max(1)
+        } else {
+            return false;
+        };
+        (self.slow, self.fast) = (0, 0);
+        let changed = auto != self.auto;
+        if changed {
+            log::info!("drawing rays {auto} pixels wide to keep up");
+        }
+        self.auto = auto;
+        changed && self.setting == RenderScale::Auto
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_frames_widen_columns_and_fast_ones_narrow_them() {
+        let mut scaler = Scaler::new(RenderScale::Auto);
+        let slow = Duration::from_secs(1);
+        for _ in 1..SLOW_FRAMES {
+            assert!(!scaler.frame(slow));
+        }
+        assert!(scaler.frame(slow));
+        assert_eq!(scaler.scale(), 2);
+        for _ in 0..SLOW_FRAMES * 4 {
+            scaler.frame(slow);
+        }
+        assert_eq!(scaler.scale(), MAX_SCALE);
+
+        for _ in 0..FAST_FRAMES {
+            scaler.frame(Duration::ZERO);
+        }
+        assert_eq!(scaler.scale(), MAX_SCALE / 2);
+
+        // fixed scales stay what they are
+        scaler.setting = RenderScale::Fixed(1);
+        assert!(!scaler.frame(Duration::ZERO));
+        assert_eq!(scaler.scale(), 1);
+
+        // auto, then every fixed scale from 1 up to the largest
+        let mut setting = RenderScale::Auto;
+        for _ in 0..MAX_SCALE.ilog2() + 1 {
+            setting = setting.next();
+        }
+        assert_eq!(setting, RenderScale::Fixed(MAX_SCALE));
+        assert_eq!(setting.next(), RenderScale::Auto);
+    }
+}
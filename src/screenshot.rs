@@ -0,0 +1,68 @@
+use crate::StringToAnyhow;
+use sdl2::image::SaveSurface;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use yaw_core::framebuffer::Image;
+
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// save a captured frame as a timestamped png
+pub(crate) fn save(image: &Image) -> anyhow::Result<PathBuf> {
+    let format = PixelFormatEnum::ARGB8888;
+    let mut bytes: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|px| px.to_ne_bytes())
+        .collect();
+    let surface = Surface::from_data(
+        &mut bytes,
+        image.width as u32,
+        image.height as u32,
+        format.byte_size_of_pixels(image.width) as u32,
+        format,
+    )
+    .ah()?;
+
+    let path = path("png")?;
+    log::info!("saving screenshot to {}", path.display());
+    surface.save(&path).ah()?;
+    download(&path, "image/png")?;
+
+    Ok(path)
+}
+
+/// timestamped path in the screenshot directory for a new file, creating the directory
+pub(crate) fn path(extension: &str) -> anyhow::Result<PathBuf> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    std::fs::create_dir_all(SCREENSHOT_DIR)?;
+    Ok(PathBuf::from(SCREENSHOT_DIR).join(format!("yaw-{timestamp}.{extension}")))
+}
+
+/// files saved in the browser only exist in the in-memory filesystem, hand them over as a
+/// download
+#[cfg(target_os = "emscripten")]
+pub(crate) fn download(path: &Path, mime: &str) -> anyhow::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let script = format!(
+        "(function () {{
+            var blob = new Blob([FS.readFile('{path}')], {{ type: '{mime}' }});
+            var link = document.createElement('a');
+            link.href = URL.createObjectURL(blob);
+            link.download = '{name}';
+            link.click();
+            URL.revokeObjectURL(link.href);
+        }})()",
+        path = path.display()
+    );
+    let script = std::ffi::CString::new(script)?;
+    unsafe { crate::emscripten::emscripten_run_script(script.as_ptr()) };
+    Ok(())
+}
+
+/// files saved elsewhere are already where players can find them
+#[cfg(not(target_os = "emscripten"))]
+pub(crate) fn download(_path: &Path, _mime: &str) -> anyhow::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,438 @@
+//! rhai scripts shipped next to maps, `map.yaw` is scripted by `map.rhai`
+//!
+//! scripts define any of these callbacks:
+//!
+//! ```text
+//! fn on_load() {}
+//! fn on_tick() {}
+//! fn on_use(tile) {}       // tile is #{x, y, id}, id is "" for empty tiles
+//! fn on_enter(x, y) {}     // the player walked onto a new tile
+//! fn on_signal(id, on) {}  // a switch or plate turned a signal on or off
+//! fn on_restore(x, y, state) {} // an entity got back the state it had, before on_load
+//! ```
+//!
+//! and can call `message(text)`, `set_tile(x, y, id)`, `move_entity(x, y, to_x, to_y)`,
+//! `effect(x, y, kind)`, `health()`, `set_health(n)`, `stamina()`, `set_stamina(n)`,
+//! `ammo()`, `set_ammo(n)`, `keys()`, `set_keys(n)`, `kill_entity(x, y)`, `pick_up(x, y)`,
+//! `damage(n)`, `heal(n)`, `give_ammo(n)`, `player_x()`, `player_y()`, `entity_state(x, y, key)`
+//! and `set_entity_state(x, y, key, value)`, positions are in tiles. `damage`, `heal` and
+//! `give_ammo` amounts are for normal difficulty, the game scales them to the one being played
+//!
+//! entity state is text by one word keys, "" when not set, and entities the map placed keep it
+//! once the level is finished, `state` in `on_restore` is a map of it
+
+use glam::Vec2;
+use rhai::{Dynamic, Engine, FuncArgs, Scope, AST};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+use yaw_core::assets;
+use yaw_core::entity::StateBag;
+use yaw_core::map::{Tile, TILE_SIZE};
+use yaw_core::particle::Effect;
+
+/// changes a script asks the game to make
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Command {
+    Message(String),
+    SetTile {
+        x: usize,
+        y: usize,
+        tile: Tile,
+    },
+    /// walk the entity on a tile around walls to another tile
+    MoveEntity {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    /// remove the entity on a tile, counting it as killed or as an item picked up
+    KillEntity((usize, usize)),
+    PickUp((usize, usize)),
+    /// burst of particles in the middle of a tile
+    Effect {
+        at: (usize, usize),
+        effect: Effect,
+    },
+    SetHealth(u8),
+    SetStamina(f32),
+    SetAmmo(u16),
+    SetKeys(u8),
+    /// hurt the player, heal them or give them ammo, before scaling by difficulty
+    Damage(u8),
+    Heal(u8),
+    GiveAmmo(u16),
+    /// keep a value on the entity on a tile
+    SetEntityState {
+        at: (usize, usize),
+        key: String,
+        value: String,
+    },
+}
+
+/// what scripts can read about the player
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub(crate) struct State {
+    pub pos: Vec2,
+    pub health: u8,
+    pub stamina: f32,
+    pub ammo: u16,
+    pub keys: u8,
+}
+
+/// shared between the engine's functions and the game
+#[derive(Default)]
+struct Host {
+    state: State,
+    /// state of the entities, by the tile they are on
+    entities: BTreeMap<(usize, usize), StateBag>,
+    commands: Vec<Command>,
+}
+
+pub(crate) struct Script {
+    engine: Engine,
+    ast: AST,
+    /// callbacks the script defines, others are skipped
+    callbacks: HashSet<String>,
+    host: Rc<RefCell<Host>>,
+}
+
+impl Script {
+    /// the script next to a map, if there is one
+    pub fn load(map: &Path) -> anyhow::Result<Option<Self>> {
+        let path = assets::resolve(&map.with_extension("rhai"));
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        log::info!("loading script at {}", path.display());
+        let source = std::fs::read_to_string(&path)?;
+        Self::parse(&source).map(Some)
+    }
+
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let host = Rc::new(RefCell::new(Host::default()));
+        let mut engine = Engine::new();
+
+        let h = host.clone();
+        engine.register_fn("message", move |text: &str| {
+            h.borrow_mut().commands.push(Command::Message(text.into()));
+        });
+        let h = host.clone();
+        engine.register_fn("set_tile", move |x: i64, y: i64, id: &str| {
+            let tile = match id.chars().next() {
+                None | Some(' ') => Tile::Empty,
+                Some(id) => Tile::Custom(id),
+            };
+            h.borrow_mut().commands.push(Command::SetTile {
+                x: x.max(0) as usize,
+                y: y.max(0) as usize,
+                tile,
+            });
+        });
+        let h = host.clone();
+        engine.register_fn(
+            "move_entity",
+            move |x: i64, y: i64, to_x: i64, to_y: i64| {
+                let tile = |x: i64, y: i64| (x.max(0) as usize, y.max(0) as usize);
+                h.borrow_mut().commands.push(Command::MoveEntity {
+                    from: tile(x, y),
+                    to: tile(to_x, to_y),
+                });
+            },
+        );
+        let h = host.clone();
+        engine.register_fn("kill_entity", move |x: i64, y: i64| {
+            let at = (x.max(0) as usize, y.max(0) as usize);
+            h.borrow_mut().commands.push(Command::KillEntity(at));
+        });
+        let h = host.clone();
+        engine.register_fn("pick_up", move |x: i64, y: i64| {
+            let at = (x.max(0) as usize, y.max(0) as usize);
+            h.borrow_mut().commands.push(Command::PickUp(at));
+        });
+        let h = host.clone();
+        engine.register_fn(
+            "effect",
+            move |x: i64, y: i64, kind: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+                let effect = kind.parse::<Effect>().map_err(|err| err.to_string())?;
+                h.borrow_mut().commands.push(Command::Effect {
+                    at: (x.max(0) as usize, y.max(0) as usize),
+                    effect,
+                });
+                Ok(())
+            },
+        );
+        let h = host.clone();
+        engine.register_fn("health", move || h.borrow().state.health as i64);
+        let h = host.clone();
+        engine.register_fn("set_health", move |health: i64| {
+            let health = health.clamp(0, u8::MAX as i64) as u8;
+            h.borrow_mut().state.health = health;
+            h.borrow_mut().commands.push(Command::SetHealth(health));
+        });
+        let h = host.clone();
+        engine.register_fn("damage", move |amount: i64| {
+            let amount = amount.clamp(0, u8::MAX as i64) as u8;
+            h.borrow_mut().commands.push(Command::Damage(amount));
+        });
+        let h = host.clone();
+        engine.register_fn("heal", move |amount: i64| {
+            let amount = amount.clamp(0, u8::MAX as i64) as u8;
+            h.borrow_mut().commands.push(Command::Heal(amount));
+        });
+        let h = host.clone();
+        engine.register_fn("stamina", move || h.borrow().state.stamina as f64);
+        let h = host.clone();
+        engine.register_fn("set_stamina", move |stamina: f64| {
+            let stamina = stamina.max(0.) as f32;
+            h.borrow_mut().state.stamina = stamina;
+            h.borrow_mut().commands.push(Command::SetStamina(stamina));
+        });
+        let h = host.clone();
+        engine.register_fn("ammo", move || h.borrow().state.ammo as i64);
+        let h = host.clone();
+        engine.register_fn("set_ammo", move |ammo: i64| {
+            let ammo = ammo.clamp(0, u16::MAX as i64) as u16;
+            h.borrow_mut().state.ammo = ammo;
+            h.borrow_mut().commands.push(Command::SetAmmo(ammo));
+        });
+        let h = host.clone();
+        engine.register_fn("give_ammo", move |ammo: i64| {
+            let ammo = ammo.clamp(0, u16::MAX as i64) as u16;
+            h.borrow_mut().commands.push(Command::GiveAmmo(ammo));
+        });
+        let h = host.clone();
+        engine.register_fn("keys", move || h.borrow().state.keys as i64);
+        let h = host.clone();
+        engine.register_fn("set_keys", move |keys: i64| {
+            let keys = keys.clamp(0, u8::MAX as i64) as u8;
+            h.borrow_mut().state.keys = keys;
+            h.borrow_mut().commands.push(Command::SetKeys(keys));
+        });
+        let h = host.clone();
+        engine.register_fn("player_x", move || {
+            (h.borrow().state.pos.x / TILE_SIZE) as i64
+        });
+        let h = host.clone();
+        engine.register_fn("player_y", move || {
+            (h.borrow().state.pos.y / TILE_SIZE) as i64
+        });
+
+        let h = host.clone();
+        engine.register_fn("entity_state", move |x: i64, y: i64, key: &str| {
+            let at = (x.max(0) as usize, y.max(0) as usize);
+            let host = h.borrow();
+            let value = host.entities.get(&at).and_then(|state| state.get(key));
+            value.cloned().unwrap_or_default()
+        });
+        let h = host.clone();
+        engine.register_fn(
+            "set_entity_state",
+            move |x: i64, y: i64, key: &str, value: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+                if key.is_empty() || key.contains(char::is_whitespace) {
+                    return Err(format!("entity state keys are one word: {key:?}").into());
+                }
+                let at = (x.max(0) as usize, y.max(0) as usize);
+                // saved a line per value
+                let value = value.replace('\n', " ");
+                let mut host = h.borrow_mut();
+                let state = host.entities.entry(at).or_default();
+                state.insert(key.into(), value.clone());
+                host.commands.push(Command::SetEntityState {
+                    at,
+                    key: key.into(),
+                    value,
+                });
+                Ok(())
+            },
+        );
+
+        let ast = engine.compile(source)?;
+        let callbacks = ast.iter_functions().map(|f| f.name.to_string()).collect();
+
+        Ok(Self {
+            engine,
+            ast,
+            callbacks,
+            host,
+        })
+    }
+
+    /// what `entity_state` reads from now on, by the tile of each entity
+    pub fn set_entity_states(&mut self, entities: BTreeMap<(usize, usize), StateBag>) {
+        self.host.borrow_mut().entities = entities;
+    }
+
+    /// run a callback if the script has it, returns what it asked for
+    pub fn call(
+        &mut self,
+        name: &str,
+        args: impl FuncArgs,
+        state: State,
+    ) -> anyhow::Result<Vec<Command>> {
+        if !self.callbacks.contains(name) {
+            return Ok(Vec::new());
+        }
+
+        self.host.borrow_mut().state = state;
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args);
+        let commands = std::mem::take(&mut self.host.borrow_mut().commands);
+        if let Err(err) = result {
+            anyhow::bail!("{name}: {err}");
+        }
+
+        Ok(commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callbacks_issue_commands() {
+        let mut script = Script::parse(
+            r##"
+            fn on_enter(x, y) {
+                if x == 2 { set_tile(x + 1, y, "#"); }
+                if x == 5 { move_entity(6, y, 1, 1); }
+                if x == 7 { kill_entity(x, y); pick_up(x + 1, y); }
+                if x == 9 { damage(300); heal(5); give_ammo(-1); }
+            }
+            fn on_use(tile) {
+                message("used " + tile.id);
+                set_health(health() - 10);
+            }
+            fn on_load() {
+                set_ammo(ammo() + 5);
+                set_keys(keys() - 1);
+            }
+            "##,
+        )
+        .unwrap();
+
+        let state = State {
+            health: 100,
+            ..State::default()
+        };
+        assert_eq!(script.call("on_enter", (1_i64, 1_i64), state).unwrap(), []);
+        assert_eq!(
+            script.call("on_enter", (2_i64, 1_i64), state).unwrap(),
+            [Command::SetTile {
+                x: 3,
+                y: 1,
+                tile: Tile::Custom('#')
+            }]
+        );
+
+        assert_eq!(
+            script.call("on_enter", (5_i64, 2_i64), state).unwrap(),
+            [Command::MoveEntity {
+                from: (6, 2),
+                to: (1, 1)
+            }]
+        );
+
+        assert_eq!(
+            script.call("on_enter", (7_i64, 2_i64), state).unwrap(),
+            [Command::KillEntity((7, 2)), Command::PickUp((8, 2))]
+        );
+        assert_eq!(
+            script.call("on_enter", (9_i64, 2_i64), state).unwrap(),
+            [Command::Damage(255), Command::Heal(5), Command::GiveAmmo(0)]
+        );
+
+        let mut tile = rhai::Map::new();
+        tile.insert("id".into(), "-".into());
+        assert_eq!(
+            script.call("on_use", (tile,), state).unwrap(),
+            [Command::Message("used -".into()), Command::SetHealth(90)]
+        );
+
+        let state = State {
+            ammo: 10,
+            ..State::default()
+        };
+        assert_eq!(
+            script.call("on_load", (), state).unwrap(),
+            [Command::SetAmmo(15), Command::SetKeys(0)]
+        );
+
+        // missing callbacks do nothing
+        assert_eq!(script.call("on_tick", (), state).unwrap(), []);
+    }
+
+    #[test]
+    fn entity_state_is_read_back_once_set() {
+        let mut script = Script::parse(
+            r#"
+            fn on_use(tile) {
+                if entity_state(tile.x, tile.y, "opened") == "" {
+                    set_entity_state(tile.x, tile.y, "opened", "yes\nreally");
+                }
+                message(entity_state(tile.x, tile.y, "opened"));
+            }
+            fn on_restore(x, y, state) {
+                message(`${x} ${y} ${state.opened}`);
+            }
+            fn on_load() { set_entity_state(1, 1, "two words", "no"); }
+            "#,
+        )
+        .unwrap();
+
+        let mut tile = rhai::Map::new();
+        tile.insert("x".into(), 2_i64.into());
+        tile.insert("y".into(), 3_i64.into());
+        assert_eq!(
+            script
+                .call("on_use", (tile.clone(),), State::default())
+                .unwrap(),
+            [
+                Command::SetEntityState {
+                    at: (2, 3),
+                    key: "opened".into(),
+                    value: "yes really".into()
+                },
+                Command::Message("yes really".into())
+            ]
+        );
+
+        let state = StateBag::from([("opened".into(), "no".into())]);
+        script.set_entity_states(BTreeMap::from([((2, 3), state.clone())]));
+        assert_eq!(
+            script.call("on_use", (tile,), State::default()).unwrap(),
+            [Command::Message("no".into())]
+        );
+
+        let state = state.into_iter().map(|(k, v)| (k.into(), v.into()));
+        let args = (2_i64, 3_i64, state.collect::<rhai::Map>());
+        assert_eq!(
+            script.call("on_restore", args, State::default()).unwrap(),
+            [Command::Message("2 3 no".into())]
+        );
+
+        assert!(script.call("on_load", (), State::default()).is_err());
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert!(Script::parse("fn on_load( {").is_err());
+
+        let mut script = Script::parse("fn on_load() { explode(); }").unwrap();
+        assert!(script.call("on_load", (), State::default()).is_err());
+
+        let mut script = Script::parse(r#"fn on_load() { effect(1, 1, "fire"); }"#).unwrap();
+        assert!(script.call("on_load", (), State::default()).is_err());
+        let mut script = Script::parse(r#"fn on_load() { effect(1, 2, "sparks"); }"#).unwrap();
+        assert_eq!(
+            script.call("on_load", (), State::default()).unwrap(),
+            [Command::Effect {
+                at: (1, 2),
+                effect: Effect::Sparks
+            }]
+        );
+    }
+}
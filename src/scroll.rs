@@ -0,0 +1,124 @@
+//! text too long for the screen, scrolled with the arrow and page keys
+
+use crate::theme::HudTheme;
+use crate::{HEIGHT, WIDTH};
+use sdl2::keyboard::Keycode;
+use yaw_core::render::{renderer_lines, Renderer};
+
+/// where the first line is drawn and how far apart lines are
+const TOP: i32 = 64;
+const LINE_HEIGHT: i32 = 24;
+/// space left below the last line
+const BOTTOM: i32 = 32;
+/// where the marks for more text above or below are drawn
+const MARK_X: i32 = WIDTH as i32 - 40;
+
+pub(crate) struct ScrollText {
+    pub title: String,
+    pub lines: Vec<String>,
+    /// first line shown
+    top: usize,
+}
+
+impl ScrollText {
+    pub fn new(title: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            top: 0,
+        }
+    }
+
+    /// text split into lines as wide as the screen, wrapped like `renderer` wraps them
+    pub fn wrapped(
+        title: impl Into<String>,
+        text: &str,
+        renderer: &mut dyn Renderer,
+        theme: &HudTheme,
+    ) -> Self {
+        let style = theme.body().wrapped(theme.wrap_width(WIDTH as u32));
+        let lines = renderer_lines(renderer, text, &style)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        Self::new(title, lines)
+    }
+
+    /// lines that fit on screen at once
+    fn page() -> usize {
+        ((HEIGHT as i32 - TOP - BOTTOM) / LINE_HEIGHT) as usize
+    }
+
+    /// furthest down the text can be scrolled, so the last page is full
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(Self::page())
+    }
+
+    /// scroll, returns whether the key did
+    pub fn key(&mut self, key: Keycode) -> bool {
+        self.top = match key {
+            Keycode::Up => self.top.saturating_sub(1),
+            Keycode::Down => self.top + 1,
+            Keycode::PageUp => self.top.saturating_sub(Self::page()),
+            Keycode::PageDown | Keycode::Space => self.top + Self::page(),
+            Keycode::Home => 0,
+            Keycode::End => self.max_top(),
+            _ => return false,
+        }
+        .min(self.max_top());
+        true
+    }
+
+    /// draw the title and the lines in view, with marks where there is more
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &HudTheme) -> anyhow::Result<()> {
+        renderer.draw_text(&self.title, &theme.title(), theme.margin, theme.margin)?;
+
+        let style = theme.body();
+        let shown = self.lines.iter().skip(self.top).take(Self::page());
+        for (idx, line) in shown.enumerate() {
+            // empty lines cannot be rendered, but still take up space
+            if !line.is_empty() {
+                renderer.draw_text(line, &style, theme.margin, TOP + (idx as i32 * LINE_HEIGHT))?;
+            }
+        }
+
+        let more = style.italic();
+        if self.top > 0 {
+            renderer.draw_text("^", &more, MARK_X, TOP)?;
+        }
+        if self.top < self.max_top() {
+            renderer.draw_text("v", &more, MARK_X, HEIGHT as i32 - BOTTOM - LINE_HEIGHT)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolling_stays_within_the_text() {
+        let lines = (0..ScrollText::page() + 5)
+            .map(|idx| idx.to_string())
+            .collect();
+        let mut text = ScrollText::new("", lines);
+        assert!(text.key(Keycode::Up));
+        assert_eq!(text.top, 0);
+        text.key(Keycode::Down);
+        assert_eq!(text.top, 1);
+        text.key(Keycode::PageDown);
+        assert_eq!(text.top, 5);
+        text.key(Keycode::Home);
+        assert_eq!(text.top, 0);
+        text.key(Keycode::End);
+        assert_eq!(text.top, 5);
+        assert!(!text.key(Keycode::Return));
+
+        // short texts do not scroll at all
+        let mut short = ScrollText::new("", vec!["line".into()]);
+        short.key(Keycode::PageDown);
+        assert_eq!(short.top, 0);
+    }
+}
@@ -0,0 +1,124 @@
+//! how a level went, and the best time of each map kept in a file between runs
+
+use crate::modifiers::Modifiers;
+use crate::TARGET_FPS;
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// what happened in the current level
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub(crate) struct LevelStats {
+    /// frames spent playing, not counting menus or pauses
+    pub frames: u64,
+    pub kills: u32,
+    pub items: u32,
+    /// secrets found, out of how many the level has
+    pub secrets: u32,
+    pub secret_total: u32,
+    /// whether any player lost health
+    pub hurt: bool,
+    /// seed of `--randomize`, to play the same textures again
+    pub seed: Option<u64>,
+    /// picked on the modifiers screen when the level started
+    pub modifiers: Modifiers,
+}
+
+/// frames as `m:ss`, or `m:ss.t` with tenths of a second
+pub(crate) fn clock(frames: u64, tenths: bool) -> String {
+    let seconds = frames / TARGET_FPS;
+    let time = format!("{}:{:02}", seconds / 60, seconds % 60);
+    if tenths {
+        format!("{time}.{}", ((frames % TARGET_FPS) * 10) / TARGET_FPS)
+    } else {
+        time
+    }
+}
+
+/// fastest time of each map, one `<frames> <map>` line per map
+pub(crate) struct BestTimes {
+    path: PathBuf,
+    times: BTreeMap<String, u64>,
+}
+
+impl BestTimes {
+    /// no times yet, saved to `path` once there are
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            times: BTreeMap::new(),
+        }
+    }
+
+    /// read the times saved at `path`, none when there is no file yet
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let mut times = BTreeMap::new();
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                for line in text.lines().filter(|line| !line.is_empty()) {
+                    let (frames, map) = line
+                        .split_once(' ')
+                        .with_context(|| format!("invalid best time: {line}"))?;
+                    times.insert(map.to_string(), frames.parse()?);
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(Self { path, times })
+    }
+
+    pub fn get(&self, map: &str) -> Option<u64> {
+        self.times.get(map).copied()
+    }
+
+    /// keep a time if it beats the best one and save, returns whether it did
+    pub fn record(&mut self, map: &str, frames: u64) -> anyhow::Result<bool> {
+        if self.get(map).is_some_and(|best| best <= frames) {
+            return Ok(false);
+        }
+
+        self.times.insert(map.to_string(), frames);
+        let text = self
+            .times
+            .iter()
+            .map(|(map, frames)| format!("{frames} {map}\n"))
+            .collect::<String>();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, text)?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clocks_count_minutes_and_seconds() {
+        assert_eq!(clock(0, false), "0:00");
+        assert_eq!(clock((TARGET_FPS * 75) + (TARGET_FPS / 2), true), "1:15.5");
+    }
+
+    #[test]
+    fn best_times_are_kept_between_runs() {
+        let path = std::env::temp_dir().join(format!("yaw-best-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut times = BestTimes::load(path.clone()).unwrap();
+        assert_eq!(times.get("map/map.yaw"), None);
+        assert!(times.record("map/map.yaw", 300).unwrap());
+        assert!(!times.record("map/map.yaw", 400).unwrap());
+        assert!(times.record("map/my map.yaw", 50).unwrap());
+
+        let times = BestTimes::load(path.clone()).unwrap();
+        assert_eq!(times.get("map/map.yaw"), Some(300));
+        assert_eq!(times.get("map/my map.yaw"), Some(50));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,35 @@
+//! directories for files that only matter while something runs, like maps written by tests
+
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// directories made by this process so far, so each one is new
+static MADE: AtomicUsize = AtomicUsize::new(0);
+
+/// an empty directory of its own, removed with everything in it once dropped
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// make a directory in the system temp directory, `name` says what it is for
+    pub fn new(name: &str) -> anyhow::Result<Self> {
+        let made = MADE.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("yaw-{name}-{}-{made}", std::process::id()));
+        // left over from an earlier run that had the same process id
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
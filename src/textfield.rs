@@ -0,0 +1,129 @@
+//! a line of typed text with a cursor, for the console, seeds and anything else taking text
+//!
+//! characters come from text input events through [`TextField::type_text`], editing keys
+//! through [`TextField::key`]
+
+use sdl2::keyboard::Keycode;
+
+pub(crate) struct TextField {
+    text: String,
+    /// position of the cursor in characters
+    cursor: usize,
+    /// most characters the text can have
+    max_len: usize,
+    /// whether a typed character is kept, others are dropped
+    accept: fn(char) -> bool,
+}
+
+impl TextField {
+    pub fn new(max_len: usize, accept: fn(char) -> bool) -> Self {
+        Self {
+            text: String::new(),
+            cursor: 0,
+            max_len,
+            accept,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// replace the text, with the cursor after it
+    pub fn set(&mut self, text: &str) {
+        self.clear();
+        self.type_text(text);
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// the text, leaving the field empty
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    /// byte offset of a character position
+    fn offset(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(offset, _)| offset)
+    }
+
+    /// insert typed text at the cursor, as much of it as is accepted and fits
+    pub fn type_text(&mut self, text: &str) {
+        for ch in text.chars().filter(|ch| (self.accept)(*ch)) {
+            if self.text.chars().count() >= self.max_len {
+                break;
+            }
+            let offset = self.offset(self.cursor);
+            self.text.insert(offset, ch);
+            self.cursor += 1;
+        }
+    }
+
+    /// move the cursor or delete, returns whether the key did anything to the field
+    pub fn key(&mut self, key: Keycode) -> bool {
+        let len = self.text.chars().count();
+        match key {
+            Keycode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let offset = self.offset(self.cursor);
+                self.text.remove(offset);
+            }
+            Keycode::Delete if self.cursor < len => {
+                let offset = self.offset(self.cursor);
+                self.text.remove(offset);
+            }
+            Keycode::Left if self.cursor > 0 => self.cursor -= 1,
+            Keycode::Right if self.cursor < len => self.cursor += 1,
+            Keycode::Home => self.cursor = 0,
+            Keycode::End => self.cursor = len,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// the text with `cursor` drawn where the cursor is
+    pub fn with_cursor(&self, cursor: char) -> String {
+        let mut text = self.text.clone();
+        text.insert(self.offset(self.cursor), cursor);
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_at_the_cursor() {
+        let mut field = TextField::new(6, |ch| ch.is_ascii_digit() || ch == 'é');
+        field.type_text("12a3");
+        assert_eq!(field.text(), "123");
+
+        field.key(Keycode::Left);
+        field.key(Keycode::Left);
+        field.type_text("é");
+        assert_eq!(field.with_cursor('_'), "1é_23");
+        assert!(field.key(Keycode::Delete));
+        assert!(field.key(Keycode::Backspace));
+        assert_eq!(field.with_cursor('_'), "1_3");
+
+        // full fields drop the rest
+        field.key(Keycode::End);
+        field.type_text("456789");
+        assert_eq!(field.text(), "134567");
+
+        field.key(Keycode::Home);
+        assert!(!field.key(Keycode::Backspace));
+        assert!(!field.key(Keycode::Return));
+        assert_eq!(field.take(), "134567");
+        assert_eq!(field.with_cursor('_'), "_");
+    }
+}
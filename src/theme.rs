@@ -0,0 +1,399 @@
+//! how text, menus and the hud look, read from a toml file so the ui can be restyled
+//!
+//! a theme is a few `key = value` lines, with `[status_bar]` and `[minimap]` sections for
+//! those, colors are written `"#rrggbb"` or `"#rrggbbaa"` and pairs `[x, y]`. whatever a theme
+//! leaves out looks as it does by default
+
+use crate::hud::{Field, MinimapStyle, StatusBar};
+use anyhow::Context;
+use std::path::Path;
+use std::str::FromStr;
+use yaw_core::assets;
+use yaw_core::color::{parse_hex_color, Color};
+use yaw_core::render::TextStyle;
+
+/// where the theme is looked up in the data directories
+const PATH: &str = "theme.toml";
+/// what stands out in high contrast themes, against black and white
+const HIGHLIGHT: Color = Color::RGB(0xff, 0xdd, 0);
+
+/// colors swapped into the theme for players who tell some colors apart poorly
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub(crate) enum Palette {
+    /// the colors of the theme
+    #[default]
+    Theme,
+    /// no greens against reds
+    Deuteranopia,
+    Protanopia,
+    /// no blues against yellows
+    Tritanopia,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [
+        Palette::Theme,
+        Palette::Deuteranopia,
+        Palette::Protanopia,
+        Palette::Tritanopia,
+    ];
+
+    /// how it is saved
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Theme => "theme",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Protanopia => "protanopia",
+            Palette::Tritanopia => "tritanopia",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|palette| palette.name() == name)
+    }
+
+    /// language key of its name
+    pub fn key(self) -> &'static str {
+        match self {
+            Palette::Theme => "palette.theme",
+            Palette::Deuteranopia => "palette.deuteranopia",
+            Palette::Protanopia => "palette.protanopia",
+            Palette::Tritanopia => "palette.tritanopia",
+        }
+    }
+
+    /// the next palette, for menus that cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            Palette::Theme => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Protanopia,
+            Palette::Protanopia => Palette::Tritanopia,
+            Palette::Tritanopia => Palette::Theme,
+        }
+    }
+
+    /// theme lines setting its colors, built in
+    fn preset(self) -> Option<&'static str> {
+        match self {
+            Palette::Theme => None,
+            Palette::Deuteranopia => Some(include_str!("../palettes/deuteranopia.toml")),
+            Palette::Protanopia => Some(include_str!("../palettes/protanopia.toml")),
+            Palette::Tritanopia => Some(include_str!("../palettes/tritanopia.toml")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HudTheme {
+    /// color of titles and text
+    pub text: Color,
+    pub title_size: u16,
+    pub text_size: u16,
+    /// smooth the edges of text, which takes longer to draw
+    pub blended: bool,
+    /// behind text shown over the view, like messages
+    pub background: Color,
+    /// around text in boxes, across and down
+    pub padding: (u32, u32),
+    /// space between the edges of the screen and titles and text
+    pub margin: i32,
+    /// where the first option of menus and lines of screens are, and how far apart
+    pub top: i32,
+    pub line_height: i32,
+    /// text and box of the selected menu option
+    pub selected: Color,
+    pub selected_background: Color,
+    pub status_bar: StatusBar,
+    pub minimap: MinimapStyle,
+}
+
+impl Default for HudTheme {
+    fn default() -> Self {
+        Self {
+            text: Color::GREEN,
+            title_size: 24,
+            text_size: 16,
+            blended: false,
+            background: Color::BLACK,
+            padding: (8, 4),
+            margin: 16,
+            top: 64,
+            line_height: 28,
+            selected: Color::BLACK,
+            selected_background: Color::GREEN,
+            status_bar: StatusBar::default(),
+            minimap: MinimapStyle::default(),
+        }
+    }
+}
+
+impl HudTheme {
+    /// the theme at `path`, or else in the data directories, or the default one
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let path = assets::resolve(Path::new(PATH));
+                if !path.exists() {
+                    return Ok(Self::default());
+                }
+                path
+            }
+        };
+        let theme = std::fs::read_to_string(&path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        Self::parse(&theme).with_context(|| format!("could not parse {}", path.display()))
+    }
+
+    pub fn parse(theme: &str) -> anyhow::Result<Self> {
+        let mut this = Self::default();
+        this.apply(theme)?;
+        Ok(this)
+    }
+
+    /// change what a theme sets, keeping the rest
+    fn apply(&mut self, theme: &str) -> anyhow::Result<()> {
+        let mut section = String::new();
+        for (idx, line) in theme.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = format!("{}.", name.trim());
+                continue;
+            }
+            self.set(&section, line)
+                .with_context(|| format!("line {}: {line}", idx + 1))?;
+        }
+
+        Ok(())
+    }
+
+    /// the same theme in the colors of a palette
+    pub fn with_palette(&self, palette: Palette) -> anyhow::Result<Self> {
+        let mut this = self.clone();
+        if let Some(preset) = palette.preset() {
+            this.apply(preset)
+                .with_context(|| format!("could not apply palette {}", palette.name()))?;
+        }
+        Ok(this)
+    }
+
+    /// apply one `key = value` line of a section
+    fn set(&mut self, section: &str, line: &str) -> anyhow::Result<()> {
+        let (key, value) = line.split_once('=').context("expected key = value")?;
+        let (key, value) = (format!("{section}{}", key.trim()), value.trim());
+
+        match key.as_str() {
+            "text" => self.text = color(value)?,
+            "title_size" => self.title_size = number(value)?,
+            "text_size" => self.text_size = number(value)?,
+            "blended" => self.blended = number(value)?,
+            "background" => self.background = color(value)?,
+            "padding" => self.padding = pair(value)?,
+            "margin" => self.margin = number(value)?,
+            "top" => self.top = number(value)?,
+            "line_height" => self.line_height = number(value)?,
+            "selected" => self.selected = color(value)?,
+            "selected_background" => self.selected_background = color(value)?,
+            "status_bar.height" => self.status_bar.height = number(value)?,
+            "status_bar.background" => self.status_bar.background = color(value)?,
+            "status_bar.label" => self.status_bar.label = color(value)?,
+            "status_bar.value" => self.status_bar.value = color(value)?,
+            "status_bar.label_size" => self.status_bar.label_size = number(value)?,
+            "status_bar.value_size" => self.status_bar.value_size = number(value)?,
+            "minimap.background" => self.minimap.background = color(value)?,
+            "minimap.wall" => self.minimap.wall = color(value)?,
+            "minimap.ray" => self.minimap.ray = color(value)?,
+            "minimap.cone" => self.minimap.cone = color(value)?,
+            // where a field of the status bar is, from and to which fraction of its width
+            _ => {
+                let field = key
+                    .strip_prefix("status_bar.")
+                    .and_then(Field::from_name)
+                    .with_context(|| format!("unknown key: {key}"))?;
+                let (from, to) = pair::<f32>(value)?;
+                anyhow::ensure!(
+                    (0. ..=1.).contains(&from) && from < to && to <= 1.,
+                    "fields go from and to a fraction of the bar"
+                );
+                self.status_bar.slots.retain(|(slot, ..)| *slot != field);
+                self.status_bar.slots.push((field, from, to));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the same theme in bigger text and colors that stand out more, for players who have
+    /// trouble reading it
+    pub fn high_contrast(&self) -> Self {
+        let bigger = |size: u16| size + (size / 4);
+        Self {
+            text: Color::WHITE,
+            title_size: bigger(self.title_size),
+            text_size: bigger(self.text_size),
+            background: Color::BLACK,
+            line_height: self.line_height + (bigger(self.text_size) - self.text_size) as i32,
+            selected: Color::BLACK,
+            selected_background: HIGHLIGHT,
+            status_bar: StatusBar {
+                height: self.status_bar.height + 8,
+                background: Color::BLACK,
+                label: Color::WHITE,
+                value: HIGHLIGHT,
+                label_size: bigger(self.status_bar.label_size),
+                value_size: bigger(self.status_bar.value_size),
+                ..self.status_bar.clone()
+            },
+            minimap: MinimapStyle {
+                background: Color::RGBA(0, 0, 0, 0xdd),
+                wall: Color::WHITE,
+                ray: HIGHLIGHT,
+                cone: Color::RGBA(0xff, 0xdd, 0, 0x80),
+            },
+            ..self.clone()
+        }
+    }
+
+    /// titles at the top of screens
+    pub fn title(&self) -> TextStyle {
+        self.style(self.title_size, self.text).italic()
+    }
+
+    pub fn body(&self) -> TextStyle {
+        self.style(self.text_size, self.text)
+    }
+
+    pub fn style(&self, size: u16, color: Color) -> TextStyle {
+        let style = TextStyle::new(size, color);
+        if self.blended {
+            style.blended()
+        } else {
+            style
+        }
+    }
+
+    /// widest text in a box can be to fit between the margins of an area `width` wide
+    pub fn wrap_width(&self, width: u32) -> u32 {
+        width.saturating_sub((self.margin as u32 + self.padding.0) * 2)
+    }
+
+    /// text over the view, in a box so it can be read on anything
+    pub fn boxed(&self) -> TextStyle {
+        self.body().boxed(self.background, self.padding)
+    }
+}
+
+/// a quoted hex color
+fn color(value: &str) -> anyhow::Result<Color> {
+    let hex = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .context("colors must be quoted")?;
+    parse_hex_color(hex)
+}
+
+fn number<T: FromStr>(value: &str) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    Ok(value.parse()?)
+}
+
+/// `[a, b]`
+fn pair<T: FromStr>(value: &str) -> anyhow::Result<(T, T)>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let (a, b) = value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .and_then(|value| value.split_once(','))
+        .context("expected [a, b]")?;
+    Ok((number(a.trim())?, number(b.trim())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_change_only_what_they_set() {
+        let theme = HudTheme::parse(
+            "# amber
+            text = \"#ffb000\"
+            padding = [6, 2]
+            blended = true
+
+            [status_bar]
+            height = 40
+            health = [0.5, 0.6]
+
+            [minimap]
+            cone = \"#ffb00040\"",
+        )
+        .unwrap();
+        assert_eq!(theme.text, Color::RGB(0xff, 0xb0, 0));
+        assert_eq!(theme.padding, (6, 2));
+        assert!(theme.body().blended);
+        assert_eq!(theme.title_size, HudTheme::default().title_size);
+        assert_eq!(theme.status_bar.height, 40);
+        assert_eq!(
+            theme.status_bar.slots.last(),
+            Some(&(Field::Health, 0.5, 0.6))
+        );
+        assert_eq!(
+            theme.status_bar.slots.len(),
+            StatusBar::default().slots.len()
+        );
+        assert_eq!(theme.minimap.cone, Color::RGBA(0xff, 0xb0, 0, 0x40));
+
+        for line in [
+            "text = #ffb000",
+            "title_size = big",
+            "padding = 8",
+            "blended = yes",
+            "[status_bar]\nhealth = [0.6, 0.5]",
+            "[minimap]\nheight = 1",
+        ] {
+            assert!(HudTheme::parse(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn high_contrast_is_bigger_and_brighter() {
+        let theme = HudTheme::default();
+        let high_contrast = theme.high_contrast();
+        assert!(high_contrast.text_size > theme.text_size);
+        assert!(high_contrast.status_bar.value_size > theme.status_bar.value_size);
+        assert_eq!(high_contrast.text, Color::WHITE);
+        assert_eq!(high_contrast.status_bar.slots, theme.status_bar.slots);
+    }
+
+    #[test]
+    fn palettes_change_only_colors() {
+        let theme = HudTheme::parse("text_size = 20").unwrap();
+        for palette in Palette::ALL {
+            assert_eq!(Palette::from_name(palette.name()), Some(palette));
+            let colored = theme.with_palette(palette).unwrap();
+            assert_eq!(colored.text_size, 20);
+            assert_eq!(colored.status_bar.slots, theme.status_bar.slots);
+            if palette != Palette::Theme {
+                assert_ne!(colored.minimap.wall, theme.minimap.wall, "{palette:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn example_themes_parse() {
+        for entry in std::fs::read_dir("themes").unwrap() {
+            let path = entry.unwrap().path();
+            HudTheme::load(Some(&path)).unwrap();
+        }
+    }
+}
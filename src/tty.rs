@@ -0,0 +1,369 @@
+//! draws frames as colored characters in the terminal, with input read from it too
+
+use crate::cli::Args;
+use crate::game::{Game, GameState};
+use crate::pacing::Pacer;
+use crate::{HEIGHT, TARGET_FPS, WIDTH};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color as TtyColor, Print, SetBackgroundColor, SetForegroundColor};
+use crossterm::{cursor, terminal, QueueableCommand};
+use sdl2::keyboard::Keycode;
+use std::collections::{HashMap, HashSet};
+use std::io::{Stdout, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use yaw_core::color::Color;
+use yaw_core::framebuffer::Image;
+use yaw_core::render::{wrap_lines, Column, Rect, Renderer, TextStyle};
+
+/// characters for walls, from far away to close by
+const SHADES: &[char] = &['.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// terminals only report presses, keys count as held for this many frames after one
+const HOLD_FRAMES: u64 = TARGET_FPS / 6;
+
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    ch: char,
+    fg: Color,
+    bg: Color,
+}
+
+impl Cell {
+    const BLANK: Self = Self {
+        ch: ' ',
+        fg: Color::WHITE,
+        bg: Color::BLACK,
+    };
+}
+
+fn tty_color(color: Color) -> TtyColor {
+    TtyColor::Rgb {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+    }
+}
+
+/// rasterizes into a grid of characters, scaled down from screen coordinates
+pub(crate) struct TtyRenderer {
+    stdout: Stdout,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+}
+
+impl TtyRenderer {
+    pub fn new() -> anyhow::Result<Self> {
+        let mut stdout = std::io::stdout();
+        terminal::enable_raw_mode()?;
+        stdout
+            .queue(terminal::EnterAlternateScreen)?
+            .queue(cursor::Hide)?
+            .flush()?;
+
+        let mut this = Self {
+            stdout,
+            cols: 0,
+            rows: 0,
+            cells: Vec::new(),
+        };
+        this.resize()?;
+
+        Ok(this)
+    }
+
+    /// match the grid to the terminal size
+    fn resize(&mut self) -> anyhow::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        self.cols = (cols as usize).max(1);
+        self.rows = (rows as usize).max(1);
+        self.cells = vec![Cell::BLANK; self.cols * self.rows];
+
+        Ok(())
+    }
+
+    fn col(&self, x: i32) -> i32 {
+        ((x as i64 * self.cols as i64) / WIDTH as i64) as i32
+    }
+
+    fn row(&self, y: i32) -> i32 {
+        ((y as i64 * self.rows as i64) / HEIGHT as i64) as i32
+    }
+
+    fn cell(&mut self, col: i32, row: i32) -> Option<&mut Cell> {
+        if (0..self.cols as i32).contains(&col) && (0..self.rows as i32).contains(&row) {
+            Some(&mut self.cells[(row as usize * self.cols) + col as usize])
+        } else {
+            None
+        }
+    }
+
+    /// width of a line of text in screen coordinates, a cell per character
+    fn text_width(&self, line: &str) -> u32 {
+        line.chars().count() as u32 * WIDTH.div_ceil(self.cols) as u32
+    }
+
+    fn lines<'a>(&self, text: &'a str, style: &TextStyle) -> Vec<&'a str> {
+        wrap_lines(text, style, |line| self.text_width(line))
+    }
+}
+
+impl Drop for TtyRenderer {
+    fn drop(&mut self) {
+        let _ = self
+            .stdout
+            .queue(SetBackgroundColor(TtyColor::Reset))
+            .and_then(|stdout| stdout.queue(cursor::Show))
+            .and_then(|stdout| stdout.queue(terminal::LeaveAlternateScreen))
+            .and_then(|stdout| stdout.flush());
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Renderer for TtyRenderer {
+    fn draw_column(&mut self, column: &Column) -> anyhow::Result<()> {
+        // taller columns are closer
+        let near = (column.height as f32 / HEIGHT as f32).clamp(0., 1.);
+        let ch = SHADES[(near * (SHADES.len() - 1) as f32) as usize];
+        let fg = column.overlay.blend_over(column.tint);
+
+        let (left, right) = (
+            self.col(column.x),
+            self.col(column.x + column.width as i32 - 1),
+        );
+        let top = self.row(column.top);
+        let bottom = self.row(column.top + column.height as i32);
+        for col in left..=right {
+            for row in top..=bottom {
+                if let Some(cell) = self.cell(col, row) {
+                    *cell = Cell {
+                        ch,
+                        fg,
+                        bg: Color::BLACK,
+                    };
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: Option<Rect>, color: Color) -> anyhow::Result<()> {
+        let (left, top, right, bottom) = match rect {
+            Some(rect) => (
+                self.col(rect.x),
+                self.row(rect.y),
+                self.col(rect.right()),
+                self.row(rect.bottom()),
+            ),
+            None => (0, 0, self.cols as i32, self.rows as i32),
+        };
+
+        for row in top..bottom.max(top + 1) {
+            for col in left..right.max(left + 1) {
+                if let Some(cell) = self.cell(col, row) {
+                    cell.bg = color.blend_over(cell.bg);
+                    if color.a == 0xff {
+                        cell.ch = ' ';
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) -> anyhow::Result<()> {
+        let (from, to) = (
+            (self.col(from.0), self.row(from.1)),
+            (self.col(to.0), self.row(to.1)),
+        );
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let steps = dx.abs().max(dy.abs()).max(1);
+        for step in 0..=steps {
+            if let Some(cell) =
+                self.cell(from.0 + (dx * step) / steps, from.1 + (dy * step) / steps)
+            {
+                cell.ch = '*';
+                cell.fg = color;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_image(&mut self, _path: &Path, _rect: Option<Rect>) -> anyhow::Result<()> {
+        // images are too detailed for a handful of characters
+        Ok(())
+    }
+
+    fn draw_text(&mut self, text: &str, style: &TextStyle, x: i32, y: i32) -> anyhow::Result<Rect> {
+        let col = self.col(x + style.padding.0 as i32);
+        let row = self.row(y + style.padding.1 as i32);
+        for (line_idx, line) in self.lines(text, style).iter().enumerate() {
+            for (idx, ch) in line.chars().enumerate() {
+                if let Some(cell) = self.cell(col + idx as i32, row + line_idx as i32) {
+                    cell.ch = ch;
+                    cell.fg = style.fg;
+                    if let Some(bg) = style.bg {
+                        cell.bg = bg.blend_over(cell.bg);
+                    }
+                }
+            }
+        }
+
+        let (width, height) = self.measure_text(text, style)?;
+        Ok(Rect::new(x, y, width, height))
+    }
+
+    /// the area in screen coordinates, a cell high per line
+    fn measure_text(&mut self, text: &str, style: &TextStyle) -> anyhow::Result<(u32, u32)> {
+        let lines = self.lines(text, style);
+        let width = lines
+            .iter()
+            .map(|line| self.text_width(line))
+            .max()
+            .unwrap_or(0);
+        let cell_height = HEIGHT.div_ceil(self.rows) as u32;
+        Ok((
+            width + (style.padding.0 * 2),
+            (cell_height * lines.len() as u32) + (style.padding.1 * 2),
+        ))
+    }
+
+    fn capture(&mut self) -> anyhow::Result<Image> {
+        // one pixel per cell, in the color of its background
+        Ok(Image {
+            width: self.cols,
+            height: self.rows,
+            pixels: self.cells.iter().map(|cell| cell.bg.to_argb()).collect(),
+        })
+    }
+
+    fn present(&mut self) -> anyhow::Result<()> {
+        self.stdout.queue(cursor::MoveTo(0, 0))?;
+        let mut colors = None;
+        for (row, cells) in self.cells.chunks_exact(self.cols).enumerate() {
+            self.stdout.queue(cursor::MoveTo(0, row as u16))?;
+            for cell in cells {
+                if colors != Some((cell.fg, cell.bg)) {
+                    colors = Some((cell.fg, cell.bg));
+                    self.stdout
+                        .queue(SetForegroundColor(tty_color(cell.fg)))?
+                        .queue(SetBackgroundColor(tty_color(cell.bg)))?;
+                }
+                self.stdout.queue(Print(cell.ch))?;
+            }
+        }
+        self.stdout.flush()?;
+
+        // pick up terminal resizes for the next frame
+        let (cols, rows) = terminal::size()?;
+        if (cols as usize, rows as usize) != (self.cols, self.rows) {
+            self.resize()?;
+        } else {
+            self.cells.fill(Cell::BLANK);
+        }
+
+        Ok(())
+    }
+
+    fn preload(&mut self, _path: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn clear_cache(&mut self) {}
+
+    fn set_title(&mut self, title: &str) -> anyhow::Result<()> {
+        self.stdout.queue(terminal::SetTitle(title))?;
+        Ok(())
+    }
+
+    fn flash(&mut self) -> anyhow::Result<()> {
+        // the bell, which terminals show by flashing or marking their window
+        self.stdout.queue(Print('\x07'))?;
+        Ok(())
+    }
+}
+
+/// terminal key to the sdl key the game expects
+fn keycode(code: KeyCode) -> Option<Keycode> {
+    match code {
+        KeyCode::Up => Some(Keycode::Up),
+        KeyCode::Down => Some(Keycode::Down),
+        KeyCode::Left => Some(Keycode::Left),
+        KeyCode::Right => Some(Keycode::Right),
+        KeyCode::Enter => Some(Keycode::Return),
+        KeyCode::Esc => Some(Keycode::Escape),
+        KeyCode::Backspace => Some(Keycode::Backspace),
+        KeyCode::Tab => Some(Keycode::Tab),
+        KeyCode::F(12) => Some(Keycode::F12),
+        // printable keys share their codes with ascii
+        KeyCode::Char(ch) if ch.is_ascii() => Keycode::from_i32(ch.to_ascii_lowercase() as i32),
+        _ => None,
+    }
+}
+
+/// play in the terminal until the game exits or ctrl+c is pressed
+pub(crate) fn run(args: &Args) -> anyhow::Result<()> {
+    let mut game = Game::new(Box::new(TtyRenderer::new()?), None, args, None)?;
+    let mut pacer = Pacer::new(args.frame_rate);
+    // frames left until each key counts as released
+    let mut held = HashMap::<Keycode, u64>::new();
+
+    loop {
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => {
+                    if key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL)
+                    {
+                        return Ok(());
+                    }
+
+                    let shift = key.modifiers.contains(KeyModifiers::SHIFT)
+                        || matches!(key.code, KeyCode::Char(ch) if ch.is_ascii_uppercase());
+                    let keys = keycode(key.code)
+                        .into_iter()
+                        .chain(shift.then_some(Keycode::LShift));
+                    for k in keys {
+                        // repeats only keep the key held
+                        if held.insert(k, HOLD_FRAMES).is_none() {
+                            game.key_pressed(k);
+                        }
+                    }
+                    if let KeyCode::Char(ch) = key.code {
+                        game.text_input(&ch.to_string());
+                    }
+                }
+                Event::Resize(..) => game.update = true,
+                _ => {}
+            }
+        }
+
+        for _ in 0..pacer.ticks(Instant::now()) {
+            game.keys_held(&held.keys().copied().collect::<HashSet<_>>());
+            game.tick();
+            held.retain(|k, frames| {
+                *frames -= 1;
+                if *frames == 0 {
+                    game.key_up(*k);
+                }
+                *frames > 0
+            });
+        }
+        if game.game_state == GameState::Exit {
+            return Ok(());
+        }
+
+        let drew = game.update || pacer.redraws();
+        if drew {
+            game.draw()?;
+            game.renderer.present()?;
+            game.update_title();
+            game.update = game.animating();
+        }
+        pacer.wait(drew);
+    }
+}
@@ -0,0 +1,185 @@
+//! files kept in memory instead of on disk, like textures embedded in compiled maps
+//! or the contents of `.yawpak` archives, and where files on disk are looked for
+//!
+//! relative paths are looked up in `YAW_DATA`, the directory of the executable, `yaw` in each
+//! XDG data directory and the working directory, in that order, so the game runs from anywhere
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+static FILES: Mutex<BTreeMap<PathBuf, Arc<[u8]>>> = Mutex::new(BTreeMap::new());
+
+/// environment variable naming a directory searched for data before any other
+pub const DATA_ENV: &str = "YAW_DATA";
+/// name of the directory in XDG data directories
+const DATA_NAME: &str = "yaw";
+
+/// serve `bytes` for `path` from now on, instead of whatever is on disk
+pub fn insert(path: impl Into<PathBuf>, bytes: impl Into<Arc<[u8]>>) {
+    FILES.lock().unwrap().insert(path.into(), bytes.into());
+}
+
+/// read a file from memory if it was inserted, from disk otherwise
+pub fn read(path: &Path) -> std::io::Result<Arc<[u8]>> {
+    if let Some(bytes) = FILES.lock().unwrap().get(path) {
+        return Ok(bytes.clone());
+    }
+
+    std::fs::read(resolve(path)).map(Into::into)
+}
+
+/// where the files of the game are looked for, in order
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::env::var_os(DATA_ENV)
+        .map(Into::into)
+        .into_iter()
+        .collect();
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(dir);
+    }
+    dirs.extend(data_home());
+    let system = std::env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    dirs.extend(std::env::split_paths(&system).map(|dir| dir.join(DATA_NAME)));
+    dirs.push(PathBuf::new());
+
+    dirs
+}
+
+/// directory for data of the user, like `~/.local/share/yaw`
+fn data_home() -> Option<PathBuf> {
+    let home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".local/share")))?;
+    Some(home.join(DATA_NAME))
+}
+
+/// the first of `dirs` that has a relative path in it
+fn find(path: &Path, dirs: &[PathBuf]) -> Option<PathBuf> {
+    dirs.iter()
+        .map(|dir| dir.join(path))
+        .find(|candidate| candidate.exists())
+}
+
+/// where a file or directory is on disk, absolute paths and ones found nowhere stay as they are
+pub fn resolve(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.into();
+    }
+    find(path, &data_dirs()).unwrap_or_else(|| path.into())
+}
+
+/// where a file the game writes is kept, wherever it already is, or else in `YAW_DATA` or the
+/// XDG data home, whose directory might not exist yet
+pub fn save_path(name: &Path) -> PathBuf {
+    if let Some(found) = find(name, &data_dirs()) {
+        return found;
+    }
+    std::env::var_os(DATA_ENV)
+        .map(PathBuf::from)
+        .or_else(data_home)
+        .map_or_else(|| name.into(), |dir| dir.join(name))
+}
+
+/// start of archives written by [`pack`]
+pub const PAK_MAGIC: &[u8] = b"YAWP";
+/// bumped whenever the layout of archives changes
+const PAK_VERSION: u8 = 1;
+
+/// what an archive holds after its header
+#[derive(Serialize, Deserialize)]
+struct Pak {
+    /// files relative to the archive
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// bundle files into one archive, paths are kept as given
+pub fn pack(files: Vec<(PathBuf, Vec<u8>)>) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = PAK_MAGIC.to_vec();
+    bytes.push(PAK_VERSION);
+    bytes.extend(postcard::to_stdvec(&Pak { files })?);
+
+    Ok(bytes)
+}
+
+/// serve every file of the archive at `path` as if the archive were a directory,
+/// `path/map.yaw` reads `map.yaw` from inside it
+pub fn mount(path: &Path, archive: &[u8]) -> anyhow::Result<()> {
+    let Some([version, body @ ..]) = archive.strip_prefix(PAK_MAGIC) else {
+        anyhow::bail!("{} is not an archive", path.display());
+    };
+    anyhow::ensure!(
+        *version == PAK_VERSION,
+        "archive was packed by another version, version {version} instead of {PAK_VERSION}"
+    );
+
+    let Pak { files } = postcard::from_bytes(body)?;
+    for (file, bytes) in files {
+        insert(path.join(file), bytes);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_comes_before_disk() {
+        let path = Path::new("assets/test/inserted.png");
+        assert!(read(path).is_err());
+        insert(path, b"png".as_slice());
+        assert_eq!(&*read(path).unwrap(), b"png");
+    }
+
+    #[test]
+    fn relative_paths_are_found_in_the_first_data_dir_with_them() {
+        let root = std::env::temp_dir().join(format!("yaw-data-{}", std::process::id()));
+        let dirs = [root.join("first"), root.join("second"), root.join("third")];
+        for dir in &dirs[1..] {
+            std::fs::create_dir_all(dir.join("map")).unwrap();
+            std::fs::write(dir.join("map/map.yaw"), "map").unwrap();
+        }
+
+        assert_eq!(
+            find(Path::new("map/map.yaw"), &dirs),
+            Some(dirs[1].join("map/map.yaw"))
+        );
+        assert_eq!(find(Path::new("map"), &dirs), Some(dirs[1].join("map")));
+        assert_eq!(find(Path::new("map/other.yaw"), &dirs), None);
+        assert_eq!(resolve(&dirs[0].join("x.png")), dirs[0].join("x.png"));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn archives_mount_like_directories() {
+        let archive = pack(vec![
+            ("map.yaw".into(), b"map".to_vec()),
+            ("tex/wall.png".into(), b"wall".to_vec()),
+        ])
+        .unwrap();
+        mount(Path::new("assets/test.yawpak"), &archive).unwrap();
+        assert_eq!(
+            &*read(Path::new("assets/test.yawpak/map.yaw")).unwrap(),
+            b"map"
+        );
+        assert_eq!(
+            &*read(Path::new("assets/test.yawpak/tex/wall.png")).unwrap(),
+            b"wall"
+        );
+
+        assert!(mount(Path::new("assets/other.yawpak"), b"map").is_err());
+        let mut newer = archive.clone();
+        newer[PAK_MAGIC.len()] += 1;
+        assert!(mount(Path::new("assets/other.yawpak"), &newer).is_err());
+    }
+}
@@ -0,0 +1,756 @@
+use crate::color::Color;
+use crate::map::{Map, Meta, TILE_SIZE};
+use crate::ray::{cast_lanes, Cardinal, RayCast, LANES};
+use crate::render::{Column, Rect, Renderer};
+use glam::Vec2;
+use std::path::PathBuf;
+
+/// flat image in the world that always faces the camera
+#[derive(Clone, PartialEq, Debug)]
+pub struct Sprite {
+    pub pos: Vec2,
+    /// image file, transparent pixels are skipped
+    pub texture: PathBuf,
+}
+
+/// distance to the wall of each column along the view direction, so whatever is drawn over
+/// walls can be hidden column by column
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct ZBuffer(pub Vec<f32>);
+
+impl ZBuffer {
+    /// whether the wall of a column is in front of something `depth` away,
+    /// columns outside of the buffer hide everything
+    pub fn hides(&self, column: usize, depth: f32) -> bool {
+        self.0.get(column).is_none_or(|wall| *wall < depth)
+    }
+
+    /// [`ZBuffer::hides`] for a pixel column of a view `width` pixels wide, which might have
+    /// been cast at a lower resolution than that
+    pub fn hides_pixel(&self, x: usize, width: usize, depth: f32) -> bool {
+        self.hides((x * self.len()) / width.max(1), depth)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// where each column of a view looks, kept between frames so rays need no trigonometry and
+/// only built again when the fov or width of the view changes
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct ColumnTable {
+    fov: f32,
+    /// angle of each column from the view direction
+    angles: Vec<f32>,
+    /// how far to the side each column looks per pixel forward, the tangent of its angle
+    tans: Vec<f32>,
+}
+
+impl ColumnTable {
+    pub fn new(fov: f32, columns: usize) -> Self {
+        let projection = projection(fov, columns);
+        // evenly spaced across the projection plane, not evenly spaced angles
+        let tans: Vec<f32> = (-(columns as isize) / 2..(columns as isize - (columns as isize / 2)))
+            .map(|ray_number| ray_number as f32 / projection)
+            .collect();
+
+        Self {
+            fov,
+            angles: tans.iter().map(|tan| tan.atan()).collect(),
+            tans,
+        }
+    }
+
+    /// build the table again if the fov or number of columns is not what it was built for
+    pub fn fit(&mut self, fov: f32, columns: usize) {
+        if self.fov != fov || self.tans.len() != columns {
+            *self = Self::new(fov, columns);
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.tans.len()
+    }
+}
+
+/// left edge and width of the pixels column `column` out of `columns` covers in a view `width`
+/// pixels wide
+pub fn column_span(column: usize, columns: usize, width: u32) -> (i32, u32) {
+    let edge = |column: usize| (column * width as usize) / columns.max(1);
+    let (left, right) = (edge(column), edge(column + 1));
+    (left as i32, (right - left) as u32)
+}
+
+/// distance from the eye to a projection plane `columns` wide that spans `fov` degrees
+fn projection(fov: f32, columns: usize) -> f32 {
+    (columns as f32 / 2.) / (fov.to_radians() / 2.).tan()
+}
+
+/// closest a wall is drawn, in pixels
+const MIN_DEPTH: f32 = 1.;
+
+/// how far the flashlight reaches, in tiles
+const FLASHLIGHT_RANGE: f32 = 6.;
+/// part of the view the flashlight cone covers on each side of the center
+const FLASHLIGHT_CONE: f32 = 0.2;
+/// light level of everything outside of the flashlight cone
+const FLASHLIGHT_DARK: u8 = 0x40;
+
+/// drawn over the lower half of walls seen across water
+const WATER_TINT: Color = Color::RGBA(0x20, 0x50, 0xa0, 0x60);
+/// how many pixels walls sway up and down in a wobbling view, and how quickly the wave
+/// changes from column to column
+const WOBBLE_HEIGHT: f32 = 3.;
+const WOBBLE_WAVE: f32 = 0.03;
+
+/// point of view frames are rendered from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Camera {
+    pub pos: Vec2,
+    /// angle in radians, 0 looks east
+    pub direction: f32,
+    /// horizontal field of view in degrees
+    pub fov: f32,
+    /// light up the center of the view and darken the rest
+    pub flashlight: bool,
+    /// phase of a wave walls sway along, like when wading through water, none to hold still
+    pub wobble: Option<f32>,
+    /// how far the eyes are raised above a flat floor, like on stairs, in pixels of the map
+    pub height: f32,
+    /// draw the fog of maps that have some, walls stay clear otherwise
+    pub fog: bool,
+}
+
+impl Camera {
+    pub const DEFAULT_FOV: f32 = 60.;
+
+    pub fn new(pos: Vec2, direction: f32) -> Self {
+        Self {
+            pos,
+            direction,
+            fov: Self::DEFAULT_FOV,
+            flashlight: false,
+            wobble: None,
+            height: 0.,
+            fog: true,
+        }
+    }
+
+    /// flashlight brightness from 0 to 1 for a column of the view and a distance in pixels
+    fn beam(&self, column: usize, columns: usize, distance: f32) -> f32 {
+        if !self.flashlight {
+            return 0.;
+        }
+
+        let from_center = (column as f32 - (columns as f32 / 2.)).abs() / columns as f32;
+        let cone = 1. - (from_center / FLASHLIGHT_CONE);
+        let reach = 1. - (distance / (FLASHLIGHT_RANGE * TILE_SIZE));
+        cone.clamp(0., 1.) * reach.clamp(0., 1.)
+    }
+
+    /// pixels a column of the view is moved down by the wobble
+    fn sway(&self, column: usize) -> i32 {
+        self.wobble.map_or(0, |phase| {
+            (WOBBLE_HEIGHT * ((column as f32 * WOBBLE_WAVE) + phase).sin()).round() as i32
+        })
+    }
+
+    /// pixels something `distance` away moves down the view by the eyes being raised
+    fn lift(&self, projection: f32, distance: f32) -> i32 {
+        ((self.height * projection) / distance).round() as i32
+    }
+
+    /// distance from the camera to a projection plane `columns` wide that spans the fov
+    pub fn projection(&self, columns: usize) -> f32 {
+        projection(self.fov, columns)
+    }
+
+    /// cast one ray per screen column, left to right
+    pub fn cast(&self, map: &Map, columns: usize) -> Vec<RayCast> {
+        self.cast_with(map, &ColumnTable::new(self.fov, columns))
+    }
+
+    /// [`Camera::cast`] with the columns of a table kept between frames
+    pub fn cast_with(&self, map: &Map, table: &ColumnTable) -> Vec<RayCast> {
+        let forward = Vec2::from_angle(self.direction);
+        let side = forward.perp();
+        let mut slices = Vec::with_capacity(table.columns() + LANES);
+        for (angles, tans) in table.angles.chunks(LANES).zip(table.tans.chunks(LANES)) {
+            // the last rays are marched along with ones that look nowhere, which are left out
+            let dirs = std::array::from_fn(|lane| {
+                tans.get(lane)
+                    .map_or(Vec2::ZERO, |tan| forward + (side * *tan))
+            });
+            let angles = std::array::from_fn(|lane| {
+                self.direction + angles.get(lane).copied().unwrap_or_default()
+            });
+            slices.extend(cast_lanes(map, self.pos, dirs, angles));
+        }
+        slices.truncate(table.columns());
+        slices
+    }
+
+    /// where a position shows up in a view `columns` wide, as the column of its center and
+    /// its distance along the view direction, none when it is behind the camera
+    pub fn project(&self, pos: Vec2, columns: usize) -> Option<(f32, f32)> {
+        let forward = Vec2::from_angle(self.direction);
+        let offset = pos - self.pos;
+
+        // distance along the view direction, and to the side of it
+        let distance = offset.dot(forward);
+        if distance <= 0. {
+            return None;
+        }
+        let side = offset.dot(forward.perp());
+
+        let center = ((side / distance) * self.projection(columns)) + (columns / 2) as f32;
+        Some((center, distance))
+    }
+
+    /// distance of a ray's hit along the view direction, which keeps walls from bulging
+    fn depth_of(&self, slice: &RayCast) -> f32 {
+        // standing on the edge of a wall hits it at no distance, which would make it
+        // infinitely tall
+        slice
+            .vec
+            .dot(Vec2::from_angle(self.direction))
+            .max(MIN_DEPTH)
+    }
+
+    /// depth of every column drawn from `slices`
+    pub fn depth(&self, slices: &[RayCast]) -> ZBuffer {
+        ZBuffer(slices.iter().map(|slice| self.depth_of(slice)).collect())
+    }
+
+    /// draw ceiling, floor and walls for rays cast from this camera into an area of the screen
+    pub fn draw(
+        &self,
+        map: &Map,
+        slices: &[RayCast],
+        renderer: &mut dyn Renderer,
+        viewport: Rect,
+    ) -> anyhow::Result<()> {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = viewport;
+
+        let background = |color: Color| {
+            if self.flashlight {
+                color.modulate(Color::RGB(
+                    FLASHLIGHT_DARK,
+                    FLASHLIGHT_DARK,
+                    FLASHLIGHT_DARK,
+                ))
+            } else {
+                color
+            }
+        };
+
+        // DRAW CEILING
+        renderer.draw_rect(
+            Some(Rect::new(x, y, width, height / 2)),
+            background(map.ceiling_color()),
+        )?;
+
+        // DRAW FLOOR
+        renderer.draw_rect(
+            Some(Rect::new(
+                x,
+                y + (height as i32 / 2),
+                width,
+                height - (height / 2),
+            )),
+            background(map.floor_color()),
+        )?;
+
+        // DRAW WALLS
+        // heights are in pixels even when there are fewer rays than pixels across
+        let projection = self.projection(width as usize);
+        for (i, slice) in slices.iter().enumerate() {
+            let (left, span) = column_span(i, slices.len(), width);
+            // get height of line to draw (correcting fisheye effect)
+            let depth = self.depth_of(slice);
+            let line_height = (TILE_SIZE * projection) / depth;
+
+            let top = y
+                + ((height as i32 - line_height as i32) / 2)
+                + self.sway(left as usize)
+                + self.lift(projection, depth);
+
+            // rays that went as far as they could show where they stopped
+            if !slice.hit {
+                renderer.draw_rect(
+                    Some(Rect::new(x + left, top, span, line_height as u32)),
+                    fallback_color(map, self.fog),
+                )?;
+                continue;
+            }
+
+            // sample correct area of wall texture to draw
+            let custom_tile = &map.custom_tiles[&slice.tile];
+            let hit_where = if custom_tile.mirrored {
+                TILE_SIZE - slice.hit_where
+            } else {
+                slice.hit_where
+            };
+            let (texture, texture_x) = match map.face_texture(slice.tile, slice.face_direction) {
+                Some(texture) => (texture, hit_where / TILE_SIZE),
+                None => {
+                    let face = custom_tile.faces[slice.face_direction as usize] as usize as f32;
+                    (
+                        map.tex_path(slice.tile),
+                        (face + (hit_where / TILE_SIZE)) / 4.,
+                    )
+                }
+            };
+
+            // light of the tile the wall is seen from
+            let seen_from = self.pos + slice.vec - slice.vec.normalize_or_zero();
+            let beam = self.beam(i, slices.len(), slice.vec.length());
+            let tint = if custom_tile.unlit {
+                custom_tile.tint
+            } else {
+                let mut light = map.light(seen_from);
+                if self.flashlight {
+                    let lit = |level: u8| {
+                        ((level as u16 * FLASHLIGHT_DARK as u16) / 0xff)
+                            .max((0xff as f32 * beam) as u16) as u8
+                    };
+                    light = Color::RGB(lit(light.r), lit(light.g), lit(light.b));
+                }
+                custom_tile.tint.modulate(light)
+            };
+            // the beam cuts through fog
+            let mut overlay = slice_overlay(map, slice, self.fog);
+            overlay.a = (overlay.a as f32 * (1. - (beam / 2.))) as u8;
+
+            renderer.draw_column(&Column {
+                x: x + left,
+                top,
+                width: span,
+                height: line_height as u32,
+                texture: &texture,
+                texture_x,
+                tint,
+                overlay,
+            })?;
+
+            // water covers the lower half of walls standing in it
+            if map.is_water(seen_from) {
+                let half = line_height as u32 / 2;
+                renderer.draw_rect(
+                    Some(Rect::new(
+                        x + left,
+                        top + half as i32,
+                        span,
+                        line_height as u32 - half,
+                    )),
+                    WATER_TINT,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// draw sprites over walls with the given depth, hidden where a wall is closer
+    pub fn draw_sprites(
+        &self,
+        depth: &ZBuffer,
+        sprites: &[Sprite],
+        renderer: &mut dyn Renderer,
+        viewport: Rect,
+    ) -> anyhow::Result<()> {
+        let columns = viewport.width as usize;
+        let projection = self.projection(columns);
+
+        // far to near, so closer sprites end up on top
+        let mut sprites = sprites
+            .iter()
+            .map(|sprite| (sprite, sprite.pos - self.pos))
+            .collect::<Vec<_>>();
+        sprites.sort_by(|(_, a), (_, b)| b.length().total_cmp(&a.length()));
+
+        for (sprite, _) in sprites {
+            let Some((center, distance)) = self.project(sprite.pos, columns) else {
+                continue;
+            };
+
+            // as tall as a wall at the same distance and just as wide
+            let height = (TILE_SIZE * projection) / distance;
+            let width = height;
+            let left = center - (width / 2.);
+            let (first, last) = (
+                left.max(0.) as usize,
+                ((left + width).max(0.) as usize).min(columns),
+            );
+            for col in first..last {
+                // walls in front of the sprite hide it
+                if depth.hides_pixel(col, columns, distance) {
+                    continue;
+                }
+
+                renderer.draw_column(&Column {
+                    x: viewport.x + col as i32,
+                    width: 1,
+                    top: viewport.y
+                        + ((viewport.height as i32 - height as i32) / 2)
+                        + self.lift(projection, distance),
+                    height: height as u32,
+                    texture: &sprite.texture,
+                    texture_x: ((col as f32 - left) / width).clamp(0., 1. - f32::EPSILON),
+                    tint: Color::WHITE,
+                    overlay: Color::RGBA(0, 0, 0, 0),
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// cast rays and draw a whole view
+    pub fn render(
+        &self,
+        map: &Map,
+        renderer: &mut dyn Renderer,
+        viewport: Rect,
+    ) -> anyhow::Result<()> {
+        let slices = self.cast(map, viewport.width as usize);
+        self.draw(map, &slices, renderer, viewport)
+    }
+}
+
+/// color of rays that did not hit anything, fading into the fog if there is any
+fn fallback_color(map: &Map, fog: bool) -> Color {
+    map.meta
+        .iter()
+        .filter(|_| fog)
+        .find_map(|meta| match meta {
+            Meta::Fog { color, .. } => Some(*color),
+            _ => None,
+        })
+        .unwrap_or(Color::BLACK)
+}
+
+/// color drawn over a wall slice, for fog and contrast between faces
+fn slice_overlay(map: &Map, slice: &RayCast, fog: bool) -> Color {
+    let mut overlay = Color::RGBA(0, 0, 0, 0);
+    if map.custom_tiles[&slice.tile].unlit {
+        return overlay;
+    }
+
+    if let Some(Meta::Fog { dof, color, curve }) = map
+        .meta
+        .iter()
+        .filter(|_| fog)
+        .find(|item| matches!(item, Meta::Fog { .. }))
+    {
+        // add depth of field fog, translucent fog never hides walls completely
+        let fog = curve.amount(slice.vec.length() / ((*dof as f32) * TILE_SIZE));
+        overlay = Color::RGBA(color.r, color.g, color.b, (color.a as f32 * fog) as u8);
+    }
+
+    // slightly discolor walls that face different directions for contrast
+    overlay.a = overlay.a.saturating_add(match slice.face_direction {
+        Cardinal::North | Cardinal::South => 0,
+        Cardinal::East | Cardinal::West => 0x22,
+    });
+    overlay
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Image;
+    use crate::render::TextStyle;
+    use std::path::Path;
+
+    const BOX: &str = "!!!!MAIN
+#wall.png,collide
+
+#####
+#   #
+# * #
+#   #
+#####";
+
+    /// remembers the wall columns it was asked to draw
+    #[derive(Default)]
+    struct Columns(Vec<(i32, u32, f32)>);
+
+    impl Renderer for Columns {
+        fn draw_column(&mut self, column: &Column) -> anyhow::Result<()> {
+            self.0.push((column.x, column.height, column.texture_x));
+            Ok(())
+        }
+
+        fn draw_rect(&mut self, _rect: Option<Rect>, _color: Color) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn draw_line(
+            &mut self,
+            _from: (i32, i32),
+            _to: (i32, i32),
+            _color: Color,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn draw_image(&mut self, _path: &Path, _rect: Option<Rect>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn draw_text(
+            &mut self,
+            _text: &str,
+            _style: &TextStyle,
+            _x: i32,
+            _y: i32,
+        ) -> anyhow::Result<Rect> {
+            Ok(Rect::default())
+        }
+
+        fn measure_text(&mut self, _text: &str, _style: &TextStyle) -> anyhow::Result<(u32, u32)> {
+            Ok((0, 0))
+        }
+
+        fn capture(&mut self) -> anyhow::Result<Image> {
+            anyhow::bail!("nothing to capture")
+        }
+
+        fn present(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn preload(&mut self, _path: &Path) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn clear_cache(&mut self) {}
+    }
+
+    #[test]
+    fn casts_one_ray_per_column() {
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        let camera = Camera::new(map.get_spawn().unwrap(), 0.);
+
+        for columns in [1, 7, 8] {
+            assert_eq!(camera.cast(&map, columns).len(), columns);
+        }
+    }
+
+    #[test]
+    fn column_tables_look_where_angles_would() {
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        let camera = Camera::new(map.get_spawn().unwrap() + Vec2::new(5., -3.), 0.7);
+        let mut table = ColumnTable::default();
+        table.fit(camera.fov, 9);
+        assert_eq!(table.columns(), 9);
+
+        for slice in camera.cast_with(&map, &table) {
+            let ray = crate::ray::cast_ray(&map, camera.pos, slice.angle);
+            assert!((ray.vec - slice.vec).length() < 0.01, "{ray:?} {slice:?}");
+        }
+
+        // only built again when something changed
+        table.angles.clear();
+        table.fit(camera.fov, 9);
+        assert!(table.angles.is_empty());
+        table.fit(90., 9);
+        assert_eq!(table, ColumnTable::new(90., 9));
+    }
+
+    #[test]
+    fn wobbling_sways_columns() {
+        let camera = Camera::new(Vec2::ZERO, 0.);
+        assert_eq!(camera.sway(10), 0);
+
+        let camera = Camera {
+            wobble: Some(std::f32::consts::FRAC_PI_2),
+            ..camera
+        };
+        assert_eq!(camera.sway(0), WOBBLE_HEIGHT as i32);
+        assert!((0..640).all(|column| camera.sway(column).abs() <= WOBBLE_HEIGHT as i32));
+        assert!((0..640).any(|column| camera.sway(column) < 0));
+    }
+
+    #[test]
+    fn raised_eyes_move_closer_things_further_down() {
+        assert_eq!(Camera::new(Vec2::ZERO, 0.).lift(100., 50.), 0);
+
+        let camera = Camera {
+            height: 8.,
+            ..Camera::new(Vec2::ZERO, 0.)
+        };
+        assert_eq!(camera.lift(100., 50.), 16);
+        assert!(camera.lift(100., 25.) > camera.lift(100., 50.));
+    }
+
+    #[test]
+    fn fewer_rays_draw_wider_columns() {
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        let camera = Camera::new(map.get_spawn().unwrap(), 0.);
+        let draw = |rays| {
+            let mut columns = Columns::default();
+            camera
+                .draw(
+                    &map,
+                    &camera.cast(&map, rays),
+                    &mut columns,
+                    Rect::new(0, 0, 8, 100),
+                )
+                .unwrap();
+            columns.0
+        };
+
+        let full = draw(8);
+        let half = draw(4);
+        assert_eq!(
+            half.iter().map(|(x, _, _)| *x).collect::<Vec<_>>(),
+            [0, 2, 4, 6]
+        );
+        // the middle ray looks straight ahead either way, and walls are just as tall
+        assert_eq!(half[2].1, full[4].1);
+
+        assert_eq!(column_span(1, 3, 8), (2, 3));
+        assert_eq!(column_span(2, 3, 8), (5, 3));
+    }
+
+    #[test]
+    fn walls_hide_sprites() {
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        let camera = Camera::new(map.get_spawn().unwrap() + Vec2::splat(TILE_SIZE / 2.), 0.);
+        let depth = camera.depth(&camera.cast(&map, 8));
+        let draw = |pos| {
+            let mut columns = Columns::default();
+            let sprite = Sprite {
+                pos,
+                texture: "player.png".into(),
+            };
+            camera
+                .draw_sprites(&depth, &[sprite], &mut columns, Rect::new(0, 0, 8, 100))
+                .unwrap();
+            columns.0
+        };
+
+        // in front of the wall, behind it and behind the camera
+        let visible = draw(camera.pos + Vec2::new(TILE_SIZE, 0.));
+        assert!(visible.iter().any(|(x, _, _)| *x == 4));
+        assert!(visible.len() <= 8);
+        assert!(draw(camera.pos + Vec2::new(TILE_SIZE * 3., 0.)).is_empty());
+        assert!(draw(camera.pos - Vec2::new(TILE_SIZE, 0.)).is_empty());
+    }
+
+    #[test]
+    fn depth_is_flat_for_a_straight_wall() {
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        let camera = Camera::new(map.get_spawn().unwrap() + Vec2::splat(TILE_SIZE / 2.), 0.);
+        let depth = camera.depth(&camera.cast(&map, 8));
+
+        assert_eq!(depth.len(), 8);
+        for wall in depth.0.iter() {
+            assert!((wall - (TILE_SIZE * 1.5)).abs() < 0.01, "{wall}");
+        }
+        assert!(depth.hides(0, TILE_SIZE * 2.));
+        assert!(!depth.hides(0, TILE_SIZE / 2.));
+        assert!(depth.hides(8, 0.));
+    }
+
+    #[test]
+    fn walls_touching_the_camera_have_a_depth() {
+        // the corner of the open area, on the edge of the walls above and to the left
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        for direction in [0., 1., 2., std::f32::consts::PI, 4., 5., 6.] {
+            let camera = Camera::new(Vec2::splat(TILE_SIZE), direction);
+            let depth = camera.depth(&camera.cast(&map, 8));
+            for wall in depth.0.iter() {
+                assert!(wall.is_finite() && *wall > 0., "{direction} {wall}");
+            }
+            camera
+                .render(&map, &mut Columns::default(), Rect::new(0, 0, 8, 100))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn draws_every_column_of_a_wall() {
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        let camera = Camera::new(map.get_spawn().unwrap() + Vec2::splat(TILE_SIZE / 2.), 0.);
+        let mut columns = Columns::default();
+        camera
+            .render(&map, &mut columns, Rect::new(10, 0, 8, 100))
+            .unwrap();
+
+        assert_eq!(columns.0.len(), 8);
+        for (i, (x, height, texture_x)) in columns.0.iter().enumerate() {
+            assert_eq!(*x, 10 + i as i32);
+            // the wall is straight ahead, fisheye correction keeps it flat
+            assert!(height.abs_diff(columns.0[0].1) <= 1, "column {i}");
+            assert!((0. ..1.).contains(texture_x));
+        }
+    }
+
+    #[test]
+    fn flashlight_lights_the_center() {
+        let mut camera = Camera::new(Vec2::ZERO, 0.);
+        assert_eq!(camera.beam(50, 100, TILE_SIZE), 0.);
+
+        camera.flashlight = true;
+        assert!(camera.beam(50, 100, TILE_SIZE) > 0.8);
+        assert!(camera.beam(50, 100, TILE_SIZE) > camera.beam(60, 100, TILE_SIZE));
+        assert_eq!(camera.beam(0, 100, TILE_SIZE), 0.);
+        assert_eq!(camera.beam(50, 100, TILE_SIZE * 10.), 0.);
+    }
+
+    #[test]
+    fn wider_fov_shrinks_walls() {
+        let map = Map::parse(BOX, Path::new("")).unwrap();
+        let center_height = |fov| {
+            let camera = Camera {
+                fov,
+                ..Camera::new(map.get_spawn().unwrap() + Vec2::splat(TILE_SIZE / 2.), 0.)
+            };
+            let mut columns = Columns::default();
+            camera
+                .render(&map, &mut columns, Rect::new(0, 0, 64, 100))
+                .unwrap();
+            columns.0[32].1
+        };
+
+        assert!(center_height(50.) > center_height(60.));
+        assert!(center_height(60.) > center_height(110.));
+    }
+
+    #[test]
+    fn translucent_fog_never_hides_walls() {
+        let fog = |color, enabled| {
+            let map = Map::parse(
+                &format!("!!!!META\nfog,dof=1,color={color}\n\n{BOX}"),
+                Path::new(""),
+            )
+            .unwrap();
+            let far = RayCast {
+                vec: Vec2::new(0., TILE_SIZE * 10.),
+                hit: true,
+                angle: 0.,
+                face_direction: Cardinal::North,
+                hit_where: 0.,
+                tile: '#',
+            };
+            slice_overlay(&map, &far, enabled).a
+        };
+        assert_eq!(fog("#000000", true), 0xff);
+        assert_eq!(fog("#00000080", true), 0x80);
+        // cameras without fog see walls clearly
+        assert_eq!(fog("#000000", false), 0);
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// rgba color, independent of whatever draws it
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Self = Self::RGB(0xff, 0xff, 0xff);
+    pub const BLACK: Self = Self::RGB(0, 0, 0);
+    pub const GREEN: Self = Self::RGB(0, 0xff, 0);
+
+    #[allow(non_snake_case)]
+    pub const fn RGB(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 0xff }
+    }
+
+    #[allow(non_snake_case)]
+    pub const fn RGBA(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+/// `#rrggbb`, or `#rrggbbaa` for translucent colors
+pub fn parse_hex_color(hex: &str) -> anyhow::Result<Color> {
+    let digits = hex
+        .strip_prefix('#')
+        .filter(|digits| {
+            matches!(digits.len(), 6 | 8) && digits.bytes().all(|byte| byte.is_ascii_hexdigit())
+        })
+        .with_context(|| format!("not a hex color: {hex}"))?;
+    let channel = |idx: usize| u8::from_str_radix(&digits[idx * 2..(idx + 1) * 2], 16);
+    let a = if digits.len() == 8 { channel(3)? } else { 0xff };
+
+    Ok(Color::RGBA(channel(0)?, channel(1)?, channel(2)?, a))
+}
+
+/// `#rrggbb`, or `#rrggbbaa` for translucent colors, as read by [`parse_hex_color`]
+pub fn hex_color(color: Color) -> String {
+    let mut hex = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+    if color.a != 0xff {
+        hex += &format!("{:02x}", color.a);
+    }
+    hex
+}
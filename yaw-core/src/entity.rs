@@ -0,0 +1,415 @@
+//! dynamic objects as ids with optional components, updated and drawn in one place
+
+use crate::camera::Sprite;
+use crate::map::{Map, TILE_SIZE};
+use glam::Vec2;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// handle to an object in a [`World`], never reused after despawning
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Entity(u32);
+
+/// components of one kind, ordered by entity so updates are deterministic
+pub type Storage<T> = BTreeMap<Entity, T>;
+
+/// key/value state scripts keep on an entity, see [`World::states`]
+pub type StateBag = BTreeMap<String, String>;
+
+/// blocks movement through a circle around the position
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Collider {
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Ai {
+    /// stand still
+    Idle,
+    /// walk towards the closest target within range that is in sight
+    Chase { speed: f32, range: f32 },
+}
+
+/// waypoints an entity walks along, see [`World::walk_to`]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Route {
+    pub speed: f32,
+    /// next waypoint first, the entity stops after the last
+    pub waypoints: Vec<Vec2>,
+}
+
+#[derive(Default)]
+pub struct World {
+    next: u32,
+    pub positions: Storage<Vec2>,
+    /// image drawn as a billboard at the position
+    pub sprites: Storage<PathBuf>,
+    pub colliders: Storage<Collider>,
+    /// entities are removed once this reaches 0
+    pub health: Storage<u8>,
+    pub ai: Storage<Ai>,
+    /// walking somewhere, ai waits until the route is done
+    pub routes: Storage<Route>,
+    /// crates players push a tile at a time, see [`World::push`]
+    pub pushable: BTreeSet<Entity>,
+    /// file of what someone friendly says when players talk to them
+    pub dialogue: Storage<PathBuf>,
+    /// tile the map placed an entity on, naming its state between visits to the level
+    pub origins: Storage<(usize, usize)>,
+    /// what the level script kept on an entity, by key
+    pub state: Storage<StateBag>,
+}
+
+impl World {
+    /// new entity without any components
+    pub fn spawn(&mut self) -> Entity {
+        let entity = Entity(self.next);
+        self.next += 1;
+        entity
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.positions.remove(&entity);
+        self.sprites.remove(&entity);
+        self.colliders.remove(&entity);
+        self.health.remove(&entity);
+        self.ai.remove(&entity);
+        self.routes.remove(&entity);
+        self.pushable.remove(&entity);
+        self.dialogue.remove(&entity);
+        self.origins.remove(&entity);
+        self.state.remove(&entity);
+    }
+
+    /// whether the entity has a position in the world
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.positions.contains_key(&entity)
+    }
+
+    /// entity whose collider covers a position, its edge included like the edge of a wall
+    /// tile, so nothing gets onto the tile of a crate
+    pub fn colliding(&self, position: Vec2) -> Option<Entity> {
+        self.colliders.iter().find_map(|(entity, collider)| {
+            let pos = self.positions.get(entity)?;
+            (pos.distance(position) <= collider.radius).then_some(*entity)
+        })
+    }
+
+    /// first entity standing on a tile
+    pub fn at_tile(&self, x: usize, y: usize) -> Option<Entity> {
+        self.positions.iter().find_map(|(entity, pos)| {
+            let tile = ((pos.x / TILE_SIZE) as usize, (pos.y / TILE_SIZE) as usize);
+            (tile == (x, y)).then_some(*entity)
+        })
+    }
+
+    /// state of every entity that has some and came from the map, by the tile it came from
+    pub fn states(&self) -> BTreeMap<(usize, usize), StateBag> {
+        self.state
+            .iter()
+            .filter(|(_, state)| !state.is_empty())
+            .filter_map(|(entity, state)| Some((*self.origins.get(entity)?, state.clone())))
+            .collect()
+    }
+
+    /// give entities back the state kept by [`World::states`], returns the ones that got some
+    pub fn restore(&mut self, states: &BTreeMap<(usize, usize), StateBag>) -> Vec<Entity> {
+        let mut restored = Vec::new();
+        for (entity, origin) in &self.origins {
+            if let Some(state) = states.get(origin) {
+                self.state.insert(*entity, state.clone());
+                restored.push(*entity);
+            }
+        }
+        restored
+    }
+
+    /// walk an entity around walls to a position, returns whether there is a way there
+    pub fn walk_to(&mut self, map: &Map, entity: Entity, to: Vec2, speed: f32) -> bool {
+        let Some(waypoints) = self
+            .positions
+            .get(&entity)
+            .and_then(|pos| map.find_path(*pos, to))
+        else {
+            return false;
+        };
+
+        self.routes.insert(entity, Route { speed, waypoints });
+        true
+    }
+
+    /// slide a pushable entity to the middle of the next tile in a direction of `(x, y)` tiles,
+    /// returns whether it goes. it does not while it is still sliding, or when a wall, another
+    /// entity or one of `blockers`, like players, is on that tile
+    pub fn push(
+        &mut self,
+        map: &Map,
+        entity: Entity,
+        direction: (i32, i32),
+        speed: f32,
+        blockers: &[Vec2],
+    ) -> bool {
+        if !self.pushable.contains(&entity) || self.routes.contains_key(&entity) {
+            return false;
+        }
+        let Some(pos) = self.positions.get(&entity) else {
+            return false;
+        };
+
+        let tile = |pos: Vec2| (pos / TILE_SIZE).floor();
+        let to = (tile(*pos) + Vec2::new(direction.0 as f32, direction.1 as f32) + 0.5) * TILE_SIZE;
+        let taken = self
+            .positions
+            .iter()
+            .filter(|(other, _)| **other != entity)
+            .map(|(_, pos)| *pos)
+            .chain(blockers.iter().copied())
+            .chain(
+                self.routes
+                    .values()
+                    .filter_map(|route| route.waypoints.last().copied()),
+            )
+            .any(|pos| tile(pos) == tile(to));
+        if !map.contains(to) || map.colliding(to, true).is_some() || taken {
+            return false;
+        }
+
+        self.routes.insert(
+            entity,
+            Route {
+                speed,
+                waypoints: vec![to],
+            },
+        );
+        true
+    }
+
+    /// advance one frame, `targets` are what chasing entities walk towards
+    pub fn tick(&mut self, map: &Map, targets: &[Vec2]) {
+        let dead = self
+            .health
+            .iter()
+            .filter(|(_, health)| **health == 0)
+            .map(|(entity, _)| *entity)
+            .collect::<Vec<_>>();
+        for entity in dead {
+            self.despawn(entity);
+        }
+
+        for (entity, route) in self.routes.iter_mut() {
+            let Some(pos) = self.positions.get_mut(entity) else {
+                continue;
+            };
+            let mut step = route.speed;
+            while let Some(waypoint) = route.waypoints.first() {
+                let left = pos.distance(*waypoint);
+                if left > step {
+                    *pos = pos.move_towards(*waypoint, step);
+                    break;
+                }
+                *pos = *waypoint;
+                step -= left;
+                route.waypoints.remove(0);
+            }
+        }
+        self.routes.retain(|_, route| !route.waypoints.is_empty());
+
+        for (entity, ai) in self.ai.iter() {
+            if self.routes.contains_key(entity) {
+                continue;
+            }
+            let Some(pos) = self.positions.get_mut(entity) else {
+                continue;
+            };
+
+            match *ai {
+                Ai::Idle => {}
+                Ai::Chase { speed, range } => {
+                    let Some(target) = targets
+                        .iter()
+                        .filter(|target| {
+                            target.distance(*pos) <= range && map.line_of_sight(*pos, **target)
+                        })
+                        .min_by(|a, b| a.distance(*pos).total_cmp(&b.distance(*pos)))
+                    else {
+                        continue;
+                    };
+
+                    // head for the next tile on the way around walls
+                    let next = map
+                        .find_path(*pos, *target)
+                        .and_then(|path| path.first().copied())
+                        .unwrap_or(*target);
+                    // stop short instead of stepping past the target
+                    let step = (next - *pos).clamp_length_max(speed);
+                    if map.colliding(*pos + Vec2::new(step.x, 0.), true).is_none() {
+                        pos.x += step.x;
+                    }
+                    if map.colliding(*pos + Vec2::new(0., step.y), true).is_none() {
+                        pos.y += step.y;
+                    }
+                }
+            }
+        }
+    }
+
+    /// billboards of every entity that has a position and an image
+    pub fn sprites(&self) -> Vec<Sprite> {
+        self.sprites
+            .iter()
+            .filter_map(|(entity, texture)| {
+                Some(Sprite {
+                    pos: *self.positions.get(entity)?,
+                    texture: texture.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const MAP: &str = "!!!!MAIN
+#wall.png,collide
+
+#####
+#*  #
+#####";
+
+    #[test]
+    fn despawning_removes_components() {
+        let mut world = World::default();
+        let entity = world.spawn();
+        world.positions.insert(entity, Vec2::new(48., 48.));
+        world.sprites.insert(entity, "images/player.png".into());
+        world.colliders.insert(entity, Collider { radius: 8. });
+        assert_eq!(world.sprites().len(), 1);
+        assert_eq!(world.colliding(Vec2::new(50., 50.)), Some(entity));
+        assert_eq!(world.colliding(Vec2::new(56., 48.)), Some(entity));
+        assert_eq!(world.colliding(Vec2::new(57., 48.)), None);
+
+        world.despawn(entity);
+        assert!(!world.contains(entity));
+        assert!(world.sprites().is_empty());
+        assert_eq!(world.colliding(Vec2::new(50., 50.)), None);
+        assert_ne!(world.spawn(), entity);
+    }
+
+    #[test]
+    fn chasers_walk_to_targets_in_range() {
+        let map = Map::parse(MAP, Path::new("")).unwrap();
+        let mut world = World::default();
+        let chaser = world.spawn();
+        world.positions.insert(chaser, Vec2::new(112., 48.));
+        world.ai.insert(
+            chaser,
+            Ai::Chase {
+                speed: 2.,
+                range: 100.,
+            },
+        );
+
+        world.tick(&map, &[Vec2::new(48., 48.)]);
+        assert_eq!(world.positions[&chaser], Vec2::new(110., 48.));
+
+        // out of range
+        world.tick(&map, &[Vec2::new(48., 200.)]);
+        assert_eq!(world.positions[&chaser], Vec2::new(110., 48.));
+    }
+
+    #[test]
+    fn routes_lead_around_walls() {
+        let map = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\n\n#####\n#* ##\n## ##\n#   #\n#####",
+            Path::new(""),
+        )
+        .unwrap();
+        let mut world = World::default();
+        let walker = world.spawn();
+        world.positions.insert(walker, Vec2::new(48., 48.));
+        assert_eq!(world.at_tile(1, 1), Some(walker));
+
+        assert!(!world.walk_to(&map, walker, Vec2::new(16., 16.), 4.));
+        assert!(world.walk_to(&map, walker, Vec2::new(48., 112.), 4.));
+        for _ in 0..100 {
+            world.tick(&map, &[]);
+            assert!(map.colliding(world.positions[&walker], true).is_none());
+        }
+        assert_eq!(world.positions[&walker], Vec2::new(48., 112.));
+        assert!(world.routes.is_empty());
+        assert_eq!(world.at_tile(1, 3), Some(walker));
+    }
+
+    #[test]
+    fn crates_are_pushed_a_tile_into_free_space() {
+        let map = Map::parse(MAP, Path::new("")).unwrap();
+        let mut world = World::default();
+        let pushed = world.spawn();
+        world.positions.insert(pushed, Vec2::new(80., 48.));
+        world.pushable.insert(pushed);
+
+        // walls and players are in the way
+        assert!(!world.push(&map, pushed, (0, 1), 4., &[]));
+        assert!(!world.push(&map, pushed, (-1, 0), 4., &[Vec2::new(40., 40.)]));
+
+        assert!(world.push(&map, pushed, (1, 0), 4., &[]));
+        // not again until it got there
+        assert!(!world.push(&map, pushed, (-1, 0), 4., &[]));
+        for _ in 0..8 {
+            world.tick(&map, &[]);
+        }
+        assert_eq!(world.positions[&pushed], Vec2::new(112., 48.));
+        assert!(!world.push(&map, pushed, (1, 0), 4., &[]));
+
+        // only pushable entities move
+        let other = world.spawn();
+        world.positions.insert(other, Vec2::new(48., 48.));
+        assert!(!world.push(&map, other, (1, 0), 4., &[]));
+    }
+
+    #[test]
+    fn states_are_kept_by_where_entities_came_from() {
+        let mut world = World::default();
+        let moved = world.spawn();
+        world.positions.insert(moved, Vec2::new(112., 48.));
+        world.origins.insert(moved, (1, 1));
+        world
+            .state
+            .insert(moved, StateBag::from([("opened".into(), "yes".into())]));
+        // spawned later, not by the map
+        let spawned = world.spawn();
+        world.positions.insert(spawned, Vec2::new(48., 48.));
+        world
+            .state
+            .insert(spawned, StateBag::from([("angry".into(), "no".into())]));
+        let states = world.states();
+        assert_eq!(states.keys().collect::<Vec<_>>(), [&(1, 1)]);
+
+        let mut world = World::default();
+        let other = world.spawn();
+        world.origins.insert(other, (2, 1));
+        let again = world.spawn();
+        world.origins.insert(again, (1, 1));
+        assert_eq!(world.restore(&states), [again]);
+        assert_eq!(world.state[&again]["opened"], "yes");
+        assert!(!world.state.contains_key(&other));
+
+        world.despawn(again);
+        assert!(world.states().is_empty());
+    }
+
+    #[test]
+    fn entities_without_health_are_removed() {
+        let map = Map::parse(MAP, Path::new("")).unwrap();
+        let mut world = World::default();
+        let entity = world.spawn();
+        world.positions.insert(entity, Vec2::new(48., 48.));
+        world.health.insert(entity, 0);
+
+        world.tick(&map, &[]);
+        assert!(!world.contains(entity));
+    }
+}
@@ -0,0 +1,535 @@
+use crate::assets;
+use crate::color::Color;
+use crate::render::{CacheStats, Column, Rect, Renderer, TextStyle};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// decoded texture, `0xAARRGGBB` pixels row by row
+#[derive(Clone, PartialEq, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Image {
+    /// decode a png file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = assets::read(path)?;
+        let mut decoder = png::Decoder::new(&*bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut bytes = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut bytes)?;
+        let bytes = &bytes[..info.buffer_size()];
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => bytes
+                .chunks_exact(4)
+                .map(|px| Color::RGBA(px[0], px[1], px[2], px[3]).to_argb())
+                .collect(),
+            png::ColorType::Rgb => bytes
+                .chunks_exact(3)
+                .map(|px| Color::RGB(px[0], px[1], px[2]).to_argb())
+                .collect(),
+            png::ColorType::GrayscaleAlpha => bytes
+                .chunks_exact(2)
+                .map(|px| Color::RGBA(px[0], px[0], px[0], px[1]).to_argb())
+                .collect(),
+            png::ColorType::Grayscale => bytes
+                .iter()
+                .map(|px| Color::RGB(*px, *px, *px).to_argb())
+                .collect(),
+            png::ColorType::Indexed => anyhow::bail!("palette was not expanded"),
+        };
+
+        Ok(Self {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    /// encode as a png file
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data = self
+            .pixels
+            .iter()
+            .flat_map(|px| {
+                let color = Color::from_argb(*px);
+                [color.r, color.g, color.b, color.a]
+            })
+            .collect::<Vec<_>>();
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+
+        Ok(bytes)
+    }
+
+    /// magenta and black checkerboard drawn instead of textures that could not be loaded
+    pub fn missing() -> Self {
+        const SIZE: usize = 16;
+        let pixels = (0..SIZE * SIZE)
+            .map(|idx| {
+                let checker = ((idx % SIZE) / (SIZE / 2)) ^ ((idx / SIZE) / (SIZE / 2));
+                if checker == 0 {
+                    Color::RGB(0xff, 0, 0xff).to_argb()
+                } else {
+                    Color::BLACK.to_argb()
+                }
+            })
+            .collect();
+
+        Self {
+            width: SIZE,
+            height: SIZE,
+            pixels,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.pixels[(y.min(self.height - 1) * self.width) + x.min(self.width - 1)]
+    }
+
+    /// this image at half the size, every pixel averaging the 2x2 pixels it covers
+    pub fn half(&self) -> Self {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let texels = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .map(|(dx, dy)| self.get((x * 2) + dx, (y * 2) + dy).to_be_bytes());
+                let average = |channel: usize| {
+                    (texels.iter().map(|t| t[channel] as u32).sum::<u32>() / 4) as u8
+                };
+                pixels.push(u32::from_be_bytes([
+                    average(0),
+                    average(1),
+                    average(2),
+                    average(3),
+                ]));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// this image followed by smaller and smaller halves, down to a single pixel row
+    pub fn mipmaps(&self) -> Vec<Self> {
+        let mut levels = vec![self.clone()];
+        while let Some(last) = levels.last().filter(|image| image.height > 1) {
+            levels.push(last.half());
+        }
+        levels
+    }
+}
+
+/// pixels drawn in software, `0xAARRGGBB` row by row
+#[derive(Clone, PartialEq, Debug)]
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Color {
+    pub const fn to_argb(self) -> u32 {
+        u32::from_be_bytes([self.a, self.r, self.g, self.b])
+    }
+
+    pub const fn from_argb(argb: u32) -> Self {
+        let [a, r, g, b] = argb.to_be_bytes();
+        Self { r, g, b, a }
+    }
+
+    /// multiply every channel with another color
+    pub const fn modulate(self, other: Color) -> Self {
+        Self {
+            r: ((self.r as u16 * other.r as u16) / 0xff) as u8,
+            g: ((self.g as u16 * other.g as u16) / 0xff) as u8,
+            b: ((self.b as u16 * other.b as u16) / 0xff) as u8,
+            a: self.a,
+        }
+    }
+
+    /// draw this color over another one, using this color's alpha
+    pub fn blend_over(self, dst: Color) -> Self {
+        let mix = |src: u8, dst: u8| {
+            ((src as u16 * self.a as u16 + dst as u16 * (0xff - self.a as u16)) / 0xff) as u8
+        };
+
+        Self {
+            r: mix(self.r, dst.r),
+            g: mix(self.g, dst.g),
+            b: mix(self.b, dst.b),
+            a: 0xff,
+        }
+    }
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// fill an area, clipped to the framebuffer, translucent colors are blended
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        let argb = color.to_argb();
+        let (x0, x1) = self.clip(x, width, self.width);
+        let (y0, y1) = self.clip(y, height, self.height);
+
+        for row in y0..y1 {
+            let pixels = &mut self.pixels[(row * self.width) + x0..(row * self.width) + x1];
+            if color.a == 0xff {
+                pixels.fill(argb);
+            } else {
+                for pixel in pixels {
+                    *pixel = color.blend_over(Color::from_argb(*pixel)).to_argb();
+                }
+            }
+        }
+    }
+
+    /// draw a one pixel wide line, blended
+    pub fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let steps = dx.abs().max(dy.abs()).max(1);
+
+        for step in 0..=steps {
+            let x = from.0 + (dx * step) / steps;
+            let y = from.1 + (dy * step) / steps;
+            if (0..self.width as i32).contains(&x) && (0..self.height as i32).contains(&y) {
+                let pixel = &mut self.pixels[(y as usize * self.width) + x as usize];
+                *pixel = color.blend_over(Color::from_argb(*pixel)).to_argb();
+            }
+        }
+    }
+
+    /// draw a whole image stretched over an area, blended by its alpha channel
+    pub fn draw_image(&mut self, image: &Image, x: i32, y: i32, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let (x0, x1) = self.clip(x, width, self.width);
+        let (y0, y1) = self.clip(y, height, self.height);
+        for row in y0..y1 {
+            let image_y = ((row as i32 - y) as usize * image.height) / height as usize;
+            for col in x0..x1 {
+                let image_x = ((col as i32 - x) as usize * image.width) / width as usize;
+                let pixel = &mut self.pixels[(row * self.width) + col];
+                *pixel = Color::from_argb(image.get(image_x, image_y))
+                    .blend_over(Color::from_argb(*pixel))
+                    .to_argb();
+            }
+        }
+    }
+
+    /// draw one column of an image stretched to `height` pixels starting at `top`,
+    /// `shade` is applied to every sampled pixel
+    pub fn draw_column(
+        &mut self,
+        x: usize,
+        top: i32,
+        height: u32,
+        image: &Image,
+        image_x: usize,
+        shade: impl Fn(Color) -> Color,
+    ) {
+        if x >= self.width || height == 0 {
+            return;
+        }
+
+        let (y0, y1) = self.clip(top, height, self.height);
+        for y in y0..y1 {
+            let image_y = ((y as i32 - top) as usize * image.height) / height as usize;
+            let texel = Color::from_argb(image.get(image_x, image_y));
+            // fully transparent texels let whatever is behind show through
+            if texel.a != 0 {
+                self.pixels[(y * self.width) + x] = shade(texel).to_argb();
+            }
+        }
+    }
+
+    /// clip a span starting at `start` to `0..max`
+    fn clip(&self, start: i32, len: u32, max: usize) -> (usize, usize) {
+        let end = (start as i64 + len as i64).clamp(0, max as i64) as usize;
+        (start.clamp(0, max as i32) as usize, end)
+    }
+}
+
+/// renders into a framebuffer in memory, loading png textures as needed
+#[derive(Clone, Debug)]
+pub struct PixelRenderer {
+    pub framebuffer: Framebuffer,
+    /// decoded textures
+    images: HashMap<PathBuf, Image>,
+    /// downscaled wall textures, so far away walls don't shimmer
+    mipmaps: HashMap<PathBuf, Vec<Image>>,
+    cache_stats: CacheStats,
+}
+
+impl PixelRenderer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            framebuffer: Framebuffer::new(width, height),
+            images: HashMap::new(),
+            mipmaps: HashMap::new(),
+            cache_stats: CacheStats::default(),
+        }
+    }
+
+    /// use an already decoded image for a path instead of loading the file
+    pub fn insert_image(&mut self, path: impl Into<PathBuf>, image: Image) {
+        let path = path.into();
+        self.mipmaps.remove(&path);
+        self.images.insert(path, image);
+    }
+
+    /// decode an image file, cached, files that fail to load are drawn as [`Image::missing`]
+    fn load(&mut self, path: &Path) {
+        if self.images.contains_key(path) {
+            self.cache_stats.hits += 1;
+        } else {
+            self.cache_stats.misses += 1;
+            let image = Image::load(path).unwrap_or_else(|err| {
+                log::warn!("could not load {}: {err}", path.display());
+                Image::missing()
+            });
+            self.images.insert(path.into(), image);
+        }
+    }
+
+    fn full(&self) -> Rect {
+        Rect::new(
+            0,
+            0,
+            self.framebuffer.width as u32,
+            self.framebuffer.height as u32,
+        )
+    }
+}
+
+impl Renderer for PixelRenderer {
+    fn draw_column(&mut self, column: &Column) -> anyhow::Result<()> {
+        self.load(column.texture);
+        let levels = self
+            .mipmaps
+            .entry(column.texture.into())
+            .or_insert_with(|| self.images[column.texture].mipmaps());
+        // the smallest level that still has a texel for every pixel drawn
+        let image = levels
+            .iter()
+            .rev()
+            .find(|image| image.height >= column.height as usize)
+            .unwrap_or(&levels[0]);
+        let image_x = (column.texture_x * image.width as f32) as usize;
+        for x in column.x.max(0)..column.x + column.width as i32 {
+            self.framebuffer.draw_column(
+                x as usize,
+                column.top,
+                column.height,
+                image,
+                image_x,
+                |texel| column.overlay.blend_over(texel.modulate(column.tint)),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: Option<Rect>, color: Color) -> anyhow::Result<()> {
+        let rect = rect.unwrap_or(self.full());
+        self.framebuffer
+            .fill_rect(rect.x, rect.y, rect.width, rect.height, color);
+
+        Ok(())
+    }
+
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) -> anyhow::Result<()> {
+        self.framebuffer.draw_line(from, to, color);
+        Ok(())
+    }
+
+    fn draw_image(&mut self, path: &Path, rect: Option<Rect>) -> anyhow::Result<()> {
+        let rect = rect.unwrap_or(self.full());
+        self.load(path);
+        self.framebuffer
+            .draw_image(&self.images[path], rect.x, rect.y, rect.width, rect.height);
+
+        Ok(())
+    }
+
+    fn draw_text(
+        &mut self,
+        _text: &str,
+        _style: &TextStyle,
+        _x: i32,
+        _y: i32,
+    ) -> anyhow::Result<Rect> {
+        anyhow::bail!("pixel renderer has no font to draw text with")
+    }
+
+    fn measure_text(&mut self, _text: &str, _style: &TextStyle) -> anyhow::Result<(u32, u32)> {
+        anyhow::bail!("pixel renderer has no font to measure text with")
+    }
+
+    fn capture(&mut self) -> anyhow::Result<Image> {
+        Ok(Image {
+            width: self.framebuffer.width,
+            height: self.framebuffer.height,
+            pixels: self.framebuffer.pixels.clone(),
+        })
+    }
+
+    fn present(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn preload(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.load(path);
+        Ok(())
+    }
+
+    fn clear_cache(&mut self) {
+        self.images.clear();
+        self.mipmaps.clear();
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        self.cache_stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_rect_is_clipped() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.fill_rect(-2, 2, 4, 8, Color::WHITE);
+
+        let white = Color::WHITE.to_argb();
+        for (i, pixel) in framebuffer.pixels.iter().enumerate() {
+            let (x, y) = (i % 4, i / 4);
+            assert_eq!(*pixel == white, x < 2 && y >= 2, "pixel at {x}, {y}");
+        }
+    }
+
+    #[test]
+    fn draw_column_stretches_and_shades() {
+        let image = Image {
+            width: 1,
+            height: 2,
+            pixels: vec![Color::WHITE.to_argb(), Color::BLACK.to_argb()],
+        };
+        let mut framebuffer = Framebuffer::new(1, 4);
+        framebuffer.draw_column(0, 0, 4, &image, 0, |texel| {
+            texel.modulate(Color::RGB(0xff, 0, 0))
+        });
+
+        let red = Color::RGB(0xff, 0, 0).to_argb();
+        let black = Color::BLACK.to_argb();
+        assert_eq!(framebuffer.pixels, vec![red, red, black, black]);
+    }
+
+    #[test]
+    fn far_columns_sample_averaged_texels() {
+        let checker = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                Color::WHITE.to_argb(),
+                Color::BLACK.to_argb(),
+                Color::BLACK.to_argb(),
+                Color::WHITE.to_argb(),
+            ],
+        };
+        let levels = checker.mipmaps();
+        assert_eq!(levels.len(), 2);
+        assert_eq!((levels[1].width, levels[1].height), (1, 1));
+
+        let mut renderer = PixelRenderer::new(2, 2);
+        renderer.insert_image("checker.png", checker);
+        let column = |x, height| Column {
+            x,
+            top: 0,
+            width: 1,
+            height,
+            texture: Path::new("checker.png"),
+            texture_x: 0.,
+            tint: Color::WHITE,
+            overlay: Color::RGBA(0, 0, 0, 0),
+        };
+        renderer.draw_column(&column(0, 2)).unwrap();
+        renderer.draw_column(&column(1, 1)).unwrap();
+
+        let grey = Color::RGB(0x7f, 0x7f, 0x7f).to_argb();
+        let pixels = &renderer.framebuffer.pixels;
+        assert_eq!(
+            (pixels[0], pixels[2]),
+            (Color::WHITE.to_argb(), Color::BLACK.to_argb())
+        );
+        assert_eq!(pixels[1], grey);
+    }
+
+    #[test]
+    fn missing_textures_are_drawn_as_a_checkerboard() {
+        let mut renderer = PixelRenderer::new(1, 16);
+        renderer
+            .draw_column(&Column {
+                x: 0,
+                top: 0,
+                width: 1,
+                height: 16,
+                texture: Path::new("does/not/exist.png"),
+                texture_x: 0.,
+                tint: Color::WHITE,
+                overlay: Color::RGBA(0, 0, 0, 0),
+            })
+            .unwrap();
+
+        let magenta = Color::RGB(0xff, 0, 0xff).to_argb();
+        let pixels = &renderer.framebuffer.pixels;
+        assert_eq!(pixels[0], magenta);
+        assert_eq!(pixels[15], Color::BLACK.to_argb());
+
+        // and only tried once
+        renderer.preload(Path::new("does/not/exist.png")).unwrap();
+        assert_eq!(renderer.cache_stats(), CacheStats { hits: 1, misses: 1 });
+    }
+
+    #[test]
+    fn lines_include_both_ends() {
+        let mut framebuffer = Framebuffer::new(4, 4);
+        framebuffer.draw_line((0, 0), (3, 3), Color::WHITE);
+
+        let white = Color::WHITE.to_argb();
+        for (i, pixel) in framebuffer.pixels.iter().enumerate() {
+            assert_eq!(*pixel == white, i % 5 == 0, "pixel {i}");
+        }
+    }
+
+    #[test]
+    fn blending_respects_alpha() {
+        let over = Color::RGBA(0xff, 0xff, 0xff, 0).blend_over(Color::BLACK);
+        assert_eq!(over, Color::BLACK);
+
+        let over = Color::RGBA(0xff, 0xff, 0xff, 0xff).blend_over(Color::BLACK);
+        assert_eq!(over, Color::WHITE);
+    }
+}
@@ -0,0 +1,17 @@
+//! Map loading, raycasting and software rendering for yaw, without any windowing dependencies.
+
+pub mod assets;
+pub mod camera;
+pub mod color;
+pub mod entity;
+pub mod framebuffer;
+pub mod light;
+pub mod lint;
+pub mod map;
+pub mod mapgen;
+pub mod particle;
+pub mod ray;
+pub mod render;
+pub mod thumbnail;
+pub mod trigger;
+pub mod visibility;
@@ -0,0 +1,272 @@
+//! static lighting baked once per map, lights are `light,x=..,y=..,radius=..,color=..` metas
+//!
+//! lights with `flicker=sine` or `flicker=random` are baked on their own and added to the
+//! steady ones every tick by [`flicker`]
+
+use crate::color::Color;
+use crate::map::{Map, Meta, TILE_SIZE};
+use glam::Vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::str::FromStr;
+
+/// light level of tiles no light reaches, in maps that have lights
+pub const AMBIENT: Color = Color::RGB(0x30, 0x30, 0x30);
+
+/// ticks a `sine` light takes to dim and brighten again
+const SINE_PERIOD: f32 = 60.;
+/// ticks a `random` light keeps its brightness, and how often it dims
+const RANDOM_TICKS: u64 = 3;
+const RANDOM_DIM_CHANCE: f64 = 0.2;
+
+/// how a light changes its brightness over time
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Flicker {
+    /// dims and brightens smoothly
+    Sine,
+    /// mostly on, dimming now and then like a broken lamp
+    Random,
+}
+
+impl Flicker {
+    /// how maps spell it
+    pub fn name(self) -> &'static str {
+        match self {
+            Flicker::Sine => "sine",
+            Flicker::Random => "random",
+        }
+    }
+
+    /// brightness from 0 to 1 on a tick, `seed` keeps lights from flickering in step
+    pub fn brightness(self, tick: u64, seed: u64) -> f32 {
+        match self {
+            Flicker::Sine => {
+                let phase = (seed % SINE_PERIOD as u64) as f32;
+                0.6 + (0.4 * ((tick as f32 + phase) * TAU / SINE_PERIOD).sin())
+            }
+            Flicker::Random => {
+                let mut rng = StdRng::seed_from_u64((tick / RANDOM_TICKS) ^ seed.rotate_left(32));
+                if rng.random_bool(RANDOM_DIM_CHANCE) {
+                    rng.random_range(0.1..0.5)
+                } else {
+                    1.
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Flicker {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "sine" => Ok(Flicker::Sine),
+            "random" => Ok(Flicker::Random),
+            other => anyhow::bail!("unrecognized flicker: {other}"),
+        }
+    }
+}
+
+/// light a flickering light adds when at its brightest
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Flickering {
+    pub flicker: Flicker,
+    /// tile the light is on, so lights flicker out of step
+    pub tile: usize,
+    /// tiles it reaches and the light it adds to each
+    pub reach: Vec<(usize, Color)>,
+}
+
+fn center(x: usize, y: usize) -> Vec2 {
+    (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE
+}
+
+/// `color` scaled by `intensity` added to `level`
+fn add(level: Color, color: Color, intensity: f32) -> Color {
+    let add =
+        |level: u8, channel: u8| (level as f32 + (channel as f32 * intensity)).min(255.) as u8;
+    Color::RGB(
+        add(level.r, color.r),
+        add(level.g, color.g),
+        add(level.b, color.b),
+    )
+}
+
+/// how strongly a light on `(x, y)` reaches each tile it reaches
+fn reach(map: &Map, (x, y): (usize, usize), radius: u8) -> Vec<(usize, f32)> {
+    let pos = center(x, y);
+    let radius = radius as f32 * TILE_SIZE;
+    (0..map.main_tiles.len())
+        .filter_map(|idx| {
+            // walls are lit by the tile in front of them
+            if map.colliding(map.idx_to_vec(idx), false).is_some() {
+                return None;
+            }
+
+            let offset = center(idx % map.width, idx / map.width) - pos;
+            let distance = offset.length();
+            // walls between the light and the tile cast shadows
+            (distance < radius && map.line_of_sight(pos, pos + offset))
+                .then(|| (idx, 1. - (distance / radius)))
+        })
+        .collect()
+}
+
+/// light of the steady lights reaching each tile, empty when the map has no lights at all so
+/// it stays fully lit
+pub fn bake(map: &Map) -> Vec<Color> {
+    let lights = map
+        .meta
+        .iter()
+        .filter_map(|meta| match meta {
+            Meta::Light {
+                x,
+                y,
+                radius,
+                color,
+                flicker,
+            } => Some(((*x, *y), *radius, *color, flicker.is_some())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if lights.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lightmap = vec![AMBIENT; map.main_tiles.len()];
+    for (tile, radius, color, _) in lights.into_iter().filter(|(.., flickers)| !flickers) {
+        for (idx, intensity) in reach(map, tile, radius) {
+            lightmap[idx] = add(lightmap[idx], color, intensity);
+        }
+    }
+
+    lightmap
+}
+
+/// light of each flickering light at its brightest, by tile so the order is always the same
+pub fn bake_flickers(map: &Map) -> Vec<Flickering> {
+    let mut flickers = map
+        .meta
+        .iter()
+        .filter_map(|meta| match meta {
+            Meta::Light {
+                x,
+                y,
+                radius,
+                color,
+                flicker: Some(flicker),
+            } => Some(Flickering {
+                flicker: *flicker,
+                tile: (y * map.width) + x,
+                reach: reach(map, (*x, *y), *radius)
+                    .into_iter()
+                    .map(|(idx, intensity)| (idx, add(Color::BLACK, *color, intensity)))
+                    .collect(),
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    flickers.sort_by_key(|light| light.tile);
+    flickers
+}
+
+/// light reaching each tile on a tick, the steady light of `base` with the flickering lights
+/// added as bright as they are then
+pub fn flicker(base: &[Color], flickers: &[Flickering], tick: u64) -> Vec<Color> {
+    add_flickers(base, flickers, |light| {
+        light.flicker.brightness(tick, light.tile as u64)
+    })
+}
+
+/// light reaching each tile with the flickering lights at their brightest, on every tick
+pub fn steady(base: &[Color], flickers: &[Flickering]) -> Vec<Color> {
+    add_flickers(base, flickers, |_| 1.)
+}
+
+fn add_flickers(
+    base: &[Color],
+    flickers: &[Flickering],
+    brightness: impl Fn(&Flickering) -> f32,
+) -> Vec<Color> {
+    let mut lightmap = base.to_vec();
+    for light in flickers {
+        let brightness = brightness(light);
+        for (idx, color) in light.reach.iter() {
+            lightmap[*idx] = add(lightmap[*idx], *color, brightness);
+        }
+    }
+    lightmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const ROOMS: &str = "!!!!META
+light,x=1,y=1,radius=5,color=#ff0000
+
+!!!!MAIN
+#wall.png,collide
+
+#######
+#*  # #
+#   # #
+#######";
+
+    #[test]
+    fn lights_fade_and_stop_at_walls() {
+        let map = Map::parse(ROOMS, Path::new("")).unwrap();
+        let lightmap = bake(&map);
+        let at = |x, y| lightmap[(y * map.width) + x];
+
+        assert_eq!(at(1, 1), Color::RGB(0xff, 0x30, 0x30));
+        assert!(at(2, 1).r > at(3, 1).r);
+        assert!(at(3, 1).r > AMBIENT.r);
+        // behind the wall, within the radius
+        assert_eq!(at(5, 1), AMBIENT);
+    }
+
+    #[test]
+    fn flickering_lights_change_over_time() {
+        let text = ROOMS.replace("color=#ff0000", "color=#ff0000,flicker=sine");
+        let mut map = Map::parse(&text, Path::new("")).unwrap();
+        let at = |map: &Map| map.light(Vec2::splat(48.));
+        let brightest = Color::RGB(0xff, 0x30, 0x30);
+
+        let mut seen = Vec::new();
+        for tick in 0..SINE_PERIOD as u64 {
+            // flickering changes no tiles
+            assert!(!map.set_tick(tick));
+            seen.push(at(&map).r);
+        }
+        assert!(seen.iter().all(|r| (AMBIENT.r..=brightest.r).contains(r)));
+        assert!(seen.iter().max() > seen.iter().min());
+        // behind the wall stays dark
+        assert_eq!(map.light(Vec2::new(176., 48.)), AMBIENT);
+
+        // steady lights hold still at their brightest
+        map.steady = true;
+        for tick in 0..SINE_PERIOD as u64 {
+            map.set_tick(tick);
+            assert_eq!(at(&map), brightest);
+        }
+
+        for tick in 0..100 {
+            let brightness = Flicker::Random.brightness(tick, 3);
+            assert!((0.1..=1.).contains(&brightness));
+            assert_eq!(brightness, Flicker::Random.brightness(tick, 3));
+        }
+        assert!("strobe".parse::<Flicker>().is_err());
+    }
+
+    #[test]
+    fn maps_without_lights_are_fully_lit() {
+        let map = Map::parse("!!!!MAIN\n#wall.png\n\n#*", Path::new("")).unwrap();
+        assert!(bake(&map).is_empty());
+        assert_eq!(map.light(Vec2::new(48., 16.)), Color::WHITE);
+    }
+}
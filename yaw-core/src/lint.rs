@@ -0,0 +1,168 @@
+//! checks of a map beyond whether it can be read, for map authors
+//!
+//! tiles count as open when players can get through them at some point: walls that open on
+//! a cycle or a signal, crates and walls triggers replace are all open
+
+use crate::map::{Map, Tile};
+use crate::trigger::Action;
+use std::collections::{HashSet, VecDeque};
+
+/// what is wrong with a map, errors keep it from being played properly
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Report {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// look for spawns, exits and open tiles players cannot get to
+pub fn check(map: &Map) -> Report {
+    let mut report = Report::default();
+    let replaced = map
+        .triggers
+        .iter()
+        .filter_map(|trigger| match trigger.action {
+            Action::SetTile { at, .. } => Some((at.1 * map.width) + at.0),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let open = (0..map.main_tiles.len())
+        .map(|idx| replaced.contains(&idx) || is_open(map, idx))
+        .collect::<Vec<_>>();
+
+    let spawns = map
+        .main_tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| matches!(tile, Tile::Spawn | Tile::NamedSpawn(_)))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    if !map.main_tiles.contains(&Tile::Spawn) {
+        report.errors.push("no `*` spawn to start at".into());
+    }
+
+    let reached = flood(map, &open, &spawns);
+    let exits = (0..map.main_tiles.len())
+        .filter(|idx| is_exit(map, *idx))
+        .collect::<Vec<_>>();
+    if exits.is_empty() {
+        report
+            .warnings
+            .push("no exit, the level cannot be finished".into());
+    } else if !spawns.is_empty() && exits.iter().all(|idx| !reached[*idx]) {
+        let (x, y) = (exits[0] % map.width, exits[0] / map.width);
+        report.errors.push(format!(
+            "no exit can be reached from a spawn, like the one at {x},{y}"
+        ));
+    }
+
+    // one warning for each area cut off from the rest, instead of each tile of it
+    let mut seen = reached.clone();
+    for idx in 0..open.len() {
+        if !open[idx] || seen[idx] || spawns.is_empty() {
+            continue;
+        }
+        let area = flood(map, &open, &[idx]);
+        let tiles = area.iter().filter(|reached| **reached).count();
+        seen.iter_mut()
+            .zip(&area)
+            .for_each(|(seen, reached)| *seen |= reached);
+        let (x, y) = (idx % map.width, idx / map.width);
+        report.warnings.push(format!(
+            "{tiles} open tiles cannot be reached from a spawn, starting at {x},{y}"
+        ));
+    }
+
+    for idx in (0..reached.len()).filter(|idx| reached[*idx]) {
+        let (x, y) = (idx % map.width, idx / map.width);
+        if x == 0 || y == 0 || x + 1 == map.width || y + 1 == map.height {
+            report.warnings.push(format!(
+                "players can walk off the edge of the map at {x},{y}"
+            ));
+        }
+    }
+
+    report
+}
+
+fn is_open(map: &Map, idx: usize) -> bool {
+    let Tile::Custom(id) = &map.main_tiles[idx] else {
+        return true;
+    };
+    map.custom_tiles.get(id).is_none_or(|tile| {
+        tile.floor
+            || !tile.collidable
+            || tile.pushable
+            || tile.cycle.is_some()
+            || tile.door.is_some()
+    })
+}
+
+fn is_exit(map: &Map, idx: usize) -> bool {
+    match &map.main_tiles[idx] {
+        Tile::Custom(id) => map.custom_tiles.get(id).is_some_and(|tile| tile.exit),
+        _ => false,
+    }
+}
+
+/// open tiles players can walk to from any of `starts`
+fn flood(map: &Map, open: &[bool], starts: &[usize]) -> Vec<bool> {
+    let mut reached = vec![false; open.len()];
+    let mut queue = starts.iter().copied().collect::<VecDeque<_>>();
+    for idx in starts {
+        reached[*idx] = true;
+    }
+    while let Some(idx) = queue.pop_front() {
+        let (x, y) = (idx % map.width, idx / map.width);
+        let neighbours = [
+            (x > 0).then(|| idx - 1),
+            (x + 1 < map.width).then(|| idx + 1),
+            (y > 0).then(|| idx - map.width),
+            (y + 1 < map.height).then(|| idx + map.width),
+        ];
+        for next in neighbours.into_iter().flatten() {
+            if open[next] && !reached[next] {
+                reached[next] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const TILES: &str = "!!!!MAIN\n#wall.png,collide\nEexit.png,floor,exit\n\n";
+
+    fn lint(rows: &str) -> Report {
+        check(&Map::parse(&format!("{TILES}{rows}"), Path::new("")).unwrap())
+    }
+
+    #[test]
+    fn finds_what_cannot_be_reached() {
+        assert_eq!(lint("#####\n#*  #\n#  E#\n#####\n"), Report::default());
+
+        let report = lint("#######\n#* # E#\n#######\n");
+        assert_eq!(report.errors.len(), 1, "{report:?}");
+        assert_eq!(
+            report.warnings,
+            ["2 open tiles cannot be reached from a spawn, starting at 4,1"]
+        );
+
+        let report = lint("####\n#*  \n####\n");
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            report.warnings,
+            [
+                "no exit, the level cannot be finished",
+                "players can walk off the edge of the map at 3,1"
+            ]
+        );
+
+        let report = lint("###\n#E#\n###\n");
+        assert_eq!(report.errors, ["no `*` spawn to start at"]);
+    }
+}
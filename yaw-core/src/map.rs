@@ -0,0 +1,2483 @@
+use crate::assets;
+use crate::color::{hex_color, parse_hex_color, Color};
+use crate::light::{self, Flicker, Flickering};
+use crate::ray::{self, Cardinal};
+use crate::trigger::{Action, Trigger};
+use anyhow::Context;
+use glam::Vec2;
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const TILE_SIZE: f32 = 32.;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Tile {
+    Empty,
+    Spawn,
+    /// spawn that can be picked by its id, declared with a `*<id>` line like `*1`
+    NamedSpawn(char),
+    Custom(char),
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomTile {
+    pub collidable: bool,
+    pub tex_path: String,
+    pub half_width: bool,
+    pub half_height: bool,
+    /// floor material only, never hit by rays or collided with
+    pub floor: bool,
+    /// standing on this tile finishes the level
+    pub exit: bool,
+    /// touching this tile is where players come back after dying
+    pub checkpoint: bool,
+    /// players wade through this tile slowly, its step sound plays as a splash when they step in
+    pub water: bool,
+    /// how far the floor of the tile is raised, in pixels up to `TILE_SIZE`, for stairs and
+    /// platforms
+    pub floor_height: f32,
+    /// how much faster players walk on this tile, below 1 for slower, like mud
+    pub speed_mul: f32,
+    /// players slide on this tile, speeding up and slowing down gradually, like on ice
+    pub slippery: bool,
+    /// a crate players push around instead of part of the map, see [`Map::take_pushables`]
+    pub pushable: bool,
+    /// someone players talk to instead of part of the map, this is the file of what they say,
+    /// see [`Map::take_npcs`]
+    pub dialogue: Option<String>,
+    pub step_sound: Option<String>,
+    /// what using the tile does, like `open`, shown to players looking at it
+    pub prompt: Option<String>,
+    /// texture strip to sample for each face, indexed by the face that was hit
+    pub faces: [Cardinal; 4],
+    /// whole images used instead of a strip for some faces, from `north=`, `east=`, `south=`
+    /// and `west=`, indexed by the face that was hit
+    pub face_textures: [Option<String>; 4],
+    /// sample the texture strips right to left
+    pub mirrored: bool,
+    /// color multiplied with the texture
+    pub tint: Color,
+    /// glows on its own, fog and shading do not apply
+    pub unlit: bool,
+    pub shape: Shape,
+    /// keeps opening and closing, like a crusher, solid all the time without one
+    pub cycle: Option<Cycle>,
+    /// using this tile turns a signal on or off, like a lever
+    pub switch: Option<String>,
+    /// a player or crate on this tile keeps a signal on, like a pressure plate
+    pub plate: Option<String>,
+    /// opens or closes with a signal, see [`Door`]
+    pub door: Option<Door>,
+}
+
+impl CustomTile {
+    /// wall drawn with the image at `tex_path`, relative to the map, that nothing collides
+    /// with, like a `#wall.png` line without flags
+    ///
+    /// ```
+    /// use yaw_core::map::CustomTile;
+    ///
+    /// let wall = CustomTile {
+    ///     collidable: true,
+    ///     ..CustomTile::new("wall.png")
+    /// };
+    /// ```
+    pub fn new(tex_path: impl Into<String>) -> Self {
+        Self {
+            collidable: false,
+            tex_path: tex_path.into(),
+            half_width: false,
+            half_height: false,
+            floor: false,
+            exit: false,
+            checkpoint: false,
+            water: false,
+            floor_height: 0.,
+            speed_mul: 1.,
+            slippery: false,
+            pushable: false,
+            dialogue: None,
+            step_sound: None,
+            prompt: None,
+            faces: [
+                Cardinal::North,
+                Cardinal::East,
+                Cardinal::South,
+                Cardinal::West,
+            ],
+            face_textures: [None, None, None, None],
+            mirrored: false,
+            tint: Color::WHITE,
+            unlit: false,
+            shape: Shape::Square,
+            cycle: None,
+            switch: None,
+            plate: None,
+            door: None,
+        }
+    }
+
+    /// definition line of the main section, without the id
+    fn to_line(&self) -> String {
+        let mut line = self.tex_path.clone();
+        let flags = [
+            (self.collidable, "collide"),
+            (self.half_width, "half_width"),
+            (self.half_height, "half_height"),
+            (self.floor, "floor"),
+            (self.exit, "exit"),
+            (self.checkpoint, "checkpoint"),
+            (self.water, "water"),
+            (self.slippery, "slippery"),
+            (self.pushable, "pushable"),
+            (self.unlit, "unlit"),
+        ];
+        for (_, flag) in flags.iter().filter(|(set, _)| *set) {
+            line += &format!(",{flag}");
+        }
+        if self.floor_height != 0. {
+            line += &format!(",floor_height={}", self.floor_height);
+        }
+        if self.speed_mul != 1. {
+            line += &format!(",speed_mul={}", self.speed_mul);
+        }
+        if let Some(dialogue) = &self.dialogue {
+            line += &format!(",dialogue={dialogue}");
+        }
+        if let Some(sound) = &self.step_sound {
+            line += &format!(",step_sound={sound}");
+        }
+        if let Some(prompt) = &self.prompt {
+            line += &format!(",prompt={prompt}");
+        }
+        for (key, texture) in ["north", "east", "south", "west"]
+            .iter()
+            .zip(&self.face_textures)
+        {
+            if let Some(texture) = texture {
+                line += &format!(",{key}={texture}");
+            }
+        }
+        match self.shape {
+            Shape::Square => {}
+            Shape::Diagonal(corner) => line += &format!(",diagonal={}", corner.name()),
+            Shape::Curve(corner) => line += &format!(",curve={}", corner.name()),
+        }
+        if let Some(cycle) = self.cycle {
+            line += &format!(",cycle={}", cycle.to_param());
+        }
+        if let Some(signal) = &self.switch {
+            line += &format!(",switch={signal}");
+        }
+        if let Some(signal) = &self.plate {
+            line += &format!(",plate={signal}");
+        }
+        match &self.door {
+            Some(door) if door.barrier => line += &format!(",barrier={}", door.signal),
+            Some(door) => line += &format!(",door={}", door.signal),
+            None => {}
+        }
+
+        line
+    }
+}
+
+/// a tile wired to the switches and plates of a signal
+///
+/// `door=gate` is open while the signal `gate` is on, `barrier=gate` is closed while it is on
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Door {
+    pub signal: String,
+    /// closes instead of opening
+    pub barrier: bool,
+}
+
+/// how long a tile stays open and closed in turn, in ticks
+///
+/// `cycle=60:30` is open for 60 ticks and then closed for 30, `cycle=60:30:45` starts 45 ticks
+/// into that
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Cycle {
+    pub open: u32,
+    pub closed: u32,
+    pub offset: u32,
+}
+
+impl Cycle {
+    /// whether the tile is closed on a tick
+    pub fn closed_at(self, tick: u64) -> bool {
+        (tick + self.offset as u64) % (self.open + self.closed) as u64 >= self.open as u64
+    }
+
+    fn to_param(self) -> String {
+        match self.offset {
+            0 => format!("{}:{}", self.open, self.closed),
+            offset => format!("{}:{}:{offset}", self.open, self.closed),
+        }
+    }
+}
+
+impl FromStr for Cycle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let ticks = s
+            .split(':')
+            .map(str::parse)
+            .collect::<Result<Vec<u32>, _>>()
+            .with_context(|| format!("invalid cycle: {s}"))?;
+        let (open, closed, offset) = match ticks[..] {
+            [open, closed] => (open, closed, 0),
+            [open, closed, offset] => (open, closed, offset),
+            _ => anyhow::bail!("expected open:closed or open:closed:offset ticks: {s}"),
+        };
+        anyhow::ensure!(open + closed > 0, "cycle can not be 0 ticks long: {s}");
+
+        Ok(Self {
+            open,
+            closed,
+            offset,
+        })
+    }
+}
+
+/// corner of a tile
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Corner {
+    NorthEast,
+    SouthEast,
+    SouthWest,
+    NorthWest,
+}
+
+impl Corner {
+    /// how maps spell the corner
+    fn name(self) -> &'static str {
+        match self {
+            Corner::NorthEast => "ne",
+            Corner::SouthEast => "se",
+            Corner::SouthWest => "sw",
+            Corner::NorthWest => "nw",
+        }
+    }
+
+    /// position within a tile, from 0 to `TILE_SIZE` on each axis
+    pub fn offset(self) -> Vec2 {
+        match self {
+            Corner::NorthEast => Vec2::new(TILE_SIZE, 0.),
+            Corner::SouthEast => Vec2::splat(TILE_SIZE),
+            Corner::SouthWest => Vec2::new(0., TILE_SIZE),
+            Corner::NorthWest => Vec2::ZERO,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Corner::NorthEast => Corner::SouthWest,
+            Corner::SouthEast => Corner::NorthWest,
+            Corner::SouthWest => Corner::NorthEast,
+            Corner::NorthWest => Corner::SouthEast,
+        }
+    }
+
+    /// face whose texture strip is used for walls cutting off this corner
+    pub fn face(self) -> Cardinal {
+        match self {
+            Corner::NorthEast => Cardinal::North,
+            Corner::SouthEast => Cardinal::East,
+            Corner::SouthWest => Cardinal::South,
+            Corner::NorthWest => Cardinal::West,
+        }
+    }
+
+    /// where the corner ends up after the transformation
+    fn transform(self, transform: Transform) -> Self {
+        match (transform, self) {
+            (Transform::FlipH, Corner::NorthEast) => Corner::NorthWest,
+            (Transform::FlipH, Corner::NorthWest) => Corner::NorthEast,
+            (Transform::FlipH, Corner::SouthEast) => Corner::SouthWest,
+            (Transform::FlipH, Corner::SouthWest) => Corner::SouthEast,
+            (Transform::FlipV, Corner::NorthEast) => Corner::SouthEast,
+            (Transform::FlipV, Corner::SouthEast) => Corner::NorthEast,
+            (Transform::FlipV, Corner::NorthWest) => Corner::SouthWest,
+            (Transform::FlipV, Corner::SouthWest) => Corner::NorthWest,
+            (Transform::Rot90, Corner::NorthEast) => Corner::SouthEast,
+            (Transform::Rot90, Corner::SouthEast) => Corner::SouthWest,
+            (Transform::Rot90, Corner::SouthWest) => Corner::NorthWest,
+            (Transform::Rot90, Corner::NorthWest) => Corner::NorthEast,
+        }
+    }
+}
+
+impl FromStr for Corner {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "ne" => Ok(Corner::NorthEast),
+            "se" => Ok(Corner::SouthEast),
+            "sw" => Ok(Corner::SouthWest),
+            "nw" => Ok(Corner::NorthWest),
+            other => anyhow::bail!("unrecognized corner: {other}"),
+        }
+    }
+}
+
+/// geometry of a wall tile as rays see it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Shape {
+    #[default]
+    Square,
+    /// straight wall from corner to corner, `diagonal=ne` cuts away the north east corner
+    Diagonal(Corner),
+    /// quarter circle around the opposite corner, `curve=ne` rounds off the north east corner
+    Curve(Corner),
+}
+
+impl Shape {
+    /// read the shape from the parameters of a tile definition
+    fn parse(params: &[&str]) -> anyhow::Result<Self> {
+        for param in params {
+            if let Some(corner) = param.strip_prefix("diagonal=") {
+                return Ok(Shape::Diagonal(corner.parse()?));
+            }
+            if let Some(corner) = param.strip_prefix("curve=") {
+                return Ok(Shape::Curve(corner.parse()?));
+            }
+        }
+
+        Ok(Shape::Square)
+    }
+
+    /// whether a position within the tile is solid
+    pub fn contains(self, local: Vec2) -> bool {
+        match self {
+            Shape::Square => true,
+            Shape::Diagonal(corner) => {
+                let center = Vec2::splat(TILE_SIZE / 2.);
+                (local - center).dot(corner.offset() - center) <= 0.
+            }
+            Shape::Curve(corner) => local.distance(corner.opposite().offset()) <= TILE_SIZE,
+        }
+    }
+
+    fn transform(self, transform: Transform) -> Self {
+        match self {
+            Shape::Square => Shape::Square,
+            Shape::Diagonal(corner) => Shape::Diagonal(corner.transform(transform)),
+            Shape::Curve(corner) => Shape::Curve(corner.transform(transform)),
+        }
+    }
+}
+
+/// transformations that can be applied to a whole map
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transform {
+    FlipH,
+    FlipV,
+    /// rotate clockwise by 90 degrees
+    Rot90,
+}
+
+impl Transform {
+    /// where a face ends up after the transformation
+    fn face(self, face: Cardinal) -> Cardinal {
+        match (self, face) {
+            (Transform::FlipH, Cardinal::East) => Cardinal::West,
+            (Transform::FlipH, Cardinal::West) => Cardinal::East,
+            (Transform::FlipV, Cardinal::North) => Cardinal::South,
+            (Transform::FlipV, Cardinal::South) => Cardinal::North,
+            (Transform::Rot90, Cardinal::North) => Cardinal::East,
+            (Transform::Rot90, Cardinal::East) => Cardinal::South,
+            (Transform::Rot90, Cardinal::South) => Cardinal::West,
+            (Transform::Rot90, Cardinal::West) => Cardinal::North,
+            (_, face) => face,
+        }
+    }
+}
+
+impl Transform {
+    /// where a tile of a map that was `width` by `height` tiles ends up
+    pub fn tile(self, (x, y): (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Transform::FlipH => (width - 1 - x, y),
+            Transform::FlipV => (x, height - 1 - y),
+            Transform::Rot90 => (height - 1 - y, x),
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "flip_h" => Ok(Transform::FlipH),
+            "flip_v" => Ok(Transform::FlipV),
+            "rot90" => Ok(Transform::Rot90),
+            other => anyhow::bail!("unrecognized map transform: {other}"),
+        }
+    }
+}
+
+/// how fog thickens with distance
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum FogCurve {
+    #[default]
+    Linear,
+    /// thickens quickly up close, then slowly
+    Exp,
+    /// stays thin up close, then thickens quickly
+    Exp2,
+}
+
+impl FogCurve {
+    /// how maps spell the curve
+    fn name(self) -> &'static str {
+        match self {
+            FogCurve::Linear => "linear",
+            FogCurve::Exp => "exp",
+            FogCurve::Exp2 => "exp2",
+        }
+    }
+
+    /// fog from 0 to 1, `distance` is 1 at the depth of field
+    pub fn amount(self, distance: f32) -> f32 {
+        let amount = match self {
+            FogCurve::Linear => distance,
+            FogCurve::Exp => 1. - (-4. * distance).exp(),
+            FogCurve::Exp2 => 1. - (-4. * distance * distance).exp(),
+        };
+        amount.clamp(0., 1.)
+    }
+}
+
+impl FromStr for FogCurve {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "linear" => Ok(FogCurve::Linear),
+            "exp" => Ok(FogCurve::Exp),
+            "exp2" => Ok(FogCurve::Exp2),
+            other => anyhow::bail!("unrecognized fog curve: {other}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Meta {
+    Fog {
+        dof: u8,
+        color: Color,
+        curve: FogCurve,
+    },
+    /// how many tiles rays travel before giving up
+    Dof(u16),
+    /// lights tiles within `radius` tiles, baked at load
+    Light {
+        x: usize,
+        y: usize,
+        radius: u8,
+        color: Color,
+        flicker: Option<Flicker>,
+    },
+    /// times players can come back from a checkpoint before the run is over
+    Lives(u8),
+    /// way the player faces entering at a spawn, the `*` one when `id` is none
+    SpawnFacing {
+        id: Option<char>,
+        facing: Cardinal,
+    },
+    /// color of the lower and upper half of the view, behind the walls
+    FloorColor(Color),
+    CeilingColor(Color),
+    /// how players move and how much health they start with on this map
+    Player(PlayerMeta),
+    /// exits only finish the level once every objective is done, see [`Map::objectives`]
+    ExitNeedsObjectives,
+}
+
+/// what a map changes about players, whatever is none stays as the game has it
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct PlayerMeta {
+    /// pixels walked forward or back per second
+    pub speed: Option<f32>,
+    /// pixels walked sideways per second
+    pub strafe_speed: Option<f32>,
+    /// health before difficulty is applied
+    pub health: Option<u8>,
+    /// radians turned per second
+    pub turn_speed: Option<f32>,
+}
+
+// speeds are positive and finite, see `Map::parse_meta`, so they equal themselves and equal
+// speeds have equal bits
+impl Eq for PlayerMeta {}
+
+impl Hash for PlayerMeta {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.speed.map(f32::to_bits).hash(state);
+        self.strafe_speed.map(f32::to_bits).hash(state);
+        self.health.hash(state);
+        self.turn_speed.map(f32::to_bits).hash(state);
+    }
+}
+
+impl Meta {
+    /// line of the meta section
+    fn to_line(self) -> String {
+        match self {
+            Meta::Fog { dof, color, curve } => format!(
+                "fog,dof={dof},color={},curve={}",
+                hex_color(color),
+                curve.name()
+            ),
+            Meta::Dof(steps) => format!("dof,steps={steps}"),
+            Meta::Light {
+                x,
+                y,
+                radius,
+                color,
+                flicker,
+            } => {
+                let mut line = format!(
+                    "light,x={x},y={y},radius={radius},color={}",
+                    hex_color(color)
+                );
+                if let Some(flicker) = flicker {
+                    line += &format!(",flicker={}", flicker.name());
+                }
+                line
+            }
+            Meta::Lives(count) => format!("lives,count={count}"),
+            Meta::SpawnFacing { id: None, facing } => format!("spawn,dir={}", facing.name()),
+            Meta::SpawnFacing {
+                id: Some(id),
+                facing,
+            } => format!("spawn,id={id},dir={}", facing.name()),
+            Meta::FloorColor(color) => format!("floor_color={}", hex_color(color)),
+            Meta::CeilingColor(color) => format!("ceiling_color={}", hex_color(color)),
+            Meta::ExitNeedsObjectives => "exit=objectives".into(),
+            Meta::Player(PlayerMeta {
+                speed,
+                strafe_speed,
+                health,
+                turn_speed,
+            }) => {
+                let mut line = "player".to_string();
+                if let Some(speed) = speed {
+                    line += &format!(",speed={speed}");
+                }
+                if let Some(strafe_speed) = strafe_speed {
+                    line += &format!(",strafe_speed={strafe_speed}");
+                }
+                if let Some(health) = health {
+                    line += &format!(",health={health}");
+                }
+                if let Some(turn_speed) = turn_speed {
+                    line += &format!(",turn_speed={turn_speed}");
+                }
+                line
+            }
+        }
+    }
+}
+
+/// a level: a grid of tiles with its tile definitions, meta and triggers
+///
+/// read one with [`Map::load`] or [`Map::parse`], or put one together with [`MapBuilder`]
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Map {
+    pub width: usize,
+    pub height: usize,
+    pub main_tiles: Vec<Tile>,
+    pub custom_tiles: HashMap<char, CustomTile>,
+    pub meta: HashSet<Meta>,
+    /// what the map is called and who made it, from `name=` and `author=` lines of the meta
+    /// section, which can have commas in them
+    pub name: Option<String>,
+    pub author: Option<String>,
+    /// seconds a good run of the level takes, from `par_time=<seconds>`
+    pub par_time: Option<u32>,
+    /// what players are asked to do in the level, in order, from `objective,<id>,<text>` lines
+    /// of the meta section. `complete` triggers mark them done
+    pub objectives: Vec<Objective>,
+    /// text files shown before the level starts and once it is finished, from `briefing=<file>`
+    /// and `debriefing=<file>` lines of the meta section, relative to the map
+    pub briefing: Option<String>,
+    pub debriefing: Option<String>,
+    pub triggers: Vec<Trigger>,
+    /// light reaching each tile, empty when the map has no lights
+    pub lightmap: Vec<Color>,
+    /// light of the steady lights, that flickering ones are added to
+    base_light: Vec<Color>,
+    pub flickers: Vec<Flickering>,
+    /// flickering lights shine steadily at their brightest instead, for players bothered by
+    /// flashing lights
+    #[serde(skip)]
+    pub steady: bool,
+    /// tick of the simulation, for tiles that open and close
+    #[serde(skip)]
+    tick: u64,
+    /// signals of the switches that were used, until they are used again
+    #[serde(skip)]
+    switched: BTreeSet<String>,
+    /// signals that are on, from switches and plates, doors wired to them are open
+    #[serde(skip)]
+    signals: BTreeSet<String>,
+    #[serde(skip)]
+    prefix: PathBuf,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Objective {
+    /// what `complete` triggers call it
+    pub id: String,
+    /// what players are told to do
+    pub text: String,
+}
+
+/// start of maps written by [`Map::compile`]
+const COMPILED_MAGIC: &[u8] = b"YAWB";
+/// bumped whenever the layout of [`Map`] changes
+const COMPILED_VERSION: u8 = 17;
+
+/// what a compiled map file holds after its header
+#[derive(Serialize, Deserialize)]
+struct Compiled {
+    map: Map,
+    /// textures and text files relative to the map
+    textures: Vec<(PathBuf, Vec<u8>)>,
+}
+
+/// the map inside an archive written by [`Map::pack`]
+const PAK_MAP: &str = "map.yaw";
+
+impl Map {
+    /// load a `.yaw` map, one compiled with [`Map::compile`] or an archive from [`Map::pack`]
+    pub fn load(name: PathBuf) -> anyhow::Result<Self> {
+        log::info!("loading map at {}", name.display());
+        let bytes = assets::read(&name)?;
+        if bytes.starts_with(assets::PAK_MAGIC) {
+            assets::mount(&name, &bytes)?;
+            return Self::load(name.join(PAK_MAP));
+        }
+
+        let prefix = name.parent().unwrap_or(Path::new(""));
+        if bytes.starts_with(COMPILED_MAGIC) {
+            return Self::decode(&bytes, prefix);
+        }
+
+        Self::parse(std::str::from_utf8(&bytes)?, prefix)
+    }
+
+    /// binary form of this map that loads without parsing, `embed` puts its textures inside
+    pub fn compile(&self, embed: bool) -> anyhow::Result<Vec<u8>> {
+        let mut textures = Vec::new();
+        if embed {
+            for path in self.files() {
+                let bytes = assets::read(&path)
+                    .with_context(|| format!("could not embed {}", path.display()))?;
+                let relative = path.strip_prefix(&self.prefix).unwrap_or(&path);
+                textures.push((relative.into(), bytes.to_vec()));
+            }
+        }
+
+        let compiled = Compiled {
+            map: self.clone(),
+            textures,
+        };
+        let mut bytes = COMPILED_MAGIC.to_vec();
+        bytes.push(COMPILED_VERSION);
+        bytes.extend(postcard::to_stdvec(&compiled)?);
+
+        Ok(bytes)
+    }
+
+    /// archive holding the map at `path` and every file it uses, loaded with [`Map::load`]
+    pub fn pack(path: &Path) -> anyhow::Result<Vec<u8>> {
+        let map = Self::load(path.into())?;
+        let mut files = vec![(PAK_MAP.into(), assets::read(path)?.to_vec())];
+        for file in map.files() {
+            let bytes = assets::read(&file)
+                .with_context(|| format!("could not pack {}", file.display()))?;
+            let relative = file.strip_prefix(&map.prefix).unwrap_or(&file);
+            files.push((relative.into(), bytes.to_vec()));
+        }
+
+        assets::pack(files)
+    }
+
+    /// read a map written by [`Map::compile`], embedded textures are served by [`assets`]
+    pub fn decode(bytes: &[u8], prefix: &Path) -> anyhow::Result<Self> {
+        let Some([version, body @ ..]) = bytes.strip_prefix(COMPILED_MAGIC) else {
+            anyhow::bail!("not a compiled map");
+        };
+        anyhow::ensure!(
+            *version == COMPILED_VERSION,
+            "map was compiled by another version, version {version} instead of {COMPILED_VERSION}"
+        );
+
+        let Compiled { mut map, textures } = postcard::from_bytes(body)?;
+        map.prefix = prefix.into();
+        for (path, bytes) in textures {
+            assets::insert(prefix.join(path), bytes);
+        }
+
+        Ok(map)
+    }
+
+    /// parse a map, paths in it are relative to `prefix`
+    pub fn parse(file: &str, prefix: &Path) -> anyhow::Result<Self> {
+        let mut lines = file.lines();
+        let mut this = Self {
+            prefix: prefix.into(),
+            ..Self::default()
+        };
+
+        while let Some(line) = lines.by_ref().next() {
+            match line {
+                "!!!!META" => this.parse_meta(&mut lines)?,
+                "!!!!MAIN" => this.parse_main(&mut lines)?,
+                "!!!!TRIGGERS" => this.parse_triggers(&mut lines)?,
+                other => anyhow::bail!("unrecognized directive: {other}"),
+            }
+        }
+
+        this.finish()
+    }
+
+    /// check that everything the map refers to exists and bake its lights
+    fn finish(mut self) -> anyhow::Result<Self> {
+        for trigger in self.triggers.iter() {
+            if let Action::SetTile {
+                tile: Tile::Custom(id),
+                ..
+            } = trigger.action
+            {
+                if !self.custom_tiles.contains_key(&id) {
+                    anyhow::bail!("trigger sets unknown tile: {id}");
+                }
+            }
+            if let Action::Complete(id) = &trigger.action {
+                anyhow::ensure!(
+                    self.objective(id).is_some(),
+                    "trigger completes unknown objective: {id}"
+                );
+            }
+        }
+        self.bake_lights();
+
+        Ok(self)
+    }
+
+    /// the map in the text format [`Map::parse`] reads, paths are written relative to where
+    /// the map was loaded from
+    ///
+    /// transformations and [`Map::randomize`] are not kept, they are written as they were
+    /// before
+    pub fn to_yaw(&self) -> String {
+        let mut out = String::new();
+
+        let values = [
+            ("author", self.author.clone()),
+            ("briefing", self.briefing.clone()),
+            ("debriefing", self.debriefing.clone()),
+            ("name", self.name.clone()),
+            ("par_time", self.par_time.map(|seconds| seconds.to_string())),
+        ];
+        if !self.meta.is_empty()
+            || !self.objectives.is_empty()
+            || values.iter().any(|(_, value)| value.is_some())
+        {
+            // sets are unordered, sort so the same map is always written the same
+            let mut lines = self
+                .meta
+                .iter()
+                .map(|meta| meta.to_line())
+                .collect::<Vec<_>>();
+            lines.sort();
+            lines.extend(
+                values
+                    .iter()
+                    .filter_map(|(key, value)| Some(format!("{key}={}", value.as_ref()?))),
+            );
+            lines.extend(
+                self.objectives
+                    .iter()
+                    .map(|objective| format!("objective,{},{}", objective.id, objective.text)),
+            );
+            out += "!!!!META\n";
+            for line in lines {
+                out += &line;
+                out += "\n";
+            }
+            out += "\n";
+        }
+
+        let mut ids = self.custom_tiles.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        let spawn_ids = self
+            .spawns()
+            .into_iter()
+            .filter_map(|(id, _)| id)
+            .collect::<BTreeSet<_>>();
+        out += "!!!!MAIN\n";
+        for id in ids {
+            out.push(id);
+            out += &self.custom_tiles[&id].to_line();
+            out += "\n";
+        }
+        for id in spawn_ids {
+            out.push('*');
+            out.push(id);
+            out += "\n";
+        }
+        out += "\n";
+        for row in self.main_tiles.chunks(self.width.max(1)) {
+            out.extend(row.iter().map(|tile| match tile {
+                Tile::Empty => ' ',
+                Tile::Spawn => '*',
+                Tile::NamedSpawn(id) | Tile::Custom(id) => *id,
+            }));
+            out += "\n";
+        }
+
+        if !self.triggers.is_empty() {
+            out += "\n!!!!TRIGGERS\n";
+            for trigger in self.triggers.iter() {
+                out += &trigger.to_line(&self.prefix);
+                out += "\n";
+            }
+        }
+
+        out
+    }
+
+    fn parse_meta<'lines>(
+        &mut self,
+        mut lines: impl Iterator<Item = &'lines str>,
+    ) -> anyhow::Result<()> {
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+
+            // names can have commas in them
+            if let Some(name) = line.strip_prefix("name=") {
+                self.name = Some(name.into());
+                continue;
+            }
+            if let Some(author) = line.strip_prefix("author=") {
+                self.author = Some(author.into());
+                continue;
+            }
+
+            // the text of objectives can have commas in it
+            if let Some(objective) = line.strip_prefix("objective,") {
+                let (id, text) = objective
+                    .split_once(',')
+                    .context("expected objective,<id>,<text>")?;
+                anyhow::ensure!(
+                    !id.is_empty() && !text.is_empty(),
+                    "objectives need an id and text: {line}"
+                );
+                anyhow::ensure!(
+                    self.objective(id).is_none(),
+                    "more than one objective is called {id}"
+                );
+                self.objectives.push(Objective {
+                    id: id.into(),
+                    text: text.into(),
+                });
+                continue;
+            }
+
+            let mut chunks = line.split(',');
+            let directive = chunks.by_ref().next().unwrap();
+            // directives of a single value are written `name=value`
+            let (directive, value) = directive
+                .split_once('=')
+                .map_or((directive, None), |(name, value)| (name, Some(value)));
+            let params = chunks
+                .map(|param| param.split_once('='))
+                .collect::<Option<HashMap<_, _>>>()
+                .context("incorrectly formatted meta")?;
+            match directive {
+                "fog" => {
+                    self.meta.insert(Meta::Fog {
+                        dof: params.get("dof").unwrap_or(&"4").parse()?,
+                        color: parse_hex_color(params.get("color").unwrap_or(&"#000000"))?,
+                        curve: params.get("curve").unwrap_or(&"linear").parse()?,
+                    });
+                }
+                "dof" => {
+                    let steps = params.get("steps").context("missing steps")?;
+                    self.meta.insert(Meta::Dof(steps.parse()?));
+                }
+                "light" => {
+                    let param = |key| params.get(key).copied().context(format!("missing {key}"));
+                    self.meta.insert(Meta::Light {
+                        x: param("x")?.parse()?,
+                        y: param("y")?.parse()?,
+                        radius: params.get("radius").unwrap_or(&"4").parse()?,
+                        color: parse_hex_color(params.get("color").unwrap_or(&"#ffffff"))?,
+                        flicker: params
+                            .get("flicker")
+                            .map(|flicker| flicker.parse())
+                            .transpose()?,
+                    });
+                }
+                "lives" => {
+                    let count = params.get("count").context("missing count")?;
+                    self.meta.insert(Meta::Lives(count.parse()?));
+                }
+                "spawn" => {
+                    let id = match params.get("id") {
+                        Some(id) => {
+                            let mut chars = id.chars();
+                            let (Some(id), None) = (chars.next(), chars.next()) else {
+                                anyhow::bail!("spawn ids are one character: {id}");
+                            };
+                            Some(id)
+                        }
+                        None => None,
+                    };
+                    let facing = params.get("dir").context("missing dir")?;
+                    self.meta.insert(Meta::SpawnFacing {
+                        id,
+                        facing: facing.parse()?,
+                    });
+                }
+                "par_time" => {
+                    let seconds = value.context("missing seconds")?;
+                    self.par_time = Some(seconds.parse()?);
+                }
+                "briefing" => self.briefing = Some(value.context("missing file")?.into()),
+                "debriefing" => self.debriefing = Some(value.context("missing file")?.into()),
+                "exit" => match value {
+                    Some("objectives") => {
+                        self.meta.insert(Meta::ExitNeedsObjectives);
+                    }
+                    _ => anyhow::bail!("exits can only need objectives: {line}"),
+                },
+                "floor_color" => {
+                    let color = value.context("missing color")?;
+                    self.meta.insert(Meta::FloorColor(parse_hex_color(color)?));
+                }
+                "ceiling_color" => {
+                    let color = value.context("missing color")?;
+                    self.meta
+                        .insert(Meta::CeilingColor(parse_hex_color(color)?));
+                }
+                "player" => {
+                    let speed = |key| {
+                        params
+                            .get(key)
+                            .map(|speed| {
+                                let speed = speed.parse::<f32>()?;
+                                anyhow::ensure!(
+                                    speed.is_finite() && speed > 0.,
+                                    "{key} must be above 0: {speed}"
+                                );
+                                Ok(speed)
+                            })
+                            .transpose()
+                    };
+                    self.meta.insert(Meta::Player(PlayerMeta {
+                        speed: speed("speed")?,
+                        strafe_speed: speed("strafe_speed")?,
+                        health: params
+                            .get("health")
+                            .map(|health| health.parse())
+                            .transpose()?,
+                        turn_speed: speed("turn_speed")?,
+                    }));
+                }
+                other => anyhow::bail!("unrecognized meta directive: {other}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_triggers<'lines>(
+        &mut self,
+        mut lines: impl Iterator<Item = &'lines str>,
+    ) -> anyhow::Result<()> {
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+
+            let trigger = Trigger::parse(line, &self.prefix)
+                .with_context(|| format!("invalid trigger: {line}"))?;
+            self.triggers.push(trigger);
+        }
+
+        Ok(())
+    }
+
+    fn parse_main<'lines>(
+        &mut self,
+        mut lines: impl Iterator<Item = &'lines str>,
+    ) -> anyhow::Result<()> {
+        let mut custom_tiles = HashMap::new();
+        let mut spawn_ids = HashSet::new();
+
+        for s in lines.by_ref() {
+            if s.is_empty() {
+                break;
+            }
+
+            let mut chars = s.chars();
+            let id = chars.by_ref().next().unwrap();
+            if id == '*' {
+                let (Some(spawn), None) = (chars.next(), chars.next()) else {
+                    anyhow::bail!("expected one character after * for a named spawn: {s}");
+                };
+                spawn_ids.insert(spawn);
+                continue;
+            }
+            let other_raw = chars.collect::<String>();
+            let other = other_raw.split(',').collect::<Vec<_>>();
+
+            custom_tiles.insert(
+                id,
+                CustomTile {
+                    collidable: other.contains(&"collide"),
+                    tex_path: other[0].into(),
+                    half_width: other.contains(&"half_width"),
+                    half_height: other.contains(&"half_height"),
+                    floor: other.contains(&"floor"),
+                    exit: other.contains(&"exit"),
+                    checkpoint: other.contains(&"checkpoint"),
+                    water: other.contains(&"water"),
+                    floor_height: match other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("floor_height="))
+                    {
+                        Some(height) => {
+                            let height = height.parse()?;
+                            anyhow::ensure!(
+                                (0. ..TILE_SIZE).contains(&height),
+                                "floor_height must be from 0 to below {TILE_SIZE}: {s}"
+                            );
+                            height
+                        }
+                        None => 0.,
+                    },
+                    speed_mul: match other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("speed_mul="))
+                    {
+                        Some(mul) => {
+                            let mul: f32 = mul.parse()?;
+                            anyhow::ensure!(
+                                mul.is_finite() && mul > 0.,
+                                "speed_mul must be above 0: {s}"
+                            );
+                            mul
+                        }
+                        None => 1.,
+                    },
+                    slippery: other.contains(&"slippery"),
+                    pushable: other.contains(&"pushable"),
+                    dialogue: other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("dialogue="))
+                        .map(Into::into),
+                    step_sound: other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("step_sound="))
+                        .map(Into::into),
+                    prompt: other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("prompt="))
+                        .map(Into::into),
+                    faces: [
+                        Cardinal::North,
+                        Cardinal::East,
+                        Cardinal::South,
+                        Cardinal::West,
+                    ],
+                    face_textures: ["north=", "east=", "south=", "west="].map(|key| {
+                        other
+                            .iter()
+                            .find_map(|param| param.strip_prefix(key))
+                            .map(Into::into)
+                    }),
+                    mirrored: false,
+                    tint: Color::WHITE,
+                    unlit: other.contains(&"unlit"),
+                    shape: Shape::parse(&other)?,
+                    cycle: other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("cycle="))
+                        .map(str::parse)
+                        .transpose()?,
+                    switch: other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("switch="))
+                        .map(Into::into),
+                    plate: other
+                        .iter()
+                        .find_map(|param| param.strip_prefix("plate="))
+                        .map(Into::into),
+                    door: match (
+                        other.iter().find_map(|param| param.strip_prefix("door=")),
+                        other
+                            .iter()
+                            .find_map(|param| param.strip_prefix("barrier=")),
+                    ) {
+                        (Some(_), Some(_)) => {
+                            anyhow::bail!("a tile can not be both a door and a barrier: {s}")
+                        }
+                        (Some(signal), None) => Some(Door {
+                            signal: signal.into(),
+                            barrier: false,
+                        }),
+                        (None, Some(signal)) => Some(Door {
+                            signal: signal.into(),
+                            barrier: true,
+                        }),
+                        (None, None) => None,
+                    },
+                },
+            );
+        }
+
+        for id in spawn_ids.iter() {
+            anyhow::ensure!(
+                !custom_tiles.contains_key(id) && !matches!(id, ' ' | '*'),
+                "{id:?} can not be both a spawn and a tile"
+            );
+        }
+
+        let mut height = 0;
+        let mut tiles = vec![];
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+
+            height += 1;
+            for tile in line.chars() {
+                tiles.push(match tile {
+                    ' ' => Tile::Empty,
+                    '*' => Tile::Spawn,
+                    tile if spawn_ids.contains(&tile) => Tile::NamedSpawn(tile),
+                    tile if custom_tiles.contains_key(&tile) => Tile::Custom(tile),
+                    other => anyhow::bail!("invalid tile in map: {other}"),
+                });
+            }
+        }
+
+        self.width = tiles.len() / height;
+        self.height = height;
+        self.main_tiles = tiles;
+        self.custom_tiles = custom_tiles;
+
+        Ok(())
+    }
+
+    /// flip or rotate the map, keeping textures facing the right way
+    pub fn transform(&mut self, transform: Transform) {
+        log::info!("applying map transform {transform:?}");
+        let (width, height) = match transform {
+            Transform::FlipH | Transform::FlipV => (self.width, self.height),
+            Transform::Rot90 => (self.height, self.width),
+        };
+
+        let mut tiles = vec![Tile::Empty; self.main_tiles.len()];
+        for (idx, tile) in self.main_tiles.drain(..).enumerate() {
+            let (new_x, new_y) = transform.tile(
+                (idx % self.width, idx / self.width),
+                self.width,
+                self.height,
+            );
+            tiles[(new_y * width) + new_x] = tile;
+        }
+
+        self.meta = self
+            .meta
+            .drain()
+            .map(|meta| match meta {
+                Meta::Light {
+                    x,
+                    y,
+                    radius,
+                    color,
+                    flicker,
+                } => {
+                    let (x, y) = transform.tile((x, y), self.width, self.height);
+                    Meta::Light {
+                        x,
+                        y,
+                        radius,
+                        color,
+                        flicker,
+                    }
+                }
+                Meta::SpawnFacing { id, facing } => Meta::SpawnFacing {
+                    id,
+                    facing: transform.face(facing),
+                },
+                other => other,
+            })
+            .collect();
+
+        for trigger in self.triggers.iter_mut() {
+            trigger.transform(transform, self.width, self.height);
+        }
+
+        for tile in self.custom_tiles.values_mut() {
+            let mut faces = tile.faces;
+            let mut face_textures = tile.face_textures.clone();
+            for face in [
+                Cardinal::North,
+                Cardinal::East,
+                Cardinal::South,
+                Cardinal::West,
+            ] {
+                faces[transform.face(face) as usize] = tile.faces[face as usize];
+                face_textures[transform.face(face) as usize] =
+                    tile.face_textures[face as usize].clone();
+            }
+            tile.faces = faces;
+            tile.face_textures = face_textures;
+
+            tile.shape = tile.shape.transform(transform);
+            match transform {
+                Transform::FlipH | Transform::FlipV => tile.mirrored = !tile.mirrored,
+                Transform::Rot90 => std::mem::swap(&mut tile.half_width, &mut tile.half_height),
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+        self.main_tiles = tiles;
+        self.bake_lights();
+    }
+
+    fn bake_lights(&mut self) {
+        self.base_light = light::bake(self);
+        self.flickers = light::bake_flickers(self);
+        self.lightmap = light::flicker(&self.base_light, &self.flickers, self.tick);
+    }
+
+    /// shuffle which texture belongs to which tile and tint them, same seed gives the same map
+    pub fn randomize(&mut self, seed: u64) {
+        log::info!("randomizing map with seed {seed}");
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        // hashmap order is random, sort so the seed is all that matters
+        let mut ids = self.custom_tiles.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        let mut tex_paths = ids
+            .iter()
+            .map(|id| self.custom_tiles[id].tex_path.clone())
+            .collect::<Vec<_>>();
+        tex_paths.shuffle(&mut rng);
+
+        for (id, tex_path) in ids.iter().zip(tex_paths) {
+            let tile = self.custom_tiles.get_mut(id).unwrap();
+            tile.tex_path = tex_path;
+            tile.tint = Color::RGB(
+                rng.random_range(0x80..=0xff),
+                rng.random_range(0x80..=0xff),
+                rng.random_range(0x80..=0xff),
+            );
+        }
+    }
+
+    /// directory the paths in this map are relative to
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    pub fn tex_path(&self, id: char) -> PathBuf {
+        self.prefix.join(&self.custom_tiles[&id].tex_path)
+    }
+
+    /// every image walls of this map are drawn with
+    pub fn textures(&self) -> BTreeSet<PathBuf> {
+        self.custom_tiles
+            .values()
+            .filter(|tile| !tile.floor)
+            .flat_map(|tile| {
+                std::iter::once(&tile.tex_path).chain(tile.face_textures.iter().flatten())
+            })
+            .map(|path| self.prefix.join(path))
+            .collect()
+    }
+
+    /// every file the map uses, its textures, briefings and dialogues
+    pub fn files(&self) -> BTreeSet<PathBuf> {
+        let texts = self
+            .custom_tiles
+            .values()
+            .filter_map(|tile| tile.dialogue.as_ref())
+            .chain(self.briefing.iter())
+            .chain(self.debriefing.iter())
+            .map(|path| self.prefix.join(path));
+        self.textures().into_iter().chain(texts).collect()
+    }
+
+    /// text file shown before the level starts
+    pub fn briefing_path(&self) -> Option<PathBuf> {
+        self.briefing.as_ref().map(|path| self.prefix.join(path))
+    }
+
+    /// text file shown once the level is finished
+    pub fn debriefing_path(&self) -> Option<PathBuf> {
+        self.debriefing.as_ref().map(|path| self.prefix.join(path))
+    }
+
+    /// image of a single face of a tile, if it has its own instead of a strip of the texture
+    pub fn face_texture(&self, id: char, face: Cardinal) -> Option<PathBuf> {
+        self.custom_tiles[&id].face_textures[face as usize]
+            .as_ref()
+            .map(|path| self.prefix.join(path))
+    }
+
+    /// footstep sound of the floor material at a position
+    pub fn step_sound(&self, position: Vec2) -> Option<PathBuf> {
+        match self.main_tiles.get(self.vec_to_idx(position)) {
+            Some(Tile::Custom(id)) => self.custom_tiles[id]
+                .step_sound
+                .as_ref()
+                .map(|path| self.prefix.join(path)),
+            _ => None,
+        }
+    }
+
+    /// whether a position is on an exit tile
+    pub fn is_exit(&self, position: Vec2) -> bool {
+        match self.main_tiles.get(self.vec_to_idx(position)) {
+            Some(Tile::Custom(id)) => self.custom_tiles[id].exit,
+            _ => false,
+        }
+    }
+
+    /// whether a position is on a checkpoint tile
+    pub fn is_checkpoint(&self, position: Vec2) -> bool {
+        match self.main_tiles.get(self.vec_to_idx(position)) {
+            Some(Tile::Custom(id)) => self.custom_tiles[id].checkpoint,
+            _ => false,
+        }
+    }
+
+    /// how far the floor at a position is raised
+    pub fn floor_height(&self, position: Vec2) -> f32 {
+        match self.main_tiles.get(self.vec_to_idx(position)) {
+            Some(Tile::Custom(id)) => self.custom_tiles[id].floor_height,
+            _ => 0.,
+        }
+    }
+
+    /// how much faster players walk at a position
+    pub fn speed_mul(&self, position: Vec2) -> f32 {
+        match self.main_tiles.get(self.vec_to_idx(position)) {
+            Some(Tile::Custom(id)) => self.custom_tiles[id].speed_mul,
+            _ => 1.,
+        }
+    }
+
+    /// whether players slide at a position
+    pub fn is_slippery(&self, position: Vec2) -> bool {
+        match self.main_tiles.get(self.vec_to_idx(position)) {
+            Some(Tile::Custom(id)) => self.custom_tiles[id].slippery,
+            _ => false,
+        }
+    }
+
+    /// whether a position is in water
+    pub fn is_water(&self, position: Vec2) -> bool {
+        match self.main_tiles.get(self.vec_to_idx(position)) {
+            Some(Tile::Custom(id)) => self.custom_tiles[id].water,
+            _ => false,
+        }
+    }
+
+    /// objective with an id, if the map has one
+    pub fn objective(&self, id: &str) -> Option<&Objective> {
+        self.objectives.iter().find(|objective| objective.id == id)
+    }
+
+    /// whether exits wait for every objective to be done
+    pub fn exit_needs_objectives(&self) -> bool {
+        self.meta.contains(&Meta::ExitNeedsObjectives)
+    }
+
+    /// times players can come back from a checkpoint, none for as often as they like
+    pub fn lives(&self) -> Option<u8> {
+        self.meta.iter().find_map(|meta| match meta {
+            Meta::Lives(count) => Some(*count),
+            _ => None,
+        })
+    }
+
+    /// what the map changes about players
+    pub fn player_meta(&self) -> PlayerMeta {
+        self.meta
+            .iter()
+            .find_map(|meta| match meta {
+                Meta::Player(player) => Some(*player),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// color the floor is filled with, white unless the map sets one
+    pub fn floor_color(&self) -> Color {
+        self.meta
+            .iter()
+            .find_map(|meta| match meta {
+                Meta::FloorColor(color) => Some(*color),
+                _ => None,
+            })
+            .unwrap_or(Color::WHITE)
+    }
+
+    /// color the ceiling is filled with, white unless the map sets one
+    pub fn ceiling_color(&self) -> Color {
+        self.meta
+            .iter()
+            .find_map(|meta| match meta {
+                Meta::CeilingColor(color) => Some(*color),
+                _ => None,
+            })
+            .unwrap_or(Color::WHITE)
+    }
+
+    pub fn idx_to_vec(&self, idx: usize) -> Vec2 {
+        let x = idx % self.width;
+        let y = (idx - x) / self.width;
+        Vec2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE)
+    }
+
+    pub fn vec_to_idx(&self, vec: Vec2) -> usize {
+        ((vec.y / TILE_SIZE) as usize * self.width) + ((vec.x / TILE_SIZE) as usize)
+    }
+
+    /// tile at a position in tiles, none outside the map
+    pub fn tile_mut(&mut self, x: usize, y: usize) -> Option<&mut Tile> {
+        if x >= self.width {
+            return None;
+        }
+        self.main_tiles.get_mut((y * self.width) + x)
+    }
+
+    /// how many tiles rays travel before giving up, enough to cross the whole map by default
+    pub fn dof(&self) -> usize {
+        self.meta
+            .iter()
+            .find_map(|meta| match meta {
+                Meta::Dof(steps) => Some(*steps as usize),
+                _ => None,
+            })
+            .unwrap_or(self.width + self.height)
+    }
+
+    /// light reaching a position, white when the map has no lights
+    pub fn light(&self, position: Vec2) -> Color {
+        if !self.contains(position) {
+            return Color::WHITE;
+        }
+        self.lightmap
+            .get(self.vec_to_idx(position))
+            .copied()
+            .unwrap_or(Color::WHITE)
+    }
+
+    /// the `*` spawn, or the first named one on maps without it
+    pub fn get_spawn(&self) -> Option<Vec2> {
+        let spawns = self.spawns();
+        spawns
+            .iter()
+            .find(|(id, _)| id.is_none())
+            .or(spawns.first())
+            .map(|(_, pos)| *pos)
+    }
+
+    /// every spawn and its id, row by row
+    pub fn spawns(&self) -> Vec<(Option<char>, Vec2)> {
+        self.main_tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, tile)| match tile {
+                Tile::Spawn => Some((None, self.idx_to_vec(idx))),
+                Tile::NamedSpawn(id) => Some((Some(*id), self.idx_to_vec(idx))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// the spawn named `id`, like `1` for a `*1` spawn
+    pub fn spawn_with_id(&self, id: char) -> Option<Vec2> {
+        self.main_tiles
+            .iter()
+            .position(|tile| tile == &Tile::NamedSpawn(id))
+            .map(|idx| self.idx_to_vec(idx))
+    }
+
+    /// any of the spawns, the same one for the same seed
+    pub fn random_spawn(&self, seed: u64) -> Option<Vec2> {
+        self.spawns()
+            .choose(&mut StdRng::seed_from_u64(seed))
+            .map(|(_, pos)| *pos)
+    }
+
+    /// angle to face entering at the spawn on a position, east unless the map says otherwise
+    pub fn spawn_facing(&self, spawn: Vec2) -> f32 {
+        let id = match self.main_tiles.get(self.vec_to_idx(spawn)) {
+            Some(Tile::Spawn) => None,
+            Some(Tile::NamedSpawn(id)) => Some(*id),
+            _ => return 0.,
+        };
+        self.meta
+            .iter()
+            .find_map(|meta| match meta {
+                Meta::SpawnFacing { id: of, facing } if *of == id => Some(facing.angle()),
+                _ => None,
+            })
+            .unwrap_or(0.)
+    }
+
+    /// whether a position is on the map at all
+    pub fn contains(&self, position: Vec2) -> bool {
+        position.x >= 0.
+            && position.y >= 0.
+            && position.x < self.width as f32 * TILE_SIZE
+            && position.y < self.height as f32 * TILE_SIZE
+    }
+
+    /// whether nothing solid is between two positions, by the same marching rays use
+    pub fn line_of_sight(&self, a: Vec2, b: Vec2) -> bool {
+        let distance = a.distance(b);
+        ray::march_within(self, a, b - a, distance).is_none_or(|hit| hit.vec.length() >= distance)
+    }
+
+    /// whether a player could stand on a tile, doors that do not collide can be walked through
+    fn walkable(&self, x: usize, y: usize) -> bool {
+        x < self.width
+            && y < self.height
+            && self
+                .colliding((Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE, true)
+                .is_none()
+    }
+
+    /// shortest walk between two positions using a*, as the centers of the tiles to cross
+    /// followed by `to`, none when there is no way there
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        if !self.contains(from) || !self.contains(to) {
+            return None;
+        }
+        let tile = |pos: Vec2| ((pos.x / TILE_SIZE) as usize, (pos.y / TILE_SIZE) as usize);
+        let (start, goal) = (tile(from), tile(to));
+        if !self.walkable(goal.0, goal.1) {
+            return None;
+        }
+
+        let idx = |(x, y): (usize, usize)| (y * self.width) + x;
+        let distance = |(x, y): (usize, usize)| x.abs_diff(goal.0) + y.abs_diff(goal.1);
+        let mut cost = vec![usize::MAX; self.main_tiles.len()];
+        let mut came_from: Vec<Option<(usize, usize)>> = vec![None; self.main_tiles.len()];
+        let mut open = BinaryHeap::from([Reverse((distance(start), start))]);
+        cost[idx(start)] = 0;
+
+        while let Some(Reverse((_, current))) = open.pop() {
+            if current == goal {
+                let mut path = vec![to];
+                let mut at = current;
+                while let Some(previous) = came_from[idx(at)] {
+                    if previous != start {
+                        path.push(
+                            (Vec2::new(previous.0 as f32, previous.1 as f32) + 0.5) * TILE_SIZE,
+                        );
+                    }
+                    at = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let (x, y) = current;
+            let neighbors = [
+                (x + 1, y),
+                (x.wrapping_sub(1), y),
+                (x, y + 1),
+                (x, y.wrapping_sub(1)),
+            ];
+            for next in neighbors {
+                if !self.walkable(next.0, next.1) {
+                    continue;
+                }
+                let next_cost = cost[idx(current)] + 1;
+                if next_cost < cost[idx(next)] {
+                    cost[idx(next)] = next_cost;
+                    came_from[idx(next)] = Some(current);
+                    open.push(Reverse((next_cost + distance(next), next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// take the pushable tiles out of the map, leaving empty tiles, returns the middle of each
+    /// and its image
+    pub fn take_pushables(&mut self) -> Vec<(Vec2, PathBuf)> {
+        self.take_tiles(|tile| tile.pushable)
+            .into_iter()
+            .map(|(pos, id)| (pos, self.tex_path(id)))
+            .collect()
+    }
+
+    /// take the tiles with a dialogue out of the map like [`Map::take_pushables`], returns the
+    /// middle of each, its image and its dialogue file
+    pub fn take_npcs(&mut self) -> Vec<(Vec2, PathBuf, PathBuf)> {
+        self.take_tiles(|tile| tile.dialogue.is_some())
+            .into_iter()
+            .map(|(pos, id)| {
+                let dialogue = self.custom_tiles[&id].dialogue.as_ref().unwrap();
+                (pos, self.tex_path(id), self.prefix.join(dialogue))
+            })
+            .collect()
+    }
+
+    /// replace the tiles `take` picks with empty ones, returns the middle and id of each
+    fn take_tiles(&mut self, take: impl Fn(&CustomTile) -> bool) -> Vec<(Vec2, char)> {
+        let mut taken = Vec::new();
+        for idx in 0..self.main_tiles.len() {
+            let Tile::Custom(id) = self.main_tiles[idx] else {
+                continue;
+            };
+            if take(&self.custom_tiles[&id]) {
+                self.main_tiles[idx] = Tile::Empty;
+                taken.push((self.idx_to_vec(idx) + (TILE_SIZE / 2.), id));
+            }
+        }
+        // light goes where the tiles were
+        if !taken.is_empty() {
+            self.bake_lights();
+        }
+        taken
+    }
+
+    /// move tiles that open and close and flickering lights on to a tick, returns whether any
+    /// tile opened or closed
+    pub fn set_tick(&mut self, tick: u64) -> bool {
+        let changed = self.custom_tiles.values().any(|tile| {
+            tile.cycle
+                .is_some_and(|cycle| cycle.closed_at(self.tick) != cycle.closed_at(tick))
+        });
+        self.tick = tick;
+        if !self.flickers.is_empty() {
+            self.lightmap = if self.steady {
+                light::steady(&self.base_light, &self.flickers)
+            } else {
+                light::flicker(&self.base_light, &self.flickers, tick)
+            };
+        }
+        changed
+    }
+
+    /// use the switch on a tile, turning its signal on or off, returns whether there is one.
+    /// doors only follow once [`Map::update_signals`] is called
+    pub fn flip_switch(&mut self, x: usize, y: usize) -> bool {
+        let signal = match self.main_tiles.get((y * self.width) + x) {
+            Some(Tile::Custom(id)) if x < self.width => self.custom_tiles[id].switch.clone(),
+            _ => None,
+        };
+        let Some(signal) = signal else {
+            return false;
+        };
+        if !self.switched.remove(&signal) {
+            self.switched.insert(signal);
+        }
+        true
+    }
+
+    /// turn on the signals of used switches and of plates with something at one of `standing`
+    /// on them, and off the rest, opening and closing the doors wired to them. returns the
+    /// signals that turned on or off
+    pub fn update_signals(&mut self, standing: &[Vec2]) -> Vec<(String, bool)> {
+        let mut signals = self.switched.clone();
+        for pos in standing.iter().filter(|pos| self.contains(**pos)) {
+            if let Some(Tile::Custom(id)) = self.main_tiles.get(self.vec_to_idx(*pos)) {
+                if let Some(signal) = &self.custom_tiles[id].plate {
+                    signals.insert(signal.clone());
+                }
+            }
+        }
+
+        let changed = signals
+            .symmetric_difference(&self.signals)
+            .map(|signal| (signal.clone(), signals.contains(signal)))
+            .collect();
+        self.signals = signals;
+        changed
+    }
+
+    /// whether a signal is on
+    pub fn signal(&self, signal: &str) -> bool {
+        self.signals.contains(signal)
+    }
+
+    pub fn colliding(&self, position: Vec2, is_player: bool) -> Option<char> {
+        if !self.contains(position) {
+            return None;
+        }
+        self.colliding_idx(self.vec_to_idx(position), is_player)
+    }
+
+    /// [`Map::colliding`] for a cell in tiles, for code stepping through the grid
+    pub fn colliding_cell(&self, x: i32, y: i32, is_player: bool) -> Option<char> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        self.colliding_idx((y as usize * self.width) + x as usize, is_player)
+    }
+
+    fn colliding_idx(&self, idx: usize, is_player: bool) -> Option<char> {
+        match self.main_tiles.get(idx) {
+            Some(Tile::Custom(id))
+                if self.custom_tiles.get(id).is_some_and(|tile| {
+                    !tile.floor
+                        && (!is_player || tile.collidable)
+                        && tile.cycle.is_none_or(|cycle| cycle.closed_at(self.tick))
+                        && tile
+                            .door
+                            .as_ref()
+                            .is_none_or(|door| self.signal(&door.signal) == door.barrier)
+                }) =>
+            {
+                Some(*id)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// puts a [`Map`] together tile by tile, for tools that make maps instead of reading them
+///
+/// ```
+/// use yaw_core::map::{CustomTile, MapBuilder, Tile};
+///
+/// let map = MapBuilder::new(3, 1)
+///     .custom_tile('#', CustomTile::new("wall.png"))
+///     .tile(0, 0, Tile::Custom('#'))
+///     .tile(1, 0, Tile::Spawn)
+///     .build()?;
+/// assert!(map.get_spawn().is_some());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct MapBuilder {
+    map: Map,
+    /// tiles placed so far, checked once the map is built
+    tiles: Vec<(usize, usize, Tile)>,
+}
+
+impl MapBuilder {
+    /// empty map of `width` by `height` tiles
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            map: Map {
+                width,
+                height,
+                main_tiles: vec![Tile::Empty; width * height],
+                ..Map::default()
+            },
+            tiles: Vec::new(),
+        }
+    }
+
+    /// directory texture and sound paths are relative to, the working directory by default
+    pub fn prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.map.prefix = prefix.into();
+        self
+    }
+
+    /// define what `id` looks like, so [`Tile::Custom`] can use it
+    pub fn custom_tile(mut self, id: char, tile: CustomTile) -> Self {
+        self.map.custom_tiles.insert(id, tile);
+        self
+    }
+
+    /// place a tile, replacing what was there
+    pub fn tile(mut self, x: usize, y: usize, tile: Tile) -> Self {
+        self.tiles.push((x, y, tile));
+        self
+    }
+
+    pub fn meta(mut self, meta: Meta) -> Self {
+        self.map.meta.insert(meta);
+        self
+    }
+
+    pub fn trigger(mut self, trigger: Trigger) -> Self {
+        self.map.triggers.push(trigger);
+        self
+    }
+
+    /// the finished map, fails if a tile is outside of it or was never defined
+    pub fn build(mut self) -> anyhow::Result<Map> {
+        for id in [' ', '*'] {
+            anyhow::ensure!(
+                !self.map.custom_tiles.contains_key(&id),
+                "{id:?} can not be a custom tile"
+            );
+        }
+        for (x, y, tile) in self.tiles {
+            match tile {
+                Tile::Custom(id) => anyhow::ensure!(
+                    self.map.custom_tiles.contains_key(&id),
+                    "unknown tile: {id}"
+                ),
+                Tile::NamedSpawn(id) => anyhow::ensure!(
+                    !self.map.custom_tiles.contains_key(&id) && !matches!(id, ' ' | '*'),
+                    "{id:?} can not be both a spawn and a tile"
+                ),
+                Tile::Empty | Tile::Spawn => {}
+            }
+            let (width, height) = (self.map.width, self.map.height);
+            anyhow::ensure!(
+                x < width && y < height,
+                "tile at {x}:{y} is outside of the {width}x{height} map"
+            );
+            *self.map.tile_mut(x, y).unwrap() = tile;
+        }
+
+        self.map.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const MAP: &str = "!!!!MAIN
+#wall.png,collide
+-door.png,half_height
+
+###
+#*#
+#-#";
+
+    #[test]
+    fn parses_tiles() {
+        let map = Map::parse(MAP, Path::new("map")).unwrap();
+        assert_eq!((map.width, map.height), (3, 3));
+        assert_eq!(map.get_spawn(), Some(Vec2::new(32., 32.)));
+        assert!(map.custom_tiles[&'#'].collidable);
+        assert!(map.custom_tiles[&'-'].half_height);
+        assert_eq!(map.tex_path('-'), PathBuf::from("map/door.png"));
+
+        // nothing to collide with outside of the map
+        assert_eq!(map.colliding(Vec2::new(16., 16.), true), Some('#'));
+        assert_eq!(map.colliding(Vec2::new(-16., 16.), true), None);
+        assert_eq!(map.colliding(Vec2::new(112., 16.), true), None);
+    }
+
+    #[test]
+    fn spawns_can_be_picked_by_id() {
+        let text = "!!!!MAIN\n#wall.png\n*1\n*w\n\n#1*w#\n";
+        let map = Map::parse(text, Path::new("")).unwrap();
+        let tile = |x: f32| Vec2::new(x * TILE_SIZE, 0.);
+        assert_eq!(map.get_spawn(), Some(tile(2.)));
+        assert_eq!(map.spawn_with_id('w'), Some(tile(3.)));
+        assert_eq!(map.spawn_with_id('2'), None);
+        assert_eq!(
+            map.spawns(),
+            [
+                (Some('1'), tile(1.)),
+                (None, tile(2.)),
+                (Some('w'), tile(3.))
+            ]
+        );
+        assert_eq!(map.random_spawn(7), map.random_spawn(7));
+        assert_eq!(map.to_yaw(), text);
+
+        // without `*`, the first named spawn is the one to use
+        let map = Map::parse("!!!!MAIN\n*1\n*2\n\n 21", Path::new("")).unwrap();
+        assert_eq!(map.get_spawn(), Some(tile(1.)));
+
+        assert!(Map::parse("!!!!MAIN\n#wall.png\n*#\n\n#*", Path::new("")).is_err());
+        assert!(Map::parse("!!!!MAIN\n*12\n\n*", Path::new("")).is_err());
+        assert!(Map::parse("!!!!MAIN\n_a.png,floor_height=32\n\n*_", Path::new("")).is_err());
+        assert!(Map::parse("!!!!MAIN\n_a.png,speed_mul=0\n\n*_", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn spawns_face_the_way_the_map_says() {
+        let mut map = Map::parse(
+            "!!!!META\nspawn,dir=north\nspawn,id=1,dir=west\n\n!!!!MAIN\n*1\n*2\n\n*12",
+            Path::new(""),
+        )
+        .unwrap();
+        let tile = |x: f32| Vec2::new(x * TILE_SIZE, 0.);
+        assert_eq!(map.spawn_facing(tile(0.)), 3. * FRAC_PI_2);
+        assert_eq!(map.spawn_facing(tile(1.)), PI);
+        assert_eq!(map.spawn_facing(tile(2.)), 0.);
+
+        // facings turn with the map
+        map.transform(Transform::FlipH);
+        assert_eq!(map.spawn_facing(tile(1.)), 0.);
+        assert_eq!(map.spawn_facing(tile(2.)), 3. * FRAC_PI_2);
+
+        assert!(Map::parse("!!!!META\nspawn,dir=up\n", Path::new("")).is_err());
+        assert!(Map::parse("!!!!META\nspawn,id=12,dir=east\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn cycling_tiles_open_and_close() {
+        let text = "!!!!MAIN\n#wall.png,collide,cycle=2:1\n=wall.png,cycle=2:1:1\n\n*#=\n";
+        let mut map = Map::parse(text, Path::new("")).unwrap();
+        let solid =
+            |map: &Map| [1., 2.].map(|x| map.colliding(Vec2::new(x + 0.5, 0.5) * TILE_SIZE, false));
+        assert_eq!(solid(&map), [None, None]);
+        assert!(map.set_tick(1));
+        assert_eq!(solid(&map), [None, Some('=')]);
+        assert!(map.set_tick(2));
+        assert_eq!(solid(&map), [Some('#'), None]);
+        assert!(map.set_tick(3));
+        // a whole cycle later nothing is different
+        assert!(!map.set_tick(6));
+        assert_eq!(solid(&map), [None, None]);
+        assert_eq!(map.to_yaw(), text);
+
+        assert!("0:0".parse::<Cycle>().is_err());
+        assert!("30".parse::<Cycle>().is_err());
+        assert!("30:x".parse::<Cycle>().is_err());
+    }
+
+    #[test]
+    fn switches_and_plates_open_doors() {
+        let text = "!!!!MAIN\n#wall.png,collide\n-bars.png,collide,barrier=b\n/lever.png,collide,switch=a\n\
+                    _plate.png,floor,plate=b\n|door.png,collide,door=a\n\n#/#\n*_|\n##-\n";
+        let mut map = Map::parse(text, Path::new("")).unwrap();
+        let solid = |map: &Map| {
+            [(2., 1.), (2., 2.)]
+                .map(|(x, y)| map.colliding(Vec2::new(x + 0.5, y + 0.5) * TILE_SIZE, true))
+        };
+        assert!(map.update_signals(&[]).is_empty());
+        assert_eq!(solid(&map), [Some('|'), None]);
+
+        assert!(map.flip_switch(1, 0));
+        assert!(!map.flip_switch(0, 0));
+        assert_eq!(map.update_signals(&[]), [("a".to_string(), true)]);
+        assert_eq!(solid(&map), [None, None]);
+
+        // something on the plate keeps it down
+        let plate = Vec2::new(1.5, 1.5) * TILE_SIZE;
+        assert_eq!(map.update_signals(&[plate]), [("b".to_string(), true)]);
+        assert!(map.update_signals(&[plate]).is_empty());
+        assert_eq!(solid(&map), [None, Some('-')]);
+        map.flip_switch(1, 0);
+        assert_eq!(
+            map.update_signals(&[]),
+            [("a".to_string(), false), ("b".to_string(), false)]
+        );
+        assert_eq!(solid(&map), [Some('|'), None]);
+        assert_eq!(map.to_yaw(), text);
+
+        assert!(Map::parse("!!!!MAIN\n|door.png,door=a,barrier=a\n\n|\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn parses_fog_and_unlit_tiles() {
+        let map = Map::parse(
+            "!!!!META\nfog,dof=6,curve=exp2\n\n!!!!MAIN\n#wall.png,unlit\n\n#*",
+            Path::new(""),
+        )
+        .unwrap();
+        assert!(map.meta.contains(&Meta::Fog {
+            dof: 6,
+            color: Color::BLACK,
+            curve: FogCurve::Exp2,
+        }));
+        assert!(map.custom_tiles[&'#'].unlit);
+        assert!(Map::parse("!!!!META\nfog,curve=cubic\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn hex_colors_round_trip() {
+        for (hex, color) in [
+            ("#123456", Color::RGB(0x12, 0x34, 0x56)),
+            ("#abcdef", Color::RGB(0xab, 0xcd, 0xef)),
+            ("#ff000080", Color::RGBA(0xff, 0, 0, 0x80)),
+            ("#00000000", Color::RGBA(0, 0, 0, 0)),
+        ] {
+            assert_eq!(parse_hex_color(hex).unwrap(), color, "{hex}");
+            assert_eq!(hex_color(color), hex);
+        }
+        // opaque colors are written without their alpha
+        assert_eq!(hex_color(parse_hex_color("#ABCDEFff").unwrap()), "#abcdef");
+
+        for hex in [
+            "123456", "#12345", "#1234567", "#12345g", "#+12345", "#äbcde",
+        ] {
+            assert!(parse_hex_color(hex).is_err(), "{hex}");
+        }
+    }
+
+    #[test]
+    fn parses_player_overrides() {
+        let map = Map::parse(
+            "!!!!META\nplayer,speed=90,strafe_speed=45,health=100\n",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(
+            map.player_meta(),
+            PlayerMeta {
+                speed: Some(90.),
+                strafe_speed: Some(45.),
+                health: Some(100),
+                turn_speed: None,
+            }
+        );
+        for meta in [
+            "speed=0",
+            "strafe_speed=-1",
+            "turn_speed=-1",
+            "speed=inf",
+            "health=300",
+        ] {
+            assert!(
+                Map::parse(&format!("!!!!META\nplayer,{meta}\n"), Path::new("")).is_err(),
+                "{meta}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_floor_and_ceiling_colors() {
+        let map = Map::parse(
+            "!!!!META\nfloor_color=#333333\nceiling_color=#102222\n\n!!!!MAIN\n#wall.png\n\n#*",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(map.floor_color(), Color::RGB(0x33, 0x33, 0x33));
+        assert_eq!(map.ceiling_color(), Color::RGB(0x10, 0x22, 0x22));
+        let fog = Map::parse("!!!!META\nfog,color=#ff000080\n", Path::new("")).unwrap();
+        assert!(fog.meta.contains(&Meta::Fog {
+            dof: 4,
+            color: Color::RGBA(0xff, 0, 0, 0x80),
+            curve: FogCurve::Linear,
+        }));
+        assert_eq!(
+            Map::parse("!!!!MAIN\n\n*", Path::new(""))
+                .unwrap()
+                .floor_color(),
+            Color::WHITE
+        );
+        assert!(Map::parse("!!!!META\nfloor_color\n", Path::new("")).is_err());
+        assert_eq!(map.player_meta(), PlayerMeta::default());
+        assert!(Map::parse("!!!!META\nceiling_color=white\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn parses_and_transforms_tile_shapes() {
+        let mut map = Map::parse(
+            "!!!!MAIN\n/wall.png,diagonal=ne\n(wall.png,curve=sw\n\n/(*",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(
+            map.custom_tiles[&'/'].shape,
+            Shape::Diagonal(Corner::NorthEast)
+        );
+        assert!(map.custom_tiles[&'/'].shape.contains(Vec2::new(4., 28.)));
+        assert!(!map.custom_tiles[&'/'].shape.contains(Vec2::new(28., 4.)));
+        assert!(!map.custom_tiles[&'('].shape.contains(Vec2::new(1., 31.)));
+
+        map.transform(Transform::Rot90);
+        assert_eq!(
+            map.custom_tiles[&'/'].shape,
+            Shape::Diagonal(Corner::SouthEast)
+        );
+        assert_eq!(
+            map.custom_tiles[&'('].shape,
+            Shape::Curve(Corner::NorthWest)
+        );
+        assert!(Map::parse("!!!!MAIN\n/wall.png,curve=up\n\n/*", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn compiled_maps_load_the_same() {
+        assets::insert("compiled/wall.png", b"not really a png".as_slice());
+        let map = Map::parse(
+            "!!!!META\nlight,x=1,y=0,radius=3,color=#ffffff\n\n!!!!MAIN\n#wall.png\n\n#*",
+            Path::new("compiled"),
+        )
+        .unwrap();
+
+        let bytes = map.compile(false).unwrap();
+        assert!(Map::decode(&bytes, Path::new("compiled")).unwrap() == map);
+
+        // embedded textures are found next to wherever the map is loaded from
+        let bytes = map.compile(true).unwrap();
+        let moved = Map::decode(&bytes, Path::new("moved")).unwrap();
+        assert_eq!(moved.lightmap, map.lightmap);
+        assert_eq!(
+            &*assets::read(&moved.tex_path('#')).unwrap(),
+            b"not really a png"
+        );
+
+        assert!(Map::decode(b"!!!!MAIN", Path::new("")).is_err());
+        let mut newer = bytes.clone();
+        newer[COMPILED_MAGIC.len()] += 1;
+        assert!(Map::decode(&newer, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn packed_maps_bring_their_textures() {
+        let path = std::env::temp_dir().join(format!("yaw-pack-{}.yawpak", std::process::id()));
+        std::fs::write(&path, Map::pack(Path::new("../map/map.yaw")).unwrap()).unwrap();
+
+        let map = Map::load(path.clone()).unwrap();
+        let original = Map::load("../map/map.yaw".into()).unwrap();
+        assert!(map.main_tiles == original.main_tiles);
+        assert!(!map.textures().is_empty());
+        for texture in map.textures() {
+            assert!(texture.starts_with(&path));
+            assert!(assets::read(&texture).is_ok());
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn built_maps_write_and_parse_the_same() {
+        let map = MapBuilder::new(3, 2)
+            .prefix("built")
+            .custom_tile(
+                '#',
+                CustomTile {
+                    collidable: true,
+                    step_sound: Some("step.wav".into()),
+                    prompt: Some("knock".into()),
+                    checkpoint: true,
+                    water: true,
+                    floor_height: 8.,
+                    speed_mul: 1.5,
+                    slippery: true,
+                    ..CustomTile::new("wall.png")
+                },
+            )
+            .custom_tile(
+                '/',
+                CustomTile {
+                    face_textures: [Some("sign.png".into()), None, None, None],
+                    shape: Shape::Curve(Corner::SouthWest),
+                    ..CustomTile::new("wall.png")
+                },
+            )
+            .tile(0, 0, Tile::Custom('#'))
+            .tile(1, 0, Tile::Custom('/'))
+            .tile(1, 1, Tile::Spawn)
+            .meta(Meta::Dof(8))
+            .meta(Meta::Lives(3))
+            .meta(Meta::FloorColor(Color::RGB(0x44, 0x44, 0x44)))
+            .meta(Meta::Player(PlayerMeta {
+                speed: Some(75.),
+                strafe_speed: None,
+                health: None,
+                turn_speed: Some(1.5),
+            }))
+            .meta(Meta::Light {
+                x: 1,
+                y: 1,
+                radius: 3,
+                color: Color::WHITE,
+                flicker: Some(Flicker::Random),
+            })
+            .trigger(
+                Trigger::parse("sound,x=2,y=1,once,path=hello.wav", Path::new("built")).unwrap(),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(map.get_spawn(), Some(Vec2::splat(TILE_SIZE)));
+        assert!(map.is_checkpoint(Vec2::ZERO));
+        assert!(map.is_water(Vec2::ZERO));
+        assert_eq!(map.floor_height(Vec2::ZERO), 8.);
+        assert_eq!(map.speed_mul(Vec2::ZERO), 1.5);
+        assert!(map.is_slippery(Vec2::ZERO));
+        assert_eq!(map.speed_mul(Vec2::splat(TILE_SIZE)), 1.);
+        assert_eq!(map.lives(), Some(3));
+
+        let text = map.to_yaw();
+        assert!(
+            Map::parse(&text, Path::new("built")).unwrap() == map,
+            "{text}"
+        );
+
+        assert!(MapBuilder::new(1, 1)
+            .tile(1, 0, Tile::Spawn)
+            .build()
+            .is_err());
+        assert!(MapBuilder::new(1, 1)
+            .tile(0, 0, Tile::Custom('#'))
+            .build()
+            .is_err());
+        assert!(MapBuilder::new(1, 1)
+            .custom_tile('*', CustomTile::new("wall.png"))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn paths_go_around_walls_and_through_doors() {
+        let map = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\n-door.png,half_height\n\n#####\n#* ##\n## -#\n#   #\n#####",
+            Path::new(""),
+        )
+        .unwrap();
+        let center = |x: usize, y: usize| (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE;
+
+        let path = map.find_path(center(1, 1), Vec2::new(40., 110.)).unwrap();
+        assert_eq!(
+            path,
+            [
+                center(2, 1),
+                center(2, 2),
+                center(2, 3),
+                Vec2::new(40., 110.)
+            ]
+        );
+        // the door does not collide, so the way through it is as short
+        assert_eq!(map.find_path(center(3, 2), center(3, 3)).unwrap().len(), 1);
+
+        assert_eq!(
+            map.find_path(center(1, 1), center(1, 1)),
+            Some(vec![center(1, 1)])
+        );
+        assert_eq!(map.find_path(center(1, 1), center(0, 0)), None);
+        assert_eq!(map.find_path(center(1, 1), Vec2::new(-5., 5.)), None);
+
+        let closed = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\n\n#####\n#*# #\n#####",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(closed.find_path(center(1, 1), center(3, 1)), None);
+    }
+
+    #[test]
+    fn walls_block_line_of_sight() {
+        let map = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\n-door.png,half_height\n\n#####\n#* ##\n## -#\n#   #\n#####",
+            Path::new(""),
+        )
+        .unwrap();
+        let center = |x: usize, y: usize| (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE;
+
+        assert!(map.line_of_sight(center(2, 1), center(2, 3)));
+        assert!(map.line_of_sight(center(3, 3), center(1, 3)));
+        assert!(!map.line_of_sight(center(1, 1), center(1, 3)));
+        // doors are in the way even though players walk through them
+        assert!(!map.line_of_sight(center(3, 3), Vec2::new(112., 40.)));
+        assert!(map.line_of_sight(center(1, 1), center(1, 1)));
+
+        // further than rays travel is still blocked by walls in between
+        let mut far = Map::parse(
+            "!!!!META\ndof,steps=1\n\n!!!!MAIN\n#wall.png,collide\n\n*  #  ",
+            Path::new(""),
+        )
+        .unwrap();
+        assert!(!far.line_of_sight(center(0, 0), center(5, 0)));
+        far.main_tiles[3] = Tile::Empty;
+        assert!(far.line_of_sight(center(0, 0), center(5, 0)));
+    }
+
+    #[test]
+    fn fog_curves_reach_full_fog() {
+        for curve in [FogCurve::Linear, FogCurve::Exp, FogCurve::Exp2] {
+            assert_eq!(curve.amount(0.), 0.);
+            assert!(curve.amount(1.) > 0.95);
+            assert!(curve.amount(0.25) < curve.amount(0.5));
+        }
+        assert!(FogCurve::Exp.amount(0.25) > FogCurve::Exp2.amount(0.25));
+    }
+
+    #[test]
+    fn rotates_tiles_and_faces() {
+        let mut map = Map::parse(MAP, Path::new("")).unwrap();
+        map.transform(Transform::Rot90);
+
+        // the door at the bottom ends up on the left, turned sideways
+        assert_eq!(map.main_tiles[3], Tile::Custom('-'));
+        assert!(map.custom_tiles[&'-'].half_width);
+        assert!(!map.custom_tiles[&'-'].half_height);
+        assert_eq!(
+            map.custom_tiles[&'#'].faces[Cardinal::East as usize],
+            Cardinal::North
+        );
+    }
+
+    #[test]
+    fn faces_can_have_their_own_textures() {
+        let mut map = Map::parse(
+            "!!!!MAIN\n#wall.png,north=brick.png,west=door.png\n\n#*",
+            Path::new("map"),
+        )
+        .unwrap();
+        assert_eq!(
+            map.face_texture('#', Cardinal::North),
+            Some(PathBuf::from("map/brick.png"))
+        );
+        assert_eq!(map.face_texture('#', Cardinal::South), None);
+        assert_eq!(
+            map.textures().into_iter().collect::<Vec<_>>(),
+            ["map/brick.png", "map/door.png", "map/wall.png"].map(PathBuf::from)
+        );
+
+        map.transform(Transform::Rot90);
+        assert_eq!(
+            map.face_texture('#', Cardinal::East),
+            Some(PathBuf::from("map/brick.png"))
+        );
+        assert_eq!(
+            map.face_texture('#', Cardinal::North),
+            Some(PathBuf::from("map/door.png"))
+        );
+    }
+
+    #[test]
+    fn flipping_twice_is_identity() {
+        let original = Map::parse(MAP, Path::new("")).unwrap();
+        let mut map = original.clone();
+        map.transform(Transform::FlipV);
+        assert_eq!(map.main_tiles[1], Tile::Custom('-'));
+        map.transform(Transform::FlipV);
+        assert!(map == original);
+    }
+
+    #[test]
+    fn randomizing_is_deterministic() {
+        let mut a = Map::parse(MAP, Path::new("")).unwrap();
+        let mut b = a.clone();
+        a.randomize(42);
+        b.randomize(42);
+        assert!(a == b);
+    }
+
+    #[test]
+    fn pushable_tiles_are_taken_out() {
+        let mut map = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\nccrate.png,collide,pushable\n\n#####\n#*c #\n#####",
+            Path::new("map"),
+        )
+        .unwrap();
+        assert!(map.colliding(Vec2::new(80., 48.), true).is_some());
+
+        assert_eq!(
+            map.take_pushables(),
+            [(Vec2::new(80., 48.), PathBuf::from("map/crate.png"))]
+        );
+        assert_eq!(map.main_tiles[(map.width) + 2], Tile::Empty);
+        assert!(map.colliding(Vec2::new(80., 48.), true).is_none());
+        assert!(map.take_pushables().is_empty());
+    }
+
+    #[test]
+    fn parses_objectives() {
+        let map = Map::parse(
+            "!!!!META\nobjective,lever,Pull the lever, then run\nexit=objectives\nobjective,key,Find the key\n\n\
+             !!!!MAIN\n\n*  \n\n!!!!TRIGGERS\ncomplete,x=1,y=0,once,objective=lever\n",
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(
+            map.objectives
+                .iter()
+                .map(|objective| objective.id.as_str())
+                .collect::<Vec<_>>(),
+            ["lever", "key"]
+        );
+        assert_eq!(
+            map.objective("lever").unwrap().text,
+            "Pull the lever, then run"
+        );
+        assert!(map.exit_needs_objectives());
+        assert_eq!(map.triggers[0].action, Action::Complete("lever".into()));
+        assert!(Map::parse(&map.to_yaw(), Path::new("")).unwrap() == map);
+
+        for text in [
+            "!!!!META\nobjective,lever\n",
+            "!!!!META\nobjective,a,A\nobjective,a,B\n",
+            "!!!!META\nexit=key\n",
+            "!!!!MAIN\n\n* \n\n!!!!TRIGGERS\ncomplete,x=1,y=0,objective=lever\n",
+        ] {
+            assert!(Map::parse(text, Path::new("")).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn briefings_are_files_of_the_map() {
+        let text = "!!!!META\nbriefing=story/start.txt\ndebriefing=story/end.txt\n\n\
+                    !!!!MAIN\n#wall.png,collide\nnguard.png,dialogue=guard.txt\n\n*#n\n";
+        let map = Map::parse(text, Path::new("map")).unwrap();
+        assert_eq!(map.briefing_path(), Some("map/story/start.txt".into()));
+        assert_eq!(map.debriefing_path(), Some("map/story/end.txt".into()));
+        assert_eq!(
+            map.files(),
+            [
+                "map/guard.png",
+                "map/guard.txt",
+                "map/story/end.txt",
+                "map/story/start.txt",
+                "map/wall.png"
+            ]
+            .map(PathBuf::from)
+            .into()
+        );
+        assert_eq!(map.to_yaw(), text);
+        assert!(Map::parse("!!!!META\nbriefing\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn parses_names_authors_and_par_times() {
+        let text = "!!!!META\nauthor=Jo, with help\nname=The Cellar, Part 2\npar_time=95\n\n\
+                    !!!!MAIN\n#wall.png,collide\n\n*#\n";
+        let map = Map::parse(text, Path::new("map")).unwrap();
+        assert_eq!(map.name.as_deref(), Some("The Cellar, Part 2"));
+        assert_eq!(map.author.as_deref(), Some("Jo, with help"));
+        assert_eq!(map.par_time, Some(95));
+        assert_eq!(map.to_yaw(), text);
+
+        let map = Map::parse("!!!!MAIN\n\n*\n", Path::new("")).unwrap();
+        assert_eq!((map.name, map.author, map.par_time), (None, None, None));
+        assert!(Map::parse("!!!!META\npar_time=soon\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn npc_tiles_are_taken_out() {
+        let text = "!!!!MAIN\n#wall.png,collide\nnguard.png,collide,dialogue=guard.txt\n\n#####\n#*n #\n#####\n";
+        let mut map = Map::parse(text, Path::new("map")).unwrap();
+        assert_eq!(map.to_yaw(), text);
+
+        assert_eq!(
+            map.take_npcs(),
+            [(
+                Vec2::new(80., 48.),
+                PathBuf::from("map/guard.png"),
+                PathBuf::from("map/guard.txt")
+            )]
+        );
+        assert!(map.colliding(Vec2::new(80., 48.), true).is_none());
+        assert!(map.take_pushables().is_empty());
+    }
+}
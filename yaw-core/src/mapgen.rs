@@ -0,0 +1,332 @@
+//! random levels built from the tiles of a template map
+
+use crate::map::{CustomTile, Map, MapBuilder, Meta, Shape, Tile};
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// smallest map that still fits a room with walls around it
+const MIN_SIZE: usize = 7;
+/// how often a room is tried before giving up on placing more
+const ROOM_ATTEMPTS: usize = 80;
+const MIN_ROOM: usize = 3;
+const MAX_ROOM: usize = 8;
+/// one in this many walls uses another wall of the template
+const WALL_VARIETY: u32 = 8;
+
+/// how a random level is laid out
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Layout {
+    /// rooms joined by corridors
+    #[default]
+    Rooms,
+    /// corridors one tile wide with a single way between any two places
+    Maze,
+}
+
+impl Layout {
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Rooms => "rooms",
+            Layout::Maze => "maze",
+        }
+    }
+
+    /// the other layout, for menus that cycle through them
+    pub fn next(self) -> Self {
+        match self {
+            Layout::Rooms => Layout::Maze,
+            Layout::Maze => Layout::Rooms,
+        }
+    }
+}
+
+/// grid of open and solid tiles being carved
+struct Grid {
+    width: usize,
+    height: usize,
+    open: Vec<bool>,
+}
+
+impl Grid {
+    fn carve(&mut self, x: usize, y: usize) {
+        self.open[(y * self.width) + x] = true;
+    }
+
+    fn is_open(&self, x: usize, y: usize) -> bool {
+        self.open[(y * self.width) + x]
+    }
+
+    /// open tile furthest from `start` by walking, `start` when nothing else is reachable
+    fn furthest(&self, start: (usize, usize)) -> (usize, usize) {
+        let mut seen = vec![false; self.open.len()];
+        let mut queue = VecDeque::from([start]);
+        let mut last = start;
+        seen[(start.1 * self.width) + start.0] = true;
+        while let Some((x, y)) = queue.pop_front() {
+            last = (x, y);
+            for (nx, ny) in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                let idx = (ny * self.width) + nx;
+                if self.is_open(nx, ny) && !seen[idx] {
+                    seen[idx] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+
+        last
+    }
+}
+
+/// a `width` by `height` level using the walls, exit and look of `template`,
+/// the same seed always gives the same level
+pub fn generate(
+    template: &Map,
+    width: usize,
+    height: usize,
+    layout: Layout,
+    seed: u64,
+) -> anyhow::Result<Map> {
+    anyhow::ensure!(
+        width >= MIN_SIZE && height >= MIN_SIZE,
+        "random levels are at least {MIN_SIZE}x{MIN_SIZE} tiles"
+    );
+
+    // sorted since hashmap order is random, and only the seed should matter
+    let mut ids = template.custom_tiles.keys().copied().collect::<Vec<_>>();
+    ids.sort_unstable();
+    let is_wall = |tile: &CustomTile| {
+        tile.collidable
+            && !tile.floor
+            && !tile.exit
+            && !tile.half_width
+            && !tile.half_height
+            && tile.shape == Shape::Square
+            && tile.cycle.is_none()
+    };
+    let walls = ids
+        .iter()
+        .copied()
+        .filter(|id| is_wall(&template.custom_tiles[id]))
+        .collect::<Vec<_>>();
+    let exit = ids
+        .iter()
+        .copied()
+        .find(|id| template.custom_tiles[id].exit);
+    anyhow::ensure!(!walls.is_empty(), "template has no solid walls");
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = Grid {
+        width,
+        height,
+        open: vec![false; width * height],
+    };
+    let spawn = match layout {
+        Layout::Rooms => rooms(&mut grid, &mut rng),
+        Layout::Maze => maze(&mut grid, &mut rng),
+    };
+    let goal = grid.furthest(spawn);
+
+    let mut builder = MapBuilder::new(width, height).prefix(template.prefix());
+    for id in ids {
+        builder = builder.custom_tile(id, template.custom_tiles[&id].clone());
+    }
+    for meta in template.meta.iter() {
+        // lights belong to tiles of the template that are not here
+        if matches!(
+            meta,
+            Meta::Fog { .. }
+                | Meta::Dof(_)
+                | Meta::FloorColor(_)
+                | Meta::CeilingColor(_)
+                | Meta::Player(_)
+        ) {
+            builder = builder.meta(*meta);
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let tile = if (x, y) == spawn {
+                Tile::Spawn
+            } else if (x, y) == goal && exit.is_some() {
+                Tile::Custom(exit.unwrap())
+            } else if grid.is_open(x, y) {
+                Tile::Empty
+            } else if rng.random_ratio(1, WALL_VARIETY) {
+                Tile::Custom(*walls.choose(&mut rng).unwrap())
+            } else {
+                Tile::Custom(walls[0])
+            };
+            builder = builder.tile(x, y, tile);
+        }
+    }
+
+    builder.build()
+}
+
+/// carve rooms and the corridors between them, returns where to spawn
+fn rooms(grid: &mut Grid, rng: &mut StdRng) -> (usize, usize) {
+    let mut rooms: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let max_width = MAX_ROOM.min(grid.width - 2);
+    let max_height = MAX_ROOM.min(grid.height - 2);
+
+    for _ in 0..ROOM_ATTEMPTS {
+        let (w, h) = (
+            rng.random_range(MIN_ROOM..=max_width),
+            rng.random_range(MIN_ROOM..=max_height),
+        );
+        let (x, y) = (
+            rng.random_range(1..=grid.width - 1 - w),
+            rng.random_range(1..=grid.height - 1 - h),
+        );
+
+        // keep a wall between rooms so they do not merge into one
+        let overlaps = rooms
+            .iter()
+            .any(|&(ox, oy, ow, oh)| x <= ox + ow && ox <= x + w && y <= oy + oh && oy <= y + h);
+        if overlaps {
+            continue;
+        }
+
+        for ry in y..y + h {
+            for rx in x..x + w {
+                grid.carve(rx, ry);
+            }
+        }
+
+        let center = (x + w / 2, y + h / 2);
+        if let Some(&(px, py, pw, ph)) = rooms.last() {
+            let previous = (px + pw / 2, py + ph / 2);
+            // bend the corridor either way round
+            let corner = if rng.random_bool(0.5) {
+                (center.0, previous.1)
+            } else {
+                (previous.0, center.1)
+            };
+            for (from, to) in [(previous, corner), (corner, center)] {
+                for cy in from.1.min(to.1)..=from.1.max(to.1) {
+                    for cx in from.0.min(to.0)..=from.0.max(to.0) {
+                        grid.carve(cx, cy);
+                    }
+                }
+            }
+        }
+        rooms.push((x, y, w, h));
+    }
+
+    let (x, y, w, h) = rooms[0];
+    (x + w / 2, y + h / 2)
+}
+
+/// carve a maze by walking randomly and backing up at dead ends, returns where to spawn
+fn maze(grid: &mut Grid, rng: &mut StdRng) -> (usize, usize) {
+    // cells sit on odd tiles with walls between them
+    let (cells_x, cells_y) = ((grid.width - 1) / 2, (grid.height - 1) / 2);
+    let mut visited = vec![false; cells_x * cells_y];
+    let mut stack = vec![(0, 0)];
+    visited[0] = true;
+    grid.carve(1, 1);
+
+    while let Some(&(cx, cy)) = stack.last() {
+        let next = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .into_iter()
+            .filter_map(|(dx, dy)| {
+                let (nx, ny) = (cx as isize + dx, cy as isize + dy);
+                (nx >= 0 && ny >= 0 && (nx as usize) < cells_x && (ny as usize) < cells_y)
+                    .then_some((nx as usize, ny as usize))
+            })
+            .filter(|&(nx, ny)| !visited[(ny * cells_x) + nx])
+            .collect::<Vec<_>>();
+
+        match next.choose(rng) {
+            Some(&(nx, ny)) => {
+                visited[(ny * cells_x) + nx] = true;
+                // the wall between both cells and the new cell
+                grid.carve(cx + nx + 1, cy + ny + 1);
+                grid.carve((nx * 2) + 1, (ny * 2) + 1);
+                stack.push((nx, ny));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+
+    (1, 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const TEMPLATE: &str = "!!!!META
+fog,dof=6,color=#000000
+light,x=1,y=1
+
+!!!!MAIN
+#wall.png,collide
++wall.tga,collide
+-door.png,half_height
+>door.png,exit
+
+###
+#*#
+###";
+
+    #[test]
+    fn levels_are_solid_around_and_reach_the_exit() {
+        let template = Map::parse(TEMPLATE, Path::new("map")).unwrap();
+        for layout in [Layout::Rooms, Layout::Maze] {
+            for seed in 0..20 {
+                let map = generate(&template, 21, 15, layout, seed).unwrap();
+                assert!(map == generate(&template, 21, 15, layout, seed).unwrap());
+                assert_eq!(map.tex_path('#'), Path::new("map/wall.png"));
+                assert_eq!(map.meta.len(), 1);
+
+                let solid = |x: usize, y: usize| {
+                    matches!(&map.main_tiles[(y * map.width) + x],
+                        Tile::Custom(id) if is_solid(&map, *id))
+                };
+                for x in 0..map.width {
+                    assert!(solid(x, 0) && solid(x, map.height - 1));
+                }
+                for y in 0..map.height {
+                    assert!(solid(0, y) && solid(map.width - 1, y));
+                }
+
+                let exit = map
+                    .main_tiles
+                    .iter()
+                    .position(|tile| tile == &Tile::Custom('>'))
+                    .unwrap();
+                let grid = Grid {
+                    width: map.width,
+                    height: map.height,
+                    open: map
+                        .main_tiles
+                        .iter()
+                        .map(|tile| !matches!(tile, Tile::Custom(id) if is_solid(&map, *id)))
+                        .collect(),
+                };
+                let spawn = map.vec_to_idx(map.get_spawn().unwrap());
+                let spawn = (spawn % map.width, spawn / map.width);
+                assert_eq!(
+                    grid.furthest(spawn),
+                    (exit % map.width, exit / map.width),
+                    "{layout:?} {seed}"
+                );
+            }
+        }
+
+        assert!(generate(&template, 6, 15, Layout::Rooms, 0).is_err());
+        let no_walls = Map::parse("!!!!MAIN\n>door.png,exit\n\n*", Path::new("")).unwrap();
+        assert!(generate(&no_walls, 9, 9, Layout::Maze, 0).is_err());
+    }
+
+    fn is_solid(map: &Map, id: char) -> bool {
+        map.custom_tiles[&id].collidable
+    }
+}
@@ -0,0 +1,291 @@
+//! short lived specks like blood and sparks, moved every frame and drawn over walls
+
+use crate::camera::{Camera, ZBuffer};
+use crate::color::Color;
+use crate::map::{Map, TILE_SIZE};
+use crate::render::{Rect, Renderer};
+use glam::{Vec2, Vec3};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::str::FromStr;
+
+/// kinds of bursts of particles
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Effect {
+    /// red drops that fall to the floor
+    Blood,
+    /// fast yellow specks that die quickly
+    Sparks,
+    /// gray puffs that rise slowly
+    Smoke,
+    /// ring of light flying outwards, where something appears
+    Teleport,
+}
+
+impl Effect {
+    /// how maps and scripts spell the effect
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Blood => "blood",
+            Effect::Sparks => "sparks",
+            Effect::Smoke => "smoke",
+            Effect::Teleport => "teleport",
+        }
+    }
+}
+
+impl FromStr for Effect {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "blood" => Ok(Effect::Blood),
+            "sparks" => Ok(Effect::Sparks),
+            "smoke" => Ok(Effect::Smoke),
+            "teleport" => Ok(Effect::Teleport),
+            other => anyhow::bail!("unrecognized effect: {other}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Particle {
+    /// position on the map, `z` is the height above the floor in pixels
+    pub pos: Vec3,
+    /// pixels per frame
+    pub velocity: Vec3,
+    /// taken off the upwards velocity every frame, negative floats up
+    pub gravity: f32,
+    pub color: Color,
+    /// width and height in pixels, at the same scale as the map
+    pub size: f32,
+    /// frames left before it disappears, and how many it started with
+    pub life: u32,
+    pub lifetime: u32,
+}
+
+/// every live particle of a level
+pub struct Particles {
+    pub particles: Vec<Particle>,
+    rng: StdRng,
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self {
+            particles: Vec::new(),
+            // particles only need to look random, the same burst every run is fine
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Particles {
+    /// add a burst of particles at a position on the map
+    pub fn emit(&mut self, effect: Effect, at: Vec2) {
+        let (count, color, size, lifetime, gravity) = match effect {
+            Effect::Blood => (24, Color::RGB(0xaa, 0, 0), 2., 40, 0.3),
+            Effect::Sparks => (16, Color::RGB(0xff, 0xcc, 0x33), 1., 20, 0.2),
+            Effect::Smoke => (12, Color::RGB(0x80, 0x80, 0x80), 4., 60, -0.01),
+            Effect::Teleport => (32, Color::RGB(0xaa, 0xff, 0xff), 2., 15, 0.),
+        };
+
+        for idx in 0..count {
+            let rng = &mut self.rng;
+            let (angle, speed, up, height) = match effect {
+                Effect::Blood => (
+                    rng.random_range(0. ..TAU),
+                    rng.random_range(0.5..2.),
+                    rng.random_range(1. ..3.),
+                    TILE_SIZE / 2.,
+                ),
+                Effect::Sparks => (
+                    rng.random_range(0. ..TAU),
+                    rng.random_range(2. ..4.),
+                    rng.random_range(0. ..2.),
+                    TILE_SIZE / 2.,
+                ),
+                Effect::Smoke => (
+                    rng.random_range(0. ..TAU),
+                    rng.random_range(0. ..0.2),
+                    rng.random_range(0.3..0.6),
+                    TILE_SIZE / 4.,
+                ),
+                // evenly spread around a ring, from the floor to the ceiling
+                Effect::Teleport => (
+                    (idx as f32 / count as f32) * TAU,
+                    1.5,
+                    0.,
+                    rng.random_range(0. ..TILE_SIZE),
+                ),
+            };
+
+            self.particles.push(Particle {
+                pos: at.extend(height),
+                velocity: (Vec2::from_angle(angle) * speed).extend(up),
+                gravity,
+                color,
+                size,
+                life: lifetime,
+                lifetime,
+            });
+        }
+    }
+
+    /// move particles a frame, stopping them at walls and the floor, and drop dead ones
+    pub fn tick(&mut self, map: &Map) {
+        for particle in self.particles.iter_mut() {
+            let next = particle.pos.truncate() + particle.velocity.truncate();
+            if map.colliding(next, false).is_some() {
+                particle.velocity.x = 0.;
+                particle.velocity.y = 0.;
+            } else {
+                particle.pos.x = next.x;
+                particle.pos.y = next.y;
+            }
+
+            particle.velocity.z -= particle.gravity;
+            particle.pos.z = (particle.pos.z + particle.velocity.z).min(TILE_SIZE);
+            // landed, slide a little and stay down
+            if particle.pos.z <= 0. {
+                particle.pos.z = 0.;
+                particle.velocity *= Vec3::new(0.5, 0.5, 0.);
+            }
+
+            particle.life -= 1;
+        }
+
+        self.particles.retain(|particle| particle.life > 0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// draw particles from a camera over walls with the given depth, fading as they die
+    pub fn draw(
+        &self,
+        camera: &Camera,
+        depth: &ZBuffer,
+        renderer: &mut dyn Renderer,
+        viewport: Rect,
+    ) -> anyhow::Result<()> {
+        let columns = viewport.width as usize;
+        let projection = camera.projection(columns);
+
+        for particle in self.particles.iter() {
+            let Some((center, distance)) = camera.project(particle.pos.truncate(), columns) else {
+                continue;
+            };
+
+            // walls span from the floor to the ceiling around the middle of the view
+            let scale = projection / distance;
+            let size = (particle.size * scale).max(1.);
+            let y = (viewport.height as f32 / 2.) + (((TILE_SIZE / 2.) - particle.pos.z) * scale);
+            let alpha = (0xff * particle.life / particle.lifetime.max(1)) as u8;
+            let color = Color {
+                a: alpha,
+                ..particle.color
+            };
+
+            let left = center - (size / 2.);
+            let (first, last) = (
+                left.max(0.) as usize,
+                ((left + size).max(0.) as usize).min(columns),
+            );
+            for col in first..last {
+                if depth.hides_pixel(col, columns, distance) {
+                    continue;
+                }
+                renderer.draw_rect(
+                    Some(Rect::new(
+                        viewport.x + col as i32,
+                        viewport.y + (y - (size / 2.)) as i32,
+                        1,
+                        size as u32,
+                    )),
+                    color,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::PixelRenderer;
+    use std::path::Path;
+
+    const HALL: &str = "!!!!MAIN
+#wall.png,collide
+
+#######
+#*  # #
+#######";
+
+    fn center(x: usize, y: usize) -> Vec2 {
+        (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE
+    }
+
+    #[test]
+    fn particles_settle_and_die() {
+        let map = Map::parse(HALL, Path::new("")).unwrap();
+        let mut particles = Particles::default();
+        particles.emit(Effect::Blood, center(2, 1));
+        assert_eq!(particles.particles.len(), 24);
+
+        for _ in 0..30 {
+            particles.tick(&map);
+        }
+        for particle in particles.particles.iter() {
+            assert_eq!(particle.pos.z, 0.);
+            assert!(map.colliding(particle.pos.truncate(), false).is_none());
+        }
+
+        for _ in 0..10 {
+            particles.tick(&map);
+        }
+        assert!(particles.is_empty());
+
+        assert_eq!("smoke".parse::<Effect>().unwrap(), Effect::Smoke);
+        assert!("fire".parse::<Effect>().is_err());
+    }
+
+    #[test]
+    fn walls_hide_particles() {
+        let map = Map::parse(HALL, Path::new("")).unwrap();
+        let camera = Camera::new(center(1, 1), 0.);
+        let depth = camera.depth(&camera.cast(&map, 16));
+        let draw = |at: Vec2| {
+            let mut particles = Particles::default();
+            particles.particles.push(Particle {
+                pos: at.extend(TILE_SIZE / 2.),
+                velocity: Vec3::ZERO,
+                gravity: 0.,
+                color: Color::WHITE,
+                size: 4.,
+                life: 1,
+                lifetime: 1,
+            });
+            let mut renderer = PixelRenderer::new(16, 16);
+            particles
+                .draw(&camera, &depth, &mut renderer, Rect::new(0, 0, 16, 16))
+                .unwrap();
+            renderer
+                .framebuffer
+                .pixels
+                .iter()
+                .filter(|pixel| **pixel != 0)
+                .count()
+        };
+
+        assert!(draw(center(3, 1)) > 0);
+        assert_eq!(draw(center(5, 1)), 0);
+        assert_eq!(draw(center(0, 1)), 0);
+    }
+}
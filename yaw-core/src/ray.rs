@@ -0,0 +1,624 @@
+use crate::map::{Corner, Map, Shape, Tile, TILE_SIZE};
+use glam::{IVec2, Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI, SQRT_2};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Cardinal {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Cardinal {
+    /// how maps spell the direction
+    pub fn name(self) -> &'static str {
+        match self {
+            Cardinal::North => "north",
+            Cardinal::East => "east",
+            Cardinal::South => "south",
+            Cardinal::West => "west",
+        }
+    }
+
+    /// angle of something facing this way, north is up the map
+    pub fn angle(self) -> f32 {
+        match self {
+            Cardinal::East => 0.,
+            Cardinal::South => FRAC_PI_2,
+            Cardinal::West => PI,
+            Cardinal::North => 3. * FRAC_PI_2,
+        }
+    }
+}
+
+impl FromStr for Cardinal {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "north" => Ok(Cardinal::North),
+            "east" => Ok(Cardinal::East),
+            "south" => Ok(Cardinal::South),
+            "west" => Ok(Cardinal::West),
+            other => anyhow::bail!("unrecognized direction: {other}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RayCast {
+    /// from the origin to the hit, or as far as the ray went if nothing was hit
+    pub vec: Vec2,
+    pub hit: bool,
+    pub angle: f32,
+    pub face_direction: Cardinal,
+    /// where along the face the ray hit, from 0 to `TILE_SIZE`
+    pub hit_where: f32,
+    pub tile: char,
+}
+
+/// wrap an angle into `0..2π`
+pub fn wrap_angle(mut angle: f32) -> f32 {
+    while angle < 0. {
+        angle += 2. * PI;
+    }
+    while angle >= 2. * PI {
+        angle -= 2. * PI;
+    }
+    angle
+}
+
+/// where a ray entered a solid tile
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hit {
+    /// from the origin to the hit
+    pub vec: Vec2,
+    /// side of the tile that was hit
+    pub face: Cardinal,
+    /// where along the face the ray hit, from 0 to `TILE_SIZE`
+    pub hit_where: f32,
+    pub tile: char,
+}
+
+/// where along a face of a square tile a position is, from 0 to `TILE_SIZE`
+fn face_where(face: Cardinal, pos: Vec2) -> f32 {
+    match face {
+        Cardinal::North => TILE_SIZE - pos.x.rem_euclid(TILE_SIZE),
+        Cardinal::East => TILE_SIZE - pos.y.rem_euclid(TILE_SIZE),
+        Cardinal::South => pos.x.rem_euclid(TILE_SIZE),
+        Cardinal::West => pos.y.rem_euclid(TILE_SIZE),
+    }
+}
+
+/// the part of a ray within one cell, from `enter` to `exit` along it
+struct Span {
+    origin: Vec2,
+    dir: Vec2,
+    cell: Vec2,
+    enter: f32,
+    exit: f32,
+}
+
+impl Span {
+    fn at(&self, t: f32) -> Vec2 {
+        self.origin + (self.dir * t)
+    }
+
+    fn within(&self, t: f32) -> bool {
+        (self.enter..=self.exit).contains(&t)
+    }
+
+    fn center(&self) -> Vec2 {
+        (self.cell + 0.5) * TILE_SIZE
+    }
+
+    /// thin tiles are planes through the middle of their cell, hit wherever the ray crosses one
+    fn thin(&self, half_width: bool, half_height: bool) -> Option<(f32, Cardinal, f32)> {
+        let center = self.center();
+        let planes = [
+            (
+                half_width,
+                (center.x - self.origin.x) / self.dir.x,
+                if self.dir.x > 0. {
+                    Cardinal::West
+                } else {
+                    Cardinal::East
+                },
+            ),
+            (
+                half_height,
+                (center.y - self.origin.y) / self.dir.y,
+                if self.dir.y > 0. {
+                    Cardinal::North
+                } else {
+                    Cardinal::South
+                },
+            ),
+        ];
+        planes
+            .into_iter()
+            .filter(|(thin, t, _)| *thin && self.within(*t))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(_, t, face)| (t, face, face_where(face, self.at(t))))
+    }
+
+    /// straight wall between the two corners next to the cut away one
+    fn diagonal(&self, corner: Corner) -> Option<(f32, Cardinal, f32)> {
+        let center = self.center();
+        // points from the solid side towards the open corner
+        let normal = (corner.offset() - (TILE_SIZE / 2.)).normalize();
+        let facing = normal.dot(self.dir);
+        if facing >= 0. {
+            return None;
+        }
+
+        let t = normal.dot(center - self.origin) / facing;
+        if !self.within(t) {
+            return None;
+        }
+        let along = (self.at(t) - center).dot(normal.perp()) / (TILE_SIZE * SQRT_2);
+        Some((
+            t,
+            corner.face(),
+            ((along + 0.5) * TILE_SIZE).clamp(0., TILE_SIZE),
+        ))
+    }
+
+    /// quarter circle around the corner opposite of the rounded off one
+    fn curve(&self, corner: Corner) -> Option<(f32, Cardinal, f32)> {
+        let around = (self.cell * TILE_SIZE) + corner.opposite().offset();
+        let from = self.origin - around;
+        let b = from.dot(self.dir);
+        let discriminant = (b * b) - (from.length_squared() - (TILE_SIZE * TILE_SIZE));
+        if discriminant < 0. {
+            return None;
+        }
+
+        // the ray starts outside, so the nearer crossing is where it enters
+        let t = -b - discriminant.sqrt();
+        if !self.within(t) {
+            return None;
+        }
+        let rel = (self.at(t) - around).abs();
+        Some((
+            t,
+            corner.face(),
+            (rel.x.atan2(rel.y) / FRAC_PI_2) * TILE_SIZE,
+        ))
+    }
+}
+
+/// step through the grid cell by cell from `origin` along `dir` until a tile is hit,
+/// giving up after [`Map::dof`] tiles
+pub fn march(map: &Map, origin: Vec2, dir: Vec2) -> Option<Hit> {
+    march_within(map, origin, dir, map.dof() as f32 * TILE_SIZE)
+}
+
+/// [`march`], giving up after `limit` pixels instead
+pub(crate) fn march_within(map: &Map, origin: Vec2, dir: Vec2, limit: f32) -> Option<Hit> {
+    let dir = dir.normalize_or_zero();
+    if dir == Vec2::ZERO {
+        return None;
+    }
+
+    // whole cells, so stepping needs no rounding or dividing
+    let mut cell = (origin / TILE_SIZE).floor().as_ivec2();
+    let step = dir.signum().as_ivec2();
+    // distance along the ray between grid lines on each axis
+    let delta = (TILE_SIZE / dir).abs();
+    // distance along the ray to the next grid line on each axis
+    let next = |origin: f32, cell: f32, dir: f32| {
+        if dir > 0. {
+            ((cell + 1.) * TILE_SIZE - origin) / dir
+        } else if dir < 0. {
+            (cell * TILE_SIZE - origin) / dir
+        } else {
+            f32::INFINITY
+        }
+    };
+    let corner = cell.as_vec2();
+    let mut t_max = Vec2::new(
+        next(origin.x, corner.x, dir.x),
+        next(origin.y, corner.y, dir.y),
+    );
+
+    loop {
+        // cross whichever grid line comes first
+        let (t, face) = if t_max.x < t_max.y {
+            cell.x += step.x;
+            let t = t_max.x;
+            t_max.x += delta.x;
+            (t, across_x(step.x > 0))
+        } else {
+            cell.y += step.y;
+            let t = t_max.y;
+            t_max.y += delta.y;
+            (t, across_y(step.y > 0))
+        };
+        if t > limit {
+            return None;
+        }
+
+        if let Some(hit) = hit_in(map, origin, dir, cell, (t, face), t_max.min_element()) {
+            return Some(hit);
+        }
+    }
+}
+
+/// rays [`march_lanes`] steps side by side, one per lane of a [`Vec4`]
+pub const LANES: usize = 4;
+
+/// [`march`] for [`LANES`] rays from one origin at once, stepping all of them through the
+/// grid together in simd vectors and only looking up the tiles they reach one at a time
+pub fn march_lanes(map: &Map, origin: Vec2, dirs: [Vec2; LANES]) -> [Option<Hit>; LANES] {
+    let limit = map.dof() as f32 * TILE_SIZE;
+    let dirs = dirs.map(Vec2::normalize_or_zero);
+    let dir_x = Vec4::from_array(dirs.map(|dir| dir.x));
+    let dir_y = Vec4::from_array(dirs.map(|dir| dir.y));
+
+    // the same steps as `march_within`, so both find the same hits
+    let corner = (origin / TILE_SIZE).floor();
+    let (mut cell_x, mut cell_y) = (Vec4::splat(corner.x), Vec4::splat(corner.y));
+    let (step_x, step_y) = (dir_x.signum(), dir_y.signum());
+    let delta_x = (Vec4::splat(TILE_SIZE) / dir_x).abs();
+    let delta_y = (Vec4::splat(TILE_SIZE) / dir_y).abs();
+    let next = |origin: f32, cell: Vec4, dir: Vec4| {
+        let ahead = Vec4::select(dir.cmpgt(Vec4::ZERO), cell + 1., cell);
+        let t = (ahead * TILE_SIZE - origin) / dir;
+        Vec4::select(dir.cmpeq(Vec4::ZERO), Vec4::INFINITY, t)
+    };
+    let mut t_max_x = next(origin.x, cell_x, dir_x);
+    let mut t_max_y = next(origin.y, cell_y, dir_y);
+
+    let mut hits = [None; LANES];
+    // one bit per lane still looking for a hit
+    let mut marching = dirs.iter().enumerate().fold(0, |bits, (lane, dir)| {
+        bits | (u32::from(*dir != Vec2::ZERO) << lane)
+    });
+    let (width, height) = (map.width as u32, map.height as u32);
+    while marching != 0 {
+        let crossing_x = t_max_x.cmplt(t_max_y);
+        let t = Vec4::select(crossing_x, t_max_x, t_max_y);
+        cell_x += Vec4::select(crossing_x, step_x, Vec4::ZERO);
+        cell_y += Vec4::select(crossing_x, Vec4::ZERO, step_y);
+        t_max_x += Vec4::select(crossing_x, delta_x, Vec4::ZERO);
+        t_max_y += Vec4::select(crossing_x, Vec4::ZERO, delta_y);
+
+        // most cells rays cross are empty, which needs no more than a look at the tile
+        let past = t.cmpgt(Vec4::splat(limit)).bitmask();
+        let (cells_x, cells_y) = (cell_x.as_ivec4().to_array(), cell_y.as_ivec4().to_array());
+        let mut solid = 0;
+        for lane in 0..LANES {
+            // cells off the map wrap around to large numbers
+            let (x, y) = (cells_x[lane] as u32, cells_y[lane] as u32);
+            let inside = (x < width) & (y < height);
+            let idx = if inside { y * width + x } else { 0 };
+            let custom = matches!(map.main_tiles.get(idx as usize), Some(Tile::Custom(_)));
+            solid |= u32::from(inside & custom) << lane;
+        }
+        marching &= !past;
+        let mut look = marching & solid;
+        if look == 0 {
+            continue;
+        }
+
+        let (t, exit) = (t.to_array(), t_max_x.min(t_max_y).to_array());
+        let crossed_x = crossing_x.bitmask();
+        while look != 0 {
+            let lane = look.trailing_zeros() as usize;
+            look &= look - 1;
+            let face = if crossed_x & (1 << lane) != 0 {
+                across_x(step_x[lane] > 0.)
+            } else {
+                across_y(step_y[lane] > 0.)
+            };
+            let cell = IVec2::new(cells_x[lane], cells_y[lane]);
+            hits[lane] = hit_in(map, origin, dirs[lane], cell, (t[lane], face), exit[lane]);
+            if hits[lane].is_some() {
+                marching &= !(1 << lane);
+            }
+        }
+    }
+    hits
+}
+
+/// face entered by crossing a grid line between columns, going east or west
+fn across_x(east: bool) -> Cardinal {
+    if east {
+        Cardinal::West
+    } else {
+        Cardinal::East
+    }
+}
+
+/// face entered by crossing a grid line between rows, going south or north
+fn across_y(south: bool) -> Cardinal {
+    if south {
+        Cardinal::North
+    } else {
+        Cardinal::South
+    }
+}
+
+/// where a ray entering `cell` through a face at `t` along it and leaving again at `exit`
+/// hits what is in the cell, none when it is empty or the ray passes by
+fn hit_in(
+    map: &Map,
+    origin: Vec2,
+    dir: Vec2,
+    cell: IVec2,
+    (t, face): (f32, Cardinal),
+    exit: f32,
+) -> Option<Hit> {
+    let tile = map.colliding_cell(cell.x, cell.y, false)?;
+    let custom = &map.custom_tiles[&tile];
+    let cell = cell.as_vec2();
+    let span = Span {
+        origin,
+        dir,
+        cell,
+        enter: t,
+        exit,
+    };
+    let entered = (t, face, face_where(face, span.at(t)));
+    let (t, face, hit_where) = match custom.shape {
+        Shape::Square if custom.half_width || custom.half_height => {
+            span.thin(custom.half_width, custom.half_height)
+        }
+        // entered through a side that is solid all the way
+        shape if shape.contains(span.at(t) - (cell * TILE_SIZE)) => Some(entered),
+        Shape::Square => Some(entered),
+        Shape::Diagonal(corner) => span.diagonal(corner),
+        Shape::Curve(corner) => span.curve(corner),
+    }?;
+
+    Some(Hit {
+        vec: dir * t,
+        face,
+        hit_where,
+        tile,
+    })
+}
+
+/// cast a single ray from `origin` at `angle`, see [`march`]
+pub fn cast_ray(map: &Map, origin: Vec2, angle: f32) -> RayCast {
+    cast_along(map, origin, Vec2::from_angle(angle), angle)
+}
+
+/// [`cast_ray`] for callers that already know the direction of `angle`, which does not need
+/// to be normalized
+pub fn cast_along(map: &Map, origin: Vec2, dir: Vec2, angle: f32) -> RayCast {
+    ray_cast(map, dir, angle, march(map, origin, dir))
+}
+
+/// [`cast_along`] for [`LANES`] rays at once, see [`march_lanes`]
+pub fn cast_lanes(
+    map: &Map,
+    origin: Vec2,
+    dirs: [Vec2; LANES],
+    angles: [f32; LANES],
+) -> [RayCast; LANES] {
+    let hits = march_lanes(map, origin, dirs);
+    std::array::from_fn(|lane| ray_cast(map, dirs[lane], angles[lane], hits[lane]))
+}
+
+/// what a ray along `dir` at `angle` saw, going as far as the map lets it without a hit
+fn ray_cast(map: &Map, dir: Vec2, angle: f32, hit: Option<Hit>) -> RayCast {
+    let angle = wrap_angle(angle);
+
+    match hit {
+        Some(hit) => RayCast {
+            vec: hit.vec,
+            hit: true,
+            angle,
+            face_direction: hit.face,
+            hit_where: hit.hit_where,
+            tile: hit.tile,
+        },
+        None => RayCast {
+            vec: dir.normalize_or_zero() * (map.dof() as f32 * TILE_SIZE),
+            hit: false,
+            angle,
+            face_direction: Cardinal::North,
+            hit_where: 0.,
+            tile: '\0',
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const BOX: &str = "!!!!MAIN
+#wall.png,collide
+
+#####
+#   #
+# * #
+#   #
+#####";
+
+    /// center of the spawn tile in `BOX`
+    const CENTER: Vec2 = Vec2::new(80., 80.);
+
+    fn box_map() -> Map {
+        Map::parse(BOX, Path::new("")).unwrap()
+    }
+
+    #[test]
+    fn wraps_angles() {
+        assert_eq!(wrap_angle(0.), 0.);
+        assert!((wrap_angle(-FRAC_PI_2) - 3. * FRAC_PI_2).abs() < 1e-5);
+        assert!((wrap_angle(5. * PI) - PI).abs() < 1e-5);
+        assert!((0. ..2. * PI).contains(&wrap_angle(2. * PI)));
+    }
+
+    #[test]
+    fn hits_walls_in_every_direction() {
+        let map = box_map();
+
+        for (angle, face, vec) in [
+            (0., Cardinal::West, Vec2::new(48., 0.)),
+            (FRAC_PI_2, Cardinal::North, Vec2::new(0., 48.)),
+            (PI, Cardinal::East, Vec2::new(-48., 0.)),
+            (3. * FRAC_PI_2, Cardinal::South, Vec2::new(0., -48.)),
+        ] {
+            let ray = cast_ray(&map, CENTER, angle);
+            assert_eq!(ray.tile, '#');
+            assert_eq!(ray.face_direction, face, "angle {angle}");
+            assert!(ray.vec.distance(vec) < 0.01, "angle {angle}: {}", ray.vec);
+            assert!((ray.hit_where - 16.).abs() < 0.01, "angle {angle}");
+        }
+    }
+
+    #[test]
+    fn hit_where_stays_on_the_face() {
+        let map = box_map();
+
+        for step in 0..720 {
+            let angle = (step as f32 / 720.) * 2. * PI;
+            let ray = cast_ray(&map, CENTER + Vec2::new(3., -5.), angle);
+            assert!(ray.vec.is_finite(), "angle {angle}");
+            assert!(
+                (0. ..=TILE_SIZE).contains(&ray.hit_where),
+                "angle {angle}: {}",
+                ray.hit_where
+            );
+        }
+    }
+
+    #[test]
+    fn march_steps_through_every_cell() {
+        let map = box_map();
+
+        // a shallow ray from near a corner crosses many cells before the wall
+        let hit = march(&map, Vec2::new(33., 33.), Vec2::new(10., 1.)).unwrap();
+        assert_eq!(hit.face, Cardinal::West);
+        assert!((hit.vec.x - 95.).abs() < 0.01, "{}", hit.vec);
+
+        // exactly along a grid line
+        let hit = march(&map, Vec2::new(64., 64.), Vec2::Y).unwrap();
+        assert_eq!(hit.face, Cardinal::North);
+        assert_eq!(hit.vec, Vec2::new(0., 64.));
+
+        assert_eq!(march(&map, CENTER, Vec2::ZERO), None);
+    }
+
+    #[test]
+    fn lanes_hit_what_single_rays_do() {
+        let map = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\n/wall.png,diagonal=nw\n(wall.png,curve=se\n\
+             -door.png,half_height\n\n######\n#/  (#\n#  * #\n# -  #\n######",
+            Path::new(""),
+        )
+        .unwrap();
+        let origin = Vec2::new(101., 83.);
+
+        // all the way around, with rays along grid lines and ones that look nowhere
+        for turn in 0..64 {
+            let dirs = std::array::from_fn(|lane| {
+                let angle = (turn * LANES + lane) as f32 * PI / 128.;
+                match lane {
+                    3 if turn % 4 == 0 => Vec2::ZERO,
+                    _ => Vec2::from_angle(angle) * 3.,
+                }
+            });
+            let lanes = march_lanes(&map, origin, dirs);
+            for (dir, hit) in dirs.into_iter().zip(lanes) {
+                assert_eq!(hit, march(&map, origin, dir), "{dir}");
+            }
+        }
+        let straight = march_lanes(&map, origin, [Vec2::X, Vec2::Y, -Vec2::X, -Vec2::Y]);
+        let faces = straight.map(|hit| hit.map(|hit| hit.face));
+        let expected = [
+            Cardinal::West,
+            Cardinal::North,
+            Cardinal::East,
+            Cardinal::South,
+        ];
+        assert_eq!(faces, expected.map(Some));
+    }
+
+    #[test]
+    fn thin_walls_are_hit_in_the_middle() {
+        let map = Map::parse(
+            "!!!!MAIN\n#wall.png\n-door.png,half_height\n\n *\n -\n  ",
+            Path::new(""),
+        )
+        .unwrap();
+
+        // straight on
+        let ray = cast_ray(&map, Vec2::new(48., 16.), FRAC_PI_2);
+        assert_eq!(ray.face_direction, Cardinal::North);
+        assert!(ray.vec.distance(Vec2::new(0., 32.)) < 0.01, "{}", ray.vec);
+        assert!((ray.hit_where - 16.).abs() < 0.01);
+
+        // entering from the side at a grazing angle still hits the plane
+        let origin = Vec2::new(8., 40.);
+        let ray = cast_ray(&map, origin, 0.2);
+        assert!(ray.hit);
+        assert_eq!(ray.face_direction, Cardinal::North);
+        assert!(((origin + ray.vec).y - 48.).abs() < 0.01, "{}", ray.vec);
+
+        // passing by before reaching the middle does not
+        assert!(!cast_ray(&map, Vec2::new(8., 34.), 0.05).hit);
+    }
+
+    #[test]
+    fn diagonal_and_curved_walls_are_hit_exactly() {
+        let map = Map::parse(
+            "!!!!MAIN\n/wall.png,diagonal=nw\n(wall.png,curve=nw\n\n*/\n (",
+            Path::new(""),
+        )
+        .unwrap();
+
+        // the diagonal runs from the north east to the south west corner of its tile
+        let ray = cast_ray(&map, Vec2::new(16., 16.), 0.);
+        assert!(ray.hit);
+        assert_eq!(ray.face_direction, Cardinal::West);
+        assert!(ray.vec.distance(Vec2::new(32., 0.)) < 0.01, "{}", ray.vec);
+        assert!((ray.hit_where - 16.).abs() < 0.01, "{}", ray.hit_where);
+
+        // the curve bulges out of its south east corner
+        let ray = cast_ray(&map, Vec2::new(16., 48.), 0.);
+        let hit = Vec2::new(16., 48.) + ray.vec;
+        assert!(ray.hit);
+        assert!(
+            (hit.distance(Vec2::new(64., 64.)) - TILE_SIZE).abs() < 0.01,
+            "{hit}"
+        );
+
+        // entering through a solid side hits that side
+        let ray = cast_ray(&map, Vec2::new(48., 80.), 3. * FRAC_PI_2);
+        assert_eq!(ray.face_direction, Cardinal::South);
+        assert!(ray.vec.distance(Vec2::new(0., -16.)) < 0.01, "{}", ray.vec);
+    }
+
+    #[test]
+    fn misses_without_walls() {
+        let map = Map::parse("!!!!MAIN\n\n*", Path::new("")).unwrap();
+        let ray = cast_ray(&map, Vec2::splat(16.), 1.);
+        assert!(!ray.hit);
+        assert!(ray.vec.is_finite());
+    }
+
+    #[test]
+    fn large_maps_march_further() {
+        // a wall far beyond the old fixed limit of 24 grid lines
+        let wide = format!("!!!!MAIN\n#wall.png\n\n*{}#", " ".repeat(40));
+        let map = Map::parse(&wide, Path::new("")).unwrap();
+        let ray = cast_ray(&map, Vec2::splat(16.), 0.);
+        assert!(ray.hit);
+        assert_eq!(ray.tile, '#');
+
+        // unless the map says otherwise
+        let map = Map::parse(&format!("!!!!META\ndof,steps=8\n\n{wide}"), Path::new("")).unwrap();
+        assert_eq!(map.dof(), 8);
+        assert!(!cast_ray(&map, Vec2::splat(16.), 0.).hit);
+    }
+}
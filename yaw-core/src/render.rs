@@ -0,0 +1,259 @@
+use crate::color::Color;
+use crate::framebuffer::Image;
+use std::cell::RefCell;
+use std::path::Path;
+
+/// area on screen in pixels
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub const fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    pub const fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+}
+
+/// one vertical strip of a wall
+#[derive(Clone, Copy, Debug)]
+pub struct Column<'a> {
+    pub x: i32,
+    pub top: i32,
+    /// pixels across, more than one for views cast at a lower resolution than they are drawn
+    pub width: u32,
+    pub height: u32,
+    /// image file the strip is sampled from
+    pub texture: &'a Path,
+    /// horizontal position in the texture, from 0 to 1
+    pub texture_x: f32,
+    /// multiplied with every texel
+    pub tint: Color,
+    /// blended over every texel, for fog and shading
+    pub overlay: Color,
+}
+
+/// how often drawn textures were already loaded, counted since the renderer was made
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// how text is drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TextStyle {
+    pub size: u16,
+    pub italic: bool,
+    pub fg: Color,
+    /// box drawn behind the text
+    pub bg: Option<Color>,
+    /// space between the text and the edge of its box
+    pub padding: (u32, u32),
+    /// widest a line can be before words go on the next one, see [`wrap_lines`]
+    pub wrap: Option<u32>,
+    /// smooth the edges of letters against what is behind them
+    pub blended: bool,
+}
+
+impl TextStyle {
+    pub const fn new(size: u16, fg: Color) -> Self {
+        Self {
+            size,
+            italic: false,
+            fg,
+            bg: None,
+            padding: (0, 0),
+            wrap: None,
+            blended: false,
+        }
+    }
+
+    pub const fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    /// draw a box behind the text
+    pub const fn boxed(mut self, bg: Color, padding: (u32, u32)) -> Self {
+        self.bg = Some(bg);
+        self.padding = padding;
+        self
+    }
+
+    /// break lines wider than `width` pixels between words
+    pub const fn wrapped(mut self, width: u32) -> Self {
+        self.wrap = Some(width);
+        self
+    }
+
+    pub const fn blended(mut self) -> Self {
+        self.blended = true;
+        self
+    }
+}
+
+/// lines text is drawn in, split at newlines and, for styles that wrap, before words that would
+/// go past [`TextStyle::wrap`] as `width` measures them. words too wide on their own are split
+/// between characters
+pub fn wrap_lines<'a>(
+    text: &'a str,
+    style: &TextStyle,
+    width: impl Fn(&str) -> u32,
+) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let Some(max) = style.wrap else {
+            lines.push(paragraph);
+            continue;
+        };
+
+        let mut rest = paragraph;
+        while width(rest) > max {
+            // the last space before the line gets too wide
+            let mut end = None;
+            for (idx, c) in rest.char_indices().skip(1) {
+                if !c.is_whitespace() {
+                    continue;
+                }
+                if width(rest[..idx].trim_end()) > max {
+                    break;
+                }
+                end = Some(idx);
+            }
+            let end = end
+                .filter(|&end| !rest[..end].trim().is_empty())
+                .unwrap_or_else(|| {
+                    rest.char_indices()
+                        .map(|(idx, _)| idx)
+                        .skip(1)
+                        .take_while(|&idx| width(&rest[..idx]) <= max)
+                        .last()
+                        .unwrap_or_else(|| rest.chars().next().map_or(rest.len(), char::len_utf8))
+                });
+
+            lines.push(rest[..end].trim_end());
+            rest = rest[end..].trim_start();
+        }
+        if !rest.is_empty() || paragraph.is_empty() {
+            lines.push(rest);
+        }
+    }
+
+    lines
+}
+
+/// [`wrap_lines`] as `renderer` would wrap the text, for text laid out a line at a time
+pub fn renderer_lines<'a>(
+    renderer: &mut dyn Renderer,
+    text: &'a str,
+    style: &TextStyle,
+) -> Vec<&'a str> {
+    let plain = TextStyle {
+        bg: None,
+        padding: (0, 0),
+        wrap: None,
+        ..*style
+    };
+    let renderer = RefCell::new(renderer);
+    wrap_lines(text, style, |line| {
+        renderer
+            .borrow_mut()
+            .measure_text(line, &plain)
+            .map_or(0, |(width, _)| width)
+    })
+}
+
+/// something frames can be drawn with, translucent colors are always blended
+pub trait Renderer {
+    /// draw a textured wall strip
+    fn draw_column(&mut self, column: &Column) -> anyhow::Result<()>;
+
+    /// fill an area, or everything with `None`
+    fn draw_rect(&mut self, rect: Option<Rect>, color: Color) -> anyhow::Result<()>;
+
+    fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) -> anyhow::Result<()>;
+
+    /// draw an image file stretched over an area, or everything with `None`
+    fn draw_image(&mut self, path: &Path, rect: Option<Rect>) -> anyhow::Result<()>;
+
+    /// draw text, on more lines if it has newlines or wraps, returns the area that was drawn
+    /// over
+    fn draw_text(&mut self, text: &str, style: &TextStyle, x: i32, y: i32) -> anyhow::Result<Rect>;
+
+    /// width and height of the area text would be drawn over, without drawing it
+    fn measure_text(&mut self, text: &str, style: &TextStyle) -> anyhow::Result<(u32, u32)>;
+
+    /// read back everything drawn so far this frame
+    fn capture(&mut self) -> anyhow::Result<Image>;
+
+    /// show the frame
+    fn present(&mut self) -> anyhow::Result<()>;
+
+    /// load a texture ahead of drawing it, so the first frame showing it does not stall
+    fn preload(&mut self, path: &Path) -> anyhow::Result<()>;
+
+    /// forget cached textures, e.g. after another map was loaded
+    fn clear_cache(&mut self);
+
+    /// lookups of the texture cache so far, renderers without one have none
+    fn cache_stats(&self) -> CacheStats {
+        CacheStats::default()
+    }
+
+    /// title of the window frames are shown in, renderers without one ignore it
+    fn set_title(&mut self, _title: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// get the attention of players looking at something else, like flashing the taskbar
+    fn flash(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_wraps_between_words() {
+        // every character is 10 pixels wide
+        let width = |text: &str| text.chars().count() as u32 * 10;
+        let style = TextStyle::new(16, Color::WHITE).wrapped(100);
+
+        assert_eq!(
+            wrap_lines("the quick brown fox jumps", &style, width),
+            ["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(wrap_lines("one\n\ntwo", &style, width), ["one", "", "two"]);
+        // long words are split, without splitting characters
+        assert_eq!(
+            wrap_lines("Straßenbahnhaltestelle", &style, width),
+            ["Straßenbah", "nhaltestel", "le"]
+        );
+        assert_eq!(wrap_lines("", &style, width), [""]);
+
+        let unwrapped = TextStyle::new(16, Color::WHITE);
+        assert_eq!(
+            wrap_lines("the quick brown fox jumps", &unwrapped, width),
+            ["the quick brown fox jumps"]
+        );
+    }
+}
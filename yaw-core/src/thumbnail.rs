@@ -0,0 +1,84 @@
+//! top-down pictures of the layout of a map, for map select and for sharing levels
+
+use crate::color::Color;
+use crate::framebuffer::Image;
+use crate::map::{Map, Tile};
+
+/// pixels per tile of thumbnails written to files
+pub const TILE_PIXELS: usize = 8;
+
+const FLOOR_COLOR: Color = Color::RGB(0x20, 0x20, 0x20);
+const WALL_COLOR: Color = Color::RGB(0xb0, 0xb0, 0xb0);
+const DOOR_COLOR: Color = Color::RGB(0xdd, 0xaa, 0);
+const EXIT_COLOR: Color = Color::RGB(0xdd, 0, 0xdd);
+const WATER_COLOR: Color = Color::RGB(0x20, 0x50, 0xa0);
+const SPAWN_COLOR: Color = Color::WHITE;
+
+/// color of a tile seen from above
+pub fn tile_color(map: &Map, tile: &Tile) -> Color {
+    let Tile::Custom(id) = tile else {
+        return match tile {
+            Tile::Spawn | Tile::NamedSpawn(_) => SPAWN_COLOR,
+            _ => FLOOR_COLOR,
+        };
+    };
+    let Some(custom) = map.custom_tiles.get(id) else {
+        return FLOOR_COLOR;
+    };
+    if custom.exit {
+        EXIT_COLOR
+    } else if custom.water {
+        WATER_COLOR
+    } else if custom.floor {
+        FLOOR_COLOR
+    } else if custom.collidable && custom.door.is_none() {
+        WALL_COLOR
+    } else {
+        // walls players walk through, or that open, are doors
+        DOOR_COLOR
+    }
+}
+
+/// the layout of a map with `scale` pixels per tile
+pub fn render(map: &Map, scale: usize) -> Image {
+    let scale = scale.max(1);
+    let (width, height) = (map.width * scale, map.height * scale);
+    let pixels = (0..width * height)
+        .map(|idx| {
+            let (x, y) = ((idx % width) / scale, (idx / width) / scale);
+            tile_color(map, &map.main_tiles[(y * map.width) + x]).to_argb()
+        })
+        .collect();
+
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn tiles_are_drawn_from_above() {
+        let map = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\nEexit.png,floor,exit\n\n###\n#*E\n",
+            Path::new(""),
+        )
+        .unwrap();
+        let image = render(&map, 2);
+        assert_eq!((image.width, image.height), (6, 4));
+        assert_eq!(image.get(1, 1), WALL_COLOR.to_argb());
+        assert_eq!(image.get(2, 2), SPAWN_COLOR.to_argb());
+        assert_eq!(image.get(5, 3), EXIT_COLOR.to_argb());
+
+        // written out and read back as a png
+        let path = std::env::temp_dir().join(format!("yaw-thumbnail-{}.png", std::process::id()));
+        std::fs::write(&path, image.to_png().unwrap()).unwrap();
+        assert_eq!(Image::load(&path).unwrap(), image);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
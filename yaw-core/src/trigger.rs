@@ -0,0 +1,465 @@
+//! rectangular areas of a map that do something when a player walks into them
+//!
+//! each line of the `!!!!TRIGGERS` section is an action followed by its parameters:
+//!
+//! ```text
+//! message,x=1,y=1,w=2,text=Find the exit
+//! sound,x=3,y=1,once,path=alarm.wav
+//! spawn,x=4,y=2,at=6:2,sprite=guard.png,chase
+//! tile,x=5,y=5,once,at=9:3,tile=empty
+//! move,x=2,y=4,once,from=6:2,to=2:6
+//! effect,x=3,y=3,at=4:3,kind=sparks
+//! marker,x=9,y=1,once,text=Exit
+//! secret,x=7,y=7,w=2
+//! complete,x=9,y=5,once,objective=lever
+//! level,x=3,y=8,map=cellar.yaw
+//! ```
+//!
+//! `chase` spawns an enemy that goes after players in sight. `plate` triggers are pressure
+//! plates, crates pushed onto them press them too and keep them down.
+//! effects are `blood`, `sparks`, `smoke` and `teleport`. markers do nothing but show their
+//! area on the compass, `once` markers disappear from it when reached. secrets count towards
+//! the level statistics when found, and are always `once`. `complete` marks an objective of the
+//! map done. `level` goes into a level of the campaign from its hub map.
+//!
+//! `x`, `y`, `w` and `h` are in tiles, `w` and `h` default to 1. `once` triggers never fire again.
+
+use crate::map::{Tile, Transform, TILE_SIZE};
+use crate::particle::Effect;
+use anyhow::Context;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Action {
+    /// show text on the hud
+    Message(String),
+    /// play a sound file
+    Sound(PathBuf),
+    /// add an entity with this image on a tile, an enemy chasing players if `chase`
+    Spawn {
+        at: (usize, usize),
+        sprite: PathBuf,
+        chase: bool,
+    },
+    /// replace a tile, like opening a wall
+    SetTile { at: (usize, usize), tile: Tile },
+    /// walk the entity standing on a tile around walls to another tile
+    Move {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    /// burst of particles in the middle of a tile
+    Effect { at: (usize, usize), effect: Effect },
+    /// objective shown on the compass, see [`Triggers::markers`]
+    Marker(String),
+    /// hidden area players get credit for finding
+    Secret,
+    /// mark an objective of the map done, by its id
+    Complete(String),
+    /// go into the level of the campaign playing this map, from its hub
+    Level(PathBuf),
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Trigger {
+    /// top left tile of the area
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub once: bool,
+    /// crates fire it too, not only players
+    pub plate: bool,
+    pub action: Action,
+}
+
+/// `x:y` in tiles
+fn parse_at(at: &str) -> anyhow::Result<(usize, usize)> {
+    let (x, y) = at.split_once(':').context("expected x:y")?;
+    Ok((x.parse()?, y.parse()?))
+}
+
+impl Trigger {
+    /// parse one line of the triggers section, paths in it are relative to `prefix`
+    pub fn parse(line: &str, prefix: &Path) -> anyhow::Result<Self> {
+        let mut chunks = line.split(',');
+        let kind = chunks.by_ref().next().unwrap();
+        let mut once = false;
+        let mut plate = false;
+        let mut chase = false;
+        let mut params = HashMap::new();
+        for chunk in chunks {
+            match chunk.split_once('=') {
+                Some((key, value)) => {
+                    params.insert(key, value);
+                }
+                None if chunk == "once" => once = true,
+                None if chunk == "plate" => plate = true,
+                None if chunk == "chase" && kind == "spawn" => chase = true,
+                None => anyhow::bail!("unrecognized trigger flag: {chunk}"),
+            }
+        }
+        let param = |key| params.get(key).copied().context(format!("missing {key}"));
+
+        let action = match kind {
+            "message" => Action::Message(param("text")?.into()),
+            "sound" => Action::Sound(prefix.join(param("path")?)),
+            "spawn" => Action::Spawn {
+                at: parse_at(param("at")?)?,
+                sprite: prefix.join(param("sprite")?),
+                chase,
+            },
+            "tile" => Action::SetTile {
+                at: parse_at(param("at")?)?,
+                tile: match param("tile")? {
+                    "empty" => Tile::Empty,
+                    id if id.chars().count() == 1 => Tile::Custom(id.chars().next().unwrap()),
+                    other => anyhow::bail!("invalid tile: {other}"),
+                },
+            },
+            "effect" => Action::Effect {
+                at: parse_at(param("at")?)?,
+                effect: param("kind")?.parse()?,
+            },
+            "marker" => Action::Marker(param("text")?.into()),
+            "secret" => Action::Secret,
+            "complete" => Action::Complete(param("objective")?.into()),
+            "level" => Action::Level(prefix.join(param("map")?)),
+            "move" => Action::Move {
+                from: parse_at(param("from")?)?,
+                to: parse_at(param("to")?)?,
+            },
+            other => anyhow::bail!("unrecognized trigger action: {other}"),
+        };
+
+        Ok(Self {
+            x: param("x")?.parse()?,
+            y: param("y")?.parse()?,
+            width: params.get("w").unwrap_or(&"1").parse()?,
+            height: params.get("h").unwrap_or(&"1").parse()?,
+            once: once || action == Action::Secret,
+            plate,
+            action,
+        })
+    }
+
+    /// line of the triggers section [`Trigger::parse`] reads back, paths are written relative
+    /// to `prefix`
+    pub fn to_line(&self, prefix: &Path) -> String {
+        let relative = |path: &PathBuf| {
+            path.strip_prefix(prefix)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        let (kind, param) = match &self.action {
+            Action::Message(text) => ("message", format!("text={text}")),
+            Action::Marker(text) => ("marker", format!("text={text}")),
+            Action::Secret => ("secret", String::new()),
+            Action::Complete(id) => ("complete", format!("objective={id}")),
+            Action::Sound(path) => ("sound", format!("path={}", relative(path))),
+            Action::Level(map) => ("level", format!("map={}", relative(map))),
+            Action::Spawn { at, sprite, chase } => (
+                "spawn",
+                format!(
+                    "at={}:{},sprite={}{}",
+                    at.0,
+                    at.1,
+                    relative(sprite),
+                    if *chase { ",chase" } else { "" }
+                ),
+            ),
+            Action::SetTile { at, tile } => {
+                let tile = match tile {
+                    Tile::Empty => "empty".into(),
+                    Tile::Spawn => "*".into(),
+                    Tile::NamedSpawn(id) | Tile::Custom(id) => id.to_string(),
+                };
+                ("tile", format!("at={}:{},tile={tile}", at.0, at.1))
+            }
+            Action::Effect { at, effect } => (
+                "effect",
+                format!("at={}:{},kind={}", at.0, at.1, effect.name()),
+            ),
+            Action::Move { from, to } => (
+                "move",
+                format!("from={}:{},to={}:{}", from.0, from.1, to.0, to.1),
+            ),
+        };
+        let once = if self.once { ",once" } else { "" };
+        let plate = if self.plate { ",plate" } else { "" };
+        let param = if param.is_empty() {
+            param
+        } else {
+            format!(",{param}")
+        };
+
+        format!(
+            "{kind},x={},y={},w={},h={}{once}{plate}{param}",
+            self.x, self.y, self.width, self.height
+        )
+    }
+
+    /// middle of the area in pixels
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(
+            self.x as f32 + (self.width as f32 / 2.),
+            self.y as f32 + (self.height as f32 / 2.),
+        ) * TILE_SIZE
+    }
+
+    /// whether a position in pixels is inside the area
+    pub fn contains(&self, position: Vec2) -> bool {
+        let (x, y) = (position.x / TILE_SIZE, position.y / TILE_SIZE);
+        x >= self.x as f32
+            && y >= self.y as f32
+            && x < (self.x + self.width) as f32
+            && y < (self.y + self.height) as f32
+    }
+
+    /// follow a transformation of a map that was `width` by `height` tiles before it
+    pub fn transform(&mut self, transform: Transform, width: usize, height: usize) {
+        let tile = |at| transform.tile(at, width, height);
+
+        let (a, b) = (
+            tile((self.x, self.y)),
+            tile((self.x + self.width - 1, self.y + self.height - 1)),
+        );
+        (self.x, self.y) = (a.0.min(b.0), a.1.min(b.1));
+        (self.width, self.height) = (a.0.abs_diff(b.0) + 1, a.1.abs_diff(b.1) + 1);
+
+        match &mut self.action {
+            Action::Spawn { at, .. } | Action::SetTile { at, .. } | Action::Effect { at, .. } => {
+                *at = tile(*at)
+            }
+            Action::Move { from, to } => (*from, *to) = (tile(*from), tile(*to)),
+            Action::Message(_)
+            | Action::Sound(_)
+            | Action::Marker(_)
+            | Action::Secret
+            | Action::Complete(_)
+            | Action::Level(_) => {}
+        }
+    }
+}
+
+/// decides which triggers of a map fire, a trigger fires when someone enters it
+#[derive(Clone, Default, Debug)]
+pub struct Triggers {
+    /// whether anyone was inside each trigger last update
+    inside: Vec<bool>,
+    /// one shot triggers that already fired
+    fired: Vec<bool>,
+}
+
+impl Triggers {
+    /// actions of every trigger entered since the last update by one of `positions`, or for
+    /// plates also by one of `weights`, like crates
+    pub fn update<'a>(
+        &mut self,
+        triggers: &'a [Trigger],
+        positions: &[Vec2],
+        weights: &[Vec2],
+    ) -> Vec<&'a Action> {
+        self.inside.resize(triggers.len(), false);
+        self.fired.resize(triggers.len(), false);
+
+        let mut actions = Vec::new();
+        for (idx, trigger) in triggers.iter().enumerate() {
+            let weighed = if trigger.plate { weights } else { &[] };
+            let inside = positions
+                .iter()
+                .chain(weighed)
+                .any(|pos| trigger.contains(*pos));
+            if inside && !self.inside[idx] && !self.fired[idx] {
+                actions.push(&trigger.action);
+                self.fired[idx] = trigger.once;
+            }
+            self.inside[idx] = inside;
+        }
+
+        actions
+    }
+
+    /// middle and text of every marker that was not reached yet
+    pub fn markers<'a>(&self, triggers: &'a [Trigger]) -> Vec<(Vec2, &'a str)> {
+        triggers
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| self.fired.get(*idx) != Some(&true))
+            .filter_map(|(_, trigger)| match &trigger.action {
+                Action::Marker(text) => Some((trigger.center(), text.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn center(x: usize, y: usize) -> Vec2 {
+        (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE
+    }
+
+    #[test]
+    fn parses_actions() {
+        let trigger = Trigger::parse("tile,x=1,y=2,w=3,once,at=4:5,tile=#", Path::new("")).unwrap();
+        assert_eq!(
+            (trigger.x, trigger.y, trigger.width, trigger.height),
+            (1, 2, 3, 1)
+        );
+        assert!(trigger.once);
+        assert_eq!(
+            trigger.action,
+            Action::SetTile {
+                at: (4, 5),
+                tile: Tile::Custom('#')
+            }
+        );
+
+        let trigger = Trigger::parse("sound,x=0,y=0,path=a.wav", Path::new("map")).unwrap();
+        assert_eq!(trigger.action, Action::Sound("map/a.wav".into()));
+
+        let trigger = Trigger::parse("effect,x=0,y=0,at=1:2,kind=smoke", Path::new("")).unwrap();
+        assert_eq!(
+            trigger.action,
+            Action::Effect {
+                at: (1, 2),
+                effect: Effect::Smoke
+            }
+        );
+        assert!(Trigger::parse("effect,x=0,y=0,at=1:2,kind=fire", Path::new("")).is_err());
+
+        let trigger = Trigger::parse("move,x=0,y=0,from=1:2,to=3:4", Path::new("")).unwrap();
+        assert_eq!(
+            trigger.action,
+            Action::Move {
+                from: (1, 2),
+                to: (3, 4)
+            }
+        );
+
+        let trigger =
+            Trigger::parse("spawn,x=0,y=0,chase,at=1:2,sprite=a.png", Path::new("map")).unwrap();
+        assert_eq!(
+            trigger.action,
+            Action::Spawn {
+                at: (1, 2),
+                sprite: "map/a.png".into(),
+                chase: true
+            }
+        );
+        assert_eq!(
+            trigger.to_line(Path::new("map")),
+            "spawn,x=0,y=0,w=1,h=1,at=1:2,sprite=a.png,chase"
+        );
+        assert!(Trigger::parse("message,x=0,y=0,chase,text=hi", Path::new("")).is_err());
+
+        let trigger = Trigger::parse("level,x=0,y=0,map=cellar.yaw", Path::new("map")).unwrap();
+        assert_eq!(trigger.action, Action::Level("map/cellar.yaw".into()));
+        assert_eq!(
+            trigger.to_line(Path::new("map")),
+            "level,x=0,y=0,w=1,h=1,map=cellar.yaw"
+        );
+
+        let trigger = Trigger::parse("secret,x=1,y=2,w=2", Path::new("")).unwrap();
+        assert!(trigger.once);
+        assert_eq!(
+            trigger.to_line(Path::new("")),
+            "secret,x=1,y=2,w=2,h=1,once"
+        );
+
+        assert!(Trigger::parse("message,x=0,y=0", Path::new("")).is_err());
+        assert!(Trigger::parse("explode,x=0,y=0", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn fires_on_entering() {
+        let triggers = [
+            Trigger::parse("message,x=1,y=1,w=2,text=again", Path::new("")).unwrap(),
+            Trigger::parse("message,x=1,y=1,once,text=once", Path::new("")).unwrap(),
+        ];
+        let mut manager = Triggers::default();
+
+        let fired = |manager: &mut Triggers, pos| manager.update(&triggers, &[pos], &[]).len();
+        assert_eq!(fired(&mut manager, center(0, 0)), 0);
+        assert_eq!(fired(&mut manager, center(1, 1)), 2);
+        // standing inside does not fire again
+        assert_eq!(fired(&mut manager, center(1, 1)), 0);
+        assert_eq!(fired(&mut manager, center(2, 1)), 0);
+        assert_eq!(fired(&mut manager, center(0, 0)), 0);
+        assert_eq!(fired(&mut manager, center(1, 1)), 1);
+    }
+
+    #[test]
+    fn crates_press_and_hold_plates() {
+        let triggers = [
+            Trigger::parse("message,x=1,y=1,plate,text=click", Path::new("")).unwrap(),
+            Trigger::parse("message,x=1,y=1,text=hi", Path::new("")).unwrap(),
+        ];
+        assert_eq!(
+            triggers[0].to_line(Path::new("")),
+            "message,x=1,y=1,w=1,h=1,plate,text=click"
+        );
+        let mut manager = Triggers::default();
+
+        let crate_on_plate = [center(1, 1)];
+        assert_eq!(
+            manager.update(&triggers, &[], &crate_on_plate),
+            [&Action::Message("click".into())]
+        );
+        // players stepping on and off a held down plate do not press it again
+        assert_eq!(
+            manager
+                .update(&triggers, &[center(1, 1)], &crate_on_plate)
+                .len(),
+            1
+        );
+        assert!(manager.update(&triggers, &[], &crate_on_plate).is_empty());
+        assert!(manager.update(&triggers, &[], &[]).is_empty());
+        assert_eq!(manager.update(&triggers, &[center(1, 1)], &[]).len(), 2);
+    }
+
+    #[test]
+    fn markers_go_away_once_reached() {
+        let triggers = [
+            Trigger::parse("marker,x=1,y=1,w=2,once,text=Exit", Path::new("")).unwrap(),
+            Trigger::parse("marker,x=3,y=3,text=Key", Path::new("")).unwrap(),
+            Trigger::parse("message,x=1,y=1,text=hi", Path::new("")).unwrap(),
+        ];
+        assert_eq!(
+            triggers[0].to_line(Path::new("")),
+            "marker,x=1,y=1,w=2,h=1,once,text=Exit"
+        );
+        let mut manager = Triggers::default();
+        assert_eq!(
+            manager.markers(&triggers),
+            [
+                (Vec2::new(2., 1.5) * TILE_SIZE, "Exit"),
+                (center(3, 3), "Key")
+            ]
+        );
+
+        manager.update(&triggers, &[center(1, 1)], &[]);
+        manager.update(&triggers, &[center(3, 3)], &[]);
+        assert_eq!(manager.markers(&triggers), [(center(3, 3), "Key")]);
+    }
+
+    #[test]
+    fn transforms_with_the_map() {
+        let mut trigger =
+            Trigger::parse("spawn,x=0,y=1,w=2,at=3:0,sprite=a.png", Path::new("")).unwrap();
+        // 4x3 map turned clockwise is 3x4
+        trigger.transform(Transform::Rot90, 4, 3);
+        assert_eq!(
+            (trigger.x, trigger.y, trigger.width, trigger.height),
+            (1, 0, 1, 2)
+        );
+        assert!(matches!(trigger.action, Action::Spawn { at: (2, 3), .. }));
+    }
+}
@@ -0,0 +1,110 @@
+//! what cameras saw in a frame, so things out of sight can be skipped
+
+use crate::camera::Sprite;
+use crate::entity::{Entity, World};
+use crate::map::Map;
+use crate::ray::RayCast;
+use glam::Vec2;
+use std::collections::BTreeSet;
+
+/// how far past a hit to look for the tile that was hit, hits lie on the edge of the tile
+const NUDGE: f32 = 0.01;
+
+/// tiles rays hit and entities in sight, from every camera of a frame
+#[derive(Clone, Default, Debug)]
+pub struct Visibility {
+    /// whether a ray hit each tile
+    tiles: Vec<bool>,
+    width: usize,
+    entities: BTreeSet<Entity>,
+}
+
+impl Visibility {
+    /// nothing seen yet
+    pub fn new(map: &Map) -> Self {
+        Self {
+            tiles: vec![false; map.main_tiles.len()],
+            width: map.width,
+            entities: BTreeSet::new(),
+        }
+    }
+
+    /// mark what a camera at `origin` that cast `slices` sees
+    pub fn see(&mut self, map: &Map, origin: Vec2, slices: &[RayCast], world: &World) {
+        for slice in slices.iter().filter(|slice| slice.hit) {
+            let hit = origin + slice.vec + (Vec2::from_angle(slice.angle) * NUDGE);
+            if map.contains(hit) {
+                self.tiles[map.vec_to_idx(hit)] = true;
+            }
+        }
+
+        for (entity, pos) in world.positions.iter() {
+            if map.line_of_sight(origin, *pos) {
+                self.entities.insert(*entity);
+            }
+        }
+    }
+
+    /// whether any ray hit the tile
+    pub fn tile(&self, x: usize, y: usize) -> bool {
+        x < self.width && self.tiles.get((y * self.width) + x) == Some(&true)
+    }
+
+    /// whether the entity is in sight of any camera
+    pub fn entity(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    /// billboards of the entities in sight, see [`World::sprites`]
+    pub fn sprites(&self, world: &World) -> Vec<Sprite> {
+        world
+            .sprites
+            .iter()
+            .filter(|(entity, _)| self.entity(**entity))
+            .filter_map(|(entity, texture)| {
+                Some(Sprite {
+                    pos: *world.positions.get(entity)?,
+                    texture: texture.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::map::TILE_SIZE;
+    use std::path::Path;
+
+    #[test]
+    fn walls_hide_tiles_and_entities_behind_them() {
+        let map = Map::parse(
+            "!!!!MAIN\n#wall.png,collide\n\n#######\n#*  # #\n#######",
+            Path::new(""),
+        )
+        .unwrap();
+        let center = |x: usize, y: usize| (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE;
+        let camera = Camera::new(center(1, 1), 0.);
+        let slices = camera.cast(&map, 32);
+
+        let mut world = World::default();
+        let (near, hidden) = (world.spawn(), world.spawn());
+        world.positions.insert(near, center(3, 1));
+        world.positions.insert(hidden, center(5, 1));
+        world.sprites.insert(near, "near.png".into());
+        world.sprites.insert(hidden, "hidden.png".into());
+
+        let mut visibility = Visibility::new(&map);
+        visibility.see(&map, camera.pos, &slices, &world);
+        assert!(visibility.tile(4, 1));
+        assert!(!visibility.tile(6, 1));
+        assert!(!visibility.tile(0, 1));
+        assert!(!visibility.tile(9, 9));
+
+        assert!(visibility.entity(near));
+        assert!(!visibility.entity(hidden));
+        assert_eq!(visibility.sprites(&world).len(), 1);
+    }
+}